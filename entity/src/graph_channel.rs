@@ -0,0 +1,101 @@
+use sea_orm::{entity::prelude::*, ActiveValue};
+use serde::{Deserialize, Serialize};
+
+use crate::seconds_since_epoch;
+
+#[derive(Copy, Clone, Default, Debug, DeriveEntity)]
+pub struct Entity;
+
+impl EntityName for Entity {
+    fn table_name(&self) -> &str {
+        "graph_channel"
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, DeriveModel, DeriveActiveModel, Deserialize, Serialize)]
+pub struct Model {
+    pub id: String,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub short_channel_id: i64,
+    pub node_one: String,
+    pub node_two: String,
+    pub capacity_sats: Option<i64>,
+    pub one_to_two_fee_base_msat: Option<i64>,
+    pub one_to_two_fee_proportional_millionths: Option<i64>,
+    pub two_to_one_fee_base_msat: Option<i64>,
+    pub two_to_one_fee_proportional_millionths: Option<i64>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveColumn)]
+pub enum Column {
+    Id,
+    CreatedAt,
+    UpdatedAt,
+    ShortChannelId,
+    NodeOne,
+    NodeTwo,
+    CapacitySats,
+    OneToTwoFeeBaseMsat,
+    OneToTwoFeeProportionalMillionths,
+    TwoToOneFeeBaseMsat,
+    TwoToOneFeeProportionalMillionths,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DerivePrimaryKey)]
+pub enum PrimaryKey {
+    Id,
+}
+
+impl PrimaryKeyTrait for PrimaryKey {
+    type ValueType = String;
+    fn auto_increment() -> bool {
+        false
+    }
+}
+
+#[derive(Copy, Clone, Debug, EnumIter)]
+pub enum Relation {}
+
+impl ColumnTrait for Column {
+    type EntityName = Entity;
+    fn def(&self) -> ColumnDef {
+        match self {
+            Self::Id => ColumnType::String(None).def().unique(),
+            Self::CreatedAt => ColumnType::BigInteger.def(),
+            Self::UpdatedAt => ColumnType::BigInteger.def(),
+            Self::ShortChannelId => ColumnType::BigInteger.def().unique(),
+            Self::NodeOne => ColumnType::String(None).def(),
+            Self::NodeTwo => ColumnType::String(None).def(),
+            Self::CapacitySats => ColumnType::BigInteger.def().null(),
+            Self::OneToTwoFeeBaseMsat => ColumnType::BigInteger.def().null(),
+            Self::OneToTwoFeeProportionalMillionths => ColumnType::BigInteger.def().null(),
+            Self::TwoToOneFeeBaseMsat => ColumnType::BigInteger.def().null(),
+            Self::TwoToOneFeeProportionalMillionths => ColumnType::BigInteger.def().null(),
+        }
+    }
+}
+
+impl RelationTrait for Relation {
+    fn def(&self) -> RelationDef {
+        panic!("No RelationDef")
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {
+    fn new() -> Self {
+        Self {
+            id: ActiveValue::Set(Uuid::new_v4().to_string()),
+            ..<Self as ActiveModelTrait>::default()
+        }
+    }
+
+    fn before_save(mut self, insert: bool) -> Result<Self, DbErr> {
+        let now: i64 = seconds_since_epoch();
+        self.updated_at = ActiveValue::Set(now);
+        if insert {
+            self.created_at = ActiveValue::Set(now);
+        }
+        Ok(self)
+    }
+}
@@ -5,13 +5,24 @@ pub use sea_orm;
 pub mod prelude;
 
 pub mod access_token;
+pub mod broadcast_log;
+pub mod channel;
+pub mod channel_event;
+pub mod channel_funding_input;
+pub mod channel_htlc_stat;
+pub mod deposit_address;
+pub mod forward;
+pub mod graph_channel;
+pub mod graph_node;
 pub mod keychain;
 pub mod kv_store;
 pub mod macaroon;
 pub mod node;
 pub mod payment;
 pub mod peer;
+pub mod peer_event;
 pub mod script_pubkey;
+pub mod subscription;
 pub mod transaction;
 pub mod utxo;
 
@@ -27,6 +27,10 @@ pub struct Model {
     pub secret: Option<String>,
     pub label: Option<String>,
     pub invoice: Option<String>,
+    pub failure_reason: Option<String>,
+    pub failure_short_channel_id: Option<i64>,
+    pub metadata: Option<String>,
+    pub ldk_payment_id: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveColumn)]
@@ -44,6 +48,10 @@ pub enum Column {
     UpdatedAt,
     Label,
     Invoice,
+    FailureReason,
+    FailureShortChannelId,
+    Metadata,
+    LdkPaymentId,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DerivePrimaryKey)]
@@ -78,6 +86,10 @@ impl ColumnTrait for Column {
             Self::Secret => ColumnType::String(None).def().null(),
             Self::Label => ColumnType::String(None).def().null(),
             Self::Invoice => ColumnType::String(None).def().null(),
+            Self::FailureReason => ColumnType::String(None).def().null(),
+            Self::FailureShortChannelId => ColumnType::BigInteger.def().null(),
+            Self::Metadata => ColumnType::String(None).def().null(),
+            Self::LdkPaymentId => ColumnType::String(None).def().null(),
         }
     }
 }
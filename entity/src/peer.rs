@@ -12,6 +12,8 @@ impl EntityName for Entity {
     }
 }
 
+// (node_id, pubkey) is unique, backed by the idx-nodeid-pubkey index.
+// Use SenseiDatabase::upsert_peer rather than inserting directly.
 #[derive(Clone, Debug, PartialEq, DeriveModel, DeriveActiveModel, Deserialize, Serialize)]
 pub struct Model {
     pub id: String,
@@ -21,6 +23,7 @@ pub struct Model {
     pub pubkey: String,
     pub zero_conf: bool,
     pub label: Option<String>,
+    pub last_connected_at: Option<i64>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveColumn)]
@@ -32,6 +35,7 @@ pub enum Column {
     Pubkey,
     Label,
     ZeroConf,
+    LastConnectedAt,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DerivePrimaryKey)]
@@ -60,6 +64,7 @@ impl ColumnTrait for Column {
             Self::Pubkey => ColumnType::String(None).def(),
             Self::Label => ColumnType::String(None).def(),
             Self::ZeroConf => ColumnType::Boolean.def(),
+            Self::LastConnectedAt => ColumnType::BigInteger.def().null(),
         }
     }
 }
@@ -10,6 +10,20 @@ mod m20220428_000002_create_script_pubkeys_table;
 mod m20220428_000003_create_transactions_table;
 mod m20220428_000004_create_keychains_table;
 mod m20220616_000001_create_peers_table;
+mod m20220701_000001_create_deposit_addresses_table;
+mod m20220705_000001_create_broadcast_logs_table;
+mod m20220708_000001_create_channel_events_table;
+mod m20220710_000001_create_forwards_table;
+mod m20220712_000001_create_channel_funding_inputs_table;
+mod m20220714_000001_add_payment_failure_reason;
+mod m20220716_000001_create_channel_htlc_stats_table;
+mod m20220718_000001_add_payment_metadata;
+mod m20220720_000001_create_channels_table;
+mod m20220722_000001_create_graph_tables;
+mod m20220724_000001_add_peer_last_connected_at;
+mod m20220728_000001_create_peer_events_table;
+mod m20220730_000001_create_subscriptions_table;
+mod m20220801_000001_add_payment_ldk_payment_id;
 
 pub struct Migrator;
 
@@ -28,6 +42,20 @@ impl MigratorTrait for Migrator {
             Box::new(m20220428_000003_create_transactions_table::Migration),
             Box::new(m20220428_000004_create_keychains_table::Migration),
             Box::new(m20220616_000001_create_peers_table::Migration),
+            Box::new(m20220701_000001_create_deposit_addresses_table::Migration),
+            Box::new(m20220705_000001_create_broadcast_logs_table::Migration),
+            Box::new(m20220708_000001_create_channel_events_table::Migration),
+            Box::new(m20220710_000001_create_forwards_table::Migration),
+            Box::new(m20220712_000001_create_channel_funding_inputs_table::Migration),
+            Box::new(m20220714_000001_add_payment_failure_reason::Migration),
+            Box::new(m20220716_000001_create_channel_htlc_stats_table::Migration),
+            Box::new(m20220718_000001_add_payment_metadata::Migration),
+            Box::new(m20220720_000001_create_channels_table::Migration),
+            Box::new(m20220722_000001_create_graph_tables::Migration),
+            Box::new(m20220724_000001_add_peer_last_connected_at::Migration),
+            Box::new(m20220728_000001_create_peer_events_table::Migration),
+            Box::new(m20220730_000001_create_subscriptions_table::Migration),
+            Box::new(m20220801_000001_add_payment_ldk_payment_id::Migration),
         ]
     }
 }
@@ -0,0 +1,70 @@
+use sea_schema::migration::prelude::*;
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220701_000001_create_deposit_addresses_table"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let _res = manager
+            .create_table(
+                Table::create()
+                    .table(DepositAddress::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(DepositAddress::Id)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(DepositAddress::CreatedAt)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(DepositAddress::UpdatedAt)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(DepositAddress::NodeId).string().not_null())
+                    .col(ColumnDef::new(DepositAddress::Address).string().not_null())
+                    .col(ColumnDef::new(DepositAddress::Label).string().not_null())
+                    .to_owned(),
+            )
+            .await;
+
+        manager
+            .create_index(
+                Index::create()
+                    .table(DepositAddress::Table)
+                    .name("idx-nodeid-address")
+                    .col(DepositAddress::NodeId)
+                    .col(DepositAddress::Address)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let mut drop_table_stmt = Table::drop();
+        drop_table_stmt.table(DepositAddress::Table);
+        manager.drop_table(drop_table_stmt).await
+    }
+}
+
+#[derive(Iden)]
+enum DepositAddress {
+    Table,
+    Id,
+    CreatedAt,
+    UpdatedAt,
+    NodeId,
+    Address,
+    Label,
+}
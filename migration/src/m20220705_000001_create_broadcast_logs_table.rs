@@ -0,0 +1,76 @@
+use sea_schema::migration::prelude::*;
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220705_000001_create_broadcast_logs_table"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let _res = manager
+            .create_table(
+                Table::create()
+                    .table(BroadcastLog::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(BroadcastLog::Id)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(BroadcastLog::CreatedAt)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(BroadcastLog::UpdatedAt)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(BroadcastLog::NodeId).string().not_null())
+                    .col(ColumnDef::new(BroadcastLog::Txid).string().not_null())
+                    .col(ColumnDef::new(BroadcastLog::Purpose).string().not_null())
+                    .col(ColumnDef::new(BroadcastLog::Context).string())
+                    .col(ColumnDef::new(BroadcastLog::FeerateSatPerVb).double())
+                    .col(ColumnDef::new(BroadcastLog::Attempts).integer().not_null())
+                    .to_owned(),
+            )
+            .await;
+
+        manager
+            .create_index(
+                Index::create()
+                    .table(BroadcastLog::Table)
+                    .name("idx-broadcastlog-nodeid-txid")
+                    .col(BroadcastLog::NodeId)
+                    .col(BroadcastLog::Txid)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let mut drop_table_stmt = Table::drop();
+        drop_table_stmt.table(BroadcastLog::Table);
+        manager.drop_table(drop_table_stmt).await
+    }
+}
+
+#[derive(Iden)]
+enum BroadcastLog {
+    Table,
+    Id,
+    CreatedAt,
+    UpdatedAt,
+    NodeId,
+    Txid,
+    Purpose,
+    Context,
+    FeerateSatPerVb,
+    Attempts,
+}
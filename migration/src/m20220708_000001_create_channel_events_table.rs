@@ -0,0 +1,73 @@
+use sea_schema::migration::prelude::*;
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220708_000001_create_channel_events_table"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let _res = manager
+            .create_table(
+                Table::create()
+                    .table(ChannelEvent::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(ChannelEvent::Id)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(ChannelEvent::CreatedAt)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ChannelEvent::UpdatedAt)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(ChannelEvent::NodeId).string().not_null())
+                    .col(ColumnDef::new(ChannelEvent::ChannelId).string().not_null())
+                    .col(ColumnDef::new(ChannelEvent::Kind).string().not_null())
+                    .col(ColumnDef::new(ChannelEvent::Source).string().not_null())
+                    .col(ColumnDef::new(ChannelEvent::Detail).string())
+                    .to_owned(),
+            )
+            .await;
+
+        manager
+            .create_index(
+                Index::create()
+                    .table(ChannelEvent::Table)
+                    .name("idx-channelevent-nodeid-channelid")
+                    .col(ChannelEvent::NodeId)
+                    .col(ChannelEvent::ChannelId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let mut drop_table_stmt = Table::drop();
+        drop_table_stmt.table(ChannelEvent::Table);
+        manager.drop_table(drop_table_stmt).await
+    }
+}
+
+#[derive(Iden)]
+enum ChannelEvent {
+    Table,
+    Id,
+    CreatedAt,
+    UpdatedAt,
+    NodeId,
+    ChannelId,
+    Kind,
+    Source,
+    Detail,
+}
@@ -0,0 +1,69 @@
+use sea_schema::migration::prelude::*;
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220710_000001_create_forwards_table"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let _res = manager
+            .create_table(
+                Table::create()
+                    .table(Forward::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Forward::Id)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Forward::CreatedAt).big_integer().not_null())
+                    .col(ColumnDef::new(Forward::UpdatedAt).big_integer().not_null())
+                    .col(ColumnDef::new(Forward::NodeId).string().not_null())
+                    .col(ColumnDef::new(Forward::PrevChannelId).string())
+                    .col(ColumnDef::new(Forward::NextChannelId).string())
+                    .col(ColumnDef::new(Forward::FeeEarnedMsat).big_integer())
+                    .col(
+                        ColumnDef::new(Forward::ClaimFromOnchainTx)
+                            .boolean()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await;
+
+        manager
+            .create_index(
+                Index::create()
+                    .table(Forward::Table)
+                    .name("idx-forward-nodeid-createdat")
+                    .col(Forward::NodeId)
+                    .col(Forward::CreatedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let mut drop_table_stmt = Table::drop();
+        drop_table_stmt.table(Forward::Table);
+        manager.drop_table(drop_table_stmt).await
+    }
+}
+
+#[derive(Iden)]
+enum Forward {
+    Table,
+    Id,
+    CreatedAt,
+    UpdatedAt,
+    NodeId,
+    PrevChannelId,
+    NextChannelId,
+    FeeEarnedMsat,
+    ClaimFromOnchainTx,
+}
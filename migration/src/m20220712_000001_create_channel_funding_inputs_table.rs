@@ -0,0 +1,101 @@
+use sea_schema::migration::prelude::*;
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220712_000001_create_channel_funding_inputs_table"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let _res = manager
+            .create_table(
+                Table::create()
+                    .table(ChannelFundingInput::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(ChannelFundingInput::Id)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(ChannelFundingInput::CreatedAt)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ChannelFundingInput::UpdatedAt)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ChannelFundingInput::NodeId)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ChannelFundingInput::ChannelId)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ChannelFundingInput::FundingTxid)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ChannelFundingInput::Txid)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ChannelFundingInput::Vout)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(ChannelFundingInput::AmountSats).big_integer())
+                    .col(
+                        ColumnDef::new(ChannelFundingInput::SharedChannels)
+                            .integer()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await;
+
+        manager
+            .create_index(
+                Index::create()
+                    .table(ChannelFundingInput::Table)
+                    .name("idx-channelfundinginput-nodeid-channelid")
+                    .col(ChannelFundingInput::NodeId)
+                    .col(ChannelFundingInput::ChannelId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let mut drop_table_stmt = Table::drop();
+        drop_table_stmt.table(ChannelFundingInput::Table);
+        manager.drop_table(drop_table_stmt).await
+    }
+}
+
+#[derive(Iden)]
+enum ChannelFundingInput {
+    Table,
+    Id,
+    CreatedAt,
+    UpdatedAt,
+    NodeId,
+    ChannelId,
+    FundingTxid,
+    Txid,
+    Vout,
+    AmountSats,
+    SharedChannels,
+}
@@ -0,0 +1,59 @@
+use sea_schema::migration::prelude::*;
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220714_000001_add_payment_failure_reason"
+    }
+}
+
+// sqlite can only add one column per statement
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Payment::Table)
+                    .add_column(ColumnDef::new(Payment::FailureReason).string())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Payment::Table)
+                    .add_column(ColumnDef::new(Payment::FailureShortChannelId).big_integer())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Payment::Table)
+                    .drop_column(Payment::FailureReason)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Payment::Table)
+                    .drop_column(Payment::FailureShortChannelId)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum Payment {
+    Table,
+    FailureReason,
+    FailureShortChannelId,
+}
@@ -0,0 +1,96 @@
+use sea_schema::migration::prelude::*;
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220716_000001_create_channel_htlc_stats_table"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let _res = manager
+            .create_table(
+                Table::create()
+                    .table(ChannelHtlcStat::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(ChannelHtlcStat::Id)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(ChannelHtlcStat::CreatedAt)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ChannelHtlcStat::UpdatedAt)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(ChannelHtlcStat::NodeId).string().not_null())
+                    .col(
+                        ColumnDef::new(ChannelHtlcStat::ChannelId)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ChannelHtlcStat::InboundSucceeded)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ChannelHtlcStat::InboundFailed)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ChannelHtlcStat::OutboundSucceeded)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ChannelHtlcStat::OutboundFailed)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await;
+
+        manager
+            .create_index(
+                Index::create()
+                    .table(ChannelHtlcStat::Table)
+                    .name("idx-channelhtlcstat-nodeid-channelid")
+                    .col(ChannelHtlcStat::NodeId)
+                    .col(ChannelHtlcStat::ChannelId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let mut drop_table_stmt = Table::drop();
+        drop_table_stmt.table(ChannelHtlcStat::Table);
+        manager.drop_table(drop_table_stmt).await
+    }
+}
+
+#[derive(Iden)]
+enum ChannelHtlcStat {
+    Table,
+    Id,
+    CreatedAt,
+    UpdatedAt,
+    NodeId,
+    ChannelId,
+    InboundSucceeded,
+    InboundFailed,
+    OutboundSucceeded,
+    OutboundFailed,
+}
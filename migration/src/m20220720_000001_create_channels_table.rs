@@ -0,0 +1,80 @@
+use sea_schema::migration::prelude::*;
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220720_000001_create_channels_table"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let _res = manager
+            .create_table(
+                Table::create()
+                    .table(Channel::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Channel::Id)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Channel::CreatedAt).big_integer().not_null())
+                    .col(ColumnDef::new(Channel::UpdatedAt).big_integer().not_null())
+                    .col(ColumnDef::new(Channel::NodeId).string().not_null())
+                    .col(
+                        ColumnDef::new(Channel::CounterpartyPubkey)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(Channel::UserChannelId).string().not_null())
+                    .col(ColumnDef::new(Channel::ChannelId).string().not_null())
+                    .col(ColumnDef::new(Channel::ShortChannelId).big_integer())
+                    .col(ColumnDef::new(Channel::FundingTxid).string())
+                    .col(
+                        ColumnDef::new(Channel::CapacitySats)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(Channel::Label).string())
+                    .to_owned(),
+            )
+            .await;
+
+        manager
+            .create_index(
+                Index::create()
+                    .table(Channel::Table)
+                    .name("idx-channel-nodeid-userchannelid")
+                    .col(Channel::NodeId)
+                    .col(Channel::UserChannelId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let mut drop_table_stmt = Table::drop();
+        drop_table_stmt.table(Channel::Table);
+        manager.drop_table(drop_table_stmt).await
+    }
+}
+
+#[derive(Iden)]
+enum Channel {
+    Table,
+    Id,
+    CreatedAt,
+    UpdatedAt,
+    NodeId,
+    CounterpartyPubkey,
+    UserChannelId,
+    ChannelId,
+    ShortChannelId,
+    FundingTxid,
+    CapacitySats,
+    Label,
+}
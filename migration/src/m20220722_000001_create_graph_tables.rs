@@ -0,0 +1,163 @@
+use sea_schema::migration::prelude::*;
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220722_000001_create_graph_tables"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let _res = manager
+            .create_table(
+                Table::create()
+                    .table(GraphNode::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(GraphNode::Id)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(GraphNode::CreatedAt)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(GraphNode::UpdatedAt)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(GraphNode::Pubkey).string().not_null())
+                    .col(ColumnDef::new(GraphNode::Alias).string())
+                    .col(ColumnDef::new(GraphNode::LastUpdate).big_integer())
+                    .to_owned(),
+            )
+            .await;
+
+        let _res = manager
+            .create_index(
+                Index::create()
+                    .table(GraphNode::Table)
+                    .name("idx-graphnode-pubkey")
+                    .col(GraphNode::Pubkey)
+                    .unique()
+                    .to_owned(),
+            )
+            .await;
+
+        let _res = manager
+            .create_table(
+                Table::create()
+                    .table(GraphChannel::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(GraphChannel::Id)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(GraphChannel::CreatedAt)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(GraphChannel::UpdatedAt)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(GraphChannel::ShortChannelId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(GraphChannel::NodeOne).string().not_null())
+                    .col(ColumnDef::new(GraphChannel::NodeTwo).string().not_null())
+                    .col(ColumnDef::new(GraphChannel::CapacitySats).big_integer())
+                    .col(ColumnDef::new(GraphChannel::OneToTwoFeeBaseMsat).big_integer())
+                    .col(
+                        ColumnDef::new(GraphChannel::OneToTwoFeeProportionalMillionths)
+                            .big_integer(),
+                    )
+                    .col(ColumnDef::new(GraphChannel::TwoToOneFeeBaseMsat).big_integer())
+                    .col(
+                        ColumnDef::new(GraphChannel::TwoToOneFeeProportionalMillionths)
+                            .big_integer(),
+                    )
+                    .to_owned(),
+            )
+            .await;
+
+        let _res = manager
+            .create_index(
+                Index::create()
+                    .table(GraphChannel::Table)
+                    .name("idx-graphchannel-shortchannelid")
+                    .col(GraphChannel::ShortChannelId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await;
+
+        let _res = manager
+            .create_index(
+                Index::create()
+                    .table(GraphChannel::Table)
+                    .name("idx-graphchannel-nodeone")
+                    .col(GraphChannel::NodeOne)
+                    .to_owned(),
+            )
+            .await;
+
+        manager
+            .create_index(
+                Index::create()
+                    .table(GraphChannel::Table)
+                    .name("idx-graphchannel-nodetwo")
+                    .col(GraphChannel::NodeTwo)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let mut drop_table_stmt = Table::drop();
+        drop_table_stmt.table(GraphChannel::Table);
+        manager.drop_table(drop_table_stmt).await?;
+
+        let mut drop_table_stmt = Table::drop();
+        drop_table_stmt.table(GraphNode::Table);
+        manager.drop_table(drop_table_stmt).await
+    }
+}
+
+#[derive(Iden)]
+enum GraphNode {
+    Table,
+    Id,
+    CreatedAt,
+    UpdatedAt,
+    Pubkey,
+    Alias,
+    LastUpdate,
+}
+
+#[derive(Iden)]
+enum GraphChannel {
+    Table,
+    Id,
+    CreatedAt,
+    UpdatedAt,
+    ShortChannelId,
+    NodeOne,
+    NodeTwo,
+    CapacitySats,
+    OneToTwoFeeBaseMsat,
+    OneToTwoFeeProportionalMillionths,
+    TwoToOneFeeBaseMsat,
+    TwoToOneFeeProportionalMillionths,
+}
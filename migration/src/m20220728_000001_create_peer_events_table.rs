@@ -0,0 +1,69 @@
+use sea_schema::migration::prelude::*;
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20220728_000001_create_peer_events_table"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let _res = manager
+            .create_table(
+                Table::create()
+                    .table(PeerEvent::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(PeerEvent::Id)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(PeerEvent::CreatedAt)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PeerEvent::UpdatedAt)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(PeerEvent::NodeId).string().not_null())
+                    .col(ColumnDef::new(PeerEvent::Pubkey).string().not_null())
+                    .col(ColumnDef::new(PeerEvent::Connected).boolean().not_null())
+                    .to_owned(),
+            )
+            .await;
+
+        manager
+            .create_index(
+                Index::create()
+                    .table(PeerEvent::Table)
+                    .name("idx-peerevent-nodeid-pubkey")
+                    .col(PeerEvent::NodeId)
+                    .col(PeerEvent::Pubkey)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let mut drop_table_stmt = Table::drop();
+        drop_table_stmt.table(PeerEvent::Table);
+        manager.drop_table(drop_table_stmt).await
+    }
+}
+
+#[derive(Iden)]
+enum PeerEvent {
+    Table,
+    Id,
+    CreatedAt,
+    UpdatedAt,
+    NodeId,
+    Pubkey,
+    Connected,
+}
@@ -1,3 +1,4 @@
+use super::broadcaster::{RelayRejections, TransactionSubmitter};
 use base64;
 use bitcoin::blockdata::block::Block;
 use bitcoin::blockdata::transaction::Transaction;
@@ -9,7 +10,9 @@ use lightning_block_sync::rpc::RpcClient;
 use lightning_block_sync::{AsyncBlockSourceResult, BlockHeaderData, BlockSource};
 use serde_json;
 use std::collections::HashMap;
-use std::sync::atomic::{AtomicU32, Ordering};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -58,6 +61,10 @@ pub struct BitcoindClient {
     bitcoind_rpc_client: Arc<RpcClient>,
     fees: Arc<HashMap<Target, AtomicU32>>,
     handle: tokio::runtime::Handle,
+    pub relay_rejections: Arc<RelayRejections>,
+    // false while any of the estimates is one of the defaults, before the first poll or
+    // when bitcoind doesn't have enough data to estimate
+    pub fee_estimates_known: Arc<AtomicBool>,
 }
 
 #[derive(Clone, Eq, Hash, PartialEq)]
@@ -119,9 +126,12 @@ impl BitcoindClient {
             bitcoind_rpc_client: Arc::new(bitcoind_rpc_client),
             fees: Arc::new(fees),
             handle: handle.clone(),
+            relay_rejections: Arc::new(RelayRejections::default()),
+            fee_estimates_known: Arc::new(AtomicBool::new(false)),
         };
         BitcoindClient::poll_for_fee_estimates(
             client.fees.clone(),
+            client.fee_estimates_known.clone(),
             client.bitcoind_rpc_client.clone(),
             handle,
         );
@@ -130,6 +140,7 @@ impl BitcoindClient {
 
     fn poll_for_fee_estimates(
         fees: Arc<HashMap<Target, AtomicU32>>,
+        fee_estimates_known: Arc<AtomicBool>,
         rpc_client: Arc<RpcClient>,
         handle: tokio::runtime::Handle,
     ) {
@@ -145,10 +156,8 @@ impl BitcoindClient {
                         )
                         .await
                         .unwrap();
-                    match resp.feerate_sat_per_kw {
-                        Some(feerate) => std::cmp::max(feerate, MIN_FEERATE),
-                        None => MIN_FEERATE,
-                    }
+                    resp.feerate_sat_per_kw
+                        .map(|feerate| std::cmp::max(feerate, MIN_FEERATE))
                 };
 
                 let normal_estimate = {
@@ -161,10 +170,8 @@ impl BitcoindClient {
                         )
                         .await
                         .unwrap();
-                    match resp.feerate_sat_per_kw {
-                        Some(feerate) => std::cmp::max(feerate, MIN_FEERATE),
-                        None => 2000,
-                    }
+                    resp.feerate_sat_per_kw
+                        .map(|feerate| std::cmp::max(feerate, MIN_FEERATE))
                 };
 
                 let high_prio_estimate = {
@@ -178,12 +185,20 @@ impl BitcoindClient {
                         .await
                         .unwrap();
 
-                    match resp.feerate_sat_per_kw {
-                        Some(feerate) => std::cmp::max(feerate, MIN_FEERATE),
-                        None => 5000,
-                    }
+                    resp.feerate_sat_per_kw
+                        .map(|feerate| std::cmp::max(feerate, MIN_FEERATE))
                 };
 
+                fee_estimates_known.store(
+                    background_estimate.is_some()
+                        && normal_estimate.is_some()
+                        && high_prio_estimate.is_some(),
+                    Ordering::Release,
+                );
+                let background_estimate = background_estimate.unwrap_or(MIN_FEERATE);
+                let normal_estimate = normal_estimate.unwrap_or(2000);
+                let high_prio_estimate = high_prio_estimate.unwrap_or(5000);
+
                 fees.get(&Target::Background)
                     .unwrap()
                     .store(background_estimate, Ordering::Release);
@@ -221,9 +236,26 @@ impl FeeEstimator for BitcoindClient {
     }
 }
 
+impl TransactionSubmitter for BitcoindClient {
+    fn submit_transaction<'a>(
+        &'a self,
+        tx: &'a Transaction,
+    ) -> Pin<Box<dyn Future<Output = Result<Txid, String>> + Send + 'a>> {
+        Box::pin(async move {
+            let tx_serialized = serde_json::json!(encode::serialize_hex(tx));
+            self.bitcoind_rpc_client
+                .call_method::<Txid>("sendrawtransaction", &[tx_serialized])
+                .await
+                .map_err(|e| e.to_string())
+        })
+    }
+}
+
 impl BroadcasterInterface for BitcoindClient {
     fn broadcast_transaction(&self, tx: &Transaction) {
         let bitcoind_rpc_client = self.bitcoind_rpc_client.clone();
+        let relay_rejections = self.relay_rejections.clone();
+        let txid = tx.txid();
         let tx_serialized = serde_json::json!(encode::serialize_hex(tx));
         self.handle.spawn(async move {
             // This may error due to RL calling `broadcast_transaction` with the same transaction
@@ -232,9 +264,15 @@ impl BroadcasterInterface for BitcoindClient {
                 .call_method::<Txid>("sendrawtransaction", &[tx_serialized])
                 .await
             {
-                Ok(_) => {}
+                Ok(_) => relay_rejections.clear(&txid),
                 Err(e) => {
                     let err_str = e.get_ref().unwrap().to_string();
+                    if err_str.contains("min relay fee not met")
+                        || err_str.contains("mempool min fee not met")
+                    {
+                        relay_rejections.record(txid);
+                        return;
+                    }
                     if !err_str.contains("Transaction already in block chain")
                         && !err_str.contains("Inputs missing or spent")
                         && !err_str.contains("bad-txns-inputs-missingorspent")
@@ -1,42 +1,323 @@
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
+    fmt,
+    future::Future,
+    pin::Pin,
     sync::{Arc, Mutex},
+    time::{Duration, Instant},
 };
 
+use crate::database::SenseiDatabase;
 use crate::events::SenseiEvent;
+use crate::hex_utils;
 
 use super::database::WalletDatabase;
-use bitcoin::{Transaction, Txid};
+use bdk::database::Database;
+use bitcoin::{OutPoint, Transaction, Txid};
 use lightning::chain::chaininterface::BroadcasterInterface;
+use lightning::chain::transaction::OutPoint as LdkOutPoint;
 use tokio::sync::broadcast;
 
+#[derive(Clone, Debug)]
+pub enum BroadcastPurpose {
+    Funding,
+    Close,
+    Sweep,
+    Consolidation,
+    Psbt,
+    FeeBump,
+}
+
+impl fmt::Display for BroadcastPurpose {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let str = match *self {
+            BroadcastPurpose::Funding => "funding",
+            BroadcastPurpose::Close => "close",
+            BroadcastPurpose::Sweep => "sweep",
+            BroadcastPurpose::Consolidation => "consolidation",
+            BroadcastPurpose::Psbt => "psbt",
+            BroadcastPurpose::FeeBump => "fee_bump",
+        };
+        write!(f, "{}", str)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct BroadcastContext {
+    pub purpose: BroadcastPurpose,
+    pub context: Option<String>,
+    pub feerate_sat_per_vb: Option<f64>,
+}
+
+// The mempool min fee comes back down, so a tx we gave up on gets another chance once it
+// hasn't been rejected for this long.
+pub const RELAY_REJECTION_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+// How long we remember the txs we tagged or abandoned, well past any mempool expiry.
+pub const BROADCAST_STATE_TTL: Duration = Duration::from_secs(14 * 24 * 60 * 60);
+
+struct Rejections {
+    count: u32,
+    last_rejected: Instant,
+}
+
+// Counts how often the backend refused a transaction for paying less than the minimum
+// relay or mempool feerate. Shared between the backend, which sees the rejections, and
+// the broadcasters of every node.
+#[derive(Default)]
+pub struct RelayRejections {
+    rejections: Mutex<HashMap<Txid, Rejections>>,
+}
+
+impl RelayRejections {
+    pub fn record(&self, txid: Txid) {
+        let mut rejections = self.rejections.lock().unwrap();
+        let entry = rejections.entry(txid).or_insert(Rejections {
+            count: 0,
+            last_rejected: Instant::now(),
+        });
+        entry.count += 1;
+        entry.last_rejected = Instant::now();
+    }
+
+    // the backend took the tx after all
+    pub fn clear(&self, txid: &Txid) {
+        let mut rejections = self.rejections.lock().unwrap();
+        rejections.remove(txid);
+    }
+
+    pub fn count(&self, txid: &Txid) -> u32 {
+        let rejections = self.rejections.lock().unwrap();
+        rejections
+            .get(txid)
+            .map(|rejections| rejections.count)
+            .unwrap_or(0)
+    }
+
+    pub fn prune(&self, now: Instant) {
+        let mut rejections = self.rejections.lock().unwrap();
+        rejections.retain(|_txid, rejections| {
+            now.saturating_duration_since(rejections.last_rejected) < RELAY_REJECTION_TTL
+        });
+    }
+}
+
+// LDK only needs broadcasts to be fired off, this waits for the backend to accept or refuse
+// the transaction for when we have to know which it was.
+pub trait TransactionSubmitter {
+    fn submit_transaction<'a>(
+        &'a self,
+        tx: &'a Transaction,
+    ) -> Pin<Box<dyn Future<Output = Result<Txid, String>> + Send + 'a>>;
+}
+
+// A funding tx waits for every channel in it to be signed by its peer. Broadcasting it while
+// one is missing would lock that channel's funds without a commitment to get them back, so a
+// tx that isn't complete by `deadline` is abandoned instead. bdk only learns that `inputs` are
+// spent once the tx is broadcast, until then they are kept out of every other tx we build.
+pub struct Debounce {
+    pub remaining: usize,
+    pub deadline: Instant,
+    pub inputs: Vec<OutPoint>,
+}
+
 pub struct SenseiBroadcaster {
-    pub debounce: Mutex<HashMap<Txid, usize>>,
+    pub debounce: Mutex<HashMap<Txid, Debounce>>,
+    pub debounce_timeout: Duration,
+    pub abandoned: Mutex<HashMap<Txid, Instant>>,
+    pub contexts: Mutex<HashMap<Txid, (BroadcastContext, Instant)>>,
     pub node_id: String,
     pub broadcaster: Arc<dyn BroadcasterInterface + Send + Sync>,
     pub wallet_database: Arc<Mutex<WalletDatabase>>,
     pub event_sender: broadcast::Sender<SenseiEvent>,
+    // only set when broadcast logging is enabled
+    pub database: Option<Arc<SenseiDatabase>>,
+    pub relay_rejections: Arc<RelayRejections>,
+    // 0 keeps rebroadcasting no matter how often the tx was rejected
+    pub max_relay_rejections: u32,
+    pub stuck: Mutex<HashSet<Txid>>,
+    // funding outpoints of channels whose state may be stale, see stale_state
+    pub halted_spends: Mutex<HashSet<OutPoint>>,
 }
 
 impl SenseiBroadcaster {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         node_id: String,
         broadcaster: Arc<dyn BroadcasterInterface + Send + Sync>,
         wallet_database: Arc<Mutex<WalletDatabase>>,
         event_sender: broadcast::Sender<SenseiEvent>,
+        database: Option<Arc<SenseiDatabase>>,
+        relay_rejections: Arc<RelayRejections>,
+        max_relay_rejections: u32,
+        debounce_timeout: Duration,
     ) -> Self {
         Self {
             node_id,
             broadcaster,
             wallet_database,
             event_sender,
+            database,
+            relay_rejections,
+            max_relay_rejections,
             debounce: Mutex::new(HashMap::new()),
+            debounce_timeout,
+            abandoned: Mutex::new(HashMap::new()),
+            contexts: Mutex::new(HashMap::new()),
+            stuck: Mutex::new(HashSet::new()),
+            halted_spends: Mutex::new(HashSet::new()),
+        }
+    }
+
+    // A tx of ours the backend keeps refusing for its feerate will never make it in on its
+    // own, so after enough rejections we stop trying and let the operator know it needs a
+    // fee bump. Only txs we built and tagged are given up on, LDK's commitment, claim and
+    // justice txs are always rebroadcast since missing their deadline can lose the channel.
+    fn is_stuck_below_relay(&self, txid: Txid) -> bool {
+        let tagged = self.contexts.lock().unwrap().contains_key(&txid);
+        let mut stuck = self.stuck.lock().unwrap();
+        if !tagged
+            || self.max_relay_rejections == 0
+            || self.relay_rejections.count(&txid) < self.max_relay_rejections
+        {
+            stuck.remove(&txid);
+            return false;
         }
+
+        if stuck.insert(txid) {
+            println!(
+                "giving up on broadcasting {}, its feerate was rejected {} times",
+                txid, self.max_relay_rejections
+            );
+            self.event_sender
+                .send(SenseiEvent::TxStuckBelowRelay {
+                    node_id: self.node_id.clone(),
+                    txid,
+                })
+                .unwrap_or_default();
+        }
+        true
+    }
+
+    // Transactions we build ourselves are tagged before they are broadcast so the
+    // broadcast log can say why they were sent. Anything LDK broadcasts on its own
+    // is a commitment, closing or claim transaction and is logged as a close.
+    pub fn set_context(&self, txid: Txid, context: BroadcastContext) {
+        let mut contexts = self.contexts.lock().unwrap();
+        contexts.insert(txid, (context, Instant::now()));
+    }
+
+    // Forgets what we tracked about txs that are long gone, a stuck tx is retried once its
+    // rejections have expired.
+    pub fn prune(&self, now: Instant) {
+        self.relay_rejections.prune(now);
+        self.contexts
+            .lock()
+            .unwrap()
+            .retain(|_txid, (_context, tagged_at)| {
+                now.saturating_duration_since(*tagged_at) < BROADCAST_STATE_TTL
+            });
+        self.abandoned
+            .lock()
+            .unwrap()
+            .retain(|_txid, abandoned_at| {
+                now.saturating_duration_since(*abandoned_at) < BROADCAST_STATE_TTL
+            });
+        let mut stuck = self.stuck.lock().unwrap();
+        stuck.retain(|txid| self.relay_rejections.count(txid) >= self.max_relay_rejections);
+    }
+
+    pub fn halt_spends(&self, outpoints: Vec<OutPoint>) {
+        let mut halted_spends = self.halted_spends.lock().unwrap();
+        halted_spends.extend(outpoints);
+    }
+
+    pub fn resume_spends(&self) {
+        let mut halted_spends = self.halted_spends.lock().unwrap();
+        halted_spends.clear();
+    }
+
+    // Any commitment we'd broadcast for a channel with stale state could be a revoked one.
+    fn spends_halted_outpoint(&self, tx: &Transaction) -> bool {
+        let halted_spends = self.halted_spends.lock().unwrap();
+        tx.input
+            .iter()
+            .any(|input| halted_spends.contains(&input.previous_output))
+    }
+
+    pub fn set_debounce(&self, txid: Txid, count: usize, inputs: Vec<OutPoint>) {
+        let mut debounce = self.debounce.lock().unwrap();
+        debounce.insert(
+            txid,
+            Debounce {
+                remaining: count,
+                deadline: Instant::now() + self.debounce_timeout,
+                inputs,
+            },
+        );
+    }
+
+    // LDK never got the funding tx, it won't be broadcast and its inputs are free again.
+    pub fn release_debounce(&self, txid: &Txid) {
+        self.debounce.lock().unwrap().remove(txid);
+    }
+
+    // Inputs of the funding txs we built but haven't broadcast, nothing else may spend them.
+    pub fn debounced_inputs(&self) -> Vec<OutPoint> {
+        self.debounce
+            .lock()
+            .unwrap()
+            .values()
+            .flat_map(|pending| pending.inputs.clone())
+            .collect()
     }
 
-    pub fn set_debounce(&self, txid: Txid, count: usize) {
+    // Gives up on the funding txs still waiting on signatures at `now` and returns them, so
+    // the channels that were signed can be closed. The txs are never broadcast after this,
+    // even if the missing peers come around.
+    pub fn expire_debounces(&self, now: Instant) -> Vec<Txid> {
         let mut debounce = self.debounce.lock().unwrap();
-        debounce.insert(txid, count);
+        let expired = debounce
+            .iter()
+            .filter(|(_txid, pending)| pending.deadline <= now)
+            .map(|(txid, pending)| (*txid, pending.remaining))
+            .collect::<Vec<_>>();
+
+        let mut abandoned = self.abandoned.lock().unwrap();
+        for (txid, unsigned_channels) in expired.iter() {
+            debounce.remove(txid);
+            abandoned.insert(*txid, now);
+            println!(
+                "abandoning funding tx {}, {} channels were never signed",
+                txid, unsigned_channels
+            );
+            self.event_sender
+                .send(SenseiEvent::FundingBroadcastTimedOut {
+                    node_id: self.node_id.clone(),
+                    txid: *txid,
+                    unsigned_channels: *unsigned_channels,
+                })
+                .unwrap_or_default();
+        }
+
+        expired.into_iter().map(|(txid, _)| txid).collect()
+    }
+
+    // Why a transaction we are asked to rebroadcast by hand may not go out. These are the
+    // checks broadcast_transaction makes, a batch funding still waiting on its peers is never
+    // broadcast early either. Being stuck below the relay feerate doesn't stop a manual retry.
+    pub fn rebroadcast_refusal(&self, tx: &Transaction) -> Option<String> {
+        let txid = tx.txid();
+        if self.spends_halted_outpoint(tx) {
+            Some(String::from("it spends a channel with stale state"))
+        } else if self.abandoned.lock().unwrap().contains_key(&txid) {
+            Some(String::from("its batch funding was abandoned"))
+        } else if self.debounce.lock().unwrap().contains_key(&txid) {
+            Some(String::from(
+                "its batch funding is still waiting for channels to be signed",
+            ))
+        } else {
+            None
+        }
     }
 
     pub fn broadcast(&self, tx: &Transaction) {
@@ -44,8 +325,8 @@ impl SenseiBroadcaster {
 
         // TODO: there's a bug here if the broadcast fails
         //       best solution is to probably setup a zmq listener
-        let mut database = self.wallet_database.lock().unwrap();
-        database.process_mempool_tx(tx);
+        let mut wallet_database = self.wallet_database.lock().unwrap();
+        wallet_database.process_mempool_tx(tx);
 
         self.event_sender
             .send(SenseiEvent::TransactionBroadcast {
@@ -53,24 +334,99 @@ impl SenseiBroadcaster {
                 txid: tx.txid(),
             })
             .unwrap_or_default();
+
+        if let Some(database) = &self.database {
+            let context = {
+                let contexts = self.contexts.lock().unwrap();
+                contexts
+                    .get(&tx.txid())
+                    .map(|(context, _tagged_at)| context.clone())
+            }
+            .unwrap_or(BroadcastContext {
+                purpose: BroadcastPurpose::Close,
+                context: None,
+                feerate_sat_per_vb: None,
+            });
+
+            if matches!(context.purpose, BroadcastPurpose::Funding) {
+                for channel_id in funded_channel_ids(tx, &wallet_database) {
+                    let _res = database.record_channel_event_sync(
+                        &self.node_id,
+                        &hex_utils::hex_str(&channel_id),
+                        "funding_broadcast",
+                        "ldk",
+                        Some(format!("funding tx {}", tx.txid())),
+                    );
+                }
+            }
+
+            if let Err(e) = database.record_broadcast_sync(
+                self.node_id.clone(),
+                tx.txid().to_string(),
+                context.purpose.to_string(),
+                context.context,
+                context.feerate_sat_per_vb,
+            ) {
+                println!("failed to record broadcast of {}: {}", tx.txid(), e);
+            }
+        }
     }
 }
 
+// LDK only broadcasts a funding tx once every channel it funds is signed, the outputs that
+// aren't our change are those channels.
+fn funded_channel_ids(tx: &Transaction, wallet_database: &WalletDatabase) -> Vec<[u8; 32]> {
+    tx.output
+        .iter()
+        .enumerate()
+        .filter(|(_vout, output)| {
+            !matches!(
+                wallet_database.get_path_from_script_pubkey(&output.script_pubkey),
+                Ok(Some(_))
+            )
+        })
+        .map(|(vout, _output)| {
+            LdkOutPoint {
+                txid: tx.txid(),
+                index: vout as u16,
+            }
+            .to_channel_id()
+        })
+        .collect()
+}
+
 impl BroadcasterInterface for SenseiBroadcaster {
     fn broadcast_transaction(&self, tx: &Transaction) {
         let txid = tx.txid();
 
+        if self.is_stuck_below_relay(txid) {
+            return;
+        }
+
+        if self.spends_halted_outpoint(tx) {
+            println!(
+                "refusing to broadcast {}, it spends a channel with stale state",
+                txid
+            );
+            return;
+        }
+
+        if self.abandoned.lock().unwrap().contains_key(&txid) {
+            return;
+        }
+
         let mut debounce = self.debounce.lock().unwrap();
 
         let can_broadcast = match debounce.get_mut(&txid) {
-            Some(count) => {
-                *count -= 1;
-                *count == 0
+            Some(pending) => {
+                pending.remaining -= 1;
+                pending.remaining == 0
             }
             None => true,
         };
 
         if can_broadcast {
+            debounce.remove(&txid);
             self.broadcast(tx);
         }
     }
@@ -1,9 +1,11 @@
 use crate::database::{LastSync, SenseiDatabase};
+use crate::events::SenseiEvent;
 use bdk::database::{BatchDatabase, BatchOperations, Database};
 use bdk::wallet::time;
 use bdk::{BlockTime, KeychainKind, LocalUtxo, TransactionDetails};
 use bitcoin::consensus::encode::{deserialize, serialize};
-use bitcoin::{BlockHeader, OutPoint, Script, TxOut, Txid};
+use bitcoin::hashes::Hash;
+use bitcoin::{BlockHeader, OutPoint, Script, TxOut, Txid, WPubkeyHash};
 use entity::keychain::Entity as Keychain;
 use entity::kv_store;
 use entity::kv_store::Entity as KVStore;
@@ -22,7 +24,9 @@ use entity::utxo::Entity as Utxo;
 use entity::{hex_str, keychain, to_vec_unsafe};
 use lightning::chain::transaction::TransactionData;
 use lightning::chain::Listen;
+use std::collections::HashSet;
 use std::sync::Arc;
+use tokio::sync::broadcast;
 
 impl Listen for WalletDatabase {
     fn filtered_block_connected(
@@ -46,6 +50,7 @@ impl Listen for WalletDatabase {
                 &mut external_max_deriv,
             )
         }
+        wallet_database.check_unexpected_scripts(txdata);
 
         let current_ext = wallet_database
             .get_last_index(KeychainKind::External)
@@ -69,6 +74,10 @@ impl Listen for WalletDatabase {
                 .unwrap();
         }
 
+        if !wallet_database.tracks_sync {
+            return;
+        }
+
         tokio::task::block_in_place(move || {
             wallet_database.tokio_handle.block_on(async move {
                 wallet_database
@@ -133,6 +142,8 @@ pub struct WalletDatabase {
     pub node_id: String,
     pub database: Arc<SenseiDatabase>,
     pub tokio_handle: tokio::runtime::Handle,
+    pub unexpected_script_alerts: Option<broadcast::Sender<SenseiEvent>>,
+    tracks_sync: bool,
 }
 
 impl WalletDatabase {
@@ -140,11 +151,82 @@ impl WalletDatabase {
         node_id: String,
         database: Arc<SenseiDatabase>,
         tokio_handle: tokio::runtime::Handle,
+        unexpected_script_alerts: Option<broadcast::Sender<SenseiEvent>>,
     ) -> Self {
         Self {
             node_id,
             database,
             tokio_handle,
+            unexpected_script_alerts,
+            tracks_sync: true,
+        }
+    }
+
+    // A copy for replaying old blocks. It records what it finds like the live one but leaves
+    // the last synced block alone, that cursor belongs to the live chain listener.
+    pub fn for_rescan(&self) -> Self {
+        Self {
+            tracks_sync: false,
+            ..self.clone()
+        }
+    }
+
+    // Our descriptor only derives p2wpkh scripts so funds sent to the legacy p2pkh script of
+    // one of our keys are never picked up as a utxo. They share the pubkey hash with the
+    // p2wpkh script, which is how we recognize them. The funds are recoverable with the same
+    // key, we only let the operator know. A block can hold thousands of p2pkh outputs, so the
+    // scripts of a whole block are looked up at once.
+    fn check_unexpected_scripts(&self, txdata: &TransactionData) {
+        let sender = match &self.unexpected_script_alerts {
+            Some(sender) => sender,
+            None => return,
+        };
+
+        let mut candidates: Vec<(Txid, usize, &TxOut, String)> = vec![];
+        for (_, tx) in txdata {
+            let txid = tx.txid();
+            for (vout, output) in tx.output.iter().enumerate() {
+                if !output.script_pubkey.is_p2pkh() {
+                    continue;
+                }
+                let pubkey_hash = WPubkeyHash::from_slice(&output.script_pubkey[3..23]).unwrap();
+                let segwit_script = Script::new_v0_p2wpkh(&pubkey_hash);
+                candidates.push((txid, vout, output, hex_str(&serialize(&segwit_script))));
+            }
+        }
+        if candidates.is_empty() {
+            return;
+        }
+
+        let scripts = candidates
+            .iter()
+            .map(|(_, _, _, script)| script.clone())
+            .collect::<Vec<String>>();
+        let ours = match self.find_script_pubkeys(scripts) {
+            Ok(ours) => ours,
+            Err(e) => {
+                println!("failed to check for unexpected deposit scripts: {}", e);
+                return;
+            }
+        };
+
+        for (txid, vout, output, _) in candidates
+            .into_iter()
+            .filter(|(_, _, _, script)| ours.contains(script))
+        {
+            println!(
+                "WARNING: received {} sats to a legacy p2pkh script of our key in {}:{}",
+                output.value, txid, vout
+            );
+            sender
+                .send(SenseiEvent::UnexpectedDepositScript {
+                    node_id: self.node_id.clone(),
+                    txid,
+                    vout: vout as u32,
+                    amount_sats: output.value,
+                    script_type: String::from("p2pkh"),
+                })
+                .unwrap_or_default();
         }
     }
 
@@ -599,6 +681,30 @@ impl WalletDatabase {
         })
     }
 
+    // Which of `scripts` were derived by this node's wallet.
+    pub fn find_script_pubkeys(&self, scripts: Vec<String>) -> Result<HashSet<String>, bdk::Error> {
+        tokio::task::block_in_place(move || {
+            self.tokio_handle.block_on(async move {
+                let mut found = HashSet::new();
+                // stays under sqlite's limit on bound parameters
+                for chunk in scripts.chunks(500) {
+                    let script_pubkeys = ScriptPubkey::find()
+                        .filter(script_pubkey::Column::NodeId.eq(self.node_id.clone()))
+                        .filter(script_pubkey::Column::Script.is_in(chunk.to_vec()))
+                        .all(self.database.get_connection())
+                        .await
+                        .map_err(|e| bdk::Error::Generic(e.to_string()))?;
+                    found.extend(
+                        script_pubkeys
+                            .into_iter()
+                            .map(|script_pubkey| script_pubkey.script),
+                    );
+                }
+                Ok(found)
+            })
+        })
+    }
+
     pub fn get_utxo(&self, txid: String, vout: i32) -> Result<Option<utxo::Model>, bdk::Error> {
         tokio::task::block_in_place(move || {
             self.tokio_handle.block_on(async move {
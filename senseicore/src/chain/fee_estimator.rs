@@ -1,13 +1,91 @@
 use lightning::chain::chaininterface::{ConfirmationTarget, FeeEstimator};
 use std::sync::Arc;
 
+pub const MIN_FEERATE: u32 = 253;
+
+// Converts sats per 1000 weight units into the sat/vB feerate we build our own wallet
+// transactions with. A vbyte is 4 weight units, so that's a division by 250. LDK's 253
+// sat/kw floor is 1 sat/vB plus some slack for its weight estimates rounding down, bdk
+// computes the real vsize so it maps to exactly 1 sat/vB. Estimates below the floor are
+// raised to it as well, anything under 1 sat/vB wouldn't be relayed.
+pub fn sat_per_vb(sat_per_kw: u32) -> f32 {
+    if sat_per_kw <= MIN_FEERATE {
+        return 1.0;
+    }
+    sat_per_kw as f32 / 250.0
+}
+
+// Rough number of blocks a transaction paying `sat_per_kw` needs to confirm, going by the
+// confirmation targets our estimates are made for (6, 18 and 144 blocks, see BitcoindClient).
+// Anything below the Background estimate could take arbitrarily long, so that's unknown.
+pub fn confirmation_eta_blocks(sat_per_kw: u32, fee_estimator: &dyn FeeEstimator) -> Option<u32> {
+    [
+        (ConfirmationTarget::HighPriority, 6),
+        (ConfirmationTarget::Normal, 18),
+        (ConfirmationTarget::Background, 144),
+    ]
+    .into_iter()
+    .find(|(target, _)| sat_per_kw >= fee_estimator.get_est_sat_per_1000_weight(*target))
+    .map(|(_, blocks)| blocks)
+}
+
+// The errors LDK force closes a channel with when a peer's feerate is out of our range.
+const PEER_FEERATE_REJECTIONS: [&str; 2] = [
+    "Peer's feerate much too low",
+    "Peer's feerate much too high",
+];
+
+pub fn is_peer_feerate_rejection(err: &str) -> bool {
+    PEER_FEERATE_REJECTIONS
+        .iter()
+        .any(|rejection| err.starts_with(rejection))
+}
+
 pub struct SenseiFeeEstimator {
     pub fee_estimator: Arc<dyn FeeEstimator + Send + Sync>,
+    // Widens the range of commitment feerates we accept from our peers, in percent.
+    //
+    // LDK force closes a channel when a peer proposes a feerate below our Background
+    // estimate or far above our HighPriority estimate. During fee spikes our estimates
+    // can move faster than our peers' and we end up closing perfectly healthy channels.
+    // A tolerance lowers Background and raises HighPriority by this percentage.
+    //
+    // The trade-off: a lower accepted feerate means the commitment transaction may not
+    // confirm in time if the peer goes away, and a higher one means more of our balance
+    // goes to fees if we ever have to force close. Only the channel manager's estimator
+    // gets a tolerance, the channel monitors build claim transactions from the plain
+    // estimates. The channel manager also starts cooperative close negotiation at
+    // Background, so that starts lower too.
+    pub feerate_tolerance_pct: u32,
+    // Caps the feerate LDK uses for commitment transactions on channels we fund, which
+    // bounds what a force close can cost us. LDK asks for a single estimate per channel
+    // manager so the cap applies to every channel of the node. Feerates proposed by the
+    // peer on channels they fund are not capped, rejecting those would force close. The
+    // channel manager also takes Normal as the most it pays for a cooperative close of a
+    // channel we funded, so the cap lowers that too. Claim transactions aren't capped.
+    pub max_commitment_feerate: Option<u32>,
 }
 
 impl FeeEstimator for SenseiFeeEstimator {
     fn get_est_sat_per_1000_weight(&self, confirmation_target: ConfirmationTarget) -> u32 {
-        self.fee_estimator
-            .get_est_sat_per_1000_weight(confirmation_target)
+        let estimate = self
+            .fee_estimator
+            .get_est_sat_per_1000_weight(confirmation_target);
+        let tolerance = self.feerate_tolerance_pct as u64;
+
+        match confirmation_target {
+            ConfirmationTarget::Background => {
+                let tolerated = estimate as u64 * 100 / (100 + tolerance);
+                std::cmp::max(tolerated as u32, MIN_FEERATE)
+            }
+            ConfirmationTarget::HighPriority => {
+                let tolerated = estimate as u64 * (100 + tolerance) / 100;
+                tolerated.try_into().unwrap_or(u32::MAX)
+            }
+            ConfirmationTarget::Normal => match self.max_commitment_feerate {
+                Some(max_feerate) => std::cmp::min(estimate, max_feerate),
+                None => estimate,
+            },
+        }
     }
 }
@@ -7,6 +7,7 @@ use crate::node::{ChainMonitor, ChannelManager};
 use bitcoin::BlockHeader;
 use lightning::chain::transaction::TransactionData;
 use lightning::chain::Listen;
+use tokio::sync::watch;
 
 use super::database::WalletDatabase;
 
@@ -14,6 +15,7 @@ type Listener = (Arc<ChainMonitor>, Arc<ChannelManager>, WalletDatabase);
 
 pub struct SenseiChainListener {
     listeners: Mutex<HashMap<String, Listener>>,
+    block_height: watch::Sender<u32>,
 }
 
 impl Default for SenseiChainListener {
@@ -24,11 +26,18 @@ impl Default for SenseiChainListener {
 
 impl SenseiChainListener {
     pub fn new() -> Self {
+        let (block_height, _receiver) = watch::channel(0);
         Self {
             listeners: Mutex::new(HashMap::new()),
+            block_height,
         }
     }
 
+    // Changes to the height of every block connected once all listeners have seen it.
+    pub fn subscribe(&self) -> watch::Receiver<u32> {
+        self.block_height.subscribe()
+    }
+
     fn get_key(&self, listener: &Listener) -> String {
         listener.1.get_our_node_id().to_string()
     }
@@ -57,6 +66,7 @@ impl Listen for SenseiChainListener {
             chain_monitor.filtered_block_connected(header, txdata, height);
             wallet_database.filtered_block_connected(header, txdata, height);
         }
+        self.block_height.send_replace(height);
     }
 
     fn block_disconnected(&self, header: &BlockHeader, height: u32) {
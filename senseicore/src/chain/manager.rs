@@ -1,6 +1,6 @@
 use std::{
     sync::{
-        atomic::{AtomicBool, Ordering},
+        atomic::{AtomicBool, AtomicI64, Ordering},
         Arc,
     },
     time::Duration,
@@ -11,17 +11,25 @@ use crate::{
     node::{ChainMonitor, ChannelManager},
 };
 use bitcoin::BlockHash;
+use entity::seconds_since_epoch;
 use lightning::chain::{
-    chaininterface::{BroadcasterInterface, FeeEstimator},
+    chaininterface::{BroadcasterInterface, ConfirmationTarget, FeeEstimator},
     BestBlock, Listen,
 };
 use lightning_block_sync::SpvClient;
 use lightning_block_sync::{init, poll, UnboundedCache};
-use lightning_block_sync::{poll::ValidatedBlockHeader, BlockSource};
+use lightning_block_sync::{poll::ValidatedBlockHeader, BlockSource, BlockSourceError};
 use std::ops::Deref;
-use tokio::{sync::Mutex, task::JoinHandle};
+use tokio::{
+    sync::{Mutex, Notify},
+    task::JoinHandle,
+};
 
-use super::{database::WalletDatabase, listener::SenseiChainListener};
+use super::{
+    broadcaster::{RelayRejections, TransactionSubmitter},
+    database::WalletDatabase,
+    listener::SenseiChainListener,
+};
 
 pub struct SenseiChainManager {
     config: SenseiConfig,
@@ -29,9 +37,15 @@ pub struct SenseiChainManager {
     pub block_source: Arc<dyn BlockSource + Send + Sync>,
     pub fee_estimator: Arc<dyn FeeEstimator + Send + Sync>,
     pub broadcaster: Arc<dyn BroadcasterInterface + Send + Sync>,
+    pub submitter: Arc<dyn TransactionSubmitter + Send + Sync>,
+    pub relay_rejections: Arc<RelayRejections>,
+    pub fee_estimates_known: Arc<AtomicBool>,
     poller_paused: Arc<AtomicBool>,
     poller_running: Arc<AtomicBool>,
     poller_handle: Mutex<Option<JoinHandle<()>>>,
+    block_lock: Arc<Mutex<()>>,
+    sync_requested: Arc<Notify>,
+    last_sync: Arc<AtomicI64>,
 }
 
 impl SenseiChainManager {
@@ -40,6 +54,9 @@ impl SenseiChainManager {
         block_source: Arc<dyn BlockSource + Send + Sync>,
         fee_estimator: Arc<dyn FeeEstimator + Send + Sync>,
         broadcaster: Arc<dyn BroadcasterInterface + Send + Sync>,
+        submitter: Arc<dyn TransactionSubmitter + Send + Sync>,
+        relay_rejections: Arc<RelayRejections>,
+        fee_estimates_known: Arc<AtomicBool>,
     ) -> Result<Self, crate::error::Error> {
         let listener = Arc::new(SenseiChainListener::new());
         let block_source_poller = block_source.clone();
@@ -48,6 +65,13 @@ impl SenseiChainManager {
         let poller_running = Arc::new(AtomicBool::new(true));
         let poller_paused_poller = poller_paused.clone();
         let poller_running_poller = poller_running.clone();
+        let block_lock = Arc::new(Mutex::new(()));
+        let block_lock_poller = block_lock.clone();
+        let sync_requested = Arc::new(Notify::new());
+        let sync_requested_poller = sync_requested.clone();
+        let last_sync = Arc::new(AtomicI64::new(0));
+        let last_sync_poller = last_sync.clone();
+        let sync_interval = Duration::from_secs(config.wallet_sync_interval_secs);
         let poller_handle = tokio::spawn(async move {
             let mut cache = UnboundedCache::new();
             let chain_tip = init::validate_best_block_header(block_source_poller.clone())
@@ -58,9 +82,14 @@ impl SenseiChainManager {
                 SpvClient::new(chain_tip, chain_poller, &mut cache, listener_poller);
             while poller_running_poller.load(Ordering::Relaxed) {
                 if !poller_paused_poller.load(Ordering::Relaxed) {
+                    let _block_lock = block_lock_poller.lock().await;
                     let _tip = spv_client.poll_best_tip().await.unwrap();
+                    last_sync_poller.store(seconds_since_epoch(), Ordering::Relaxed);
+                }
+                tokio::select! {
+                    _ = tokio::time::sleep(sync_interval) => {},
+                    _ = sync_requested_poller.notified() => {},
                 }
-                tokio::time::sleep(Duration::from_secs(1)).await;
             }
         });
 
@@ -72,7 +101,13 @@ impl SenseiChainManager {
             block_source,
             fee_estimator,
             broadcaster,
+            submitter,
+            relay_rejections,
+            fee_estimates_known,
             poller_handle: Mutex::new(Some(poller_handle)),
+            block_lock,
+            sync_requested,
+            last_sync,
         })
     }
 
@@ -83,6 +118,32 @@ impl SenseiChainManager {
         handle.await.unwrap_or_default();
     }
 
+    // wakes the poller up so it syncs right away instead of waiting out the interval
+    pub fn request_sync(&self) {
+        self.sync_requested.notify_one();
+    }
+
+    pub fn last_sync(&self) -> i64 {
+        self.last_sync.load(Ordering::Relaxed)
+    }
+
+    // The estimate our own wallet transactions are built with. It never goes below what
+    // `min_confirmation_target` would pay, quiet mempools can push Normal low enough that
+    // a tx sits around for days.
+    pub fn wallet_feerate(&self, confirmation_target: ConfirmationTarget) -> u32 {
+        let estimate = self
+            .fee_estimator
+            .get_est_sat_per_1000_weight(confirmation_target);
+        match self.config.min_confirmation_target {
+            Some(min_target) => std::cmp::max(
+                estimate,
+                self.fee_estimator
+                    .get_est_sat_per_1000_weight(min_target.into()),
+            ),
+            None => estimate,
+        }
+    }
+
     pub async fn synchronize_to_tip(
         &self,
         chain_listeners: Vec<(BlockHash, &(dyn Listen + Send + Sync))>,
@@ -127,6 +188,58 @@ impl SenseiChainManager {
         Ok(())
     }
 
+    // Replays every block from `from_height` up to the current tip to `listener`, walking
+    // back from the tip through the headers since the block source can't look blocks up by
+    // height. Returns how many blocks were replayed. The poller keeps running meanwhile, each
+    // replayed block waits for it to be done with the tip so the two never hand blocks to the
+    // same listener at once.
+    pub async fn rescan(
+        &self,
+        listener: &(dyn Listen + Send + Sync),
+        from_height: u32,
+    ) -> Result<u32, crate::error::Error> {
+        let block_source_error = |e: BlockSourceError| {
+            crate::error::Error::Generic(format!("failed to fetch block: {:?}", e))
+        };
+
+        let (tip_hash, tip_height) = self
+            .block_source
+            .get_best_block()
+            .await
+            .map_err(block_source_error)?;
+        let tip_height = tip_height.unwrap();
+
+        let mut blocks = vec![];
+        let mut hash = tip_hash;
+        let mut height = tip_height;
+        while height >= from_height {
+            blocks.push((hash, height));
+            if height == 0 {
+                break;
+            }
+            let header = self
+                .block_source
+                .get_header(&hash, Some(height))
+                .await
+                .map_err(block_source_error)?;
+            hash = header.header.prev_blockhash;
+            height -= 1;
+        }
+
+        let blocks_scanned = blocks.len() as u32;
+        for (hash, height) in blocks.into_iter().rev() {
+            let block = self
+                .block_source
+                .get_block(&hash)
+                .await
+                .map_err(block_source_error)?;
+            let _block_lock = self.block_lock.lock().await;
+            listener.block_connected(&block, height);
+        }
+
+        Ok(blocks_scanned)
+    }
+
     pub async fn get_best_block(&self) -> Result<BestBlock, crate::error::Error> {
         let (latest_blockhash, latest_height) = self.block_source.get_best_block().await.unwrap();
         Ok(BestBlock::new(latest_blockhash, latest_height.unwrap()))
@@ -1,21 +1,543 @@
-use crate::chain::broadcaster::SenseiBroadcaster;
+use crate::chain::broadcaster::{BroadcastContext, BroadcastPurpose, SenseiBroadcaster};
+use crate::chain::fee_estimator;
 use crate::chain::manager::SenseiChainManager;
+use crate::config::SenseiConfig;
+use crate::database::SenseiDatabase;
 use crate::error::Error;
-use crate::node::{connect_peer_if_necessary, parse_peer_addr, parse_pubkey, PeerManager};
-use crate::services::node::OpenChannelRequest;
+use crate::hex_utils;
+use crate::node::{
+    connect_peer_if_necessary, parse_peer_addr, parse_pubkey, ConnectRetry, PeerManager,
+    PendingBatchFunding,
+};
+use crate::services::node::{OpenChannelRequest, OpenChannelResult};
 use crate::{chain::database::WalletDatabase, events::SenseiEvent, node::ChannelManager};
+use bdk::database::BatchDatabase;
 use bdk::{FeeRate, SignOptions};
+use bitcoin::secp256k1::PublicKey;
+use bitcoin::util::psbt::PartiallySignedTransaction;
+use bitcoin::{OutPoint, Script, Transaction, Txid};
+use futures::stream::{self, StreamExt};
 use lightning::chain::chaininterface::ConfirmationTarget;
 use rand::{thread_rng, Rng};
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
-use tokio::sync::broadcast;
+use tokio::sync::broadcast::{self, error::TryRecvError};
 
-pub struct EventFilter<F>
+// Boxed so filters for different kinds of events can be waited on together.
+pub struct EventFilter {
+    pub f: Box<dyn Fn(&SenseiEvent) -> bool + Send>,
+}
+
+// Building and signing fail for different reasons, open_batch reports them differently.
+#[derive(Debug)]
+enum FundingTxError {
+    Build(bdk::Error),
+    Sign(String),
+    FeeTooHigh { fee_sats: u64, max_fee_sats: u64 },
+}
+
+impl From<bdk::Error> for FundingTxError {
+    fn from(e: bdk::Error) -> Self {
+        FundingTxError::Build(e)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FundingFees {
+    pub fee_sats: u64,
+    pub vsize: u64,
+    pub sat_per_vb: f32,
+}
+
+// The funding timeout covers the whole batch: every channel has to get to
+// FundingGenerationReady before it runs out, it doesn't restart for each of them. Peers
+// reached over Tor can easily need more than the default 30 seconds.
+// `unspendable_utxos` are left out of coin selection, e.g. to keep a consolidation output.
+// With `max_fee_sats` the batch is abandoned instead of funded when fees spiked since it was
+// requested. Connecting to peers is retried per `connect_retry` before the funding timeout
+// starts, so the backoff adds to how long a batch can take.
+#[derive(Clone, Debug)]
+pub struct BatchOpenOptions {
+    pub funding_utxos: Option<Vec<OutPoint>>,
+    pub unspendable_utxos: Vec<OutPoint>,
+    pub max_fee_sats: Option<u64>,
+    pub funding_timeout_ms: u64,
+    pub poll_interval_ms: u64,
+    pub connect_retry: ConnectRetry,
+}
+
+impl Default for BatchOpenOptions {
+    fn default() -> Self {
+        Self {
+            funding_utxos: None,
+            unspendable_utxos: vec![],
+            max_fee_sats: None,
+            funding_timeout_ms: 30000,
+            poll_interval_ms: 500,
+            connect_retry: ConnectRetry::default(),
+        }
+    }
+}
+
+pub struct BatchOpenResult {
+    pub request: OpenChannelRequest,
+    pub temp_channel_id: Option<[u8; 32]>,
+    pub counterparty_node_id: Option<PublicKey>,
+    pub funding_txid: Option<Txid>,
+    pub funding_fees: Option<FundingFees>,
+    pub status: Result<(), Error>,
+}
+
+impl BatchOpenResult {
+    fn new(request: OpenChannelRequest, result: Result<[u8; 32], Error>) -> Self {
+        let (temp_channel_id, status) = match result {
+            Ok(temp_channel_id) => (Some(temp_channel_id), Ok(())),
+            Err(e) => (None, Err(e)),
+        };
+        Self {
+            request,
+            temp_channel_id,
+            counterparty_node_id: None,
+            funding_txid: None,
+            funding_fees: None,
+            status,
+        }
+    }
+}
+
+impl From<BatchOpenResult> for OpenChannelResult {
+    fn from(result: BatchOpenResult) -> Self {
+        match result.status {
+            Ok(()) => OpenChannelResult {
+                error: false,
+                error_message: None,
+                temp_channel_id: result
+                    .temp_channel_id
+                    .map(|temp_channel_id| hex_utils::hex_str(&temp_channel_id)),
+                funding_txid: result.funding_txid.map(|txid| txid.to_string()),
+                funding_tx_fee_sats: result.funding_fees.as_ref().map(|fees| fees.fee_sats),
+                funding_tx_vsize: result.funding_fees.as_ref().map(|fees| fees.vsize),
+                funding_tx_sat_per_vb: result.funding_fees.as_ref().map(|fees| fees.sat_per_vb),
+            },
+            Err(e) => OpenChannelResult {
+                error: true,
+                error_message: Some(e.to_string()),
+                temp_channel_id: None,
+                funding_txid: None,
+                funding_tx_fee_sats: None,
+                funding_tx_vsize: None,
+                funding_tx_sat_per_vb: None,
+            },
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CloseChannelRequest {
+    pub channel_id: [u8; 32],
+    pub force: bool,
+    pub counterparty_node_id: PublicKey,
+}
+
+// Collects one matching event per filter until every filter matched or the timeout passed.
+// The receiver has to be subscribed before the events are triggered, anything sent before
+// that is never seen. Also returns how many events were skipped because the receiver fell
+// behind the channel's capacity, an unmatched filter may have missed its event then.
+pub async fn wait_for_events(
+    event_receiver: &mut broadcast::Receiver<SenseiEvent>,
+    filters: Vec<EventFilter>,
+    timeout_ms: u64,
+    interval_ms: u64,
+) -> (Vec<SenseiEvent>, u64) {
+    wait_for_events_with(
+        event_receiver,
+        filters,
+        timeout_ms,
+        interval_ms,
+        |_event| {},
+    )
+    .await
+}
+
+const MIN_POLL_INTERVAL_MS: u64 = 10;
+
+// Runs `initiate` for every request with at most `concurrency` of them in flight, 0 is
+// raised to 1. `buffered` keeps the results in request order, whatever order they finish in.
+pub async fn initiate_concurrently<F, Fut>(
+    requests: Vec<OpenChannelRequest>,
+    concurrency: usize,
+    initiate: F,
+) -> Vec<(OpenChannelRequest, Result<[u8; 32], Error>)>
 where
-    F: Fn(SenseiEvent) -> bool,
+    F: FnMut(OpenChannelRequest) -> Fut,
+    Fut: Future<Output = (OpenChannelRequest, Result<[u8; 32], Error>)>,
 {
-    pub f: F,
+    stream::iter(requests)
+        .map(initiate)
+        .buffered(std::cmp::max(concurrency, 1))
+        .collect::<Vec<_>>()
+        .await
+}
+
+// A channel either gets to FundingGenerationReady or is closed before it, when the peer
+// disconnects or rejects it.
+pub fn funding_filter(filter_node_id: String, request_user_channel_id: u64) -> EventFilter {
+    EventFilter {
+        f: Box::new(move |event| match event {
+            SenseiEvent::FundingGenerationReady {
+                node_id,
+                user_channel_id,
+                ..
+            }
+            | SenseiEvent::ChannelClosed {
+                node_id,
+                user_channel_id,
+                ..
+            } => *node_id == filter_node_id && *user_channel_id == request_user_channel_id,
+            _ => false,
+        }),
+    }
+}
+
+// Like wait_for_events, calling `on_match` with each matching event as soon as it arrives.
+// Intervals below 10ms are raised to it, 0 would never get any closer to the timeout.
+pub async fn wait_for_events_with(
+    event_receiver: &mut broadcast::Receiver<SenseiEvent>,
+    mut filters: Vec<EventFilter>,
+    timeout_ms: u64,
+    interval_ms: u64,
+    mut on_match: impl FnMut(&SenseiEvent),
+) -> (Vec<SenseiEvent>, u64) {
+    let interval_ms = std::cmp::max(interval_ms, MIN_POLL_INTERVAL_MS);
+    let mut events = vec![];
+    let mut skipped = 0;
+    let mut current_ms = 0;
+    while current_ms < timeout_ms {
+        loop {
+            let event = match event_receiver.try_recv() {
+                Ok(event) => event,
+                Err(TryRecvError::Lagged(lagged)) => {
+                    println!("event receiver lagged, skipped {} events", lagged);
+                    skipped += lagged;
+                    continue;
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Closed) => return (events, skipped),
+            };
+            let filter_index = filters
+                .iter()
+                .enumerate()
+                .find(|(_index, filter)| (filter.f)(&event))
+                .map(|(index, _filter)| index);
+
+            if let Some(index) = filter_index {
+                on_match(&event);
+                events.push(event);
+                filters.swap_remove(index);
+            }
+
+            if filters.is_empty() {
+                return (events, skipped);
+            }
+        }
+        tokio::time::sleep(Duration::from_millis(interval_ms)).await;
+        current_ms += interval_ms;
+    }
+    (events, skipped)
+}
+
+// Rough sizes for estimating a funding tx before it's built: p2wpkh inputs, the p2wsh
+// channel outputs and one change output.
+const FUNDING_TX_OVERHEAD_VBYTES: u64 = 11;
+const FUNDING_INPUT_VBYTES: u64 = 68;
+const FUNDING_OUTPUT_VBYTES: u64 = 43;
+const CHANGE_OUTPUT_VBYTES: u64 = 31;
+
+pub struct FundsEstimate {
+    pub batch_sats: u64,
+    pub fee_sats: u64,
+    pub balance_sats: u64,
+}
+
+impl FundsEstimate {
+    pub fn check(&self) -> Result<(), Error> {
+        if self.balance_sats == 0 {
+            return Err(Error::NoSpendableFunds(self.balance_sats));
+        }
+        let needed_sats = self.batch_sats.saturating_add(self.fee_sats);
+        if self.balance_sats < needed_sats {
+            return Err(Error::InsufficientFunds(needed_sats, self.balance_sats));
+        }
+        Ok(())
+    }
+}
+
+// Estimates what funding a batch from the wallet would take. The estimate assumes coin
+// selection spends the largest utxos first, the real fee is only known once the funding
+// tx is built. `excluded_utxos` can't be selected, they don't count towards the balance.
+pub fn estimate_wallet_funds<D: BatchDatabase>(
+    wallet: &bdk::Wallet<D>,
+    requests: &[OpenChannelRequest],
+    sat_per_vb: f32,
+    excluded_utxos: &[OutPoint],
+) -> Result<FundsEstimate, Error> {
+    // an amount that overflows can never be funded, saturating keeps it that way
+    let batch_sats = requests.iter().fold(0u64, |total, request| {
+        total.saturating_add(request.amount_sats)
+    });
+
+    let mut utxo_sats = wallet
+        .list_unspent()?
+        .into_iter()
+        .filter(|utxo| !excluded_utxos.contains(&utxo.outpoint))
+        .map(|utxo| utxo.txout.value)
+        .collect::<Vec<_>>();
+    let balance_sats = utxo_sats.iter().fold(0u64, |total, amount_sats| {
+        total.saturating_add(*amount_sats)
+    });
+    utxo_sats.sort_unstable_by(|a, b| b.cmp(a));
+    let mut selected_sats: u64 = 0;
+    let mut num_inputs = 0;
+    for amount_sats in utxo_sats {
+        if selected_sats >= batch_sats {
+            break;
+        }
+        selected_sats = selected_sats.saturating_add(amount_sats);
+        num_inputs += 1;
+    }
+
+    let vbytes = FUNDING_TX_OVERHEAD_VBYTES
+        + num_inputs * FUNDING_INPUT_VBYTES
+        + requests.len() as u64 * FUNDING_OUTPUT_VBYTES
+        + CHANGE_OUTPUT_VBYTES;
+    Ok(FundsEstimate {
+        batch_sats,
+        fee_sats: (vbytes as f32 * sat_per_vb).ceil() as u64,
+        balance_sats,
+    })
+}
+
+// Fails when the wallet can't cover the channel amounts plus an estimate of the fee, before
+// any channel is created that would have to be abandoned.
+pub fn check_wallet_funds<D: BatchDatabase>(
+    wallet: &bdk::Wallet<D>,
+    requests: &[OpenChannelRequest],
+    sat_per_vb: f32,
+    excluded_utxos: &[OutPoint],
+) -> Result<(), Error> {
+    estimate_wallet_funds(wallet, requests, sat_per_vb, excluded_utxos)?.check()
+}
+
+// Opens that passed the max_pending_channels check and are being initiated, by custom id.
+// Shared by a node's openers, so opens running at the same time count each other before
+// their channels show up in the ChannelManager.
+#[derive(Clone, Default)]
+pub struct PendingOpens {
+    custom_ids: Arc<Mutex<HashSet<u64>>>,
+}
+
+impl PendingOpens {
+    // A batch is rejected as a whole when it would take us over max_pending_channels.
+    // `pending_channels` lists the user channel ids of our pending channels, it's called
+    // under the lock so a channel is never missed between the ChannelManager and here.
+    pub fn reserve(
+        &self,
+        max_pending_channels: Option<usize>,
+        custom_ids: &[u64],
+        pending_channels: impl FnOnce() -> Vec<u64>,
+    ) -> Result<PendingOpensReservation, Error> {
+        let mut reserved = self.custom_ids.lock().unwrap();
+        if let Some(max) = max_pending_channels {
+            if Self::num_pending(&reserved, pending_channels()) + custom_ids.len() > max {
+                return Err(Error::TooManyPendingChannels(max));
+            }
+        }
+        reserved.extend(custom_ids);
+        Ok(PendingOpensReservation {
+            pending_opens: self.clone(),
+            custom_ids: custom_ids.to_vec(),
+        })
+    }
+
+    // Checks like reserve without reserving anything.
+    pub fn check(
+        &self,
+        max_pending_channels: Option<usize>,
+        new_channels: usize,
+        pending_channels: impl FnOnce() -> Vec<u64>,
+    ) -> Result<(), Error> {
+        let reserved = self.custom_ids.lock().unwrap();
+        match max_pending_channels {
+            Some(max) if Self::num_pending(&reserved, pending_channels()) + new_channels > max => {
+                Err(Error::TooManyPendingChannels(max))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // an initiated channel is in the ChannelManager and reserved, it's only counted once
+    fn num_pending(reserved: &HashSet<u64>, pending_channels: Vec<u64>) -> usize {
+        pending_channels
+            .into_iter()
+            .filter(|user_channel_id| !reserved.contains(user_channel_id))
+            .count()
+            + reserved.len()
+    }
+}
+
+// Frees the reserved slots when dropped, by then the channels are in the ChannelManager or
+// failed to be created.
+pub struct PendingOpensReservation {
+    pending_opens: PendingOpens,
+    custom_ids: Vec<u64>,
+}
+
+impl Drop for PendingOpensReservation {
+    fn drop(&mut self) {
+        let mut reserved = self.pending_opens.custom_ids.lock().unwrap();
+        for custom_id in &self.custom_ids {
+            reserved.remove(custom_id);
+        }
+    }
+}
+
+// Channels we opened that aren't ready yet, their funding is committed but not confirmed.
+pub(crate) fn num_pending_channels(channel_manager: &ChannelManager) -> usize {
+    pending_user_channel_ids(channel_manager).len()
+}
+
+pub(crate) fn pending_user_channel_ids(channel_manager: &ChannelManager) -> Vec<u64> {
+    channel_manager
+        .list_channels()
+        .iter()
+        .filter(|channel| channel.is_outbound && !channel.is_channel_ready)
+        .map(|channel| channel.user_channel_id)
+        .collect()
+}
+
+// Inputs of the funding txs we built but haven't broadcast, the batches waiting on
+// finalize_batch_funding and the txs waiting on their peers' signatures. bdk doesn't know
+// they're spent yet, so every tx we build has to leave them out. They're reserved while the
+// wallet is locked, reading them under the same lock sees every reservation made before.
+pub fn reserved_funding_inputs(
+    broadcaster: &SenseiBroadcaster,
+    pending_batch_fundings: &Mutex<HashMap<Txid, PendingBatchFunding>>,
+) -> Vec<OutPoint> {
+    let mut inputs = broadcaster.debounced_inputs();
+    inputs.extend(
+        pending_batch_fundings
+            .lock()
+            .unwrap()
+            .values()
+            .flat_map(|pending| pending.inputs.clone()),
+    );
+    inputs
+}
+
+fn fail_requests(
+    requests: Vec<OpenChannelRequest>,
+    error: impl Fn() -> Error,
+) -> Vec<BatchOpenResult> {
+    requests
+        .into_iter()
+        .map(|request| BatchOpenResult::new(request, Err(error())))
+        .collect()
+}
+
+// The outpoints a funding psbt spends, with their amounts when the psbt carries them.
+fn funding_inputs(psbt: &PartiallySignedTransaction) -> Vec<(OutPoint, Option<u64>)> {
+    psbt.unsigned_tx
+        .input
+        .iter()
+        .zip(psbt.inputs.iter())
+        .map(|(txin, input)| {
+            let amount_sats = input
+                .witness_utxo
+                .as_ref()
+                .map(|txout| txout.value)
+                .or_else(|| {
+                    input.non_witness_utxo.as_ref().and_then(|tx| {
+                        tx.output
+                            .get(txin.previous_output.vout as usize)
+                            .map(|txout| txout.value)
+                    })
+                });
+            (txin.previous_output, amount_sats)
+        })
+        .collect()
+}
+
+fn funding_fees(funding_tx: &Transaction, fee_sats: Option<u64>) -> Option<FundingFees> {
+    fee_sats.map(|fee_sats| {
+        let vsize = ((funding_tx.weight() + 3) / 4) as u64;
+        FundingFees {
+            fee_sats,
+            vsize,
+            sat_per_vb: fee_sats as f32 / vsize as f32,
+        }
+    })
+}
+
+fn funding_tx_error(e: &FundingTxError, funding_utxos: Option<&[OutPoint]>) -> Error {
+    match (e, funding_utxos) {
+        (FundingTxError::Sign(reason), _) => Error::SigningFailed(reason.clone()),
+        (
+            FundingTxError::FeeTooHigh {
+                fee_sats,
+                max_fee_sats,
+            },
+            _,
+        ) => Error::FeeExceedsMaximum(*fee_sats, *max_fee_sats),
+        (FundingTxError::Build(bdk::Error::InsufficientFunds { available: 0, .. }), None) => {
+            Error::NoSpendableFunds(0)
+        }
+        // with funding utxos, `available` is what they add up to
+        (FundingTxError::Build(bdk::Error::InsufficientFunds { needed, available }), _) => {
+            Error::InsufficientFunds(*needed, *available)
+        }
+        (FundingTxError::Build(e), Some(_)) => {
+            Error::InvalidFundingUtxos(format!("failed to build funding tx: {}", e))
+        }
+        (FundingTxError::Build(e), None) => {
+            Error::Generic(format!("failed to build funding tx: {}", e))
+        }
+    }
+}
+
+// Who to fund the channel with `user_channel_id` with, or why it never got there. Without
+// any event the peer just didn't answer in time, unless we skipped events and can't tell.
+fn funding_outcome(
+    events: &[SenseiEvent],
+    skipped: u64,
+    request_user_channel_id: u64,
+) -> Result<PublicKey, Error> {
+    let event = events.iter().find(|event| match event {
+        SenseiEvent::FundingGenerationReady {
+            user_channel_id, ..
+        }
+        | SenseiEvent::ChannelClosed {
+            user_channel_id, ..
+        } => *user_channel_id == request_user_channel_id,
+        _ => false,
+    });
+
+    match event {
+        Some(SenseiEvent::FundingGenerationReady {
+            counterparty_node_id,
+            ..
+        }) => Ok(*counterparty_node_id),
+        Some(SenseiEvent::ChannelClosed {
+            peer_disconnected: true,
+            ..
+        }) => Err(Error::PeerDisconnectedDuringFunding),
+        Some(SenseiEvent::ChannelClosed { reason, .. }) => {
+            Err(Error::ChannelRejected(reason.clone()))
+        }
+        _ if skipped > 0 => Err(Error::EventsLagged(skipped)),
+        _ => Err(Error::FundingGenerationNeverHappened),
+    }
 }
 
 pub struct ChannelOpener {
@@ -23,67 +545,257 @@ pub struct ChannelOpener {
     channel_manager: Arc<ChannelManager>,
     wallet: Arc<Mutex<bdk::Wallet<WalletDatabase>>>,
     chain_manager: Arc<SenseiChainManager>,
+    event_sender: broadcast::Sender<SenseiEvent>,
     event_receiver: broadcast::Receiver<SenseiEvent>,
     broadcaster: Arc<SenseiBroadcaster>,
     peer_manager: Arc<PeerManager>,
+    database: Arc<SenseiDatabase>,
+    config: Arc<SenseiConfig>,
+    change_script: Option<Script>,
+    pending_opens: PendingOpens,
+    pending_batch_fundings: Arc<Mutex<HashMap<Txid, PendingBatchFunding>>>,
 }
 
 impl ChannelOpener {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         node_id: String,
         channel_manager: Arc<ChannelManager>,
         chain_manager: Arc<SenseiChainManager>,
         wallet: Arc<Mutex<bdk::Wallet<WalletDatabase>>>,
-        event_receiver: broadcast::Receiver<SenseiEvent>,
+        event_sender: broadcast::Sender<SenseiEvent>,
         broadcaster: Arc<SenseiBroadcaster>,
         peer_manager: Arc<PeerManager>,
+        database: Arc<SenseiDatabase>,
+        config: Arc<SenseiConfig>,
+        change_script: Option<Script>,
+        pending_opens: PendingOpens,
+        pending_batch_fundings: Arc<Mutex<HashMap<Txid, PendingBatchFunding>>>,
     ) -> Self {
+        // subscribed right away so no FundingGenerationReady for our channels can be missed
+        let event_receiver = event_sender.subscribe();
         Self {
             node_id,
             channel_manager,
             chain_manager,
             wallet,
+            event_sender,
             event_receiver,
             broadcaster,
             peer_manager,
+            database,
+            config,
+            change_script,
+            pending_opens,
+            pending_batch_fundings,
         }
     }
 
-    async fn wait_for_events<F: Fn(SenseiEvent) -> bool>(
+    pub async fn open_batch(
         &mut self,
-        mut filters: Vec<EventFilter<F>>,
-        timeout_ms: u64,
-        interval_ms: u64,
-    ) -> Vec<SenseiEvent> {
-        let mut events = vec![];
-        let mut current_ms = 0;
-        while current_ms < timeout_ms {
-            while let Ok(event) = self.event_receiver.try_recv() {
-                let filter_index = filters
-                    .iter()
-                    .enumerate()
-                    .find(|(_index, filter)| (filter.f)(event.clone()))
-                    .map(|(index, _filter)| index);
-
-                if let Some(index) = filter_index {
-                    events.push(event);
-                    filters.swap_remove(index);
-                }
+        requests: Vec<OpenChannelRequest>,
+        options: BatchOpenOptions,
+    ) -> Vec<BatchOpenResult> {
+        let (mut results, events) = self.initiate_batch(requests, &options).await;
+        if !results.iter().any(|result| result.status.is_ok()) {
+            return results;
+        }
 
-                if filters.is_empty() {
-                    return events;
+        // build a tx with these events and requests
+        let (funding_tx, funding_fees, funding_inputs) =
+            match self.build_funding_tx(&events, &options) {
+                Ok(funding) => funding,
+                Err(e) => {
+                    self.abandon_batch(&mut results, || {
+                        funding_tx_error(&e, options.funding_utxos.as_deref())
+                    });
+                    return results;
                 }
+            };
+
+        self.fund_batch(&mut results, funding_tx, funding_fees, funding_inputs)
+            .await;
+
+        results
+    }
+
+    // Everything open_batch does up to signing. The channels wait on their FundingGenerationReady
+    // until finalize_batch_funding hands LDK the signed tx, or until the peers give up on them.
+    // The batch and its inputs are kept in `pending_batch_fundings` until then.
+    pub async fn build_batch_funding(
+        &mut self,
+        requests: Vec<OpenChannelRequest>,
+        options: BatchOpenOptions,
+    ) -> Result<(PartiallySignedTransaction, Vec<BatchOpenResult>), Error> {
+        let (mut results, events) = self.initiate_batch(requests, &options).await;
+        if !results.iter().any(|result| result.status.is_ok()) {
+            let reasons = results
+                .iter()
+                .filter_map(|result| result.status.as_ref().err())
+                .map(|e| e.to_string())
+                .collect::<Vec<String>>();
+            return Err(Error::Generic(format!(
+                "no channel in the batch got to funding: {}",
+                reasons.join(", ")
+            )));
+        }
+
+        let wallet = self.wallet.lock().unwrap();
+        let psbt = match self.build_funding_psbt(&wallet, &events, &options) {
+            Ok((psbt, _fee_sats)) => psbt,
+            Err(e) => {
+                drop(wallet);
+                let error = funding_tx_error(&e, options.funding_utxos.as_deref());
+                self.abandon_batch(&mut results, || {
+                    funding_tx_error(&e, options.funding_utxos.as_deref())
+                });
+                return Err(error);
             }
-            tokio::time::sleep(Duration::from_millis(interval_ms)).await;
-            current_ms += interval_ms;
+        };
+
+        for result in results.iter_mut().filter(|result| result.status.is_ok()) {
+            result.funding_txid = Some(psbt.unsigned_tx.txid());
         }
-        events
+
+        let pending = results
+            .iter()
+            .filter(|result| result.status.is_ok())
+            .map(|result| BatchOpenResult {
+                request: result.request.clone(),
+                temp_channel_id: result.temp_channel_id,
+                counterparty_node_id: result.counterparty_node_id,
+                funding_txid: result.funding_txid,
+                funding_fees: None,
+                status: Ok(()),
+            })
+            .collect::<Vec<_>>();
+        let inputs = psbt
+            .unsigned_tx
+            .input
+            .iter()
+            .map(|input| input.previous_output)
+            .collect();
+        self.pending_batch_fundings.lock().unwrap().insert(
+            psbt.unsigned_tx.txid(),
+            PendingBatchFunding {
+                inputs,
+                results: pending,
+            },
+        );
+        drop(wallet);
+
+        Ok((psbt, results))
     }
 
-    pub async fn open_batch(
+    // Takes the psbt from build_batch_funding back once it's signed and funds the channels of
+    // its batch that are still pending. A psbt that isn't signed yet can be sent again, its
+    // batch stays pending until then.
+    pub async fn finalize_batch_funding(
+        &mut self,
+        mut psbt: PartiallySignedTransaction,
+    ) -> Result<Vec<BatchOpenResult>, Error> {
+        let txid = psbt.unsigned_tx.txid();
+        if !self
+            .pending_batch_fundings
+            .lock()
+            .unwrap()
+            .contains_key(&txid)
+        {
+            return Err(Error::BatchFundingNotFound(txid.to_string()));
+        }
+
+        self.finalize_funding_psbt(&mut psbt)?;
+
+        // the psbt pays into every pending channel, broadcasting it with one of them cancelled
+        // or closed would lock that output in a 2-of-2 nobody has a commitment for
+        let waiting = self
+            .channel_manager
+            .list_channels()
+            .into_iter()
+            .map(|channel| channel.channel_id)
+            .collect::<Vec<[u8; 32]>>();
+
+        // its inputs go from the pending batch straight to the debounce, under the wallet lock
+        // nothing can select them in between
+        let mut pending = {
+            let _wallet = self.wallet.lock().unwrap();
+            let pending = self
+                .pending_batch_fundings
+                .lock()
+                .unwrap()
+                .remove(&txid)
+                .ok_or_else(|| Error::BatchFundingNotFound(txid.to_string()))?;
+            let unfundable = pending
+                .results
+                .iter()
+                .filter(|result| result.status.is_ok())
+                .any(|result| {
+                    result
+                        .temp_channel_id
+                        .map_or(true, |temp_channel_id| !waiting.contains(&temp_channel_id))
+                });
+            let mut results = pending.results;
+            if unfundable {
+                self.abandon_batch(&mut results, || Error::OpenCancelled);
+                return Err(Error::Generic(String::from(
+                    "a channel in this batch was cancelled or closed, build the funding psbt again",
+                )));
+            }
+            let channels_to_open = results
+                .iter()
+                .filter(|result| result.status.is_ok())
+                .count();
+            self.broadcaster
+                .set_debounce(txid, channels_to_open, pending.inputs);
+            results
+        };
+
+        let funding_inputs = funding_inputs(&psbt);
+        let input_sats = funding_inputs
+            .iter()
+            .map(|(_outpoint, amount_sats)| *amount_sats)
+            .sum::<Option<u64>>();
+        let funding_tx = psbt.extract_tx();
+        let output_sats = funding_tx
+            .output
+            .iter()
+            .map(|txout| txout.value)
+            .sum::<u64>();
+        let fee_sats = input_sats.and_then(|input_sats| input_sats.checked_sub(output_sats));
+        let funding_fees = funding_fees(&funding_tx, fee_sats);
+
+        self.fund_batch(&mut pending, funding_tx, funding_fees, funding_inputs)
+            .await;
+
+        Ok(pending)
+    }
+
+    pub fn finalize_funding_psbt(
+        &self,
+        psbt: &mut PartiallySignedTransaction,
+    ) -> Result<(), Error> {
+        let finalized = self
+            .wallet
+            .lock()
+            .unwrap()
+            .finalize_psbt(psbt, SignOptions::default())
+            .map_err(|e| Error::SigningFailed(e.to_string()))?;
+        if !finalized {
+            return Err(Error::SigningFailed(String::from(
+                "not every funding input is signed",
+            )));
+        }
+        Ok(())
+    }
+
+    // Initiates every channel and waits for them to get to FundingGenerationReady. Returns a
+    // result per request, ok for the ones that are ready to be funded, and the events to fund
+    // them with.
+    async fn initiate_batch(
         &mut self,
         requests: Vec<OpenChannelRequest>,
-    ) -> Vec<(OpenChannelRequest, Result<[u8; 32], Error>)> {
+        options: &BatchOpenOptions,
+    ) -> (Vec<BatchOpenResult>, Vec<SenseiEvent>) {
         let requests = requests
             .into_iter()
             .map(|request| OpenChannelRequest {
@@ -95,83 +807,523 @@ impl ChannelOpener {
                 ..request
             })
             .collect::<Vec<_>>();
-        let mut requests_with_results = vec![];
-        let mut filters = vec![];
-
-        for request in requests {
-            let result = self.initiate_channel_open(&request).await;
 
-            if result.is_ok() {
-                let filter_node_id = self.node_id.clone();
-                let request_user_channel_id = request.custom_id.unwrap();
-                let filter = move |event| {
-                    if let SenseiEvent::FundingGenerationReady {
-                        node_id,
-                        user_channel_id,
-                        ..
-                    } = event
-                    {
-                        if *node_id == filter_node_id && user_channel_id == request_user_channel_id
-                        {
-                            return true;
-                        }
+        // held until every channel of the batch is in the ChannelManager or failed
+        let _reservation = match self.reserve_pending(&requests) {
+            Ok(reservation) => reservation,
+            Err(e) => {
+                let message = e.to_string();
+                let results = match e {
+                    Error::TooManyPendingChannels(max) => {
+                        fail_requests(requests, || Error::TooManyPendingChannels(max))
                     }
-                    false
+                    _ => fail_requests(requests, || Error::Generic(message.clone())),
                 };
+                return (results, vec![]);
+            }
+        };
 
-                filters.push(EventFilter { f: filter })
+        if let Some(funding_utxos) = &options.funding_utxos {
+            match self.check_funding_utxos(funding_utxos, options, &requests) {
+                Err(Error::InsufficientFunds(needed_sats, funding_sats)) => {
+                    let results = fail_requests(requests, || {
+                        Error::InsufficientFunds(needed_sats, funding_sats)
+                    });
+                    return (results, vec![]);
+                }
+                Err(Error::InvalidFundingUtxos(message)) => {
+                    let results =
+                        fail_requests(requests, || Error::InvalidFundingUtxos(message.clone()));
+                    return (results, vec![]);
+                }
+                Err(e) => {
+                    let message = e.to_string();
+                    let results =
+                        fail_requests(requests, || Error::InvalidFundingUtxos(message.clone()));
+                    return (results, vec![]);
+                }
+                Ok(()) => {}
+            }
+        } else {
+            let funds_check = check_wallet_funds(
+                &self.wallet.lock().unwrap(),
+                &requests,
+                self.funding_sat_per_vb(),
+                &options.unspendable_utxos,
+            );
+            match funds_check {
+                Err(Error::NoSpendableFunds(balance_sats)) => {
+                    let results = fail_requests(requests, || Error::NoSpendableFunds(balance_sats));
+                    return (results, vec![]);
+                }
+                Err(Error::InsufficientFunds(needed_sats, balance_sats)) => {
+                    let results = fail_requests(requests, || {
+                        Error::InsufficientFunds(needed_sats, balance_sats)
+                    });
+                    return (results, vec![]);
+                }
+                Err(e) => {
+                    let message = e.to_string();
+                    let results = fail_requests(requests, || {
+                        Error::Generic(format!("failed to check wallet funds: {}", message))
+                    });
+                    return (results, vec![]);
+                }
+                Ok(()) => {}
             }
-            requests_with_results.push((request, result));
         }
 
-        // TODO: is this appropriate timeout? maybe should accept as param
-        let events = self.wait_for_events(filters, 30000, 500).await;
+        let mut results = vec![];
+        let mut filters = vec![];
 
-        // set error state for requests we didn't get an event for
-        let requests_with_results = requests_with_results
-            .drain(..)
-            .map(|(request, result)| {
+        // the event receiver was subscribed when the opener was created, so initiating
+        // channels concurrently can't lose a FundingGenerationReady that arrives before
+        // its filter is registered below.
+        let opener = &*self;
+        let initiated = initiate_concurrently(
+            requests,
+            self.config.channel_open_concurrency,
+            |request| async move {
+                let result = opener
+                    .initiate_channel_open(&request, options.connect_retry)
+                    .await;
                 if result.is_ok() {
-                    let mut channel_counterparty_node_id = None;
-                    let event_opt = events.iter().find(|event| {
-                        if let SenseiEvent::FundingGenerationReady {
-                            user_channel_id,
-                            counterparty_node_id,
-                            ..
-                        } = event
-                        {
-                            if *user_channel_id == request.custom_id.unwrap() {
-                                channel_counterparty_node_id = Some(*counterparty_node_id);
-                                return true;
-                            }
-                        }
-                        false
+                    opener.send_event(SenseiEvent::BatchChannelInitiated {
+                        node_id: opener.node_id.clone(),
+                        custom_id: request.custom_id.unwrap(),
                     });
+                }
+                (request, result)
+            },
+        )
+        .await;
 
-                    match event_opt {
-                        None => (request, Err(Error::FundingGenerationNeverHappened), None),
-                        Some(_) => (request, result, channel_counterparty_node_id),
-                    }
-                } else {
-                    (request, result, None)
+        for (request, result) in initiated {
+            if result.is_ok() {
+                filters.push(self.funding_filter(request.custom_id.unwrap()));
+            }
+            results.push(BatchOpenResult::new(request, result));
+        }
+
+        let event_sender = self.event_sender.clone();
+        let node_id = self.node_id.clone();
+        let (events, skipped) = wait_for_events_with(
+            &mut self.event_receiver,
+            filters,
+            options.funding_timeout_ms,
+            options.poll_interval_ms,
+            |event| {
+                if let SenseiEvent::FundingGenerationReady {
+                    user_channel_id, ..
+                } = event
+                {
+                    event_sender
+                        .send(SenseiEvent::BatchChannelFundingReady {
+                            node_id: node_id.clone(),
+                            custom_id: *user_channel_id,
+                        })
+                        .unwrap_or_default();
                 }
-            })
+            },
+        )
+        .await;
+
+        // set error state for requests that were closed or we didn't get an event for
+        for result in results.iter_mut().filter(|result| result.status.is_ok()) {
+            match funding_outcome(&events, skipped, result.request.custom_id.unwrap()) {
+                Ok(counterparty_node_id) => {
+                    result.counterparty_node_id = Some(counterparty_node_id);
+                }
+                Err(e) => result.status = Err(e),
+            }
+        }
+
+        (results, events)
+    }
+
+    fn reserve_pending(
+        &self,
+        requests: &[OpenChannelRequest],
+    ) -> Result<PendingOpensReservation, Error> {
+        let custom_ids = requests
+            .iter()
+            .map(|request| request.custom_id.unwrap())
             .collect::<Vec<_>>();
+        self.pending_opens
+            .reserve(self.config.max_pending_channels, &custom_ids, || {
+                pending_user_channel_ids(&self.channel_manager)
+            })
+    }
 
-        // build a tx with these events and requests
+    fn funding_sat_per_vb(&self) -> f32 {
+        fee_estimator::sat_per_vb(
+            self.chain_manager
+                .wallet_feerate(ConfirmationTarget::Normal),
+        )
+    }
+
+    fn send_event(&self, event: SenseiEvent) {
+        self.event_sender.send(event).unwrap_or_default();
+    }
+
+    // nothing will ever fund these, don't leave them waiting on us
+    fn abandon_batch(&self, results: &mut [BatchOpenResult], error: impl Fn() -> Error) {
+        for result in results.iter_mut().filter(|result| result.status.is_ok()) {
+            let _res = self.channel_manager.force_close_channel(
+                &result.temp_channel_id.unwrap(),
+                &result.counterparty_node_id.unwrap(),
+            );
+            result.status = Err(error());
+        }
+    }
+
+    async fn fund_batch(
+        &self,
+        results: &mut [BatchOpenResult],
+        funding_tx: Transaction,
+        funding_fees: Option<FundingFees>,
+        funding_inputs: Vec<(OutPoint, Option<u64>)>,
+    ) {
+        let funded_peers = results
+            .iter()
+            .filter(|result| result.status.is_ok())
+            .map(|result| result.request.counterparty_pubkey.clone())
+            .collect::<Vec<String>>();
+        self.broadcaster.set_context(
+            funding_tx.txid(),
+            BroadcastContext {
+                purpose: BroadcastPurpose::Funding,
+                context: Some(format!("channel open with {}", funded_peers.join(", "))),
+                feerate_sat_per_vb: funding_fees.as_ref().map(|fees| fees.sat_per_vb as f64),
+            },
+        );
+
+        for result in results.iter_mut().filter(|result| result.status.is_ok()) {
+            match self.channel_manager.funding_transaction_generated(
+                &result.temp_channel_id.unwrap(),
+                &result.counterparty_node_id.unwrap(),
+                funding_tx.clone(),
+            ) {
+                Ok(()) => {
+                    result.funding_txid = Some(funding_tx.txid());
+                    result.funding_fees = funding_fees.clone();
+                }
+                Err(e) => result.status = Err(Error::LdkApi(e)),
+            }
+        }
+
+        let funded_channels = results
+            .iter()
+            .filter(|result| result.status.is_ok())
+            .count();
+        if funded_channels == 0 {
+            self.broadcaster.release_debounce(&funding_tx.txid());
+        } else {
+            self.send_event(SenseiEvent::BatchFundingBroadcast {
+                node_id: self.node_id.clone(),
+                txid: funding_tx.txid(),
+                channel_count: funded_channels,
+            });
+        }
+        for result in results.iter().filter(|result| result.status.is_ok()) {
+            self.record_funded_channel(
+                &result.request,
+                &funding_tx,
+                &funding_inputs,
+                funded_channels,
+            )
+            .await;
+        }
+    }
+
+    // Opens one channel with a funding tx of its own. There is nothing to wait on but this
+    // channel's FundingGenerationReady, and LDK's broadcast of the funding tx goes out as soon
+    // as the peer signs instead of waiting for the rest of a batch.
+    pub async fn open_channel(
+        &mut self,
+        request: OpenChannelRequest,
+        options: BatchOpenOptions,
+    ) -> BatchOpenResult {
+        let request = OpenChannelRequest {
+            custom_id: Some(
+                request
+                    .custom_id
+                    .unwrap_or_else(|| thread_rng().gen_range(1..u64::MAX)),
+            ),
+            ..request
+        };
+
+        let funds_check = match &options.funding_utxos {
+            Some(funding_utxos) => {
+                self.check_funding_utxos(funding_utxos, &options, std::slice::from_ref(&request))
+            }
+            None => check_wallet_funds(
+                &self.wallet.lock().unwrap(),
+                std::slice::from_ref(&request),
+                self.funding_sat_per_vb(),
+                &options.unspendable_utxos,
+            ),
+        };
+        let reserved = self.reserve_pending(std::slice::from_ref(&request));
+        let _reservation = match reserved.and_then(|reservation| funds_check.map(|_| reservation)) {
+            Ok(reservation) => reservation,
+            Err(e) => return BatchOpenResult::new(request, Err(e)),
+        };
+
+        let initiated = self
+            .initiate_channel_open(&request, options.connect_retry)
+            .await;
+        let mut result = BatchOpenResult::new(request, initiated);
+        if result.status.is_err() {
+            return result;
+        }
+
+        let filters = vec![self.funding_filter(result.request.custom_id.unwrap())];
+        let (events, skipped) = wait_for_events(
+            &mut self.event_receiver,
+            filters,
+            options.funding_timeout_ms,
+            options.poll_interval_ms,
+        )
+        .await;
+
+        let counterparty_node_id =
+            match funding_outcome(&events, skipped, result.request.custom_id.unwrap()) {
+                Ok(counterparty_node_id) => counterparty_node_id,
+                Err(e) => {
+                    result.status = Err(e);
+                    return result;
+                }
+            };
+        result.counterparty_node_id = Some(counterparty_node_id);
+
+        let (funding_tx, funding_fees, funding_inputs) = match self
+            .build_funding_tx(&events, &options)
+        {
+            Ok(funding) => funding,
+            Err(e) => {
+                let _res = self
+                    .channel_manager
+                    .force_close_channel(&result.temp_channel_id.unwrap(), &counterparty_node_id);
+                result.status = Err(funding_tx_error(&e, options.funding_utxos.as_deref()));
+                return result;
+            }
+        };
+
+        self.broadcaster.set_context(
+            funding_tx.txid(),
+            BroadcastContext {
+                purpose: BroadcastPurpose::Funding,
+                context: Some(format!(
+                    "channel open with {}",
+                    result.request.counterparty_pubkey
+                )),
+                feerate_sat_per_vb: funding_fees.as_ref().map(|fees| fees.sat_per_vb as f64),
+            },
+        );
+
+        if let Err(e) = self.channel_manager.funding_transaction_generated(
+            &result.temp_channel_id.unwrap(),
+            &counterparty_node_id,
+            funding_tx.clone(),
+        ) {
+            self.broadcaster.release_debounce(&funding_tx.txid());
+            result.status = Err(Error::LdkApi(e));
+            return result;
+        }
+        result.funding_txid = Some(funding_tx.txid());
+        result.funding_fees = funding_fees;
+
+        self.record_funded_channel(&result.request, &funding_tx, &funding_inputs, 1)
+            .await;
+
+        result
+    }
+
+    // Starts every close and then waits for their ChannelClosed events. A force close is done
+    // as soon as it's started, a cooperative one needs the peer to sign off on a fee first and
+    // can still finish after the timeout.
+    pub async fn close_batch(
+        &mut self,
+        requests: Vec<CloseChannelRequest>,
+        timeout_ms: u64,
+        poll_interval_ms: u64,
+    ) -> Vec<(CloseChannelRequest, Result<(), Error>)> {
+        let mut results = vec![];
+        let mut filters = vec![];
+
+        for request in requests {
+            let result = if request.force {
+                self.channel_manager
+                    .force_close_channel(&request.channel_id, &request.counterparty_node_id)
+            } else {
+                self.channel_manager
+                    .close_channel(&request.channel_id, &request.counterparty_node_id)
+            };
+
+            if result.is_ok() {
+                filters.push(self.close_filter(request.channel_id));
+            }
+            results.push((request, result.map_err(Error::LdkApi)));
+        }
+
+        let (events, skipped) = wait_for_events(
+            &mut self.event_receiver,
+            filters,
+            timeout_ms,
+            poll_interval_ms,
+        )
+        .await;
+
+        for (request, result) in results.iter_mut().filter(|(_, result)| result.is_ok()) {
+            let closed = events.iter().any(|event| {
+                matches!(
+                    event,
+                    SenseiEvent::ChannelClosed { channel_id, .. } if *channel_id == request.channel_id
+                )
+            });
+            if !closed && skipped > 0 {
+                *result = Err(Error::EventsLagged(skipped));
+            } else if !closed {
+                *result = Err(Error::ChannelCloseNeverHappened);
+            }
+        }
+
+        results
+    }
+
+    fn close_filter(&self, request_channel_id: [u8; 32]) -> EventFilter {
+        let filter_node_id = self.node_id.clone();
+        EventFilter {
+            f: Box::new(move |event| {
+                matches!(
+                    event,
+                    SenseiEvent::ChannelClosed {
+                        node_id,
+                        channel_id,
+                        ..
+                    } if *node_id == filter_node_id && *channel_id == request_channel_id
+                )
+            }),
+        }
+    }
+
+    fn funding_filter(&self, request_user_channel_id: u64) -> EventFilter {
+        funding_filter(self.node_id.clone(), request_user_channel_id)
+    }
+
+    async fn record_funded_channel(
+        &self,
+        request: &OpenChannelRequest,
+        funding_tx: &Transaction,
+        funding_inputs: &[(OutPoint, Option<u64>)],
+        funded_channels: usize,
+    ) {
+        let channels = self.channel_manager.list_channels();
+        let channel = channels
+            .iter()
+            .find(|channel| channel.user_channel_id == request.custom_id.unwrap());
+        if let Some(channel) = channel {
+            // the short channel id is filled in once the funding tx confirms
+            if let Err(e) = self
+                .database
+                .update_channel_funding(
+                    &self.node_id,
+                    request.custom_id.unwrap(),
+                    &hex_utils::hex_str(&channel.channel_id),
+                    &funding_tx.txid().to_string(),
+                )
+                .await
+            {
+                println!("ERROR: failed to record channel funding: {}", e);
+            }
+
+            let _res = self
+                .database
+                .record_channel_event(
+                    &self.node_id,
+                    &hex_utils::hex_str(&channel.channel_id),
+                    "opened",
+                    "operator",
+                    Some(format!(
+                        "funding tx {} with {}",
+                        funding_tx.txid(),
+                        request.counterparty_pubkey
+                    )),
+                )
+                .await;
+
+            // every channel in a batch shares the same funding tx, so its inputs can't be
+            // attributed to any one of them. each channel records all of them along with
+            // how many channels they funded.
+            let _res = self
+                .database
+                .record_channel_funding_inputs(
+                    &self.node_id,
+                    &hex_utils::hex_str(&channel.channel_id),
+                    &funding_tx.txid().to_string(),
+                    funding_inputs,
+                    funded_channels,
+                )
+                .await;
+        }
+    }
+
+    // Spends to every channel we got a FundingGenerationReady for, from `funding_utxos` only
+    // when given and otherwise from whatever bdk selects outside of `unspendable_utxos`. The
+    // tx is debounced until every one of those channels is signed, which reserves its inputs.
+    fn build_funding_tx(
+        &self,
+        events: &[SenseiEvent],
+        options: &BatchOpenOptions,
+    ) -> Result<
+        (
+            Transaction,
+            Option<FundingFees>,
+            Vec<(OutPoint, Option<u64>)>,
+        ),
+        FundingTxError,
+    > {
         let wallet = self.wallet.lock().unwrap();
+        let (mut psbt, fee_sats) = self.build_funding_psbt(&wallet, events, options)?;
+        let finalized = wallet
+            .sign(&mut psbt, SignOptions::default())
+            .map_err(|e| FundingTxError::Sign(e.to_string()))?;
+        if !finalized {
+            return Err(FundingTxError::Sign(String::from(
+                "not every funding input could be signed",
+            )));
+        }
+        let funding_inputs = funding_inputs(&psbt);
+        let funding_tx = psbt.extract_tx();
+        let funding_fees = funding_fees(&funding_tx, fee_sats);
 
-        let mut tx_builder = wallet.build_tx();
-        let fee_sats_per_1000_wu = self
-            .chain_manager
-            .fee_estimator
-            .get_est_sat_per_1000_weight(ConfirmationTarget::Normal);
+        let channels_to_open = events
+            .iter()
+            .filter(|event| matches!(event, SenseiEvent::FundingGenerationReady { .. }))
+            .count();
+        self.broadcaster.set_debounce(
+            funding_tx.txid(),
+            channels_to_open,
+            funding_inputs
+                .iter()
+                .map(|(outpoint, _amount_sats)| *outpoint)
+                .collect(),
+        );
 
-        // TODO: is this the correct conversion??
-        let sat_per_vb = match fee_sats_per_1000_wu {
-            253 => 1.0,
-            _ => fee_sats_per_1000_wu as f32 / 250.0,
-        } as f32;
+        Ok((funding_tx, funding_fees, funding_inputs))
+    }
+
+    // The unsigned funding tx and its fee, when bdk knows it. Takes the locked wallet so the
+    // caller can reserve the inputs before anything else gets to select them.
+    fn build_funding_psbt(
+        &self,
+        wallet: &bdk::Wallet<WalletDatabase>,
+        events: &[SenseiEvent],
+        options: &BatchOpenOptions,
+    ) -> Result<(PartiallySignedTransaction, Option<u64>), FundingTxError> {
+        let mut tx_builder = wallet.build_tx();
+        let sat_per_vb = self.funding_sat_per_vb();
 
         let fee_rate = FeeRate::from_sat_per_vb(sat_per_vb);
 
@@ -186,66 +1338,127 @@ impl ChannelOpener {
             }
         });
 
+        let reserved_inputs =
+            reserved_funding_inputs(&self.broadcaster, &self.pending_batch_fundings);
+        if let Some(funding_utxos) = &options.funding_utxos {
+            // bdk spends utxos it's given even when they're unspendable
+            if let Some(outpoint) = funding_utxos
+                .iter()
+                .find(|outpoint| reserved_inputs.contains(outpoint))
+            {
+                return Err(FundingTxError::Build(bdk::Error::Generic(format!(
+                    "{} already funds a tx that isn't broadcast yet",
+                    outpoint
+                ))));
+            }
+            tx_builder
+                .add_utxos(funding_utxos)?
+                .manually_selected_only();
+        }
+        let mut unspendable_utxos = options.unspendable_utxos.clone();
+        unspendable_utxos.extend(reserved_inputs);
+        tx_builder.unspendable(unspendable_utxos);
+
+        // without a fixed change address bdk picks a fresh one from the change descriptor
+        if let Some(change_script) = &self.change_script {
+            tx_builder.drain_to(change_script.clone());
+        }
+
+        // replacing the funding tx would change the channels' funding outpoint, a stuck one
+        // is bumped by spending its change instead (see LightningNode::bump_funding_fee)
         tx_builder.fee_rate(fee_rate).enable_rbf();
-        let (mut psbt, _tx_details) = tx_builder.finish().unwrap();
-        let _finalized = wallet.sign(&mut psbt, SignOptions::default()).unwrap();
-        let funding_tx = psbt.extract_tx();
+        let (psbt, tx_details) = tx_builder.finish()?;
 
-        let channels_to_open = requests_with_results
-            .iter()
-            .filter(|(_request, result, _counterparty_node_id)| result.is_ok())
-            .count();
+        if let (Some(fee_sats), Some(max_fee_sats)) = (tx_details.fee, options.max_fee_sats) {
+            if fee_sats > max_fee_sats {
+                return Err(FundingTxError::FeeTooHigh {
+                    fee_sats,
+                    max_fee_sats,
+                });
+            }
+        }
 
-        self.broadcaster
-            .set_debounce(funding_tx.txid(), channels_to_open);
+        Ok((psbt, tx_details.fee))
+    }
 
-        requests_with_results
-            .into_iter()
-            .map(|(request, result, counterparty_node_id)| {
-                if let Ok(tcid) = result {
-                    let counterparty_node_id = counterparty_node_id.unwrap();
-                    match self.channel_manager.funding_transaction_generated(
-                        &tcid,
-                        &counterparty_node_id,
-                        funding_tx.clone(),
-                    ) {
-                        Ok(()) => (request, result),
-                        Err(e) => (request, Err(Error::LdkApi(e))),
-                    }
-                } else {
-                    (request, result)
+    // Catches utxos we can't spend, or that can't even cover the channel amounts, before any
+    // channel is initiated. Fees are only known once the tx is built.
+    fn check_funding_utxos(
+        &self,
+        funding_utxos: &[OutPoint],
+        options: &BatchOpenOptions,
+        requests: &[OpenChannelRequest],
+    ) -> Result<(), Error> {
+        let unspent = self.wallet.lock().unwrap().list_unspent()?;
+        let mut funding_sats: u64 = 0;
+        for outpoint in funding_utxos {
+            if options.unspendable_utxos.contains(outpoint) {
+                return Err(Error::InvalidFundingUtxos(format!(
+                    "{} is both a funding utxo and unspendable",
+                    outpoint
+                )));
+            }
+            match unspent.iter().find(|utxo| utxo.outpoint == *outpoint) {
+                Some(utxo) => funding_sats = funding_sats.saturating_add(utxo.txout.value),
+                None => {
+                    return Err(Error::InvalidFundingUtxos(format!(
+                        "{} is not an unspent output of this wallet",
+                        outpoint
+                    )))
                 }
-            })
-            .collect()
+            }
+        }
+
+        // an amount that overflows can never be funded, saturating keeps it that way
+        let batch_sats = requests.iter().fold(0u64, |total, request| {
+            total.saturating_add(request.amount_sats)
+        });
+        if funding_sats < batch_sats {
+            return Err(Error::InsufficientFunds(batch_sats, funding_sats));
+        }
+        Ok(())
     }
 
-    async fn initiate_channel_open(&self, request: &OpenChannelRequest) -> Result<[u8; 32], Error> {
-        let counterparty_pubkey =
-            parse_pubkey(&request.counterparty_pubkey).expect("failed to parse pubkey");
+    async fn initiate_channel_open(
+        &self,
+        request: &OpenChannelRequest,
+        connect_retry: ConnectRetry,
+    ) -> Result<[u8; 32], Error> {
+        let counterparty_pubkey = parse_pubkey(&request.counterparty_pubkey)
+            .map_err(|_| Error::InvalidPubkey(request.counterparty_pubkey.clone()))?;
+        // an inbound peer outside the allowlist can be connected until the peer events task
+        // drops it, that doesn't make it allowed
+        if !self.config.peer_allowed(&counterparty_pubkey) {
+            return Err(Error::PeerNotAllowed(counterparty_pubkey.to_string()));
+        }
         let already_connected = self
             .peer_manager
             .get_peer_node_ids()
             .contains(&counterparty_pubkey);
         if !already_connected {
-            let counterparty_host_port = request.counterparty_host_port.as_ref().expect("you must provide connection information if you are not already connected to a peer");
+            let counterparty_host_port =
+                request.counterparty_host_port.as_ref().ok_or_else(|| {
+                    Error::InvalidPeerAddress(String::from(
+                        "none given, required when not already connected to the peer",
+                    ))
+                })?;
             let counterparty_addr = parse_peer_addr(counterparty_host_port)
                 .await
-                .expect("failed to parse host port for counterparty");
+                .map_err(|_| Error::InvalidPeerAddress(counterparty_host_port.clone()))?;
             connect_peer_if_necessary(
                 counterparty_pubkey,
                 counterparty_addr,
                 self.peer_manager.clone(),
+                &self.config,
+                connect_retry,
             )
-            .await
-            .unwrap_or_else(|_| {
-                panic!(
-                    "failed to connect to peer {}@{}",
-                    counterparty_pubkey, counterparty_addr
-                )
-            });
+            .await?;
+            let _res = self
+                .database
+                .mark_peer_connected(&self.node_id, &request.counterparty_pubkey)
+                .await;
         }
 
-        // TODO: want to be logging channels in db for matching forwarded payments
         match self.channel_manager.create_channel(
             counterparty_pubkey,
             request.amount_sats,
@@ -253,12 +1466,26 @@ impl ChannelOpener {
             request.custom_id.unwrap(),
             Some(request.into()),
         ) {
-            Ok(short_channel_id) => {
+            Ok(temp_channel_id) => {
                 println!(
                     "EVENT: initiated channel with peer {}. ",
                     request.counterparty_pubkey
                 );
-                Ok(short_channel_id)
+                // keyed by custom_id, the channel id only becomes final once it's funded
+                if let Err(e) = self
+                    .database
+                    .create_channel(
+                        &self.node_id,
+                        request.custom_id.unwrap(),
+                        &request.counterparty_pubkey,
+                        &hex_utils::hex_str(&temp_channel_id),
+                        request.amount_sats,
+                    )
+                    .await
+                {
+                    println!("ERROR: failed to record channel: {}", e);
+                }
+                Ok(temp_channel_id)
             }
             Err(e) => {
                 println!("ERROR: failed to open channel: {:?}", e);
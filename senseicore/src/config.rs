@@ -7,12 +7,55 @@
 // You may not use this file except in accordance with one or both of these
 // licenses.
 
-use std::{fs, io};
+use std::{
+    collections::HashMap,
+    fs, io,
+    net::{IpAddr, SocketAddr},
+};
 
-use bitcoin::Network;
+use bitcoin::{secp256k1::PublicKey, Network};
+use lightning::chain::chaininterface::ConfirmationTarget;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
+use crate::error::Error;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfirmationPriority {
+    Background,
+    Normal,
+    HighPriority,
+}
+
+impl From<ConfirmationPriority> for ConfirmationTarget {
+    fn from(priority: ConfirmationPriority) -> Self {
+        match priority {
+            ConfirmationPriority::Background => ConfirmationTarget::Background,
+            ConfirmationPriority::Normal => ConfirmationTarget::Normal,
+            ConfirmationPriority::HighPriority => ConfirmationTarget::HighPriority,
+        }
+    }
+}
+
+// Which addresses of a peer to try first when its host resolves to both IPv4 and IPv6.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AddressFamily {
+    Ipv4,
+    Ipv6,
+}
+
+// What to do when an invoice is paid a different amount than it asked for. LDK only lets
+// through payments of at least the invoice amount, so in practice this is overpayment.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AmountMismatchPolicy {
+    Reject,
+    Accept,
+    Hold,
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct SenseiConfig {
     #[serde(skip)]
@@ -27,6 +70,46 @@ pub struct SenseiConfig {
     pub port_range_min: u16,
     pub port_range_max: u16,
     pub database_url: String,
+    pub reject_duplicate_payment_hashes: bool,
+    pub feerate_update_tolerance_pct: u32,
+    pub wallet_sync_interval_secs: u64,
+    pub wallet_cached_addresses: u32,
+    pub max_commitment_feerate_sats_per_kw: Option<u32>,
+    pub zombie_channel_timeout_secs: Option<u64>,
+    pub zombie_channel_force_close: bool,
+    pub utxo_consolidation_max_feerate: Option<u32>,
+    pub utxo_consolidation_min_utxos: usize,
+    pub utxo_consolidation_max_utxo_sats: u64,
+    pub broadcast_log_enabled: bool,
+    pub p2p_bind_address: String,
+    pub root_node_listen_port: u16,
+    pub sweep_dust_limit_sats: u64,
+    pub peer_connection_rate_limit: Option<u32>,
+    pub peer_connection_rate_window_secs: u64,
+    pub rate_limit_exempt_ips: Vec<String>,
+    pub tor_proxy: Option<String>,
+    pub peer_connect_max_attempts: u32,
+    pub peer_connect_initial_backoff_ms: u64,
+    pub preferred_address_family: Option<AddressFamily>,
+    pub peer_allowlist_enabled: bool,
+    pub peer_allowlist: Vec<String>,
+    pub accept_inbound_channels: bool,
+    pub max_auto_accept_channel_sats: Option<u64>,
+    pub min_inbound_channel_sats: Option<u64>,
+    pub reject_inbound_channels_from_unknown_nodes: bool,
+    pub channel_open_concurrency: usize,
+    pub max_pending_channels: Option<usize>,
+    pub scorer_liquidity_half_life_secs: Option<u64>,
+    pub scorer_liquidity_penalty_multiplier_msat: Option<u64>,
+    pub detect_unexpected_deposit_scripts: bool,
+    pub max_relay_rejections: u32,
+    pub fixed_change_addresses: HashMap<String, String>,
+    pub max_in_flight_outbound_msat: Option<u64>,
+    pub min_confirmation_target: Option<ConfirmationPriority>,
+    pub network_graph_snapshot_interval_secs: Option<u64>,
+    pub payment_amount_mismatch_policy: Option<AmountMismatchPolicy>,
+    pub stale_state_check: bool,
+    pub funding_broadcast_timeout_secs: u64,
 }
 
 impl Default for SenseiConfig {
@@ -45,6 +128,46 @@ impl Default for SenseiConfig {
             port_range_min: 10000,
             port_range_max: 65535,
             database_url: String::from("sensei.db"),
+            reject_duplicate_payment_hashes: false,
+            feerate_update_tolerance_pct: 0,
+            wallet_sync_interval_secs: 1,
+            wallet_cached_addresses: 100,
+            max_commitment_feerate_sats_per_kw: None,
+            zombie_channel_timeout_secs: None,
+            zombie_channel_force_close: false,
+            utxo_consolidation_max_feerate: None,
+            utxo_consolidation_min_utxos: 50,
+            utxo_consolidation_max_utxo_sats: 100_000,
+            broadcast_log_enabled: false,
+            p2p_bind_address: String::from("0.0.0.0"),
+            root_node_listen_port: 9735,
+            sweep_dust_limit_sats: 546,
+            peer_connection_rate_limit: None,
+            peer_connection_rate_window_secs: 60,
+            rate_limit_exempt_ips: vec![],
+            tor_proxy: None,
+            peer_connect_max_attempts: 3,
+            peer_connect_initial_backoff_ms: 1000,
+            preferred_address_family: None,
+            peer_allowlist_enabled: false,
+            peer_allowlist: vec![],
+            accept_inbound_channels: true,
+            max_auto_accept_channel_sats: None,
+            min_inbound_channel_sats: None,
+            reject_inbound_channels_from_unknown_nodes: false,
+            channel_open_concurrency: 4,
+            max_pending_channels: None,
+            scorer_liquidity_half_life_secs: None,
+            scorer_liquidity_penalty_multiplier_msat: None,
+            detect_unexpected_deposit_scripts: false,
+            max_relay_rejections: 0,
+            fixed_change_addresses: HashMap::new(),
+            max_in_flight_outbound_msat: None,
+            min_confirmation_target: None,
+            network_graph_snapshot_interval_secs: None,
+            payment_amount_mismatch_policy: None,
+            stale_state_check: true,
+            funding_broadcast_timeout_secs: 600,
         }
     }
 }
@@ -54,7 +177,7 @@ impl SenseiConfig {
         let mut merge_config = merge_with.unwrap_or_default();
         merge_config.path = path.clone();
 
-        match fs::read_to_string(path.clone()) {
+        let config: SenseiConfig = match fs::read_to_string(path.clone()) {
             Ok(config_str) => {
                 let mut merge_config_value = serde_json::to_value(merge_config).unwrap();
                 let merge_config_map = merge_config_value.as_object_mut().unwrap();
@@ -82,9 +205,48 @@ impl SenseiConfig {
                     panic!("failed to read configuration file");
                 }
             },
+        };
+
+        if let Err(e) = config.tor_proxy_addr() {
+            panic!("invalid configuration file: {}", e);
+        }
+        if let Err(e) = config.p2p_bind_addr(config.root_node_listen_port) {
+            panic!("invalid configuration file: {}", e);
+        }
+        config
+    }
+
+    pub fn tor_proxy_addr(&self) -> Result<Option<SocketAddr>, Error> {
+        match self.tor_proxy.as_ref() {
+            Some(proxy) => proxy
+                .parse::<SocketAddr>()
+                .map(Some)
+                .map_err(|_| Error::InvalidPeerAddress(format!("tor proxy {}", proxy))),
+            None => Ok(None),
         }
     }
 
+    // Ipv6 addresses can be given with or without brackets, SocketAddr adds them back when
+    // the address is formatted.
+    pub fn p2p_bind_addr(&self, port: u16) -> Result<SocketAddr, Error> {
+        let ip = self
+            .p2p_bind_address
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse::<IpAddr>()
+            .map_err(|_| Error::InvalidBindAddress(self.p2p_bind_address.clone()))?;
+        Ok(SocketAddr::new(ip, port))
+    }
+
+    // Only listed pubkeys can connect or open channels with us when the allowlist is enabled.
+    pub fn peer_allowed(&self, pubkey: &PublicKey) -> bool {
+        !self.peer_allowlist_enabled
+            || self
+                .peer_allowlist
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(&pubkey.to_string()))
+    }
+
     pub fn set_network(&mut self, network: Network) {
         self.network = network;
     }
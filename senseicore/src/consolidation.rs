@@ -0,0 +1,147 @@
+use crate::chain::broadcaster::{BroadcastContext, BroadcastPurpose, SenseiBroadcaster};
+use crate::chain::fee_estimator;
+use crate::chain::manager::SenseiChainManager;
+use crate::error::Error;
+use crate::node::PendingBatchFunding;
+use crate::{chain::database::WalletDatabase, events::SenseiEvent};
+use bdk::wallet::AddressIndex;
+use bdk::{FeeRate, SignOptions};
+use bitcoin::{OutPoint, Script, Txid};
+use lightning::chain::chaininterface::{BroadcasterInterface, ConfirmationTarget};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+
+pub struct UtxoConsolidator {
+    node_id: String,
+    wallet: Arc<Mutex<bdk::Wallet<WalletDatabase>>>,
+    chain_manager: Arc<SenseiChainManager>,
+    broadcaster: Arc<SenseiBroadcaster>,
+    event_sender: broadcast::Sender<SenseiEvent>,
+    max_feerate: u32,
+    min_utxos: usize,
+    max_utxo_sats: u64,
+    change_script: Option<Script>,
+    pending_batch_fundings: Arc<Mutex<HashMap<Txid, PendingBatchFunding>>>,
+}
+
+impl UtxoConsolidator {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        node_id: String,
+        wallet: Arc<Mutex<bdk::Wallet<WalletDatabase>>>,
+        chain_manager: Arc<SenseiChainManager>,
+        broadcaster: Arc<SenseiBroadcaster>,
+        event_sender: broadcast::Sender<SenseiEvent>,
+        max_feerate: u32,
+        min_utxos: usize,
+        max_utxo_sats: u64,
+        change_script: Option<Script>,
+        pending_batch_fundings: Arc<Mutex<HashMap<Txid, PendingBatchFunding>>>,
+    ) -> Self {
+        Self {
+            node_id,
+            wallet,
+            chain_manager,
+            broadcaster,
+            event_sender,
+            max_feerate,
+            min_utxos,
+            max_utxo_sats,
+            change_script,
+            pending_batch_fundings,
+        }
+    }
+
+    // Sweeps every utxo worth at most `max_utxo_sats` into a single fresh address (or the fixed
+    // change address when one is configured), but only while the background feerate is at or
+    // below `max_feerate` and there are at least `min_utxos` of them. Unconfirmed utxos are
+    // left alone, as are the inputs of funding txs that aren't broadcast yet: batch fundings
+    // waiting to be finalized and funding txs waiting on their peers' signatures.
+    pub fn consolidate(&self) -> Result<Option<Txid>, Error> {
+        let fee_sats_per_1000_wu = self
+            .chain_manager
+            .fee_estimator
+            .get_est_sat_per_1000_weight(ConfirmationTarget::Background);
+
+        if fee_sats_per_1000_wu > self.max_feerate {
+            return Ok(None);
+        }
+
+        let (consolidation_tx, sat_per_vb) = {
+            let wallet = self.wallet.lock().unwrap();
+
+            let mut locked_utxos = self
+                .pending_batch_fundings
+                .lock()
+                .unwrap()
+                .values()
+                .flat_map(|pending| pending.inputs.clone())
+                .collect::<HashSet<OutPoint>>();
+            locked_utxos.extend(self.broadcaster.debounced_inputs());
+            let confirmed_txids = wallet
+                .list_transactions(false)?
+                .into_iter()
+                .filter(|details| details.confirmation_time.is_some())
+                .map(|details| details.txid)
+                .collect::<HashSet<Txid>>();
+
+            let small_utxos = wallet
+                .list_unspent()?
+                .into_iter()
+                .filter(|utxo| utxo.txout.value <= self.max_utxo_sats)
+                .filter(|utxo| confirmed_txids.contains(&utxo.outpoint.txid))
+                .filter(|utxo| !locked_utxos.contains(&utxo.outpoint))
+                .map(|utxo| utxo.outpoint)
+                .collect::<Vec<OutPoint>>();
+
+            if small_utxos.len() < self.min_utxos {
+                return Ok(None);
+            }
+
+            let sat_per_vb = fee_estimator::sat_per_vb(fee_sats_per_1000_wu);
+
+            let drain_script = match &self.change_script {
+                Some(change_script) => change_script.clone(),
+                None => wallet.get_address(AddressIndex::New)?.script_pubkey(),
+            };
+            let mut tx_builder = wallet.build_tx();
+            tx_builder
+                .add_utxos(&small_utxos)?
+                .manually_selected_only()
+                .drain_to(drain_script)
+                .fee_rate(FeeRate::from_sat_per_vb(sat_per_vb))
+                .enable_rbf();
+            let (mut psbt, _tx_details) = tx_builder.finish()?;
+            let _finalized = wallet.sign(&mut psbt, SignOptions::default())?;
+            (psbt.extract_tx(), sat_per_vb)
+        };
+
+        let txid = consolidation_tx.txid();
+        self.broadcaster.set_context(
+            txid,
+            BroadcastContext {
+                purpose: BroadcastPurpose::Consolidation,
+                context: Some(format!("{} utxos", consolidation_tx.input.len())),
+                feerate_sat_per_vb: Some(sat_per_vb as f64),
+            },
+        );
+        self.broadcaster.broadcast_transaction(&consolidation_tx);
+
+        println!(
+            "consolidated {} utxos into {}",
+            consolidation_tx.input.len(),
+            txid
+        );
+
+        self.event_sender
+            .send(SenseiEvent::UtxosConsolidated {
+                node_id: self.node_id.clone(),
+                txid,
+                num_inputs: consolidation_tx.input.len(),
+            })
+            .unwrap_or_default();
+
+        Ok(Some(txid))
+    }
+}
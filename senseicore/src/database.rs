@@ -1,13 +1,32 @@
 use crate::error::Error;
 use crate::hex_utils;
+use crate::node::{HTLCDirection, HTLCStatus, PaymentOrigin};
 use crate::services::PaginationRequest;
 use crate::services::PaginationResponse;
 use crate::services::PaymentsFilter;
 use bdk::database::SyncTime;
 use bdk::BlockTime;
-use bitcoin::BlockHash;
+use bitcoin::{BlockHash, OutPoint};
 use entity::access_token;
 use entity::access_token::Entity as AccessToken;
+use entity::broadcast_log;
+use entity::broadcast_log::Entity as BroadcastLog;
+use entity::channel;
+use entity::channel::Entity as Channel;
+use entity::channel_event;
+use entity::channel_event::Entity as ChannelEvent;
+use entity::channel_funding_input;
+use entity::channel_funding_input::Entity as ChannelFundingInput;
+use entity::channel_htlc_stat;
+use entity::channel_htlc_stat::Entity as ChannelHtlcStat;
+use entity::deposit_address;
+use entity::deposit_address::Entity as DepositAddress;
+use entity::forward;
+use entity::forward::Entity as Forward;
+use entity::graph_channel;
+use entity::graph_channel::Entity as GraphChannel;
+use entity::graph_node;
+use entity::graph_node::Entity as GraphNode;
 use entity::kv_store;
 use entity::kv_store::Entity as KVStore;
 use entity::macaroon;
@@ -18,18 +37,26 @@ use entity::payment;
 use entity::payment::Entity as Payment;
 use entity::peer;
 use entity::peer::Entity as Peer;
+use entity::peer_event;
+use entity::peer_event::Entity as PeerEvent;
 use entity::sea_orm;
 use entity::sea_orm::ActiveValue;
 use entity::sea_orm::QueryOrder;
 use entity::seconds_since_epoch;
+use entity::subscription;
+use entity::subscription::Entity as Subscription;
 use migration::Condition;
 use migration::Expr;
 use rand::thread_rng;
 use rand::RngCore;
 use sea_orm::entity::EntityTrait;
-use sea_orm::{prelude::*, DatabaseConnection};
+use sea_orm::{
+    prelude::*, ConnectionTrait, DatabaseConnection, DbBackend, FromQueryResult, QuerySelect,
+    TransactionTrait,
+};
 use serde::Deserialize;
 use serde::Serialize;
+use std::collections::HashMap;
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
 pub struct LastSync {
@@ -38,6 +65,14 @@ pub struct LastSync {
     pub timestamp: u64,
 }
 
+// Forwarding fees of one utc day, `day` being its start.
+#[derive(FromQueryResult, Debug, Clone, PartialEq)]
+pub struct DailyForwardFees {
+    pub day: i64,
+    pub fee_earned_msat: i64,
+    pub forwards: i64,
+}
+
 impl From<LastSync> for BlockTime {
     fn from(last_sync: LastSync) -> Self {
         Self {
@@ -282,6 +317,17 @@ impl SenseiDatabase {
             .await?)
     }
 
+    pub async fn list_claiming_payments(
+        &self,
+        node_id: &str,
+    ) -> Result<Vec<payment::Model>, Error> {
+        Ok(Payment::find()
+            .filter(payment::Column::NodeId.eq(node_id))
+            .filter(payment::Column::Status.eq(HTLCStatus::Claiming.to_string()))
+            .all(&self.connection)
+            .await?)
+    }
+
     pub async fn delete_payment(&self, node_id: String, payment_hash: String) -> Result<(), Error> {
         match self.find_payment(node_id, payment_hash).await? {
             Some(payment) => {
@@ -309,6 +355,22 @@ impl SenseiDatabase {
         }
     }
 
+    pub async fn set_payment_metadata(
+        &self,
+        node_id: String,
+        payment_hash: String,
+        metadata: Option<String>,
+    ) -> Result<Option<payment::Model>, Error> {
+        match self.find_payment(node_id, payment_hash).await? {
+            Some(payment) => {
+                let mut payment: payment::ActiveModel = payment.into();
+                payment.metadata = ActiveValue::Set(metadata);
+                Ok(Some(payment.update(&self.connection).await?))
+            }
+            None => Ok(None),
+        }
+    }
+
     pub fn list_payments_sync(
         &self,
         node_id: String,
@@ -381,6 +443,36 @@ impl SenseiDatabase {
             .await?)
     }
 
+    // Updates the existing peer for (node_id, pubkey) instead of adding a second row for it.
+    pub async fn upsert_peer(
+        &self,
+        node_id: &str,
+        pubkey: &str,
+        label: Option<String>,
+        zero_conf: bool,
+    ) -> Result<peer::Model, Error> {
+        let peer = match self.find_peer(node_id, pubkey).await? {
+            Some(peer) => {
+                let mut peer: peer::ActiveModel = peer.into();
+                peer.label = ActiveValue::Set(label);
+                peer.zero_conf = ActiveValue::Set(zero_conf);
+                peer.update(&self.connection).await?
+            }
+            None => {
+                let peer = peer::ActiveModel {
+                    id: ActiveValue::Set(Uuid::new_v4().to_string()),
+                    node_id: ActiveValue::Set(node_id.to_string()),
+                    pubkey: ActiveValue::Set(pubkey.to_string()),
+                    label: ActiveValue::Set(label),
+                    zero_conf: ActiveValue::Set(zero_conf),
+                    ..Default::default()
+                };
+                peer.insert(&self.connection).await?
+            }
+        };
+        Ok(peer)
+    }
+
     pub async fn label_peer(
         &self,
         node_id: &str,
@@ -398,6 +490,16 @@ impl SenseiDatabase {
         }
     }
 
+    // Only known peers are tracked, connecting to anyone else doesn't create a peer.
+    pub async fn mark_peer_connected(&self, node_id: &str, pubkey: &str) -> Result<(), Error> {
+        if let Some(peer) = self.find_peer(node_id, pubkey).await? {
+            let mut peer: peer::ActiveModel = peer.into();
+            peer.last_connected_at = ActiveValue::Set(Some(seconds_since_epoch()));
+            peer.update(&self.connection).await?;
+        }
+        Ok(())
+    }
+
     pub fn find_peer_sync(
         &self,
         node_id: &str,
@@ -441,6 +543,878 @@ impl SenseiDatabase {
         ))
     }
 
+    pub async fn find_peers(
+        &self,
+        node_id: &str,
+        label_contains: Option<String>,
+        pubkey_prefix: Option<String>,
+    ) -> Result<Vec<peer::Model>, Error> {
+        let mut query = Peer::find().filter(peer::Column::NodeId.eq(node_id));
+        if let Some(label_contains) = label_contains {
+            query = query.filter(peer::Column::Label.contains(&label_contains));
+        }
+        if let Some(pubkey_prefix) = pubkey_prefix {
+            query = query.filter(peer::Column::Pubkey.starts_with(&pubkey_prefix));
+        }
+        Ok(query
+            .order_by_desc(peer::Column::CreatedAt)
+            .all(&self.connection)
+            .await?)
+    }
+
+    pub async fn record_peer_event(
+        &self,
+        node_id: &str,
+        pubkey: &str,
+        connected: bool,
+    ) -> Result<peer_event::Model, Error> {
+        let peer_event = peer_event::ActiveModel {
+            node_id: ActiveValue::Set(node_id.to_string()),
+            pubkey: ActiveValue::Set(pubkey.to_string()),
+            connected: ActiveValue::Set(connected),
+            ..Default::default()
+        };
+        Ok(peer_event.insert(&self.connection).await?)
+    }
+
+    pub async fn record_peer_event_at(
+        &self,
+        node_id: &str,
+        pubkey: &str,
+        connected: bool,
+        at: i64,
+    ) -> Result<peer_event::Model, Error> {
+        let peer_event = peer_event::ActiveModel {
+            node_id: ActiveValue::Set(node_id.to_string()),
+            pubkey: ActiveValue::Set(pubkey.to_string()),
+            connected: ActiveValue::Set(connected),
+            created_at: ActiveValue::Set(at),
+            ..Default::default()
+        };
+        Ok(peer_event.insert(&self.connection).await?)
+    }
+
+    // The last time the node was known to be running, written while peer events are polled.
+    pub async fn set_peer_events_heartbeat(&self, node_id: &str, at: i64) -> Result<(), Error> {
+        self.set_value(
+            node_id.to_string(),
+            String::from("peer_events/heartbeat"),
+            at.to_string().into_bytes(),
+        )
+        .await?;
+        Ok(())
+    }
+
+    pub async fn peer_events_heartbeat(&self, node_id: &str) -> Result<Option<i64>, Error> {
+        Ok(self
+            .get_value(node_id.to_string(), String::from("peer_events/heartbeat"))
+            .await?
+            .and_then(|entry| String::from_utf8(entry.v).ok())
+            .and_then(|at| at.parse().ok()))
+    }
+
+    // Funding txs FundingConfirmed was sent for, so a restart neither repeats nor misses one.
+    pub async fn set_funding_confirmed_announced(
+        &self,
+        node_id: &str,
+        txid: &str,
+    ) -> Result<(), Error> {
+        self.set_value(
+            node_id.to_string(),
+            format!("funding_confirmed/{}", txid),
+            vec![],
+        )
+        .await?;
+        Ok(())
+    }
+
+    pub async fn list_funding_confirmed_announced(
+        &self,
+        node_id: &str,
+    ) -> Result<Vec<String>, Error> {
+        Ok(self
+            .list_keys(node_id.to_string(), "funding_confirmed/")
+            .await?
+            .into_iter()
+            .filter_map(|key| {
+                key.strip_prefix("funding_confirmed/")
+                    .map(|txid| txid.to_string())
+            })
+            .collect())
+    }
+
+    // The height an imported wallet still has to be rescanned from, cleared once it was.
+    pub async fn set_pending_wallet_rescan(
+        &self,
+        node_id: &str,
+        from_height: u32,
+    ) -> Result<(), Error> {
+        self.set_value(
+            node_id.to_string(),
+            String::from("wallet/pending_rescan"),
+            from_height.to_string().into_bytes(),
+        )
+        .await?;
+        Ok(())
+    }
+
+    pub async fn pending_wallet_rescan(&self, node_id: &str) -> Result<Option<u32>, Error> {
+        Ok(self
+            .get_value(node_id.to_string(), String::from("wallet/pending_rescan"))
+            .await?
+            .and_then(|entry| String::from_utf8(entry.v).ok())
+            .and_then(|from_height| from_height.parse().ok()))
+    }
+
+    pub async fn clear_pending_wallet_rescan(&self, node_id: &str) -> Result<(), Error> {
+        KVStore::delete_many()
+            .filter(kv_store::Column::NodeId.eq(node_id))
+            .filter(kv_store::Column::K.eq("wallet/pending_rescan"))
+            .exec(&self.connection)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn last_peer_event_before(
+        &self,
+        node_id: &str,
+        pubkey: &str,
+        before: i64,
+    ) -> Result<Option<peer_event::Model>, Error> {
+        Ok(PeerEvent::find()
+            .filter(peer_event::Column::NodeId.eq(node_id))
+            .filter(peer_event::Column::Pubkey.eq(pubkey))
+            .filter(peer_event::Column::CreatedAt.lt(before))
+            .order_by_desc(peer_event::Column::CreatedAt)
+            .one(&self.connection)
+            .await?)
+    }
+
+    pub async fn list_peer_events(
+        &self,
+        node_id: &str,
+        pubkey: &str,
+        from: i64,
+    ) -> Result<Vec<peer_event::Model>, Error> {
+        Ok(PeerEvent::find()
+            .filter(peer_event::Column::NodeId.eq(node_id))
+            .filter(peer_event::Column::Pubkey.eq(pubkey))
+            .filter(peer_event::Column::CreatedAt.gte(from))
+            .order_by_asc(peer_event::Column::CreatedAt)
+            .all(&self.connection)
+            .await?)
+    }
+
+    pub async fn create_subscription(
+        &self,
+        node_id: &str,
+        amount_msat: u64,
+        description: String,
+        interval_secs: u64,
+        next_invoice_at: i64,
+    ) -> Result<subscription::Model, Error> {
+        let amount_msat: i64 = amount_msat
+            .try_into()
+            .map_err(|_e| Error::InvalidSubscription(String::from("amount is too large")))?;
+        let interval_secs: i64 = interval_secs
+            .try_into()
+            .map_err(|_e| Error::InvalidSubscription(String::from("interval is too long")))?;
+        let subscription = subscription::ActiveModel {
+            node_id: ActiveValue::Set(node_id.to_string()),
+            amount_msat: ActiveValue::Set(amount_msat),
+            description: ActiveValue::Set(description),
+            interval_secs: ActiveValue::Set(interval_secs),
+            next_invoice_at: ActiveValue::Set(next_invoice_at),
+            ..Default::default()
+        };
+        Ok(subscription.insert(&self.connection).await?)
+    }
+
+    pub async fn list_subscriptions(
+        &self,
+        node_id: &str,
+    ) -> Result<Vec<subscription::Model>, Error> {
+        Ok(Subscription::find()
+            .filter(subscription::Column::NodeId.eq(node_id))
+            .order_by_asc(subscription::Column::CreatedAt)
+            .all(&self.connection)
+            .await?)
+    }
+
+    pub async fn list_due_subscriptions(
+        &self,
+        node_id: &str,
+        now: i64,
+    ) -> Result<Vec<subscription::Model>, Error> {
+        Ok(Subscription::find()
+            .filter(subscription::Column::NodeId.eq(node_id))
+            .filter(subscription::Column::NextInvoiceAt.lte(now))
+            .all(&self.connection)
+            .await?)
+    }
+
+    pub async fn set_subscription_next_invoice_at(
+        &self,
+        subscription: subscription::Model,
+        next_invoice_at: i64,
+    ) -> Result<(), Error> {
+        let mut subscription: subscription::ActiveModel = subscription.into();
+        subscription.next_invoice_at = ActiveValue::Set(next_invoice_at);
+        subscription.update(&self.connection).await?;
+        Ok(())
+    }
+
+    pub async fn delete_subscription(&self, node_id: &str, id: &str) -> Result<(), Error> {
+        let subscription = Subscription::find_by_id(id.to_string())
+            .filter(subscription::Column::NodeId.eq(node_id))
+            .one(&self.connection)
+            .await?;
+        if let Some(subscription) = subscription {
+            let _deleted = subscription.delete(&self.connection).await?;
+        }
+        Ok(())
+    }
+
+    pub fn record_broadcast_sync(
+        &self,
+        node_id: String,
+        txid: String,
+        purpose: String,
+        context: Option<String>,
+        feerate_sat_per_vb: Option<f64>,
+    ) -> Result<broadcast_log::Model, Error> {
+        tokio::task::block_in_place(move || {
+            self.runtime_handle.block_on(async move {
+                self.record_broadcast(node_id, txid, purpose, context, feerate_sat_per_vb)
+                    .await
+            })
+        })
+    }
+
+    pub async fn record_broadcast(
+        &self,
+        node_id: String,
+        txid: String,
+        purpose: String,
+        context: Option<String>,
+        feerate_sat_per_vb: Option<f64>,
+    ) -> Result<broadcast_log::Model, Error> {
+        let existing = BroadcastLog::find()
+            .filter(broadcast_log::Column::NodeId.eq(node_id.clone()))
+            .filter(broadcast_log::Column::Txid.eq(txid.clone()))
+            .one(&self.connection)
+            .await?;
+
+        match existing {
+            Some(broadcast) => {
+                let attempts = broadcast.attempts + 1;
+                let mut broadcast: broadcast_log::ActiveModel = broadcast.into();
+                broadcast.attempts = ActiveValue::Set(attempts);
+                Ok(broadcast.update(&self.connection).await?)
+            }
+            None => {
+                let broadcast = broadcast_log::ActiveModel {
+                    node_id: ActiveValue::Set(node_id),
+                    txid: ActiveValue::Set(txid),
+                    purpose: ActiveValue::Set(purpose),
+                    context: ActiveValue::Set(context),
+                    feerate_sat_per_vb: ActiveValue::Set(feerate_sat_per_vb),
+                    attempts: ActiveValue::Set(1),
+                    ..Default::default()
+                };
+                Ok(broadcast.insert(&self.connection).await?)
+            }
+        }
+    }
+
+    pub fn record_channel_event_sync(
+        &self,
+        node_id: &str,
+        channel_id: &str,
+        kind: &str,
+        source: &str,
+        detail: Option<String>,
+    ) -> Result<channel_event::Model, Error> {
+        tokio::task::block_in_place(move || {
+            self.runtime_handle.block_on(async move {
+                self.record_channel_event(node_id, channel_id, kind, source, detail)
+                    .await
+            })
+        })
+    }
+
+    pub async fn record_channel_event(
+        &self,
+        node_id: &str,
+        channel_id: &str,
+        kind: &str,
+        source: &str,
+        detail: Option<String>,
+    ) -> Result<channel_event::Model, Error> {
+        let channel_event = channel_event::ActiveModel {
+            node_id: ActiveValue::Set(node_id.to_string()),
+            channel_id: ActiveValue::Set(channel_id.to_string()),
+            kind: ActiveValue::Set(kind.to_string()),
+            source: ActiveValue::Set(source.to_string()),
+            detail: ActiveValue::Set(detail),
+            ..Default::default()
+        };
+
+        Ok(channel_event.insert(&self.connection).await?)
+    }
+
+    pub fn record_forward_sync(
+        &self,
+        node_id: &str,
+        prev_channel_id: Option<String>,
+        next_channel_id: Option<String>,
+        fee_earned_msat: Option<u64>,
+        claim_from_onchain_tx: bool,
+    ) -> Result<forward::Model, Error> {
+        tokio::task::block_in_place(move || {
+            self.runtime_handle.block_on(async move {
+                let forward = forward::ActiveModel {
+                    node_id: ActiveValue::Set(node_id.to_string()),
+                    prev_channel_id: ActiveValue::Set(prev_channel_id),
+                    next_channel_id: ActiveValue::Set(next_channel_id),
+                    fee_earned_msat: ActiveValue::Set(
+                        fee_earned_msat.map(|fee| fee.try_into().unwrap()),
+                    ),
+                    claim_from_onchain_tx: ActiveValue::Set(claim_from_onchain_tx),
+                    ..Default::default()
+                };
+                Ok(forward.insert(&self.connection).await?)
+            })
+        })
+    }
+
+    pub async fn list_forwards_in_range(
+        &self,
+        node_id: &str,
+        from: i64,
+        to: i64,
+    ) -> Result<Vec<forward::Model>, Error> {
+        Ok(Forward::find()
+            .filter(forward::Column::NodeId.eq(node_id))
+            .filter(forward::Column::CreatedAt.between(from, to))
+            .order_by_asc(forward::Column::CreatedAt)
+            .all(&self.connection)
+            .await?)
+    }
+
+    pub async fn list_forwards_page_in_range(
+        &self,
+        node_id: &str,
+        from: i64,
+        to: i64,
+        page: usize,
+        page_size: usize,
+    ) -> Result<Vec<forward::Model>, Error> {
+        Ok(Forward::find()
+            .filter(forward::Column::NodeId.eq(node_id))
+            .filter(forward::Column::CreatedAt.between(from, to))
+            .order_by_asc(forward::Column::CreatedAt)
+            .order_by_asc(forward::Column::Id)
+            .paginate(&self.connection, page_size)
+            .fetch_page(page)
+            .await?)
+    }
+
+    // Forwarding fees per utc day, added up by the database. The range leaves out `from` and
+    // takes in `to`, so back to back ranges don't count a forward twice.
+    pub async fn daily_forward_fees(
+        &self,
+        node_id: &str,
+        from: i64,
+        to: i64,
+    ) -> Result<Vec<DailyForwardFees>, Error> {
+        const DAY: &str = "created_at - created_at % 86400";
+        // postgres and mysql sum bigints into decimals
+        let fee_earned_msat = match self.connection.get_database_backend() {
+            DbBackend::MySql => "CAST(COALESCE(SUM(fee_earned_msat), 0) AS SIGNED)",
+            DbBackend::Postgres => "CAST(COALESCE(SUM(fee_earned_msat), 0) AS BIGINT)",
+            DbBackend::Sqlite => "COALESCE(SUM(fee_earned_msat), 0)",
+        };
+
+        let mut days = Forward::find()
+            .select_only()
+            .column_as(Expr::cust(DAY), "day")
+            .column_as(Expr::cust(fee_earned_msat), "fee_earned_msat")
+            .column_as(Expr::cust("COUNT(*)"), "forwards")
+            .filter(forward::Column::NodeId.eq(node_id))
+            .filter(forward::Column::CreatedAt.gt(from))
+            .filter(forward::Column::CreatedAt.lte(to))
+            .group_by(Expr::cust(DAY))
+            .into_model::<DailyForwardFees>()
+            .all(&self.connection)
+            .await?;
+        days.sort_by_key(|day| day.day);
+        Ok(days)
+    }
+
+    // Outgoing payments that haven't succeeded or failed yet, spontaneous ones included.
+    pub async fn list_pending_outgoing_payments(
+        &self,
+        node_id: &str,
+    ) -> Result<Vec<payment::Model>, Error> {
+        Ok(Payment::find()
+            .filter(payment::Column::NodeId.eq(node_id))
+            .filter(payment::Column::Status.eq(HTLCStatus::Pending.to_string()))
+            .filter(payment::Column::Origin.is_in(vec![
+                PaymentOrigin::InvoiceOutgoing.to_string(),
+                PaymentOrigin::SpontaneousOutgoing.to_string(),
+            ]))
+            .all(&self.connection)
+            .await?)
+    }
+
+    pub async fn pending_outgoing_msat(&self, node_id: &str) -> Result<u64, Error> {
+        let payments = self.list_pending_outgoing_payments(node_id).await?;
+
+        Ok(payments
+            .iter()
+            .filter_map(|payment| payment.amt_msat)
+            .fold(0u64, |total, amt_msat| {
+                total.saturating_add(amt_msat as u64)
+            }))
+    }
+
+    // Payments are placed at the time they were last updated, which for settled payments
+    // is when they succeeded.
+    pub async fn list_settled_payments_page_in_range(
+        &self,
+        node_id: &str,
+        from: i64,
+        to: i64,
+        page: usize,
+        page_size: usize,
+    ) -> Result<Vec<payment::Model>, Error> {
+        Ok(Payment::find()
+            .filter(payment::Column::NodeId.eq(node_id))
+            .filter(payment::Column::Status.eq(HTLCStatus::Succeeded.to_string()))
+            .filter(payment::Column::UpdatedAt.between(from, to))
+            .order_by_asc(payment::Column::UpdatedAt)
+            .order_by_asc(payment::Column::Id)
+            .paginate(&self.connection, page_size)
+            .fetch_page(page)
+            .await?)
+    }
+
+    pub async fn list_channel_events(
+        &self,
+        node_id: &str,
+        channel_id: &str,
+    ) -> Result<Vec<channel_event::Model>, Error> {
+        Ok(ChannelEvent::find()
+            .filter(channel_event::Column::NodeId.eq(node_id))
+            .filter(channel_event::Column::ChannelId.eq(channel_id))
+            .order_by_asc(channel_event::Column::CreatedAt)
+            .all(&self.connection)
+            .await?)
+    }
+
+    pub async fn latest_channel_event(
+        &self,
+        node_id: &str,
+        channel_id: &str,
+        kind: &str,
+    ) -> Result<Option<channel_event::Model>, Error> {
+        Ok(ChannelEvent::find()
+            .filter(channel_event::Column::NodeId.eq(node_id))
+            .filter(channel_event::Column::ChannelId.eq(channel_id))
+            .filter(channel_event::Column::Kind.eq(kind))
+            .order_by_desc(channel_event::Column::CreatedAt)
+            .one(&self.connection)
+            .await?)
+    }
+
+    pub async fn create_channel(
+        &self,
+        node_id: &str,
+        user_channel_id: u64,
+        counterparty_pubkey: &str,
+        temp_channel_id: &str,
+        capacity_sats: u64,
+    ) -> Result<channel::Model, Error> {
+        let channel = channel::ActiveModel {
+            node_id: ActiveValue::Set(node_id.to_string()),
+            counterparty_pubkey: ActiveValue::Set(counterparty_pubkey.to_string()),
+            user_channel_id: ActiveValue::Set(user_channel_id.to_string()),
+            channel_id: ActiveValue::Set(temp_channel_id.to_string()),
+            capacity_sats: ActiveValue::Set(capacity_sats.try_into().unwrap()),
+            ..Default::default()
+        };
+        Ok(channel.insert(&self.connection).await?)
+    }
+
+    pub async fn find_channel(
+        &self,
+        node_id: &str,
+        user_channel_id: u64,
+    ) -> Result<Option<channel::Model>, Error> {
+        Ok(Channel::find()
+            .filter(channel::Column::NodeId.eq(node_id))
+            .filter(channel::Column::UserChannelId.eq(user_channel_id.to_string()))
+            .one(&self.connection)
+            .await?)
+    }
+
+    pub async fn find_channel_by_channel_id(
+        &self,
+        node_id: &str,
+        channel_id: &str,
+    ) -> Result<Option<channel::Model>, Error> {
+        Ok(Channel::find()
+            .filter(channel::Column::NodeId.eq(node_id))
+            .filter(channel::Column::ChannelId.eq(channel_id))
+            .one(&self.connection)
+            .await?)
+    }
+
+    // The channel id replaces the temporary one once the channel is funded.
+    pub async fn update_channel_funding(
+        &self,
+        node_id: &str,
+        user_channel_id: u64,
+        channel_id: &str,
+        funding_txid: &str,
+    ) -> Result<(), Error> {
+        if let Some(channel) = self.find_channel(node_id, user_channel_id).await? {
+            let mut channel: channel::ActiveModel = channel.into();
+            channel.channel_id = ActiveValue::Set(channel_id.to_string());
+            channel.funding_txid = ActiveValue::Set(Some(funding_txid.to_string()));
+            channel.update(&self.connection).await?;
+        }
+        Ok(())
+    }
+
+    // Only fills in a missing short channel id, it doesn't change once the funding confirms.
+    pub async fn set_channel_short_channel_id(
+        &self,
+        node_id: &str,
+        channel_id: &str,
+        short_channel_id: u64,
+    ) -> Result<(), Error> {
+        let short_channel_id: i64 = short_channel_id
+            .try_into()
+            .map_err(|_e| Error::InvalidChannelId(short_channel_id.to_string()))?;
+        Channel::update_many()
+            .col_expr(
+                channel::Column::ShortChannelId,
+                Expr::value(short_channel_id),
+            )
+            .filter(channel::Column::NodeId.eq(node_id))
+            .filter(channel::Column::ChannelId.eq(channel_id))
+            .filter(channel::Column::ShortChannelId.is_null())
+            .exec(&self.connection)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn label_channel(
+        &self,
+        node_id: &str,
+        channel_id: &str,
+        label: String,
+    ) -> Result<(), Error> {
+        let channel = self
+            .find_channel_by_channel_id(node_id, channel_id)
+            .await?
+            .ok_or(Error::ChannelNotFound)?;
+        let mut channel: channel::ActiveModel = channel.into();
+        channel.label = ActiveValue::Set(Some(label));
+        channel.update(&self.connection).await?;
+        Ok(())
+    }
+
+    // Labels by channel id, for the channels that have one.
+    pub fn list_channel_labels_sync(
+        &self,
+        node_id: &str,
+    ) -> Result<HashMap<String, String>, Error> {
+        tokio::task::block_in_place(move || {
+            self.runtime_handle.block_on(async move {
+                Ok(Channel::find()
+                    .filter(channel::Column::NodeId.eq(node_id))
+                    .filter(channel::Column::Label.is_not_null())
+                    .all(&self.connection)
+                    .await?
+                    .into_iter()
+                    .filter_map(|channel| Some((channel.channel_id, channel.label?)))
+                    .collect())
+            })
+        })
+    }
+
+    pub async fn record_channel_funding_inputs(
+        &self,
+        node_id: &str,
+        channel_id: &str,
+        funding_txid: &str,
+        inputs: &[(OutPoint, Option<u64>)],
+        shared_channels: usize,
+    ) -> Result<(), Error> {
+        for (outpoint, amount_sats) in inputs {
+            let funding_input = channel_funding_input::ActiveModel {
+                node_id: ActiveValue::Set(node_id.to_string()),
+                channel_id: ActiveValue::Set(channel_id.to_string()),
+                funding_txid: ActiveValue::Set(funding_txid.to_string()),
+                txid: ActiveValue::Set(outpoint.txid.to_string()),
+                vout: ActiveValue::Set(outpoint.vout.try_into().unwrap()),
+                amount_sats: ActiveValue::Set(amount_sats.map(|amount| amount.try_into().unwrap())),
+                shared_channels: ActiveValue::Set(shared_channels.try_into().unwrap()),
+                ..Default::default()
+            };
+            funding_input.insert(&self.connection).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn list_channel_funding_inputs(
+        &self,
+        node_id: &str,
+        channel_id: &str,
+    ) -> Result<Vec<channel_funding_input::Model>, Error> {
+        Ok(ChannelFundingInput::find()
+            .filter(channel_funding_input::Column::NodeId.eq(node_id))
+            .filter(channel_funding_input::Column::ChannelId.eq(channel_id))
+            .order_by_asc(channel_funding_input::Column::CreatedAt)
+            .all(&self.connection)
+            .await?)
+    }
+
+    // Counts one settled or failed HTLC against the channel it crossed, creating the row the
+    // first time the channel shows up.
+    pub fn record_channel_htlc_sync(
+        &self,
+        node_id: &str,
+        channel_id: &str,
+        direction: HTLCDirection,
+        succeeded: bool,
+    ) -> Result<channel_htlc_stat::Model, Error> {
+        tokio::task::block_in_place(move || {
+            self.runtime_handle.block_on(async move {
+                let existing = ChannelHtlcStat::find()
+                    .filter(channel_htlc_stat::Column::NodeId.eq(node_id))
+                    .filter(channel_htlc_stat::Column::ChannelId.eq(channel_id))
+                    .one(&self.connection)
+                    .await?;
+
+                let insert = existing.is_none();
+                let mut stats: channel_htlc_stat::ActiveModel = match existing {
+                    Some(stats) => stats.into(),
+                    None => channel_htlc_stat::ActiveModel {
+                        node_id: ActiveValue::Set(node_id.to_string()),
+                        channel_id: ActiveValue::Set(channel_id.to_string()),
+                        inbound_succeeded: ActiveValue::Set(0),
+                        inbound_failed: ActiveValue::Set(0),
+                        outbound_succeeded: ActiveValue::Set(0),
+                        outbound_failed: ActiveValue::Set(0),
+                        ..Default::default()
+                    },
+                };
+
+                let counter = match (direction, succeeded) {
+                    (HTLCDirection::Inbound, true) => &mut stats.inbound_succeeded,
+                    (HTLCDirection::Inbound, false) => &mut stats.inbound_failed,
+                    (HTLCDirection::Outbound, true) => &mut stats.outbound_succeeded,
+                    (HTLCDirection::Outbound, false) => &mut stats.outbound_failed,
+                };
+                *counter = ActiveValue::Set(counter.clone().unwrap() + 1);
+
+                if insert {
+                    Ok(stats.insert(&self.connection).await?)
+                } else {
+                    Ok(stats.update(&self.connection).await?)
+                }
+            })
+        })
+    }
+
+    pub async fn find_channel_htlc_stats(
+        &self,
+        node_id: &str,
+        channel_id: &str,
+    ) -> Result<Option<channel_htlc_stat::Model>, Error> {
+        Ok(ChannelHtlcStat::find()
+            .filter(channel_htlc_stat::Column::NodeId.eq(node_id))
+            .filter(channel_htlc_stat::Column::ChannelId.eq(channel_id))
+            .one(&self.connection)
+            .await?)
+    }
+
+    // Swaps the whole persisted graph for a new snapshot in one transaction, readers see
+    // either the old graph or the new one.
+    // Makes the stored graph match `nodes` and `channels`, writing only what changed since
+    // the last snapshot. Nodes are matched by pubkey and channels by short channel id, the ids
+    // and timestamps of the given models are ignored.
+    pub async fn replace_network_graph(
+        &self,
+        nodes: Vec<graph_node::Model>,
+        channels: Vec<graph_channel::Model>,
+    ) -> Result<(), Error> {
+        let now = seconds_since_epoch();
+        let txn = self.connection.begin().await?;
+
+        let mut stored_nodes = GraphNode::find()
+            .all(&txn)
+            .await?
+            .into_iter()
+            .map(|node| (node.pubkey.clone(), node))
+            .collect::<HashMap<_, _>>();
+        let mut new_nodes = vec![];
+        for node in nodes {
+            match stored_nodes.remove(&node.pubkey) {
+                None => new_nodes.push(graph_node::ActiveModel {
+                    created_at: ActiveValue::Set(now),
+                    updated_at: ActiveValue::Set(now),
+                    pubkey: ActiveValue::Set(node.pubkey),
+                    alias: ActiveValue::Set(node.alias),
+                    last_update: ActiveValue::Set(node.last_update),
+                    ..Default::default()
+                }),
+                Some(stored)
+                    if stored.alias != node.alias || stored.last_update != node.last_update =>
+                {
+                    let mut stored: graph_node::ActiveModel = stored.into();
+                    stored.alias = ActiveValue::Set(node.alias);
+                    stored.last_update = ActiveValue::Set(node.last_update);
+                    stored.update(&txn).await?;
+                }
+                Some(_) => {}
+            }
+        }
+
+        let mut stored_channels = GraphChannel::find()
+            .all(&txn)
+            .await?
+            .into_iter()
+            .map(|channel| (channel.short_channel_id, channel))
+            .collect::<HashMap<_, _>>();
+        let mut new_channels = vec![];
+        for channel in channels {
+            match stored_channels.remove(&channel.short_channel_id) {
+                None => new_channels.push(graph_channel::ActiveModel {
+                    created_at: ActiveValue::Set(now),
+                    updated_at: ActiveValue::Set(now),
+                    short_channel_id: ActiveValue::Set(channel.short_channel_id),
+                    node_one: ActiveValue::Set(channel.node_one),
+                    node_two: ActiveValue::Set(channel.node_two),
+                    capacity_sats: ActiveValue::Set(channel.capacity_sats),
+                    one_to_two_fee_base_msat: ActiveValue::Set(channel.one_to_two_fee_base_msat),
+                    one_to_two_fee_proportional_millionths: ActiveValue::Set(
+                        channel.one_to_two_fee_proportional_millionths,
+                    ),
+                    two_to_one_fee_base_msat: ActiveValue::Set(channel.two_to_one_fee_base_msat),
+                    two_to_one_fee_proportional_millionths: ActiveValue::Set(
+                        channel.two_to_one_fee_proportional_millionths,
+                    ),
+                    ..Default::default()
+                }),
+                Some(stored) if !same_graph_channel(&stored, &channel) => {
+                    let mut stored: graph_channel::ActiveModel = stored.into();
+                    stored.node_one = ActiveValue::Set(channel.node_one);
+                    stored.node_two = ActiveValue::Set(channel.node_two);
+                    stored.capacity_sats = ActiveValue::Set(channel.capacity_sats);
+                    stored.one_to_two_fee_base_msat =
+                        ActiveValue::Set(channel.one_to_two_fee_base_msat);
+                    stored.one_to_two_fee_proportional_millionths =
+                        ActiveValue::Set(channel.one_to_two_fee_proportional_millionths);
+                    stored.two_to_one_fee_base_msat =
+                        ActiveValue::Set(channel.two_to_one_fee_base_msat);
+                    stored.two_to_one_fee_proportional_millionths =
+                        ActiveValue::Set(channel.two_to_one_fee_proportional_millionths);
+                    stored.update(&txn).await?;
+                }
+                Some(_) => {}
+            }
+        }
+
+        // what's left wasn't in the graph anymore, chunks stay well below the bind
+        // parameter limits of every backend
+        let removed_channels = stored_channels
+            .into_values()
+            .map(|channel| channel.id)
+            .collect::<Vec<_>>();
+        for chunk in removed_channels.chunks(50) {
+            GraphChannel::delete_many()
+                .filter(graph_channel::Column::Id.is_in(chunk.to_vec()))
+                .exec(&txn)
+                .await?;
+        }
+        let removed_nodes = stored_nodes
+            .into_values()
+            .map(|node| node.id)
+            .collect::<Vec<_>>();
+        for chunk in removed_nodes.chunks(50) {
+            GraphNode::delete_many()
+                .filter(graph_node::Column::Id.is_in(chunk.to_vec()))
+                .exec(&txn)
+                .await?;
+        }
+        for chunk in new_nodes.chunks(50) {
+            GraphNode::insert_many(chunk.to_vec()).exec(&txn).await?;
+        }
+        for chunk in new_channels.chunks(50) {
+            GraphChannel::insert_many(chunk.to_vec()).exec(&txn).await?;
+        }
+        txn.commit().await?;
+        Ok(())
+    }
+
+    pub async fn find_graph_node(&self, pubkey: &str) -> Result<Option<graph_node::Model>, Error> {
+        Ok(GraphNode::find()
+            .filter(graph_node::Column::Pubkey.eq(pubkey))
+            .one(&self.connection)
+            .await?)
+    }
+
+    pub async fn list_graph_channels(
+        &self,
+        pubkey: &str,
+    ) -> Result<Vec<graph_channel::Model>, Error> {
+        Ok(GraphChannel::find()
+            .filter(
+                Condition::any()
+                    .add(graph_channel::Column::NodeOne.eq(pubkey))
+                    .add(graph_channel::Column::NodeTwo.eq(pubkey)),
+            )
+            .order_by_asc(graph_channel::Column::ShortChannelId)
+            .all(&self.connection)
+            .await?)
+    }
+
+    pub async fn list_all_graph_channels(&self) -> Result<Vec<graph_channel::Model>, Error> {
+        Ok(GraphChannel::find().all(&self.connection).await?)
+    }
+
+    pub async fn create_deposit_address(
+        &self,
+        node_id: &str,
+        address: &str,
+        label: String,
+    ) -> Result<deposit_address::Model, Error> {
+        let deposit_address = deposit_address::ActiveModel {
+            node_id: ActiveValue::Set(node_id.to_string()),
+            address: ActiveValue::Set(address.to_string()),
+            label: ActiveValue::Set(label),
+            ..Default::default()
+        };
+
+        Ok(deposit_address.insert(&self.connection).await?)
+    }
+
+    pub async fn list_deposit_addresses(
+        &self,
+        node_id: &str,
+    ) -> Result<Vec<deposit_address::Model>, Error> {
+        Ok(DepositAddress::find()
+            .filter(deposit_address::Column::NodeId.eq(node_id))
+            .order_by_desc(deposit_address::Column::CreatedAt)
+            .all(&self.connection)
+            .await?)
+    }
+
     pub async fn port_in_use(&self, listen_addr: &str, listen_port: i32) -> Result<bool, Error> {
         self.get_node_by_connection_info(listen_addr, listen_port)
             .await
@@ -672,3 +1646,15 @@ impl SenseiDatabase {
         }
     }
 }
+
+fn same_graph_channel(stored: &graph_channel::Model, channel: &graph_channel::Model) -> bool {
+    stored.node_one == channel.node_one
+        && stored.node_two == channel.node_two
+        && stored.capacity_sats == channel.capacity_sats
+        && stored.one_to_two_fee_base_msat == channel.one_to_two_fee_base_msat
+        && stored.one_to_two_fee_proportional_millionths
+            == channel.one_to_two_fee_proportional_millionths
+        && stored.two_to_one_fee_base_msat == channel.two_to_one_fee_base_msat
+        && stored.two_to_one_fee_proportional_millionths
+            == channel.two_to_one_fee_proportional_millionths
+}
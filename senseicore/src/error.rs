@@ -37,6 +37,46 @@ pub enum Error {
     AdminNodeNotCreated,
     FundingGenerationNeverHappened,
     NodeBeingStartedAlready,
+    InvalidMaxCommitmentFeerate,
+    SelfPayment,
+    ChannelNotFound,
+    InvalidRoute(String),
+    ListenAddressInUse(String),
+    InvalidBindAddress(String),
+    InvalidChangeAddress(String),
+    InvalidFundingUtxos(String),
+    NoSpendableFunds(u64),
+    InsufficientFunds(u64, u64),
+    InFlightLimitExceeded(u64),
+    PaymentNotFound,
+    FundingTxConfirmed(String),
+    FundingAlreadyBroadcast(u64),
+    OpenCancelled,
+    TooManyPendingChannels(usize),
+    StaleStateHalt(usize),
+    NoChangeToBump(String),
+    MetadataTooLarge(usize),
+    InvalidPubkey(String),
+    InvalidPeerAddress(String),
+    PeerConnectFailed(String),
+    PeerNotAllowed(String),
+    ChainTipUnavailable,
+    SigningFailed(String),
+    ChannelCloseNeverHappened,
+    InvalidMnemonic(String),
+    PeerDisconnectedDuringFunding,
+    ChannelRejected(String),
+    InvalidPsbt(String),
+    BatchFundingNotFound(String),
+    FeeExceedsMaximum(u64, u64),
+    NetworkGraphNotPersisted,
+    ChannelNotUsable,
+    EventsLagged(u64),
+    InvalidChannelMonitor(String),
+    ChannelMonitorExists(String),
+    InvalidSubscription(String),
+    InvalidChannelId(String),
+    InvalidFeeRate(u64, u64),
 }
 
 impl Display for Error {
@@ -56,6 +96,7 @@ impl Display for Error {
             Error::LdkInvoiceSign(e) => e.to_string(),
             Error::LdkInvoiceParse(e) => e.to_string(),
             Error::InvalidSeedLength => String::from("invalid seed length"),
+            Error::InvalidMnemonic(e) => format!("invalid mnemonic: {}", e),
             Error::SeedNotFound => String::from("seed not found for node"),
             Error::MacaroonNotFound => String::from("macaroon not found for node"),
             Error::FailedToWriteSeed => String::from("failed to write seed"),
@@ -64,9 +105,108 @@ impl Display for Error {
             Error::AdminNodeNotCreated => String::from("admin node not created"),
             Error::AdminNodeNotStarted => String::from("admin node not started"),
             Error::NodeBeingStartedAlready => String::from("node already being started"),
+            Error::ListenAddressInUse(address) => format!(
+                "failed to bind to {}, is something else already listening on it?",
+                address
+            ),
+            Error::InvalidBindAddress(address) => {
+                format!("p2p bind address {} is not an ip address", address)
+            }
+            Error::InvalidChangeAddress(address) => {
+                format!(
+                    "fixed change address {} is not in this node's wallet",
+                    address
+                )
+            }
+            Error::InvalidFundingUtxos(e) => e.to_string(),
+            Error::NoSpendableFunds(balance_sats) => format!(
+                "wallet does not have enough spendable funds, its balance is {} sats",
+                balance_sats
+            ),
+            Error::InsufficientFunds(needed_sats, balance_sats) => format!(
+                "wallet balance of {} sats can't cover the {} sats needed including fees",
+                balance_sats, needed_sats
+            ),
+            Error::InFlightLimitExceeded(max_in_flight_msat) => format!(
+                "payment would put more than {} msat of outbound payments in flight",
+                max_in_flight_msat
+            ),
+            Error::SelfPayment => String::from("cannot pay an invoice issued by this node"),
+            Error::ChannelNotFound => String::from("channel not found"),
+            Error::SigningFailed(reason) => format!("failed to sign funding tx: {}", reason),
+            Error::ChainTipUnavailable => {
+                String::from("chain backend has not provided a chain tip yet")
+            }
+            Error::PaymentNotFound => String::from("payment not found"),
+            Error::MetadataTooLarge(max_bytes) => {
+                format!("metadata can be at most {} bytes of json", max_bytes)
+            }
+            Error::InvalidRoute(reason) => format!("invalid route: {}", reason),
+            Error::InvalidPubkey(pubkey) => format!("invalid pubkey: {}", pubkey),
+            Error::InvalidPeerAddress(address) => format!("invalid peer address: {}", address),
+            Error::PeerConnectFailed(peer) => format!("failed to connect to peer {}", peer),
+            Error::PeerNotAllowed(pubkey) => format!("peer {} is not in the allowlist", pubkey),
+            Error::InvalidMaxCommitmentFeerate => {
+                String::from("max commitment feerate is below the minimum relay feerate")
+            }
+            Error::ChannelCloseNeverHappened => {
+                String::from("channel did not close before the timeout, a cooperative close may still finish later")
+            }
             Error::FundingGenerationNeverHappened => {
-                String::from("funding generation for request never happened")
+                String::from("timed out waiting for the peer to accept the channel")
+            }
+            Error::PeerDisconnectedDuringFunding => {
+                String::from("peer disconnected before the channel was funded")
+            }
+            Error::ChannelRejected(reason) => format!("peer rejected the channel: {}", reason),
+            Error::InvalidPsbt(e) => format!("invalid psbt: {}", e),
+            Error::BatchFundingNotFound(txid) => {
+                format!("no channels are waiting on funding tx {}", txid)
+            }
+            Error::EventsLagged(skipped) => format!(
+                "missed {} node events while waiting, the outcome is unknown",
+                skipped
+            ),
+            Error::InvalidChannelMonitor(e) => format!("invalid channel monitor: {}", e),
+            Error::ChannelMonitorExists(funding_txo) => format!(
+                "already watching a channel monitor for funding output {}",
+                funding_txo
+            ),
+            Error::InvalidSubscription(e) => format!("invalid subscription: {}", e),
+            Error::InvalidChannelId(channel_id) => format!("invalid channel id {}", channel_id),
+            Error::FundingTxConfirmed(txid) => {
+                format!("funding tx {} already confirmed", txid)
+            }
+            Error::FundingAlreadyBroadcast(custom_id) => format!(
+                "channel {} was already funded, it can only be closed now",
+                custom_id
+            ),
+            Error::OpenCancelled => String::from("channel open was cancelled"),
+            Error::TooManyPendingChannels(max) => {
+                format!("already at the limit of {} pending channels", max)
             }
+            Error::StaleStateHalt(channels) => format!(
+                "node is halted, {} channels have stale state, see /v1/node/stale-state",
+                channels
+            ),
+            Error::NoChangeToBump(txid) => format!(
+                "funding tx {} has no unspent change output to bump it with",
+                txid
+            ),
+            Error::ChannelNotUsable => String::from(
+                "channel did not become usable before the timeout, it stays open",
+            ),
+            Error::NetworkGraphNotPersisted => String::from(
+                "network graph snapshots are disabled, set network_graph_snapshot_interval_secs",
+            ),
+            Error::FeeExceedsMaximum(fee_sats, max_fee_sats) => format!(
+                "funding tx fee of {} sats is above the maximum of {} sats",
+                fee_sats, max_fee_sats
+            ),
+            Error::InvalidFeeRate(sat_per_vb, max_sat_per_vb) => format!(
+                "fee rate of {} sat/vB must be between 1 and {} sat/vB",
+                sat_per_vb, max_sat_per_vb
+            ),
         };
         write!(f, "{}", str)
     }
@@ -7,31 +7,53 @@
 // You may not use this file except in accordance with one or both of these
 // licenses.
 
-use crate::chain::broadcaster::SenseiBroadcaster;
+use crate::chain::broadcaster::{BroadcastContext, BroadcastPurpose, SenseiBroadcaster};
 use crate::chain::database::WalletDatabase;
+use crate::chain::fee_estimator;
 use crate::chain::manager::SenseiChainManager;
-use crate::config::SenseiConfig;
+use crate::config::{AmountMismatchPolicy, SenseiConfig};
 use crate::database::SenseiDatabase;
 use crate::events::SenseiEvent;
 use crate::hex_utils;
-use crate::node::{ChannelManager, HTLCStatus, NetworkGraph, PaymentOrigin};
+use crate::node::{
+    ChannelManager, CircularPayments, HTLCDirection, HTLCStatus, NetworkGraph,
+    PaymentFailureReason, PaymentOrigin,
+};
 
 use bdk::wallet::AddressIndex;
-use bitcoin::{secp256k1::Secp256k1, Network};
+use bitcoin::consensus::encode::serialize;
+use bitcoin::{secp256k1::Secp256k1, Network, Script, Transaction, TxOut};
 use bitcoin_bech32::WitnessProgram;
 use entity::sea_orm::ActiveValue;
 use lightning::chain::chaininterface::BroadcasterInterface;
 use lightning::routing::gossip::NodeId;
+use lightning::routing::router::RouteHop;
 use lightning::{
-    chain::{chaininterface::ConfirmationTarget, keysinterface::KeysManager},
-    util::events::{Event, EventHandler, PaymentPurpose},
+    chain::{
+        chaininterface::ConfirmationTarget,
+        keysinterface::{KeysManager, SpendableOutputDescriptor},
+    },
+    util::events::{ClosureReason, Event, EventHandler, PaymentPurpose},
 };
 use rand::{thread_rng, Rng};
+use std::panic::{self, AssertUnwindSafe};
 use std::sync::Mutex;
 use std::{sync::Arc, time::Duration};
 use tokio::runtime::Handle;
 use tokio::sync::broadcast;
 
+// Whether an inbound channel is accepted as 0-conf. Peers marked zero_conf get 0-conf
+// channels, anyone else asking for one is rejected rather than trusted with our funds.
+pub fn inbound_zero_conf(requires_zero_conf: bool, is_trusted_peer: bool) -> Result<bool, String> {
+    match (requires_zero_conf, is_trusted_peer) {
+        (_, true) => Ok(true),
+        (false, false) => Ok(false),
+        (true, false) => Err(String::from(
+            "0-conf channels are only accepted from peers marked zero_conf",
+        )),
+    }
+}
+
 pub struct LightningNodeEventHandler {
     pub node_id: String,
     pub config: Arc<SenseiConfig>,
@@ -44,6 +66,110 @@ pub struct LightningNodeEventHandler {
     pub event_sender: broadcast::Sender<SenseiEvent>,
     pub broadcaster: Arc<SenseiBroadcaster>,
     pub network_graph: Arc<NetworkGraph>,
+    pub change_script: Option<Script>,
+    pub circular_payments: CircularPayments,
+}
+
+impl LightningNodeEventHandler {
+    // Spends the outputs to a single zero value OP_RETURN output so their whole value goes
+    // to fees. LDK adds change whenever any is left over, so the tx is signed once to get its
+    // weight and again at the feerate that spends everything on fees.
+    fn sweep_to_fees(
+        &self,
+        descriptors: &[&SpendableOutputDescriptor],
+        outputs_sats: u64,
+        min_feerate: u32,
+    ) -> Option<(Transaction, u32)> {
+        let secp_ctx = Secp256k1::new();
+        // bitcoind doesn't relay txs under 65 bytes without witnesses, which one input with
+        // an empty OP_RETURN would be. Four bytes of padding bring it to 65.
+        let op_return = TxOut {
+            value: 0,
+            script_pubkey: Script::new_op_return(&[0; 4]),
+        };
+        let spend = |feerate: u32| {
+            self.keys_manager.spend_spendable_outputs(
+                descriptors,
+                vec![op_return.clone()],
+                op_return.script_pubkey.clone(),
+                feerate,
+                &secp_ctx,
+            )
+        };
+
+        let mut measured_tx = spend(min_feerate).ok()?;
+        if measured_tx.output.len() == 1 {
+            return Some((measured_tx, min_feerate));
+        }
+        measured_tx.output.truncate(1);
+        let weight = measured_tx.weight() as u64;
+        let change_weight = serialize(&op_return).len() as u64 * 4;
+
+        // LDK charges weight * feerate / 1000 and estimates witnesses a little heavier than
+        // they end up. Spending everything sits between the fee without change and the fee
+        // with it, aiming for the middle leaves room on both sides.
+        let feerate = outputs_sats * 1000 / (weight + change_weight / 2);
+        let feerate = std::cmp::max(u32::try_from(feerate).ok()?, min_feerate);
+
+        match spend(feerate) {
+            Ok(spending_tx) if spending_tx.output.len() == 1 => Some((spending_tx, feerate)),
+            _ => None,
+        }
+    }
+
+    // Our own payments leave through the channel of the first hop, LDK only tells us its short
+    // channel id so it has to still be open for us to find it.
+    fn record_first_hop_htlc(&self, path: &[RouteHop], succeeded: bool) {
+        let first_hop = match path.first() {
+            Some(first_hop) => first_hop,
+            None => return,
+        };
+        let channel = self
+            .channel_manager
+            .list_channels()
+            .into_iter()
+            .find(|channel| channel.short_channel_id == Some(first_hop.short_channel_id));
+        if let Some(channel) = channel {
+            self.record_channel_htlc(&channel.channel_id, HTLCDirection::Outbound, succeeded);
+        }
+    }
+
+    // A failed path only counts against our first hop when that is the channel that failed it.
+    // When a channel further along or the recipient failed it, our hop did its part. Failures
+    // LDK couldn't attribute aren't counted either way.
+    fn record_failed_path(
+        &self,
+        path: &[RouteHop],
+        failed_short_channel_id: Option<u64>,
+        rejected_by_dest: bool,
+    ) {
+        let first_hop_scid = match path.first() {
+            Some(first_hop) => first_hop.short_channel_id,
+            None => return,
+        };
+        match failed_short_channel_id {
+            Some(scid) if scid == first_hop_scid => self.record_first_hop_htlc(path, false),
+            Some(_scid) => self.record_first_hop_htlc(path, true),
+            None if rejected_by_dest => self.record_first_hop_htlc(path, true),
+            None => {}
+        }
+    }
+
+    fn record_channel_htlc(
+        &self,
+        channel_id: &[u8; 32],
+        direction: HTLCDirection,
+        succeeded: bool,
+    ) {
+        if let Err(e) = self.database.record_channel_htlc_sync(
+            &self.node_id,
+            &hex_utils::hex_str(channel_id),
+            direction,
+            succeeded,
+        ) {
+            println!("failed to record channel htlc: {}", e);
+        }
+    }
 }
 
 impl EventHandler for LightningNodeEventHandler {
@@ -52,19 +178,130 @@ impl EventHandler for LightningNodeEventHandler {
             Event::OpenChannelRequest {
                 temporary_channel_id,
                 counterparty_node_id,
-                funding_satoshis: _,
+                funding_satoshis,
                 push_msat: _,
-                channel_type: _,
+                channel_type,
             } => {
-                let is_trusted_peer = match self
+                let known_peer = self
                     .database
                     .find_peer_sync(&self.node_id, &counterparty_node_id.to_string())
-                {
-                    Ok(Some(known_peer)) => known_peer.zero_conf,
-                    _ => false,
+                    .ok()
+                    .flatten();
+                let is_known_peer = known_peer.is_some();
+                let is_trusted_peer =
+                    matches!(known_peer, Some(known_peer) if known_peer.zero_conf);
+
+                let send_decision = |accepted: bool, reason: Option<String>| {
+                    self.event_sender
+                        .send(SenseiEvent::InboundChannelDecision {
+                            node_id: self.node_id.clone(),
+                            counterparty_node_id: *counterparty_node_id,
+                            funding_satoshis: *funding_satoshis,
+                            accepted,
+                            reason,
+                        })
+                        .unwrap_or_default();
                 };
 
-                if is_trusted_peer {
+                if !self.config.peer_allowed(counterparty_node_id) {
+                    let reason = String::from("peer is not in the allowlist");
+                    println!(
+                        "rejecting inbound channel from {:?}: {}",
+                        counterparty_node_id, reason
+                    );
+                    let _res = self
+                        .channel_manager
+                        .force_close_channel(temporary_channel_id, counterparty_node_id);
+                    send_decision(false, Some(reason));
+                    return;
+                }
+
+                if !self.config.accept_inbound_channels {
+                    let reason = String::from("inbound channels are disabled");
+                    println!(
+                        "rejecting inbound channel from {:?}: {}",
+                        counterparty_node_id, reason
+                    );
+                    let _res = self
+                        .channel_manager
+                        .force_close_channel(temporary_channel_id, counterparty_node_id);
+                    send_decision(false, Some(reason));
+                    return;
+                }
+
+                // Trusted peers are exempt from the size limit, they were added by the operator.
+                if let Some(max_channel_sats) = self.config.max_auto_accept_channel_sats {
+                    if !is_trusted_peer && *funding_satoshis > max_channel_sats {
+                        let reason = format!(
+                            "channel of {} sats is above the auto accept limit of {} sats",
+                            funding_satoshis, max_channel_sats
+                        );
+                        println!(
+                            "rejecting inbound channel from {:?}: {}",
+                            counterparty_node_id, reason
+                        );
+                        // LDK rejects an inbound channel request by force closing it.
+                        let _res = self
+                            .channel_manager
+                            .force_close_channel(temporary_channel_id, counterparty_node_id);
+                        send_decision(false, Some(reason));
+                        return;
+                    }
+                }
+
+                // Known peers skip the usefulness filters below, they're meant for channel spam
+                // from peers the operator hasn't heard of.
+                if !is_known_peer {
+                    let mut reason = None;
+                    if let Some(min_channel_sats) = self.config.min_inbound_channel_sats {
+                        if *funding_satoshis < min_channel_sats {
+                            reason = Some(format!(
+                                "channel of {} sats is below the minimum of {} sats",
+                                funding_satoshis, min_channel_sats
+                            ));
+                        }
+                    }
+                    if reason.is_none()
+                        && self.config.reject_inbound_channels_from_unknown_nodes
+                        && self
+                            .network_graph
+                            .read_only()
+                            .nodes()
+                            .get(&NodeId::from_pubkey(counterparty_node_id))
+                            .is_none()
+                    {
+                        reason = Some(String::from("peer is not in the network graph"));
+                    }
+                    if let Some(reason) = reason {
+                        println!(
+                            "rejecting inbound channel from {:?}: {}",
+                            counterparty_node_id, reason
+                        );
+                        let _res = self
+                            .channel_manager
+                            .force_close_channel(temporary_channel_id, counterparty_node_id);
+                        send_decision(false, Some(reason));
+                        return;
+                    }
+                }
+
+                let zero_conf =
+                    match inbound_zero_conf(channel_type.requires_zero_conf(), is_trusted_peer) {
+                        Ok(zero_conf) => zero_conf,
+                        Err(reason) => {
+                            println!(
+                                "rejecting inbound channel from {:?}: {}",
+                                counterparty_node_id, reason
+                            );
+                            let _res = self
+                                .channel_manager
+                                .force_close_channel(temporary_channel_id, counterparty_node_id);
+                            send_decision(false, Some(reason));
+                            return;
+                        }
+                    };
+
+                if zero_conf {
                     match self
                         .channel_manager
                         .accept_inbound_channel_from_trusted_peer_0conf(
@@ -77,12 +314,14 @@ impl EventHandler for LightningNodeEventHandler {
                                 "accepted 0-conf inbound channel from {:?}",
                                 counterparty_node_id
                             );
+                            send_decision(true, None);
                         }
                         Err(e) => {
                             println!(
                                 "failed to accept 0-conf inbound channel from {:?}: {:?}",
                                 counterparty_node_id, e
                             );
+                            send_decision(false, Some(format!("{:?}", e)));
                         }
                     }
                 } else {
@@ -93,12 +332,14 @@ impl EventHandler for LightningNodeEventHandler {
                     ) {
                         Ok(()) => {
                             println!("accepted inbound channel from {:?}", counterparty_node_id);
+                            send_decision(true, None);
                         }
                         Err(e) => {
                             println!(
                                 "failed to accept inbound channel from {:?}: {:?}",
                                 counterparty_node_id, e
                             );
+                            send_decision(false, Some(format!("{:?}", e)));
                         }
                     }
                 }
@@ -148,18 +389,119 @@ impl EventHandler for LightningNodeEventHandler {
                     amount_msat
                 );
 
-                let payment_preimage = match purpose {
+                let hex_payment_hash = hex_utils::hex_str(&payment_hash.0);
+                let existing_payment = self
+                    .database
+                    .find_payment_sync(self.node_id.clone(), hex_payment_hash.clone())
+                    .unwrap_or(None);
+
+                if self.config.reject_duplicate_payment_hashes {
+                    if let Some(payment) = &existing_payment {
+                        if payment.status == HTLCStatus::Succeeded.to_string() {
+                            println!(
+                                "\nEVENT: failing duplicate payment for payment hash {}",
+                                hex_payment_hash
+                            );
+                            self.channel_manager.fail_htlc_backwards(payment_hash);
+                            return;
+                        }
+                    }
+                }
+
+                let (payment_preimage, origin) = match purpose {
                     PaymentPurpose::InvoicePayment {
                         payment_preimage, ..
-                    } => *payment_preimage,
-                    PaymentPurpose::SpontaneousPayment(preimage) => Some(*preimage),
+                    } => (*payment_preimage, PaymentOrigin::InvoiceIncoming),
+                    PaymentPurpose::SpontaneousPayment(preimage) => {
+                        (Some(*preimage), PaymentOrigin::SpontaneousIncoming)
+                    }
+                };
+
+                let payment_preimage = match payment_preimage {
+                    Some(payment_preimage) => payment_preimage,
+                    None => {
+                        println!(
+                            "\nEVENT: no preimage known for payment hash {}, failing back",
+                            hex_payment_hash
+                        );
+                        self.channel_manager.fail_htlc_backwards(payment_hash);
+                        return;
+                    }
+                };
+
+                // Invoices we issued are stored with the amount they asked for.
+                let expected_msat = match (&origin, &existing_payment) {
+                    (PaymentOrigin::InvoiceIncoming, Some(payment)) => {
+                        payment.amt_msat.map(|amt_msat| amt_msat as u64)
+                    }
+                    _ => None,
+                };
+                let mismatch_policy = match expected_msat {
+                    Some(expected_msat) if expected_msat != *amount_msat => {
+                        self.config.payment_amount_mismatch_policy
+                    }
+                    _ => None,
+                };
+
+                if mismatch_policy == Some(AmountMismatchPolicy::Reject) {
+                    println!(
+                        "\nEVENT: failing payment hash {} for not matching the invoice amount",
+                        hex_payment_hash
+                    );
+                    self.channel_manager.fail_htlc_backwards(payment_hash);
+                    return;
+                }
+
+                let status = match mismatch_policy {
+                    Some(AmountMismatchPolicy::Hold) => HTLCStatus::Held,
+                    _ => HTLCStatus::Claiming,
                 };
 
+                // Record that the full amount arrived before claiming so that a restart
+                // before PaymentClaimed can finish the claim instead of leaving it pending.
+                let preimage = Some(hex_utils::hex_str(&payment_preimage.0));
+                let amt_msat: Option<i64> = Some((*amount_msat).try_into().unwrap());
+                match existing_payment {
+                    Some(payment) => {
+                        let mut payment: entity::payment::ActiveModel = payment.into();
+                        payment.status = ActiveValue::Set(status.to_string());
+                        payment.preimage = ActiveValue::Set(preimage);
+                        payment.amt_msat = ActiveValue::Set(amt_msat);
+                        self.database.update_payment_sync(payment).unwrap();
+                    }
+                    None => {
+                        let payment = entity::payment::ActiveModel {
+                            node_id: ActiveValue::Set(self.node_id.clone()),
+                            payment_hash: ActiveValue::Set(hex_payment_hash.clone()),
+                            status: ActiveValue::Set(status.to_string()),
+                            preimage: ActiveValue::Set(preimage),
+                            amt_msat: ActiveValue::Set(amt_msat),
+                            origin: ActiveValue::Set(origin.to_string()),
+                            ..Default::default()
+                        };
+                        self.database.insert_payment_sync(payment).unwrap();
+                    }
+                }
+
+                if let (HTLCStatus::Held, Some(expected_msat)) = (&status, expected_msat) {
+                    println!(
+                        "\nEVENT: holding payment hash {}, expected {} millisatoshis",
+                        hex_payment_hash, expected_msat
+                    );
+                    let _res = self.event_sender.send(SenseiEvent::PaymentHeld {
+                        node_id: self.node_id.clone(),
+                        payment_hash: hex_payment_hash,
+                        expected_msat,
+                        received_msat: *amount_msat,
+                    });
+                    return;
+                }
+
                 // TODO: if we want 'hodl invoices' we should have user set a flag on the invoice when they create it
                 //       then when we receive this event we can store the preimage + flag in db for this payment
                 //       user can then manually accept it
                 //        or maybe defines some custom logic on if/when to accept it
-                self.channel_manager.claim_funds(payment_preimage.unwrap());
+                self.channel_manager.claim_funds(payment_preimage);
             }
             Event::PaymentClaimed {
                 payment_hash,
@@ -192,7 +534,7 @@ impl EventHandler for LightningNodeEventHandler {
                 let secret = payment_secret.map(|secret| hex_utils::hex_str(&secret.0));
                 let amt_msat: Option<i64> = Some((*amount_msat).try_into().unwrap());
 
-                match existing_payment {
+                let payment = match existing_payment {
                     Some(payment) => {
                         let mut payment: entity::payment::ActiveModel = payment.into();
                         payment.status = ActiveValue::Set(HTLCStatus::Succeeded.to_string());
@@ -200,10 +542,11 @@ impl EventHandler for LightningNodeEventHandler {
                         payment.secret = ActiveValue::Set(secret);
                         payment.amt_msat = ActiveValue::Set(amt_msat);
 
-                        self.database.update_payment_sync(payment).unwrap();
+                        self.database.update_payment_sync(payment).unwrap()
                     }
                     None => {
                         let payment = entity::payment::ActiveModel {
+                            node_id: ActiveValue::Set(self.node_id.clone()),
                             payment_hash: ActiveValue::Set(payment_hash),
                             status: ActiveValue::Set(HTLCStatus::Succeeded.to_string()),
                             preimage: ActiveValue::Set(preimage),
@@ -215,9 +558,20 @@ impl EventHandler for LightningNodeEventHandler {
                             ..Default::default()
                         };
 
-                        self.database.insert_payment_sync(payment).unwrap();
+                        self.database.insert_payment_sync(payment).unwrap()
                     }
                 };
+
+                self.event_sender
+                    .send(SenseiEvent::PaymentClaimed {
+                        node_id: self.node_id.clone(),
+                        payment_hash: payment.payment_hash,
+                        amount_msat: *amount_msat,
+                        preimage: payment.preimage,
+                        label: payment.label,
+                        settled_at: payment.updated_at,
+                    })
+                    .unwrap_or_default();
             }
             Event::PaymentSent {
                 payment_preimage,
@@ -225,6 +579,7 @@ impl EventHandler for LightningNodeEventHandler {
                 fee_paid_msat,
                 ..
             } => {
+                self.circular_payments.remove(payment_hash);
                 let hex_payment_hash = hex_utils::hex_str(&payment_hash.0);
 
                 let payment = self
@@ -238,6 +593,8 @@ impl EventHandler for LightningNodeEventHandler {
                     payment.preimage =
                         ActiveValue::Set(Some(hex_utils::hex_str(&payment_preimage.0)));
                     payment.status = ActiveValue::Set(HTLCStatus::Succeeded.to_string());
+                    payment.failure_reason = ActiveValue::Set(None);
+                    payment.failure_short_channel_id = ActiveValue::Set(None);
 
                     let _res = self.database.update_payment_sync(payment);
 
@@ -255,9 +612,51 @@ impl EventHandler for LightningNodeEventHandler {
                     );
                 }
             }
-            Event::PaymentPathSuccessful { .. } => {}
-            Event::PaymentPathFailed { .. } => {}
+            Event::PaymentPathSuccessful { path, .. } => {
+                self.record_first_hop_htlc(path, true);
+            }
+            Event::PaymentPathFailed {
+                payment_hash,
+                rejected_by_dest,
+                short_channel_id,
+                path,
+                all_paths_failed,
+                ..
+            } => {
+                self.record_failed_path(path, *short_channel_id, *rejected_by_dest);
+                // circular payments are sent without payment params, nothing retries them
+                if *all_paths_failed {
+                    self.circular_payments.remove(payment_hash);
+                }
+
+                // The invoice payer retries on its own, this is only final once PaymentFailed
+                // comes in. The failing channel is only known when an intermediate node failed.
+                // A path can still fail after another one settled the payment, that doesn't
+                // make the payment any less successful.
+                let hex_payment_hash = hex_utils::hex_str(&payment_hash.0);
+                let reason = match rejected_by_dest {
+                    true => PaymentFailureReason::RecipientRejected,
+                    false => PaymentFailureReason::RouteFailed,
+                };
+
+                let payment = self
+                    .database
+                    .find_payment_sync(self.node_id.clone(), hex_payment_hash);
+
+                if let Ok(Some(payment)) = payment {
+                    if payment.status == HTLCStatus::Succeeded.to_string() {
+                        return;
+                    }
+                    let mut payment: entity::payment::ActiveModel = payment.into();
+                    payment.failure_reason = ActiveValue::Set(Some(reason.to_string()));
+                    payment.failure_short_channel_id = ActiveValue::Set(
+                        short_channel_id.map(|short_channel_id| short_channel_id as i64),
+                    );
+                    let _res = self.database.update_payment_sync(payment);
+                }
+            }
             Event::PaymentFailed { payment_hash, .. } => {
+                self.circular_payments.remove(payment_hash);
                 print!(
                     "\nEVENT: Failed to send payment to payment hash {:?}: exhausted payment retry attempts",
 				    hex_utils::hex_str(&payment_hash.0)
@@ -270,8 +669,20 @@ impl EventHandler for LightningNodeEventHandler {
                     .find_payment_sync(self.node_id.clone(), hex_payment_hash);
 
                 if let Ok(Some(payment)) = payment {
+                    if payment.status == HTLCStatus::Succeeded.to_string() {
+                        println!(
+                            "not marking succeeded payment {} as failed",
+                            payment.payment_hash
+                        );
+                        return;
+                    }
+                    let failure_reason = payment
+                        .failure_reason
+                        .clone()
+                        .unwrap_or_else(|| PaymentFailureReason::RetriesExhausted.to_string());
                     let mut payment: entity::payment::ActiveModel = payment.into();
                     payment.status = ActiveValue::Set(HTLCStatus::Failed.to_string());
+                    payment.failure_reason = ActiveValue::Set(Some(failure_reason));
 
                     let res = self.database.update_payment_sync(payment);
 
@@ -342,25 +753,29 @@ impl EventHandler for LightningNodeEventHandler {
                         "\nEVENT: Forwarded payment{}{}, earning {} msat {}",
                         from_prev_str, to_next_str, fee_earned, from_onchain_str
                     );
-
-                    // let forwarded_payment = ForwardedPayment {
-                    //     hours_since_epoch: utils::hours_since_epoch().unwrap(),
-                    //     from_channel_id: None,
-                    //     to_channel_id: None,
-                    //     fees_earned_msat: *fee_earned,
-                    //     total_payments: 1,
-                    // };
-
-                    // let mut database = self.database.lock().unwrap();
-                    // database
-                    //     .record_forwarded_payment(forwarded_payment)
-                    //     .unwrap();
                 } else {
                     println!(
                         "\nEVENT: Forwarded payment{}{}, claiming onchain {}",
                         from_prev_str, to_next_str, from_onchain_str
                     );
                 }
+
+                if let Err(e) = self.database.record_forward_sync(
+                    &self.node_id,
+                    prev_channel_id.map(|channel_id| hex_utils::hex_str(&channel_id)),
+                    next_channel_id.map(|channel_id| hex_utils::hex_str(&channel_id)),
+                    *fee_earned_msat,
+                    *claim_from_onchain_tx,
+                ) {
+                    println!("failed to record forwarded payment: {}", e);
+                }
+
+                if let Some(prev_channel_id) = prev_channel_id {
+                    self.record_channel_htlc(prev_channel_id, HTLCDirection::Inbound, true);
+                }
+                if let Some(next_channel_id) = next_channel_id {
+                    self.record_channel_htlc(next_channel_id, HTLCDirection::Outbound, true);
+                }
             }
             Event::PendingHTLCsForwardable { time_forwardable } => {
                 let forwarding_channel_manager = self.channel_manager.clone();
@@ -372,39 +787,142 @@ impl EventHandler for LightningNodeEventHandler {
                 });
             }
             Event::SpendableOutputs { outputs } => {
-                let wallet = self.wallet.lock().unwrap();
-                let address_info = wallet.get_address(AddressIndex::LastUnused).unwrap();
-                let destination_address = address_info.address;
+                let destination_script = match &self.change_script {
+                    Some(change_script) => change_script.clone(),
+                    None => {
+                        let wallet = self.wallet.lock().unwrap();
+                        let address_info = wallet.get_address(AddressIndex::LastUnused).unwrap();
+                        address_info.address.script_pubkey()
+                    }
+                };
                 let output_descriptors = &outputs.iter().collect::<Vec<_>>();
+                let outputs_sats = outputs
+                    .iter()
+                    .map(|descriptor| match descriptor {
+                        SpendableOutputDescriptor::StaticOutput { output, .. } => output.value,
+                        SpendableOutputDescriptor::DelayedPaymentOutput(descriptor) => {
+                            descriptor.output.value
+                        }
+                        SpendableOutputDescriptor::StaticPaymentOutput(descriptor) => {
+                            descriptor.output.value
+                        }
+                    })
+                    .sum::<u64>();
 
-                let tx_feerate = self
+                let mut tx_feerate = self
                     .chain_manager
-                    .fee_estimator
-                    .get_est_sat_per_1000_weight(ConfirmationTarget::Normal);
-
-                let spending_tx = self
-                    .keys_manager
-                    .spend_spendable_outputs(
-                        output_descriptors,
-                        Vec::new(),
-                        destination_address.script_pubkey(),
-                        tx_feerate,
-                        &Secp256k1::new(),
-                    )
-                    .unwrap();
+                    .wallet_feerate(ConfirmationTarget::Normal);
+
+                let spending_tx = match self.keys_manager.spend_spendable_outputs(
+                    output_descriptors,
+                    Vec::new(),
+                    destination_script,
+                    tx_feerate,
+                    &Secp256k1::new(),
+                ) {
+                    Ok(spending_tx) => spending_tx,
+                    Err(()) => {
+                        println!(
+                            "ERROR: {} spendable outputs worth {} sats can't cover the fee to sweep them",
+                            outputs.len(),
+                            outputs_sats
+                        );
+                        return;
+                    }
+                };
+
+                // LDK leaves out the change output when it would be below the network dust
+                // limit, which leaves us with a transaction that has no outputs at all.
+                let swept_sats = spending_tx
+                    .output
+                    .iter()
+                    .map(|output| output.value)
+                    .sum::<u64>();
+                let spending_tx = if spending_tx.output.is_empty()
+                    || swept_sats < self.config.sweep_dust_limit_sats
+                {
+                    println!(
+                        "EVENT: sweeping {} sats from {} spendable outputs is below the dust limit of {} sats, dropping it to fees",
+                        swept_sats,
+                        outputs.len(),
+                        self.config.sweep_dust_limit_sats
+                    );
+                    match self.sweep_to_fees(output_descriptors, outputs_sats, tx_feerate) {
+                        Some((spending_tx, feerate)) => {
+                            tx_feerate = feerate;
+                            spending_tx
+                        }
+                        None => {
+                            println!(
+                                "ERROR: failed to drop {} sats of spendable outputs to fees",
+                                outputs_sats
+                            );
+                            return;
+                        }
+                    }
+                } else {
+                    spending_tx
+                };
 
+                self.broadcaster.set_context(
+                    spending_tx.txid(),
+                    BroadcastContext {
+                        purpose: BroadcastPurpose::Sweep,
+                        context: Some(format!("{} spendable outputs", outputs.len())),
+                        feerate_sat_per_vb: Some(fee_estimator::sat_per_vb(tx_feerate) as f64),
+                    },
+                );
                 self.broadcaster.broadcast_transaction(&spending_tx);
             }
             Event::ChannelClosed {
                 channel_id,
                 reason,
-                user_channel_id: _,
+                user_channel_id,
             } => {
                 println!(
                     "\nEVENT: Channel {} closed due to: {:?}",
                     hex_utils::hex_str(channel_id),
                     reason
                 );
+
+                let _res = self.database.record_channel_event_sync(
+                    &self.node_id,
+                    &hex_utils::hex_str(channel_id),
+                    "closed",
+                    "ldk",
+                    Some(format!("{:?}", reason)),
+                );
+
+                self.event_sender
+                    .send(SenseiEvent::ChannelClosed {
+                        node_id: self.node_id.clone(),
+                        channel_id: *channel_id,
+                        user_channel_id: *user_channel_id,
+                        reason: format!("{:?}", reason),
+                        peer_disconnected: matches!(reason, ClosureReason::DisconnectedPeer),
+                    })
+                    .unwrap_or_default();
+
+                // LDK doesn't report feerate updates it accepts, but a rejected update
+                // always ends up force closing the channel with a processing error.
+                if let ClosureReason::ProcessingError { err } = reason {
+                    if fee_estimator::is_peer_feerate_rejection(err) {
+                        let _res = self.database.record_channel_event_sync(
+                            &self.node_id,
+                            &hex_utils::hex_str(channel_id),
+                            "feerate_update_rejected",
+                            "peer",
+                            Some(err.clone()),
+                        );
+                        self.event_sender
+                            .send(SenseiEvent::FeerateUpdateRejected {
+                                node_id: self.node_id.clone(),
+                                channel_id: *channel_id,
+                                reason: err.clone(),
+                            })
+                            .unwrap_or_default();
+                    }
+                }
             }
             Event::DiscardFunding { .. } => {
                 // A "real" node should probably "lock" the UTXOs spent in funding transactions until
@@ -413,3 +931,92 @@ impl EventHandler for LightningNodeEventHandler {
         }
     }
 }
+
+const MAX_EVENT_ATTEMPTS: usize = 3;
+
+// What happens when the handler panics on an event.
+enum PanicRecovery {
+    // handling the event again can't repeat a side effect, so it's retried
+    Retry,
+    // the panic is reported and let through
+    Escalate,
+    // the panic isn't caught at all, the handler holds the wallet lock and a node that
+    // carried on would find it poisoned
+    Uncaught,
+}
+
+fn panic_recovery(event: &Event) -> PanicRecovery {
+    match event {
+        Event::PendingHTLCsForwardable { .. }
+        | Event::PaymentPathSuccessful { .. }
+        | Event::DiscardFunding { .. } => PanicRecovery::Retry,
+        Event::SpendableOutputs { .. } => PanicRecovery::Uncaught,
+        _ => PanicRecovery::Escalate,
+    }
+}
+
+// Keeps a panicking handler from dropping events. LDK hands each event out only once, so
+// an event whose handler panics is retried right away when that's safe, with an
+// EventLoopRestarted event emitted for every panic. Events whose handling has side
+// effects, or that keep panicking, let the panic through and stop the background
+// processor rather than dropping the event. The channel manager isn't persisted past it
+// then, so the event is handed out again when the node restarts.
+pub struct SupervisedEventHandler<H: EventHandler> {
+    node_id: String,
+    handler: H,
+    event_sender: broadcast::Sender<SenseiEvent>,
+}
+
+impl<H: EventHandler> SupervisedEventHandler<H> {
+    pub fn new(node_id: String, handler: H, event_sender: broadcast::Sender<SenseiEvent>) -> Self {
+        Self {
+            node_id,
+            handler,
+            event_sender,
+        }
+    }
+}
+
+impl<H: EventHandler> EventHandler for SupervisedEventHandler<H> {
+    fn handle_event(&self, event: &Event) {
+        let max_attempts = match panic_recovery(event) {
+            PanicRecovery::Retry => MAX_EVENT_ATTEMPTS,
+            PanicRecovery::Escalate => 1,
+            PanicRecovery::Uncaught => return self.handler.handle_event(event),
+        };
+        for attempt in 1..=max_attempts {
+            let result = panic::catch_unwind(AssertUnwindSafe(|| self.handler.handle_event(event)));
+
+            let panic = match result {
+                Ok(()) => return,
+                Err(panic) => panic,
+            };
+            let reason = if let Some(reason) = panic.downcast_ref::<&str>() {
+                reason.to_string()
+            } else if let Some(reason) = panic.downcast_ref::<String>() {
+                reason.clone()
+            } else {
+                String::from("unknown panic")
+            };
+
+            self.event_sender
+                .send(SenseiEvent::EventLoopRestarted {
+                    node_id: self.node_id.clone(),
+                    reason: reason.clone(),
+                })
+                .unwrap_or_default();
+
+            if attempt == max_attempts {
+                println!(
+                    "\nEVENT: event handler panicked handling {:?} {} times, halting: {}",
+                    event, attempt, reason
+                );
+                panic::resume_unwind(panic);
+            }
+            println!(
+                "\nEVENT: event handler panicked handling {:?}, retrying: {}",
+                event, reason
+            );
+        }
+    }
+}
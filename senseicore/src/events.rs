@@ -15,4 +15,108 @@ pub enum SenseiEvent {
         user_channel_id: u64,
         counterparty_node_id: PublicKey,
     },
+    FeerateUpdateRejected {
+        node_id: String,
+        channel_id: [u8; 32],
+        reason: String,
+    },
+    PaymentClaimed {
+        node_id: String,
+        payment_hash: String,
+        amount_msat: u64,
+        preimage: Option<String>,
+        label: Option<String>,
+        settled_at: i64,
+    },
+    EventLoopRestarted {
+        node_id: String,
+        reason: String,
+    },
+    ZombieChannelDetected {
+        node_id: String,
+        channel_id: [u8; 32],
+        counterparty_pubkey: String,
+        offline_secs: u64,
+    },
+    ZombieChannelClosing {
+        node_id: String,
+        channel_id: [u8; 32],
+        force: bool,
+    },
+    UtxosConsolidated {
+        node_id: String,
+        txid: Txid,
+        num_inputs: usize,
+    },
+    PeerRateLimited {
+        node_id: String,
+        address: String,
+        blocked_secs: u64,
+    },
+    InboundChannelDecision {
+        node_id: String,
+        counterparty_node_id: PublicKey,
+        funding_satoshis: u64,
+        accepted: bool,
+        reason: Option<String>,
+    },
+    UnexpectedDepositScript {
+        node_id: String,
+        txid: Txid,
+        vout: u32,
+        amount_sats: u64,
+        script_type: String,
+    },
+    // Progress of a batch open, keyed by the requests' custom_id. The funding tx is handed to
+    // LDK when BatchFundingBroadcast is sent, which broadcasts it once every peer has signed.
+    BatchChannelInitiated {
+        node_id: String,
+        custom_id: u64,
+    },
+    BatchChannelFundingReady {
+        node_id: String,
+        custom_id: u64,
+    },
+    BatchFundingBroadcast {
+        node_id: String,
+        txid: Txid,
+        channel_count: usize,
+    },
+    FundingConfirmed {
+        node_id: String,
+        txid: Txid,
+        confirmations: u32,
+        block_height: u32,
+    },
+    FundingBroadcastTimedOut {
+        node_id: String,
+        txid: Txid,
+        unsigned_channels: usize,
+    },
+    SubscriptionInvoiceCreated {
+        node_id: String,
+        subscription_id: String,
+        invoice: String,
+    },
+    PaymentHeld {
+        node_id: String,
+        payment_hash: String,
+        expected_msat: u64,
+        received_msat: u64,
+    },
+    StaleStateDetected {
+        node_id: String,
+        funding_txos: Vec<String>,
+    },
+    TxStuckBelowRelay {
+        node_id: String,
+        txid: Txid,
+    },
+    ChannelClosed {
+        node_id: String,
+        channel_id: [u8; 32],
+        user_channel_id: u64,
+        reason: String,
+        peer_disconnected: bool,
+    },
 }
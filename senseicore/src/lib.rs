@@ -1,6 +1,7 @@
 pub mod chain;
 pub mod channels;
 pub mod config;
+pub mod consolidation;
 pub mod database;
 pub mod disk;
 pub mod error;
@@ -10,6 +11,12 @@ pub mod hex_utils;
 pub mod network_graph;
 pub mod node;
 pub mod persist;
+pub mod rate_limit;
+pub mod revenue;
+pub mod router;
 pub mod services;
+pub mod socks;
+pub mod stale_state;
+pub mod uptime;
 pub mod utils;
 pub mod version;
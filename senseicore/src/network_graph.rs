@@ -8,12 +8,17 @@
 // licenses.
 
 use bitcoin::secp256k1::PublicKey;
+use entity::{graph_channel, graph_node, seconds_since_epoch};
 use lightning::{
     ln::msgs::{self, Init, LightningError, RoutingMessageHandler},
     util::events::{MessageSendEvent, MessageSendEventsProvider},
 };
+use std::collections::{HashMap, VecDeque};
 use std::{ops::Deref, sync::Arc};
 
+use crate::database::SenseiDatabase;
+use crate::error::Error;
+use crate::hex_utils;
 use crate::node::{NetworkGraph, NetworkGraphMessageHandler};
 
 #[derive(Clone)]
@@ -40,6 +45,106 @@ impl SenseiNetworkGraph {
     }
 }
 
+// Writes what changed in the graph since the previous snapshot to the database. The
+// database lags the in-memory graph by up to the snapshot interval.
+pub async fn snapshot_network_graph(
+    network_graph: &NetworkGraph,
+    database: &SenseiDatabase,
+) -> Result<(), Error> {
+    let now = seconds_since_epoch();
+    let (nodes, channels) = {
+        let graph = network_graph.read_only();
+        let nodes = graph
+            .nodes()
+            .iter()
+            .map(|(node_id, node)| {
+                let info = node.announcement_info.as_ref();
+                graph_node::Model {
+                    id: String::new(),
+                    created_at: now,
+                    updated_at: now,
+                    pubkey: hex_utils::hex_str(node_id.as_slice()),
+                    alias: info.map(|info| {
+                        String::from_utf8_lossy(&info.alias)
+                            .trim_end_matches('\0')
+                            .to_string()
+                    }),
+                    last_update: info.map(|info| info.last_update as i64),
+                }
+            })
+            .collect::<Vec<_>>();
+        let channels = graph
+            .channels()
+            .iter()
+            .map(|(short_channel_id, channel)| {
+                let one_to_two = channel.one_to_two.as_ref().map(|policy| policy.fees);
+                let two_to_one = channel.two_to_one.as_ref().map(|policy| policy.fees);
+                graph_channel::Model {
+                    id: String::new(),
+                    created_at: now,
+                    updated_at: now,
+                    short_channel_id: *short_channel_id as i64,
+                    node_one: hex_utils::hex_str(channel.node_one.as_slice()),
+                    node_two: hex_utils::hex_str(channel.node_two.as_slice()),
+                    capacity_sats: channel.capacity_sats.map(|capacity| capacity as i64),
+                    one_to_two_fee_base_msat: one_to_two.map(|fees| fees.base_msat as i64),
+                    one_to_two_fee_proportional_millionths: one_to_two
+                        .map(|fees| fees.proportional_millionths as i64),
+                    two_to_one_fee_base_msat: two_to_one.map(|fees| fees.base_msat as i64),
+                    two_to_one_fee_proportional_millionths: two_to_one
+                        .map(|fees| fees.proportional_millionths as i64),
+                }
+            })
+            .collect::<Vec<_>>();
+        (nodes, channels)
+    };
+
+    database.replace_network_graph(nodes, channels).await
+}
+
+// Fewest hops from `from` to `to` over `channels`, fees and capacity are not considered.
+// The path includes both ends.
+pub fn shortest_path<'a>(
+    channels: &'a [graph_channel::Model],
+    from: &'a str,
+    to: &'a str,
+) -> Option<Vec<String>> {
+    let mut adjacent: HashMap<&str, Vec<&str>> = HashMap::new();
+    for channel in channels {
+        adjacent
+            .entry(&channel.node_one)
+            .or_default()
+            .push(&channel.node_two);
+        adjacent
+            .entry(&channel.node_two)
+            .or_default()
+            .push(&channel.node_one);
+    }
+
+    let mut previous: HashMap<&str, &str> = HashMap::new();
+    previous.insert(from, from);
+    let mut queue = VecDeque::from([from]);
+    while let Some(node) = queue.pop_front() {
+        if node == to {
+            let mut path = vec![to.to_string()];
+            let mut current = to;
+            while current != from {
+                current = previous[current];
+                path.push(current.to_string());
+            }
+            path.reverse();
+            return Some(path);
+        }
+        for next in adjacent.get(node).into_iter().flatten() {
+            if !previous.contains_key(next) {
+                previous.insert(next, node);
+                queue.push_back(next);
+            }
+        }
+    }
+    None
+}
+
 pub struct OptionalNetworkGraphMsgHandler {
     pub network_graph_msg_handler: Option<Arc<NetworkGraphMessageHandler>>,
 }
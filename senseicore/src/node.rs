@@ -7,52 +7,72 @@
 // You may not use this file except in accordance with one or both of these
 // licenses.
 
-use crate::chain::broadcaster::SenseiBroadcaster;
+use crate::chain::broadcaster::{BroadcastContext, BroadcastPurpose, SenseiBroadcaster};
 use crate::chain::database::WalletDatabase;
-use crate::chain::fee_estimator::SenseiFeeEstimator;
+use crate::chain::fee_estimator::{self, SenseiFeeEstimator, MIN_FEERATE};
 use crate::chain::manager::SenseiChainManager;
-use crate::channels::ChannelOpener;
-use crate::config::SenseiConfig;
+use crate::channels::{
+    estimate_wallet_funds, num_pending_channels, pending_user_channel_ids, reserved_funding_inputs,
+    BatchOpenOptions, BatchOpenResult, ChannelOpener, CloseChannelRequest, PendingOpens,
+};
+use crate::config::{AddressFamily, SenseiConfig};
+use crate::consolidation::UtxoConsolidator;
 use crate::database::SenseiDatabase;
 use crate::disk::FilesystemLogger;
 use crate::error::Error;
-use crate::event_handler::LightningNodeEventHandler;
+use crate::event_handler::{LightningNodeEventHandler, SupervisedEventHandler};
 use crate::events::SenseiEvent;
-use crate::network_graph::OptionalNetworkGraphMsgHandler;
+use crate::network_graph::{shortest_path, snapshot_network_graph, OptionalNetworkGraphMsgHandler};
 use crate::persist::{AnyKVStore, DatabaseStore, SenseiPersister};
+use crate::rate_limit::ConnectionRateLimiter;
+use crate::revenue::{self, FeeRevenue, SECS_PER_DAY};
+use crate::router::{PathLengthLimits, SenseiRouter};
 use crate::services::node::{
-    Channel, NodeInfo, NodeRequest, NodeRequestError, NodeResponse, OpenChannelRequest,
-    OpenChannelResult, Peer, Utxo,
+    BatchCloseRequest, BatchValidation, ChainTip, Channel, ChannelGossip, ChannelHtlcStats,
+    ChannelUptime, CloseChannelResult, CurrentFeerates, Feerate, ForceCloseSimulation,
+    ForwardFeeEstimate, FundingConfirmationEta, GossipMessage, InflightOpen, LabeledTransaction,
+    LedgerEntry, Liquidity, LiquiditySummary, NodeInfo, NodeRequest, NodeRequestError,
+    NodeResponse, OpenChannelRequest, OpenChannelValidation, Peer, PeerChannels, PeerLiquidity,
+    PendingChannel, RebalanceResult, RebroadcastResult, SimulatedHtlc, Utxo,
 };
 use crate::services::{PaginationRequest, PaginationResponse, PaymentsFilter};
+use crate::socks;
+use crate::stale_state::{self, MonitorCheckpoints, StaleChannel};
+use crate::uptime;
 use crate::utils::PagedVec;
 use crate::{hex_utils, version};
+use bdk::database::Database;
+use bdk::keys::bip39::{Language, Mnemonic};
 use bdk::keys::ExtendedKey;
 use bdk::wallet::time;
 use bdk::wallet::AddressIndex;
-use bdk::TransactionDetails;
+use bdk::SignOptions;
 use bitcoin::hashes::Hash;
 use entity::sea_orm::{ActiveModelTrait, ActiveValue};
-use lightning::chain::channelmonitor::ChannelMonitor;
+use lightning::chain::chaininterface::{BroadcasterInterface, ConfirmationTarget, FeeEstimator};
+use lightning::chain::channelmonitor::{Balance, ChannelMonitor};
 
-use lightning::ln::features::InvoiceFeatures;
+use lightning::ln::features::{ChannelFeatures, InvoiceFeatures, NodeFeatures};
 use lightning::ln::msgs::NetAddress;
 use lightning_invoice::payment::PaymentError;
 use tindercrypt::cryptors::RingCryptor;
 
 use bdk::template::DescriptorTemplateOut;
 use bitcoin::hashes::hex::ToHex;
+use bitcoin::hashes::hmac::{Hmac, HmacEngine};
 use bitcoin::hashes::sha256::Hash as Sha256;
+use bitcoin::hashes::HashEngine;
 use bitcoin::network::constants::Network;
 use bitcoin::secp256k1::{PublicKey, Secp256k1};
 use bitcoin::util::bip32::{ChildNumber, DerivationPath, ExtendedPrivKey};
-use bitcoin::BlockHash;
+use bitcoin::util::psbt::PartiallySignedTransaction;
+use bitcoin::{Address, BlockHash, OutPoint, Script, Txid};
 use lightning::chain::chainmonitor;
 use lightning::chain::keysinterface::{InMemorySigner, KeysInterface, KeysManager, Recipient};
 use lightning::chain::Watch;
 use lightning::chain::{self, Filter};
 use lightning::ln::channelmanager::{self, ChannelDetails, ChannelManager as LdkChannelManager};
-use lightning::ln::channelmanager::{ChainParameters, ChannelManagerReadArgs};
+use lightning::ln::channelmanager::{ChainParameters, ChannelManagerReadArgs, PaymentId};
 use lightning::ln::peer_handler::{
     IgnoringMessageHandler, MessageHandler, PeerManager as LdkPeerManager,
 };
@@ -60,10 +80,11 @@ use lightning::ln::{PaymentHash, PaymentPreimage, PaymentSecret};
 use lightning::routing::gossip::{
     NetworkGraph as LdkNetworkGraph, NodeId, P2PGossipSync, RoutingFees,
 };
+use lightning::routing::router::{Route, RouteHop};
 use lightning::routing::router::{RouteHint, RouteHintHop};
-use lightning::routing::scoring::ProbabilisticScorer;
+use lightning::routing::scoring::{ProbabilisticScorer, ProbabilisticScoringParameters, Score};
 use lightning::util::config::UserConfig;
-use lightning::util::ser::ReadableArgs;
+use lightning::util::ser::{ReadableArgs, Writeable};
 use lightning_background_processor::BackgroundProcessor;
 use lightning_invoice::utils::DefaultRouter;
 use lightning_invoice::{payment, utils, Currency, Invoice, InvoiceDescription};
@@ -72,15 +93,17 @@ use lightning_rapid_gossip_sync::RapidGossipSync;
 use macaroon::Macaroon;
 use rand::{thread_rng, RngCore};
 use serde::{ser::SerializeSeq, Deserialize, Serialize, Serializer};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::fmt::Display;
 use std::fs::File;
+use std::future::Future;
 use std::io::Cursor;
 use std::io::Write;
 use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
 use std::str::FromStr;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
-use std::time::{Duration, SystemTime};
+use std::time::{Duration, Instant, SystemTime};
 use std::{convert::From, fmt};
 use tokio::runtime::Handle;
 use tokio::sync::broadcast;
@@ -226,6 +249,8 @@ impl From<&InvoiceFeatures> for LocalInvoiceFeatures {
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
 pub enum HTLCStatus {
     Pending,
+    Claiming,
+    Held,
     Succeeded,
     Failed,
     Unknown,
@@ -235,6 +260,8 @@ impl Display for HTLCStatus {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let str = match *self {
             HTLCStatus::Pending => "pending".to_string(),
+            HTLCStatus::Claiming => "claiming".to_string(),
+            HTLCStatus::Held => "held".to_string(),
             HTLCStatus::Succeeded => "succeeded".to_string(),
             HTLCStatus::Failed => "failed".to_string(),
             HTLCStatus::Unknown => "unknown".to_string(),
@@ -243,6 +270,36 @@ impl Display for HTLCStatus {
     }
 }
 
+// Why an outgoing payment failed. LDK reports it per path while the invoice payer retries,
+// the last one is kept once the payment has failed for good.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum PaymentFailureReason {
+    NoRoute,
+    RetriesExhausted,
+    RecipientRejected,
+    // a node along the route failed the htlc, the recipient never saw it
+    RouteFailed,
+}
+
+impl Display for PaymentFailureReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let str = match *self {
+            PaymentFailureReason::NoRoute => "no_route".to_string(),
+            PaymentFailureReason::RetriesExhausted => "retries_exhausted".to_string(),
+            PaymentFailureReason::RecipientRejected => "recipient_rejected".to_string(),
+            PaymentFailureReason::RouteFailed => "route_failed".to_string(),
+        };
+        write!(f, "{}", str)
+    }
+}
+
+// Which way an HTLC crossed one of our channels, inbound ones were added by the counterparty.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HTLCDirection {
+    Inbound,
+    Outbound,
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
 pub enum PaymentOrigin {
     InvoiceIncoming,
@@ -325,15 +382,14 @@ pub type PeerManager = SimpleArcPeerManager<
 pub type ChannelManager =
     SimpleArcChannelManager<ChainMonitor, SenseiBroadcaster, SenseiFeeEstimator, FilesystemLogger>;
 
-pub type Router = DefaultRouter<Arc<NetworkGraph>, Arc<FilesystemLogger>>;
 pub type Scorer = ProbabilisticScorer<Arc<NetworkGraph>, Arc<FilesystemLogger>>;
 
 pub type InvoicePayer = payment::InvoicePayer<
     Arc<ChannelManager>,
-    Router,
+    SenseiRouter,
     Arc<Mutex<Scorer>>,
     Arc<FilesystemLogger>,
-    Arc<LightningNodeEventHandler>,
+    Arc<SupervisedEventHandler<LightningNodeEventHandler>>,
 >;
 
 #[allow(dead_code)]
@@ -379,6 +435,36 @@ fn get_wpkh_descriptors_for_extended_key(
     (receive_descriptor_template, change_descriptor_template)
 }
 
+fn encode_payment_metadata(metadata: &BTreeMap<String, String>) -> Result<Option<String>, Error> {
+    if metadata.is_empty() {
+        return Ok(None);
+    }
+    let metadata = serde_json::to_string(metadata).unwrap();
+    if metadata.len() > MAX_PAYMENT_METADATA_BYTES {
+        return Err(Error::MetadataTooLarge(MAX_PAYMENT_METADATA_BYTES));
+    }
+    Ok(Some(metadata))
+}
+
+// A fixed change address has to be one of our own, otherwise every change output would be
+// handed to whoever owns it.
+fn fixed_change_script(
+    wallet: &bdk::Wallet<WalletDatabase>,
+    address: &str,
+    network: Network,
+) -> Result<Script, Error> {
+    let invalid = || Error::InvalidChangeAddress(address.to_string());
+    let address = Address::from_str(address).map_err(|_e| invalid())?;
+    if address.network != network {
+        return Err(invalid());
+    }
+    let script = address.script_pubkey();
+    if !wallet.is_mine(&script)? {
+        return Err(invalid());
+    }
+    Ok(script)
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct MacaroonSession {
     pub id: String,
@@ -393,13 +479,168 @@ impl MacaroonSession {
     }
 }
 
+// Metadata is stored as json next to the payment, this keeps it from growing the payments
+// table without bound.
+const MAX_PAYMENT_METADATA_BYTES: usize = 4096;
+
+// LDK refuses to fund channels of 2^24 sats or more, there is no wumbo support yet.
+const MAX_FUNDING_SATOSHIS: u64 = 1 << 24;
+
+// Due subscriptions are checked once a minute, anything shorter couldn't be kept.
+const MIN_SUBSCRIPTION_INTERVAL_SECS: u64 = 60;
+
+// Longest circle a rebalance looks for, counting the hop back to us.
+const MAX_REBALANCE_HOPS: usize = 6;
+
+// The final cltv delta of routes we build without an invoice to take it from, e.g. to pay
+// ourselves when rebalancing.
+const DEFAULT_FINAL_CLTV_EXPIRY_DELTA: u32 = 40;
+// Longer than any htlc of ours can stay in flight, LDK caps the total cltv at a week.
+const MAX_PENDING_PAYMENT_SECS: i64 = 14 * 24 * 60 * 60;
+
+// How long open_channel_and_pay waits for a zero-conf channel to be usable by default, every
+// confirmation the peer wants adds ten minutes.
+const CHANNEL_READY_TIMEOUT_MS: u64 = 60_000;
+const CHANNEL_READY_TIMEOUT_PER_CONFIRMATION_MS: u64 = 600_000;
+
+// A batch waiting on finalize_batch_funding. bdk doesn't hold on to the utxos of an
+// unsigned psbt, its inputs are kept out of other opens until it's finalized.
+pub struct PendingBatchFunding {
+    pub inputs: Vec<OutPoint>,
+    pub results: Vec<BatchOpenResult>,
+}
+
+// Circular payments pay one of our own invoices, they have no outgoing payment row to count
+// them as in flight. Their amounts are kept here by payment hash until LDK reports how they
+// ended. Only in memory, the ones pending over a restart aren't counted.
+#[derive(Clone, Default)]
+pub struct CircularPayments {
+    amounts: Arc<Mutex<HashMap<PaymentHash, u64>>>,
+}
+
+impl CircularPayments {
+    pub fn insert(&self, payment_hash: PaymentHash, amount_msat: u64) {
+        self.amounts
+            .lock()
+            .unwrap()
+            .insert(payment_hash, amount_msat);
+    }
+
+    pub fn remove(&self, payment_hash: &PaymentHash) {
+        self.amounts.lock().unwrap().remove(payment_hash);
+    }
+
+    pub fn in_flight_msat(&self) -> u64 {
+        self.amounts
+            .lock()
+            .unwrap()
+            .values()
+            .fold(0u64, |total, amount_msat| {
+                total.saturating_add(*amount_msat)
+            })
+    }
+}
+
+const LEDGER_PAGE_SIZE: usize = 500;
+
+// Walks the ledger oldest first. Payments and forwards are read from the database a page at
+// a time as the walk gets to them, so exporting a wide range doesn't load it all up front.
+pub struct LedgerCursor {
+    database: Arc<SenseiDatabase>,
+    node_id: String,
+    from: i64,
+    to: i64,
+    payments: VecDeque<LedgerEntry>,
+    next_payments_page: Option<usize>,
+    forwards: VecDeque<LedgerEntry>,
+    next_forwards_page: Option<usize>,
+    onchain: VecDeque<LedgerEntry>,
+}
+
+impl LedgerCursor {
+    pub async fn next_entry(&mut self) -> Result<Option<LedgerEntry>, Error> {
+        if let (true, Some(page)) = (self.payments.is_empty(), self.next_payments_page) {
+            let payments = self
+                .database
+                .list_settled_payments_page_in_range(
+                    &self.node_id,
+                    self.from,
+                    self.to,
+                    page,
+                    LEDGER_PAGE_SIZE,
+                )
+                .await?;
+            self.next_payments_page = (payments.len() == LEDGER_PAGE_SIZE).then(|| page + 1);
+            self.payments
+                .extend(payments.into_iter().map(payment_ledger_entry));
+        }
+
+        if let (true, Some(page)) = (self.forwards.is_empty(), self.next_forwards_page) {
+            let forwards = self
+                .database
+                .list_forwards_page_in_range(
+                    &self.node_id,
+                    self.from,
+                    self.to,
+                    page,
+                    LEDGER_PAGE_SIZE,
+                )
+                .await?;
+            self.next_forwards_page = (forwards.len() == LEDGER_PAGE_SIZE).then(|| page + 1);
+            self.forwards
+                .extend(forwards.into_iter().map(forward_ledger_entry));
+        }
+
+        let next_source = [&mut self.payments, &mut self.forwards, &mut self.onchain]
+            .into_iter()
+            .filter(|entries| !entries.is_empty())
+            .min_by_key(|entries| entries[0].timestamp);
+        Ok(next_source.and_then(|entries| entries.pop_front()))
+    }
+}
+
+fn payment_ledger_entry(payment: entity::payment::Model) -> LedgerEntry {
+    let amount_msat = payment.amt_msat.unwrap_or(0);
+    let fee_msat = payment.fee_paid_msat.unwrap_or(0);
+    let outgoing = payment.origin == PaymentOrigin::InvoiceOutgoing.to_string()
+        || payment.origin == PaymentOrigin::SpontaneousOutgoing.to_string();
+    LedgerEntry {
+        timestamp: payment.updated_at,
+        kind: String::from(if outgoing {
+            "payment_sent"
+        } else {
+            "payment_received"
+        }),
+        reference: payment.payment_hash,
+        amount_msat: if outgoing {
+            -(amount_msat + fee_msat)
+        } else {
+            amount_msat
+        },
+        fee_msat,
+        label: payment.label,
+    }
+}
+
+fn forward_ledger_entry(forward: entity::forward::Model) -> LedgerEntry {
+    let fee_earned_msat = forward.fee_earned_msat.unwrap_or(0);
+    LedgerEntry {
+        timestamp: forward.created_at,
+        kind: String::from("forward"),
+        reference: forward.id,
+        amount_msat: fee_earned_msat,
+        fee_msat: fee_earned_msat,
+        label: None,
+    }
+}
+
 #[derive(Clone)]
 pub struct LightningNode {
     pub config: Arc<SenseiConfig>,
     pub id: String,
     pub listen_port: u16,
     pub listen_addresses: Vec<String>,
-    pub seed: [u8; 32],
+    pub seed: Vec<u8>,
     pub database: Arc<SenseiDatabase>,
     pub wallet: Arc<Mutex<bdk::Wallet<WalletDatabase>>>,
     pub channel_manager: Arc<ChannelManager>,
@@ -411,11 +652,20 @@ pub struct LightningNode {
     pub keys_manager: Arc<KeysManager>,
     pub logger: Arc<FilesystemLogger>,
     pub invoice_payer: Arc<InvoicePayer>,
+    pub path_length_limits: PathLengthLimits,
     pub scorer: Arc<Mutex<Scorer>>,
     pub stop_listen: Arc<AtomicBool>,
     pub persister: Arc<SenseiPersister>,
     pub event_sender: broadcast::Sender<SenseiEvent>,
     pub broadcaster: Arc<SenseiBroadcaster>,
+    pub change_script: Option<Script>,
+    pub in_flight_lock: Arc<tokio::sync::Mutex<()>>,
+    pub circular_payments: CircularPayments,
+    pub pending_batch_fundings: Arc<Mutex<HashMap<Txid, PendingBatchFunding>>>,
+    pub pending_opens: PendingOpens,
+    pub monitor_checkpoints_path: String,
+    // the node is halted while this isn't empty
+    pub stale_channels: Arc<Mutex<Vec<StaleChannel>>>,
 }
 
 impl LightningNode {
@@ -425,7 +675,39 @@ impl LightningNode {
         seed
     }
 
-    pub fn encrypt_seed(seed: &[u8; 32], passphrase: &[u8]) -> Result<Vec<u8>, Error> {
+    // The 64 byte seed of a bip39 mnemonic, checking that every word is in the english
+    // wordlist and the checksum matches.
+    pub fn seed_from_mnemonic(mnemonic: &str, passphrase: Option<&str>) -> Result<[u8; 64], Error> {
+        let mnemonic = Mnemonic::parse_in(Language::English, mnemonic)
+            .map_err(|e| Error::InvalidMnemonic(e.to_string()))?;
+        Ok(mnemonic.to_seed(passphrase.unwrap_or("")))
+    }
+
+    // Generated seeds are handed to LDK as is. An imported bip39 seed is 64 bytes and is
+    // also the wallet's bip32 master, so LDK gets the key at m/535' instead. Any wallet
+    // restoring the mnemonic recovers the on-chain funds but not the channel keys.
+    pub fn ldk_seed(seed: &[u8]) -> Result<[u8; 32], Error> {
+        match seed.len() {
+            32 => {
+                let mut ldk_seed = [0; 32];
+                ldk_seed.copy_from_slice(seed);
+                Ok(ldk_seed)
+            }
+            64 => {
+                let secp_ctx = Secp256k1::new();
+                let key = ExtendedPrivKey::new_master(Network::Bitcoin, seed)
+                    .and_then(|master| {
+                        master
+                            .derive_priv(&secp_ctx, &[ChildNumber::from_hardened_idx(535).unwrap()])
+                    })
+                    .map_err(|_| Error::InvalidSeedLength)?;
+                Ok(key.private_key.secret_bytes())
+            }
+            _ => Err(Error::InvalidSeedLength),
+        }
+    }
+
+    pub fn encrypt_seed(seed: &[u8], passphrase: &[u8]) -> Result<Vec<u8>, Error> {
         let cryptor = RingCryptor::new();
         Ok(cryptor.seal_with_passphrase(passphrase, seed)?)
     }
@@ -434,19 +716,18 @@ impl LightningNode {
         node_id: String,
         passphrase: String,
         database: Arc<SenseiDatabase>,
-    ) -> Result<[u8; 32], Error> {
+    ) -> Result<Vec<u8>, Error> {
         let cryptor = RingCryptor::new();
-        let mut seed: [u8; 32] = [0; 32];
         match database.get_seed(node_id.clone()).await? {
             Some(encrypted_seed) => {
                 let decrypted_seed =
                     cryptor.open(passphrase.as_bytes(), encrypted_seed.as_slice())?;
 
-                if decrypted_seed.len() != 32 {
+                // generated seeds are 32 bytes, imported bip39 seeds 64
+                if decrypted_seed.len() != 32 && decrypted_seed.len() != 64 {
                     return Err(Error::InvalidSeedLength);
                 }
-                seed.copy_from_slice(decrypted_seed.as_slice());
-                Ok(seed)
+                Ok(decrypted_seed)
             }
             None => Err(Error::SeedNotFound),
         }
@@ -533,12 +814,12 @@ impl LightningNode {
             .map_err(|_e| Error::InvalidMacaroon)
     }
 
-    pub fn get_node_pubkey_from_seed(seed: &[u8; 32]) -> String {
+    pub fn get_node_pubkey_from_seed(seed: &[u8]) -> Result<String, Error> {
         let secp_ctx = Secp256k1::new();
-        let keys_manager = KeysManager::new(seed, 0, 0);
+        let keys_manager = KeysManager::new(&LightningNode::ldk_seed(seed)?, 0, 0);
         let node_secret = keys_manager.get_node_secret(Recipient::Node).unwrap();
         let node_pubkey = PublicKey::from_secret_key(&secp_ctx, &node_secret);
-        node_pubkey.to_string()
+        Ok(node_pubkey.to_string())
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -567,7 +848,11 @@ impl LightningNode {
             .duration_since(SystemTime::UNIX_EPOCH)
             .unwrap();
 
-        let keys_manager = Arc::new(KeysManager::new(&seed, cur.as_secs(), cur.subsec_nanos()));
+        let keys_manager = Arc::new(KeysManager::new(
+            &LightningNode::ldk_seed(&seed)?,
+            cur.as_secs(),
+            cur.subsec_nanos(),
+        ));
 
         let xprivkey = ExtendedPrivKey::new_master(network, &seed).unwrap();
         let xkey = ExtendedKey::from(xprivkey);
@@ -581,7 +866,14 @@ impl LightningNode {
                 account_number,
             );
 
-        let bdk_database = WalletDatabase::new(id.clone(), database.clone(), database.get_handle());
+        let bdk_database = WalletDatabase::new(
+            id.clone(),
+            database.clone(),
+            database.get_handle(),
+            config
+                .detect_unexpected_deposit_scripts
+                .then(|| event_sender.clone()),
+        );
         let wallet_database = bdk_database.clone();
 
         let bdk_wallet = bdk::Wallet::new(
@@ -592,13 +884,37 @@ impl LightningNode {
         )?;
 
         // TODO: probably can do this later, assuming this is REALLY slow
-        bdk_wallet.ensure_addresses_cached(100).unwrap();
+        bdk_wallet
+            .ensure_addresses_cached(config.wallet_cached_addresses)
+            .unwrap();
+
+        let node_pubkey = PublicKey::from_secret_key(
+            &Secp256k1::new(),
+            &keys_manager.get_node_secret(Recipient::Node).unwrap(),
+        );
+        let change_script = match config.fixed_change_addresses.get(&node_pubkey.to_string()) {
+            Some(address) => Some(fixed_change_script(&bdk_wallet, address, network)?),
+            None => None,
+        };
 
         let bdk_wallet = Arc::new(Mutex::new(bdk_wallet));
         let logger = Arc::new(FilesystemLogger::new(data_dir.clone()));
 
+        if let Some(max_feerate) = config.max_commitment_feerate_sats_per_kw {
+            if max_feerate < MIN_FEERATE {
+                return Err(Error::InvalidMaxCommitmentFeerate);
+            }
+        }
+
         let fee_estimator = Arc::new(SenseiFeeEstimator {
             fee_estimator: chain_manager.fee_estimator.clone(),
+            feerate_tolerance_pct: config.feerate_update_tolerance_pct,
+            max_commitment_feerate: config.max_commitment_feerate_sats_per_kw,
+        });
+        let monitor_fee_estimator = Arc::new(SenseiFeeEstimator {
+            fee_estimator: chain_manager.fee_estimator.clone(),
+            feerate_tolerance_pct: 0,
+            max_commitment_feerate: None,
         });
 
         let broadcaster = Arc::new(SenseiBroadcaster::new(
@@ -606,6 +922,10 @@ impl LightningNode {
             chain_manager.broadcaster.clone(),
             Arc::new(Mutex::new(wallet_database.clone())),
             event_sender.clone(),
+            config.broadcast_log_enabled.then(|| database.clone()),
+            chain_manager.relay_rejections.clone(),
+            config.max_relay_rejections,
+            Duration::from_secs(config.funding_broadcast_timeout_secs),
         ));
 
         let persistence_store =
@@ -621,12 +941,59 @@ impl LightningNode {
             None,
             broadcaster.clone(),
             logger.clone(),
-            fee_estimator.clone(),
+            monitor_fee_estimator.clone(),
             persister.clone(),
         ));
 
         let mut channelmonitors = persister.read_channelmonitors(keys_manager.clone())?;
 
+        // Compares the monitors we loaded to the update ids they had last time we ran. If any
+        // went backwards we were restored from an old backup, so nothing may spend those
+        // channels and we stay away from peers until the operator acknowledges it.
+        let monitor_checkpoints_path = format!("{}/monitor_checkpoints.json", data_dir);
+        let stale_channels = if config.stale_state_check {
+            let checkpoints = MonitorCheckpoints::read(&monitor_checkpoints_path)?;
+            let monitor_update_ids = channelmonitors
+                .iter()
+                .map(|(_, monitor)| {
+                    (
+                        monitor
+                            .get_funding_txo()
+                            .0
+                            .into_bitcoin_outpoint()
+                            .to_string(),
+                        monitor.get_latest_update_id(),
+                    )
+                })
+                .collect::<HashMap<_, _>>();
+            stale_state::stale_channels(&checkpoints, &monitor_update_ids)
+        } else {
+            vec![]
+        };
+        if !stale_channels.is_empty() {
+            let funding_txos = stale_channels
+                .iter()
+                .map(|channel| channel.funding_txo.clone())
+                .collect::<Vec<String>>();
+            eprintln!(
+                "WARNING: channel state for {} is older than last time this node ran, halting",
+                funding_txos.join(", ")
+            );
+            broadcaster.halt_spends(
+                funding_txos
+                    .iter()
+                    .filter_map(|funding_txo| OutPoint::from_str(funding_txo).ok())
+                    .collect(),
+            );
+            event_sender
+                .send(SenseiEvent::StaleStateDetected {
+                    node_id: id.clone(),
+                    funding_txos,
+                })
+                .unwrap_or_default();
+        }
+        let stale_channels = Arc::new(Mutex::new(stale_channels));
+
         // TODO: likely expose a lot of this config to our LightningNodeConfig
         let mut user_config = UserConfig::default();
 
@@ -686,7 +1053,7 @@ impl LightningNode {
                 (
                     channel_monitor,
                     broadcaster.clone(),
-                    fee_estimator.clone(),
+                    monitor_fee_estimator.clone(),
                     logger.clone(),
                 ),
                 outpoint,
@@ -752,6 +1119,8 @@ impl LightningNode {
             .await
             .unwrap();
 
+        // the graph is shared by every node, only the node that loaded it snapshots it
+        let owns_network_graph = network_graph.is_none();
         let network_graph = match network_graph {
             Some(network_graph) => network_graph,
             None => Arc::new(persister.read_network_graph()),
@@ -793,18 +1162,31 @@ impl LightningNode {
             Arc::new(IgnoringMessageHandler {}),
         ));
 
+        let mut scoring_params = ProbabilisticScoringParameters::default();
+        if let Some(half_life_secs) = config.scorer_liquidity_half_life_secs {
+            scoring_params.liquidity_offset_half_life = Duration::from_secs(half_life_secs);
+        }
+        if let Some(multiplier_msat) = config.scorer_liquidity_penalty_multiplier_msat {
+            scoring_params.liquidity_penalty_multiplier_msat = multiplier_msat;
+        }
+
         // need to move this to AdminService or root node only
         let scorer = Arc::new(Mutex::new(
-            persister.read_scorer(Arc::clone(&network_graph)),
+            persister.read_scorer(Arc::clone(&network_graph), scoring_params),
         ));
 
-        let router = DefaultRouter::new(
-            network_graph.clone(),
-            logger.clone(),
-            keys_manager.get_secure_random_bytes(),
+        let path_length_limits = PathLengthLimits::default();
+        let router = SenseiRouter::new(
+            DefaultRouter::new(
+                network_graph.clone(),
+                logger.clone(),
+                keys_manager.get_secure_random_bytes(),
+            ),
+            path_length_limits.clone(),
         );
 
-        let event_handler = Arc::new(LightningNodeEventHandler {
+        let circular_payments = CircularPayments::default();
+        let event_handler = LightningNodeEventHandler {
             node_id: id.clone(),
             config: config.clone(),
             wallet: bdk_wallet.clone(),
@@ -816,7 +1198,14 @@ impl LightningNode {
             event_sender: event_sender.clone(),
             broadcaster: broadcaster.clone(),
             network_graph: network_graph.clone(),
-        });
+            change_script: change_script.clone(),
+            circular_payments: circular_payments.clone(),
+        };
+        let event_handler = Arc::new(SupervisedEventHandler::new(
+            id.clone(),
+            event_handler,
+            event_sender.clone(),
+        ));
 
         let invoice_payer = Arc::new(InvoicePayer::new(
             channel_manager.clone(),
@@ -833,19 +1222,59 @@ impl LightningNode {
 
         let peer_manager_connection_handler = peer_manager.clone();
 
+        let bind_addr = config.p2p_bind_addr(listen_port)?;
+        let listener = tokio::net::TcpListener::bind(bind_addr)
+            .await
+            .map_err(|e| match e.kind() {
+                std::io::ErrorKind::AddrInUse => Error::ListenAddressInUse(bind_addr.to_string()),
+                _ => Error::Io(e),
+            })?;
+
+        let mut rate_limiter = config.peer_connection_rate_limit.map(|max_attempts| {
+            let exempt_ips = config
+                .rate_limit_exempt_ips
+                .iter()
+                .filter_map(|ip| ip.parse::<IpAddr>().ok())
+                .collect::<HashSet<_>>();
+            ConnectionRateLimiter::new(
+                max_attempts,
+                Duration::from_secs(config.peer_connection_rate_window_secs),
+                exempt_ips,
+            )
+        });
+        let event_sender_listener = event_sender.clone();
+        let node_id_listener = id.clone();
+        let stale_channels_listener = stale_channels.clone();
+
         let stop_listen_ref = Arc::clone(&stop_listen);
         handles.push(tokio::spawn(async move {
-            let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", listen_port))
-                .await
-                .expect(
-                    "Failed to bind to listen port - is something else already listening on it?",
-                );
             loop {
                 let peer_mgr = peer_manager_connection_handler.clone();
-                let tcp_stream = listener.accept().await.unwrap().0;
+                let (tcp_stream, remote_addr) = listener.accept().await.unwrap();
                 if stop_listen_ref.load(Ordering::Acquire) {
                     return;
                 }
+                // reestablishing a channel with stale state can get it closed against us
+                if !stale_channels_listener.lock().unwrap().is_empty() {
+                    continue;
+                }
+                if let Some(rate_limiter) = rate_limiter.as_mut() {
+                    if let Err(blocked_for) = rate_limiter.check(remote_addr.ip(), Instant::now()) {
+                        println!(
+                            "rate limited inbound connection from {} for {}s",
+                            remote_addr,
+                            blocked_for.as_secs()
+                        );
+                        event_sender_listener
+                            .send(SenseiEvent::PeerRateLimited {
+                                node_id: node_id_listener.clone(),
+                                address: remote_addr.ip().to_string(),
+                                blocked_secs: blocked_for.as_secs(),
+                            })
+                            .unwrap_or_default();
+                        continue;
+                    }
+                }
                 tokio::spawn(async move {
                     lightning_net_tokio::setup_inbound(
                         peer_mgr.clone(),
@@ -889,16 +1318,127 @@ impl LightningNode {
             Some(scorer.clone()),
         );
 
+        // Records when peers connect and disconnect, for channel uptime. Changes are noticed
+        // within the polling interval. Peers are disconnected while the node is down, so
+        // channel peers that were last seen connected are marked disconnected as of the last
+        // heartbeat before the node went down, so its downtime does not count as their uptime.
+        let channel_manager_peer_events = channel_manager.clone();
+        let peer_manager_peer_events = peer_manager.clone();
+        let database_peer_events = database.clone();
+        let node_id_peer_events = id.clone();
+        let config_peer_events = config.clone();
+        handles.push(tokio::spawn(async move {
+            let now = entity::seconds_since_epoch();
+            let heartbeat = database_peer_events
+                .peer_events_heartbeat(&node_id_peer_events)
+                .await
+                .ok()
+                .flatten();
+            let channel_peers = channel_manager_peer_events
+                .list_channels()
+                .into_iter()
+                .map(|channel| channel.counterparty.node_id.to_string())
+                .collect::<HashSet<_>>();
+            for pubkey in channel_peers {
+                if let Ok(Some(last_event)) = database_peer_events
+                    .last_peer_event_before(&node_id_peer_events, &pubkey, now + 1)
+                    .await
+                {
+                    if last_event.connected {
+                        // without a heartbeat all we know is that it was up at the last event
+                        let alive_at = heartbeat
+                            .map_or(last_event.created_at, |heartbeat| {
+                                heartbeat.max(last_event.created_at)
+                            })
+                            .min(now);
+                        if let Err(e) = database_peer_events
+                            .record_peer_event_at(&node_id_peer_events, &pubkey, false, alive_at)
+                            .await
+                        {
+                            println!("failed to record peer disconnect: {}", e);
+                        }
+                    }
+                }
+            }
+
+            let mut connected: HashSet<PublicKey> = HashSet::new();
+            let mut interval = tokio::time::interval(Duration::from_secs(5));
+            loop {
+                interval.tick().await;
+                let _res = database_peer_events
+                    .set_peer_events_heartbeat(&node_id_peer_events, entity::seconds_since_epoch())
+                    .await;
+                // the listener can't know who is connecting until the handshake is done, so
+                // inbound peers outside the allowlist are dropped here instead
+                let now_connected = peer_manager_peer_events
+                    .get_peer_node_ids()
+                    .into_iter()
+                    .filter(|pubkey| {
+                        let allowed = config_peer_events.peer_allowed(pubkey);
+                        if !allowed {
+                            println!("disconnecting peer {} not in the allowlist", pubkey);
+                            peer_manager_peer_events.disconnect_by_node_id(*pubkey, false);
+                        }
+                        allowed
+                    })
+                    .collect::<HashSet<_>>();
+                for pubkey in now_connected.difference(&connected) {
+                    let _res = database_peer_events
+                        .record_peer_event(&node_id_peer_events, &pubkey.to_string(), true)
+                        .await;
+                }
+                for pubkey in connected.difference(&now_connected) {
+                    let _res = database_peer_events
+                        .record_peer_event(&node_id_peer_events, &pubkey.to_string(), false)
+                        .await;
+                }
+                connected = now_connected;
+            }
+        }));
+
+        // Closes the channels of batch funding txs that some peer never signed. Their funding
+        // tx is never broadcast, so this only lets their peers stop waiting on it. Also prunes
+        // what the broadcaster remembers about old txs.
+        let channel_manager_debounce = channel_manager.clone();
+        let broadcaster_debounce = broadcaster.clone();
+        handles.push(tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(5));
+            loop {
+                interval.tick().await;
+                broadcaster_debounce.prune(Instant::now());
+                let expired = broadcaster_debounce.expire_debounces(Instant::now());
+                if expired.is_empty() {
+                    continue;
+                }
+                for channel in channel_manager_debounce.list_channels() {
+                    let funding_txid = channel.funding_txo.map(|txo| txo.txid);
+                    if funding_txid.map_or(false, |txid| expired.contains(&txid)) {
+                        let _res = channel_manager_debounce.force_close_channel(
+                            &channel.channel_id,
+                            &channel.counterparty.node_id,
+                        );
+                    }
+                }
+            }
+        }));
+
         // Reconnect to channel peers if possible.
 
         let channel_manager_reconnect = channel_manager.clone();
         let peer_manager_reconnect = peer_manager.clone();
         let _persister_peer = persister.clone();
         let network_graph_reconnect = network_graph.clone();
+        let database_reconnect = database.clone();
+        let node_id_reconnect = id.clone();
+        let stale_channels_reconnect = stale_channels.clone();
+        let config_reconnect = config.clone();
         handles.push(tokio::spawn(async move {
             let mut interval = tokio::time::interval(Duration::from_secs(5));
             loop {
                 interval.tick().await;
+                if !stale_channels_reconnect.lock().unwrap().is_empty() {
+                    continue;
+                }
 
                 for chan_info in channel_manager_reconnect.list_channels() {
                     let pubkey = chan_info.counterparty.node_id;
@@ -915,30 +1455,60 @@ impl LightningNode {
                             })
                         };
 
-                        if let Some(addresses) = addresses {
-                            for address in addresses {
-                                let addr = match address {
-                                    NetAddress::IPv4 { addr, port } => {
-                                        Some(SocketAddr::new(IpAddr::from(addr), port))
-                                    }
-                                    NetAddress::IPv6 { addr, port } => {
-                                        Some(SocketAddr::new(IpAddr::from(addr), port))
-                                    }
-                                    NetAddress::OnionV2(_) => None,
-                                    NetAddress::OnionV3 { .. } => None,
-                                };
-
-                                if let Some(addr) = addr {
-                                    if let Ok(()) = connect_peer_if_necessary(
-                                        pubkey,
-                                        addr,
-                                        peer_manager_reconnect.clone(),
-                                    )
-                                    .await
-                                    {
-                                        break;
-                                    }
+                        let addresses = addresses.unwrap_or_default();
+                        let addrs = addresses
+                            .iter()
+                            .filter_map(|address| match *address {
+                                NetAddress::IPv4 { addr, port } => {
+                                    Some(SocketAddr::new(IpAddr::from(addr), port))
                                 }
+                                NetAddress::IPv6 { addr, port } => {
+                                    Some(SocketAddr::new(IpAddr::from(addr), port))
+                                }
+                                NetAddress::OnionV2(_) => None,
+                                NetAddress::OnionV3 { .. } => None,
+                            })
+                            .collect::<Vec<SocketAddr>>();
+
+                        // onion only peers are reached through the proxy when there is one
+                        let peer_addr = if !addrs.is_empty() {
+                            Some(PeerAddress::Clearnet(addrs))
+                        } else if config_reconnect.tor_proxy.is_some() {
+                            addresses.iter().find_map(|address| match *address {
+                                NetAddress::OnionV3 {
+                                    ed25519_pubkey,
+                                    checksum,
+                                    version,
+                                    port,
+                                } => Some(PeerAddress::from_onion_v3(
+                                    ed25519_pubkey,
+                                    checksum,
+                                    version,
+                                    port,
+                                )),
+                                _ => None,
+                            })
+                        } else {
+                            None
+                        };
+
+                        if let Some(peer_addr) = peer_addr {
+                            // the next pass of the loop is the retry
+                            if let Ok(()) = connect_peer_if_necessary(
+                                pubkey,
+                                peer_addr,
+                                peer_manager_reconnect.clone(),
+                                &config_reconnect,
+                                ConnectRetry {
+                                    max_attempts: 1,
+                                    ..Default::default()
+                                },
+                            )
+                            .await
+                            {
+                                let _res = database_reconnect
+                                    .mark_peer_connected(&node_id_reconnect, &pubkey.to_string())
+                                    .await;
                             }
                         }
                     }
@@ -946,6 +1516,160 @@ impl LightningNode {
             }
         }));
 
+        // Close channels whose peer has been offline for longer than the configured timeout.
+        // Zombies are closed cooperatively if the peer ever comes back, and only force closed
+        // automatically when explicitly enabled; otherwise they are flagged for the operator.
+        if let Some(zombie_timeout_secs) = config.zombie_channel_timeout_secs {
+            let channel_manager_zombie = channel_manager.clone();
+            let peer_manager_zombie = peer_manager.clone();
+            let event_sender_zombie = event_sender.clone();
+            let node_id_zombie = id.clone();
+            let force_close_zombies = config.zombie_channel_force_close;
+            let database_zombie = database.clone();
+            handles.push(tokio::spawn(async move {
+                let mut offline_since: HashMap<[u8; 32], u64> = HashMap::new();
+                let mut zombies: HashSet<[u8; 32]> = HashSet::new();
+                let mut interval = tokio::time::interval(Duration::from_secs(60));
+                loop {
+                    interval.tick().await;
+                    let now = SystemTime::now()
+                        .duration_since(SystemTime::UNIX_EPOCH)
+                        .unwrap()
+                        .as_secs();
+                    let channels = channel_manager_zombie.list_channels();
+
+                    offline_since.retain(|channel_id, _| {
+                        channels.iter().any(|chan| chan.channel_id == *channel_id)
+                    });
+                    zombies.retain(|channel_id| {
+                        channels.iter().any(|chan| chan.channel_id == *channel_id)
+                    });
+
+                    for chan_info in channels {
+                        let pubkey = chan_info.counterparty.node_id;
+                        if connected_to_peer(&pubkey, peer_manager_zombie.clone()) {
+                            offline_since.remove(&chan_info.channel_id);
+                            if zombies.remove(&chan_info.channel_id) {
+                                let _res = channel_manager_zombie
+                                    .close_channel(&chan_info.channel_id, &pubkey);
+                                event_sender_zombie
+                                    .send(SenseiEvent::ZombieChannelClosing {
+                                        node_id: node_id_zombie.clone(),
+                                        channel_id: chan_info.channel_id,
+                                        force: false,
+                                    })
+                                    .unwrap_or_default();
+                                let _res = database_zombie
+                                    .record_channel_event(
+                                        &node_id_zombie,
+                                        &hex_utils::hex_str(&chan_info.channel_id),
+                                        "zombie_closing",
+                                        "sensei",
+                                        Some(String::from("cooperative")),
+                                    )
+                                    .await;
+                            }
+                            continue;
+                        }
+
+                        let since = *offline_since.entry(chan_info.channel_id).or_insert(now);
+                        let offline_secs = now - since;
+                        if offline_secs < zombie_timeout_secs
+                            || zombies.contains(&chan_info.channel_id)
+                        {
+                            continue;
+                        }
+
+                        zombies.insert(chan_info.channel_id);
+                        event_sender_zombie
+                            .send(SenseiEvent::ZombieChannelDetected {
+                                node_id: node_id_zombie.clone(),
+                                channel_id: chan_info.channel_id,
+                                counterparty_pubkey: pubkey.to_string(),
+                                offline_secs,
+                            })
+                            .unwrap_or_default();
+                        let _res = database_zombie
+                            .record_channel_event(
+                                &node_id_zombie,
+                                &hex_utils::hex_str(&chan_info.channel_id),
+                                "zombie_detected",
+                                "sensei",
+                                Some(format!("peer offline for {}s", offline_secs)),
+                            )
+                            .await;
+
+                        if force_close_zombies {
+                            let _res = channel_manager_zombie
+                                .force_close_channel(&chan_info.channel_id, &pubkey);
+                            event_sender_zombie
+                                .send(SenseiEvent::ZombieChannelClosing {
+                                    node_id: node_id_zombie.clone(),
+                                    channel_id: chan_info.channel_id,
+                                    force: true,
+                                })
+                                .unwrap_or_default();
+                            let _res = database_zombie
+                                .record_channel_event(
+                                    &node_id_zombie,
+                                    &hex_utils::hex_str(&chan_info.channel_id),
+                                    "zombie_closing",
+                                    "sensei",
+                                    Some(String::from("force")),
+                                )
+                                .await;
+                        }
+                    }
+                }
+            }));
+        }
+
+        let pending_batch_fundings = Arc::new(Mutex::new(HashMap::new()));
+
+        if let Some(max_feerate) = config.utxo_consolidation_max_feerate {
+            let consolidator = UtxoConsolidator::new(
+                id.clone(),
+                bdk_wallet.clone(),
+                chain_manager.clone(),
+                broadcaster.clone(),
+                event_sender.clone(),
+                max_feerate,
+                config.utxo_consolidation_min_utxos,
+                config.utxo_consolidation_max_utxo_sats,
+                change_script.clone(),
+                pending_batch_fundings.clone(),
+            );
+            handles.push(tokio::spawn(async move {
+                let mut interval = tokio::time::interval(Duration::from_secs(3600));
+                loop {
+                    interval.tick().await;
+                    if let Err(e) = consolidator.consolidate() {
+                        println!("failed to consolidate utxos: {}", e);
+                    }
+                }
+            }));
+        }
+
+        if let (true, Some(snapshot_interval_secs)) = (
+            owns_network_graph,
+            config.network_graph_snapshot_interval_secs,
+        ) {
+            let network_graph_snapshot = network_graph.clone();
+            let database_snapshot = database.clone();
+            handles.push(tokio::spawn(async move {
+                let mut interval =
+                    tokio::time::interval(Duration::from_secs(snapshot_interval_secs));
+                loop {
+                    interval.tick().await;
+                    if let Err(e) =
+                        snapshot_network_graph(&network_graph_snapshot, &database_snapshot).await
+                    {
+                        println!("failed to snapshot network graph: {}", e);
+                    }
+                }
+            }));
+        }
+
         // Regularly broadcast our node_announcement. This is only required (or possible) if we have
         // some public channels, and is only useful if we have public listen address(es) to announce.
         // In a production environment, this should occur only after the announcement of new channels
@@ -1009,99 +1733,542 @@ impl LightningNode {
             persister,
             event_sender,
             broadcaster,
+            change_script,
+            in_flight_lock: Arc::new(tokio::sync::Mutex::new(())),
+            circular_payments,
+            path_length_limits,
+            pending_batch_fundings,
+            pending_opens: PendingOpens::default(),
+            monitor_checkpoints_path,
+            stale_channels,
         };
 
-        Ok((lightning_node, handles, background_processor))
-    }
-
-    pub async fn open_channels(
-        &self,
-        requests: Vec<OpenChannelRequest>,
-    ) -> Vec<(OpenChannelRequest, Result<[u8; 32], Error>)> {
-        let mut opener = ChannelOpener::new(
-            self.id.clone(),
-            self.channel_manager.clone(),
-            self.chain_manager.clone(),
-            self.wallet.clone(),
-            self.event_sender.subscribe(),
-            self.broadcaster.clone(),
-            self.peer_manager.clone(),
-        );
-        opener.open_batch(requests).await
-    }
-
-    // `custom_id` will be user_channel_id in FundingGenerated event
-    // allows use to tie the create_channel call with the event
-    pub async fn open_channel(&self, request: OpenChannelRequest) -> Result<[u8; 32], Error> {
-        let requests = vec![request];
-        let mut responses = self.open_channels(requests).await;
-        let (_request, result) = responses.pop().unwrap();
-        result
-    }
-
-    pub async fn connect_to_peer(&self, pubkey: PublicKey, addr: SocketAddr) -> Result<(), Error> {
-        match lightning_net_tokio::connect_outbound(Arc::clone(&self.peer_manager), pubkey, addr)
-            .await
-        {
-            Some(connection_closed_future) => {
-                let mut connection_closed_future = Box::pin(connection_closed_future);
+        // A stale restore is only caught if it is older than the last checkpoint, so they are
+        // kept close behind the monitors.
+        if lightning_node.config.stale_state_check {
+            let node_checkpoints = lightning_node.clone();
+            handles.push(tokio::spawn(async move {
+                let mut interval = tokio::time::interval(Duration::from_secs(10));
                 loop {
-                    match futures::poll!(&mut connection_closed_future) {
-                        std::task::Poll::Ready(_) => {
-                            println!("ERROR: Peer disconnected before we finished the handshake");
-                            return Err(std::io::Error::new(
-                                std::io::ErrorKind::Other,
-                                "ERROR: peer disconnected before we finished the handshake",
-                            )
-                            .into());
+                    interval.tick().await;
+                    if node_checkpoints.stale_channels.lock().unwrap().is_empty() {
+                        if let Err(e) = node_checkpoints.write_monitor_checkpoints() {
+                            println!("failed to write monitor checkpoints: {}", e);
                         }
-                        std::task::Poll::Pending => {}
                     }
-                    // Avoid blocking the tokio context by sleeping a bit
-                    match self
-                        .peer_manager
-                        .get_peer_node_ids()
-                        .iter()
-                        .find(|id| **id == pubkey)
+                }
+            }));
+        }
+
+        // Fills in the short channel ids of the channels we opened as their funding confirms,
+        // checked whenever a block is connected.
+        let node_blocks = lightning_node.clone();
+        let mut block_connected = node_blocks.chain_manager.listener.subscribe();
+        handles.push(tokio::spawn(async move {
+            let mut recorded: HashSet<[u8; 32]> = HashSet::new();
+            loop {
+                for channel in node_blocks.channel_manager.list_channels() {
+                    let short_channel_id = match channel.short_channel_id {
+                        Some(short_channel_id) => short_channel_id,
+                        None => continue,
+                    };
+                    if recorded.contains(&channel.channel_id) {
+                        continue;
+                    }
+                    match node_blocks
+                        .database
+                        .set_channel_short_channel_id(
+                            &node_blocks.id,
+                            &hex_utils::hex_str(&channel.channel_id),
+                            short_channel_id,
+                        )
+                        .await
                     {
-                        Some(_) => break,
-                        None => tokio::time::sleep(Duration::from_millis(10)).await,
+                        Ok(()) => {
+                            recorded.insert(channel.channel_id);
+                        }
+                        Err(e) => println!("failed to record short channel id: {}", e),
                     }
                 }
+                if block_connected.changed().await.is_err() {
+                    break;
+                }
             }
-            None => {
-                println!("ERROR: failed to connect to peer");
-                return Err(std::io::Error::new(
-                    std::io::ErrorKind::Other,
-                    "ERROR: failed to connect to peer",
-                )
-                .into());
+        }));
+
+        // Announces funding txs of channels we opened once they have the confirmations their
+        // channels require, checked at startup and whenever a block is connected.
+        let node_funding = lightning_node.clone();
+        let mut funding_block_connected = node_funding.chain_manager.listener.subscribe();
+        handles.push(tokio::spawn(async move {
+            loop {
+                if let Err(e) = node_funding.announce_funding_confirmations().await {
+                    println!("failed to check funding confirmations: {}", e);
+                }
+                if funding_block_connected.changed().await.is_err() {
+                    break;
+                }
+            }
+        }));
+
+        // LDK has no events for inbound channels getting funded, channels becoming ready or
+        // peers changing their policy, so the timelines pick them up from the channel list.
+        let node_timeline = lightning_node.clone();
+        handles.push(tokio::spawn(async move {
+            let mut recorded = HashMap::new();
+            let mut interval = tokio::time::interval(Duration::from_secs(10));
+            loop {
+                interval.tick().await;
+                node_timeline.record_channel_changes(&mut recorded).await;
+            }
+        }));
+
+        // Rescans an imported wallet from its birthday, in the background so starting doesn't
+        // wait on it. A rescan that didn't finish is done again on the next start.
+        match lightning_node
+            .database
+            .pending_wallet_rescan(&lightning_node.id)
+            .await
+        {
+            Ok(Some(from_height)) => {
+                let node_rescan = lightning_node.clone();
+                handles.push(tokio::spawn(async move {
+                    match node_rescan.rescan_wallet(from_height).await {
+                        Ok(_blocks_scanned) => {
+                            if let Err(e) = node_rescan
+                                .database
+                                .clear_pending_wallet_rescan(&node_rescan.id)
+                                .await
+                            {
+                                println!("failed to clear pending wallet rescan: {}", e);
+                            }
+                        }
+                        Err(e) => println!("failed to rescan imported wallet: {}", e),
+                    }
+                }));
             }
+            Ok(None) => {}
+            Err(e) => println!("failed to check for a pending wallet rescan: {}", e),
         }
-        Ok(())
+
+        let node_subscriptions = lightning_node.clone();
+        handles.push(tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(60));
+            loop {
+                interval.tick().await;
+                if let Err(e) = node_subscriptions.invoice_due_subscriptions().await {
+                    println!("failed to create subscription invoices: {}", e);
+                }
+            }
+        }));
+
+        Ok((lightning_node, handles, background_processor))
     }
 
-    async fn keysend<K: KeysInterface>(
+    // Checks a batch the way open_channels would fail on it, without connecting to anyone or
+    // touching the wallet. Errors that fail the whole batch are in `errors`.
+    pub fn validate_batch(
         &self,
-        invoice_payer: &InvoicePayer,
-        payee_pubkey: PublicKey,
-        amt_msat: u64,
-        keys: &K,
-    ) -> Result<(), Error> {
-        let payment_preimage = keys.get_secure_random_bytes();
+        requests: &[OpenChannelRequest],
+    ) -> Result<BatchValidation, Error> {
+        let our_node_id = self.channel_manager.get_our_node_id();
+        let connected_peers = self.peer_manager.get_peer_node_ids();
+        let mut custom_ids = HashSet::new();
 
-        let status = match invoice_payer.pay_pubkey(
-            payee_pubkey,
-            PaymentPreimage(payment_preimage),
-            amt_msat,
+        let validations = requests
+            .iter()
+            .map(|request| {
+                let mut errors = vec![];
+
+                match parse_pubkey(&request.counterparty_pubkey) {
+                    Ok(pubkey) if pubkey == our_node_id => {
+                        errors.push(String::from("cannot open a channel to ourselves"))
+                    }
+                    Ok(pubkey) if !self.config.peer_allowed(&pubkey) => {
+                        errors.push(Error::PeerNotAllowed(pubkey.to_string()).to_string())
+                    }
+                    Ok(pubkey) if !connected_peers.contains(&pubkey) => {
+                        match &request.counterparty_host_port {
+                            None => errors
+                                .push(String::from("not connected to peer and no host:port given")),
+                            Some(host_port) if !is_host_port(host_port) => {
+                                errors.push(format!("invalid host:port {}", host_port))
+                            }
+                            Some(_) => {}
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(_) => errors.push(String::from("invalid counterparty pubkey")),
+                }
+
+                if request.amount_sats == 0 || request.amount_sats >= MAX_FUNDING_SATOSHIS {
+                    errors.push(format!(
+                        "amount must be between 1 and {} sats",
+                        MAX_FUNDING_SATOSHIS - 1
+                    ));
+                }
+                match request.amount_sats.checked_mul(1000) {
+                    Some(amount_msats)
+                        if request.push_amount_msats.unwrap_or(0) <= amount_msats => {}
+                    _ => errors.push(String::from("push amount is larger than the channel")),
+                }
+                if let Some(custom_id) = request.custom_id {
+                    if !custom_ids.insert(custom_id) {
+                        errors.push(format!("duplicate custom id {}", custom_id));
+                    }
+                }
+
+                OpenChannelValidation {
+                    counterparty_pubkey: request.counterparty_pubkey.clone(),
+                    custom_id: request.custom_id,
+                    errors,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let mut errors = vec![];
+        if let Err(e) =
+            self.pending_opens
+                .check(self.config.max_pending_channels, requests.len(), || {
+                    pending_user_channel_ids(&self.channel_manager)
+                })
+        {
+            errors.push(e.to_string());
+        }
+
+        let total_sats = requests.iter().try_fold(0u64, |total, request| {
+            total.checked_add(request.amount_sats)
+        });
+        if total_sats.is_none() {
+            errors.push(String::from("channel amounts add up to more than a u64"));
+        }
+
+        let sat_per_vb = fee_estimator::sat_per_vb(
+            self.chain_manager
+                .wallet_feerate(ConfirmationTarget::Normal),
+        );
+        let funds = estimate_wallet_funds(
+            &self.wallet.lock().unwrap(),
+            requests,
+            sat_per_vb,
+            &self.pending_funding_inputs(),
+        )?;
+        let funds_check = funds.check();
+        if let Err(e) = &funds_check {
+            errors.push(e.to_string());
+        }
+
+        Ok(BatchValidation {
+            requests: validations,
+            errors,
+            total_sats: total_sats.unwrap_or(u64::MAX),
+            estimated_fee_sats: funds.fee_sats,
+            available_sats: funds.balance_sats,
+            sufficient_funds: funds_check.is_ok(),
+        })
+    }
+
+    pub async fn open_channels(
+        &self,
+        requests: Vec<OpenChannelRequest>,
+        options: BatchOpenOptions,
+    ) -> Vec<BatchOpenResult> {
+        let mut opener = self.channel_opener();
+        opener
+            .open_batch(requests, self.exclude_pending_inputs(options))
+            .await
+    }
+
+    fn pending_funding_inputs(&self) -> Vec<OutPoint> {
+        reserved_funding_inputs(&self.broadcaster, &self.pending_batch_fundings)
+    }
+
+    // Leaves the reserved inputs out of the funds check before the opens start, coin selection
+    // leaves out whatever is reserved by the time the funding tx is built.
+    fn exclude_pending_inputs(&self, mut options: BatchOpenOptions) -> BatchOpenOptions {
+        options
+            .unspendable_utxos
+            .extend(self.pending_funding_inputs());
+        options
+    }
+
+    // The pending channels are only kept in memory, after a restart the psbt can't be
+    // finalized anymore and the peers eventually give up on the channels.
+    pub async fn build_batch_funding(
+        &self,
+        requests: Vec<OpenChannelRequest>,
+        options: BatchOpenOptions,
+    ) -> Result<(PartiallySignedTransaction, Vec<BatchOpenResult>), Error> {
+        let mut opener = self.channel_opener();
+        opener
+            .build_batch_funding(requests, self.exclude_pending_inputs(options))
+            .await
+    }
+
+    pub async fn finalize_batch_funding(
+        &self,
+        psbt: PartiallySignedTransaction,
+    ) -> Result<Vec<BatchOpenResult>, Error> {
+        let mut opener = self.channel_opener();
+        opener.finalize_batch_funding(psbt).await
+    }
+
+    pub fn write_monitor_checkpoints(&self) -> Result<(), Error> {
+        let update_ids = self
+            .chain_monitor
+            .list_monitors()
+            .into_iter()
+            .filter_map(|funding_txo| {
+                let monitor = self.chain_monitor.get_monitor(funding_txo).ok()?;
+                Some((
+                    funding_txo.into_bitcoin_outpoint().to_string(),
+                    monitor.get_latest_update_id(),
+                ))
+            })
+            .collect();
+        MonitorCheckpoints { update_ids }.write(&self.monitor_checkpoints_path)?;
+        Ok(())
+    }
+
+    // Takes the operator's word that the state we run with is what they want, e.g. after
+    // restoring the newest backup there is. The current monitors become the new checkpoint.
+    pub fn acknowledge_stale_state(&self) -> Result<Vec<StaleChannel>, Error> {
+        let mut stale_channels = self.stale_channels.lock().unwrap();
+        self.write_monitor_checkpoints()?;
+        self.broadcaster.resume_spends();
+        Ok(stale_channels.drain(..).collect())
+    }
+
+    pub fn list_inflight_opens(&self) -> Vec<InflightOpen> {
+        self.pending_batch_fundings
+            .lock()
+            .unwrap()
+            .iter()
+            .flat_map(|(txid, pending)| {
+                pending
+                    .results
+                    .iter()
+                    .filter(|result| result.status.is_ok())
+                    .map(move |result| InflightOpen {
+                        custom_id: result.request.custom_id.unwrap_or_default(),
+                        temp_channel_id: hex_utils::hex_str(&result.temp_channel_id.unwrap()),
+                        counterparty_pubkey: result.request.counterparty_pubkey.clone(),
+                        amount_sats: result.request.amount_sats,
+                        funding_txid: txid.to_string(),
+                    })
+            })
+            .collect()
+    }
+
+    // Drops a channel that is still waiting on finalize_batch_funding. Nothing was signed
+    // yet and bdk doesn't hold on to the utxos of an unsigned psbt, so closing the channel is
+    // all there is to undo. The rest of its batch needs a new psbt without it.
+    pub fn cancel_inflight_open(&self, custom_id: u64) -> Result<(), Error> {
+        let mut pending_batch_fundings = self.pending_batch_fundings.lock().unwrap();
+        let result = pending_batch_fundings
+            .values_mut()
+            .flat_map(|pending| pending.results.iter_mut())
+            .find(|result| result.status.is_ok() && result.request.custom_id == Some(custom_id));
+
+        match result {
+            Some(result) => {
+                self.channel_manager.force_close_channel(
+                    &result.temp_channel_id.unwrap(),
+                    &result.counterparty_node_id.unwrap(),
+                )?;
+                result.status = Err(Error::OpenCancelled);
+                // a batch with nothing left to fund frees its inputs
+                pending_batch_fundings.retain(|_txid, pending| {
+                    pending.results.iter().any(|result| result.status.is_ok())
+                });
+                Ok(())
+            }
+            None => {
+                let funded = self.channel_manager.list_channels().iter().any(|channel| {
+                    channel.is_outbound
+                        && channel.user_channel_id == custom_id
+                        && channel.funding_txo.is_some()
+                });
+                if funded {
+                    Err(Error::FundingAlreadyBroadcast(custom_id))
+                } else {
+                    Err(Error::ChannelNotFound)
+                }
+            }
+        }
+    }
+
+    // `custom_id` will be user_channel_id in FundingGenerated event
+    // allows use to tie the create_channel call with the event
+    pub async fn open_channel(
+        &self,
+        request: OpenChannelRequest,
+        options: BatchOpenOptions,
+    ) -> BatchOpenResult {
+        let mut opener = self.channel_opener();
+        opener
+            .open_channel(request, self.exclude_pending_inputs(options))
+            .await
+    }
+
+    // Opens a channel to the invoice's payee and pays the invoice over it once it's usable.
+    // A funded channel can't be taken back, so when the payment fails the channel stays open
+    // and only the payment is reported as failed. Whether the channel is zero-conf is up to
+    // the peer. Without `ready_timeout_ms` the wait is a minute plus ten per confirmation the
+    // peer wants.
+    pub async fn open_channel_and_pay(
+        &self,
+        request: OpenChannelRequest,
+        invoice: &Invoice,
+        options: BatchOpenOptions,
+        ready_timeout_ms: Option<u64>,
+    ) -> Result<(BatchOpenResult, Result<(), Error>), Error> {
+        let counterparty_pubkey = parse_pubkey(&request.counterparty_pubkey)
+            .map_err(|_| Error::InvalidPubkey(request.counterparty_pubkey.clone()))?;
+        let payee_pubkey = invoice.recover_payee_pub_key();
+        if payee_pubkey != counterparty_pubkey {
+            return Err(Error::InvalidRoute(format!(
+                "invoice is payable to {}, not to the channel's counterparty",
+                payee_pubkey
+            )));
+        }
+        let amount_msat = invoice
+            .amount_milli_satoshis()
+            .ok_or_else(|| Error::Generic(String::from("invoice has no amount")))?;
+        if invoice.is_expired() {
+            return Err(Error::Generic(String::from("invoice is expired")));
+        }
+
+        let result = self.open_channel(request, options).await;
+        if result.status.is_err() {
+            let payment = Err(Error::Generic(String::from(
+                "channel was not opened, nothing was paid",
+            )));
+            return Ok((result, payment));
+        }
+
+        let user_channel_id = result.request.custom_id.unwrap();
+        let ready_timeout_ms = ready_timeout_ms.unwrap_or_else(|| {
+            let confirmations_required = self
+                .channel_manager
+                .list_channels()
+                .into_iter()
+                .find(|channel| channel.user_channel_id == user_channel_id)
+                .and_then(|channel| channel.confirmations_required)
+                .unwrap_or(0);
+            CHANNEL_READY_TIMEOUT_MS
+                + confirmations_required as u64 * CHANNEL_READY_TIMEOUT_PER_CONFIRMATION_MS
+        });
+        let channel = match self
+            .wait_for_usable_channel(user_channel_id, ready_timeout_ms)
+            .await
+        {
+            Some(channel) => channel,
+            None => return Ok((result, Err(Error::ChannelNotUsable))),
+        };
+
+        let payment = self
+            .send_through_channel(
+                &channel,
+                &[payee_pubkey],
+                amount_msat,
+                invoice.min_final_cltv_expiry() as u32,
+                PaymentHash((*invoice.payment_hash()).into_inner()),
+                Some(*invoice.payment_secret()),
+            )
+            .await;
+        Ok((result, payment))
+    }
+
+    async fn wait_for_usable_channel(
+        &self,
+        user_channel_id: u64,
+        timeout_ms: u64,
+    ) -> Option<ChannelDetails> {
+        let interval_ms = BatchOpenOptions::default().poll_interval_ms;
+        let mut current_ms = 0;
+        loop {
+            let channel = self
+                .channel_manager
+                .list_usable_channels()
+                .into_iter()
+                .find(|channel| channel.user_channel_id == user_channel_id);
+            if channel.is_some() || current_ms >= timeout_ms {
+                return channel;
+            }
+            tokio::time::sleep(Duration::from_millis(interval_ms)).await;
+            current_ms += interval_ms;
+        }
+    }
+
+    fn channel_opener(&self) -> ChannelOpener {
+        ChannelOpener::new(
+            self.id.clone(),
+            self.channel_manager.clone(),
+            self.chain_manager.clone(),
+            self.wallet.clone(),
+            self.event_sender.clone(),
+            self.broadcaster.clone(),
+            self.peer_manager.clone(),
+            self.database.clone(),
+            self.config.clone(),
+            self.change_script.clone(),
+            self.pending_opens.clone(),
+            self.pending_batch_fundings.clone(),
+        )
+    }
+
+    // Connecting to peers for opens retries as configured.
+    fn batch_open_options(&self) -> BatchOpenOptions {
+        BatchOpenOptions {
+            connect_retry: ConnectRetry::from(self.config.as_ref()),
+            ..Default::default()
+        }
+    }
+
+    pub async fn connect_to_peer(&self, pubkey: PublicKey, addr: PeerAddress) -> Result<(), Error> {
+        connect_peer_if_necessary(
+            pubkey,
+            addr,
+            self.peer_manager.clone(),
+            &self.config,
+            ConnectRetry {
+                max_attempts: 1,
+                ..Default::default()
+            },
+        )
+        .await
+    }
+
+    async fn keysend<K: KeysInterface>(
+        &self,
+        invoice_payer: &InvoicePayer,
+        payee_pubkey: PublicKey,
+        amt_msat: u64,
+        keys: &K,
+    ) -> Result<(), Error> {
+        if payee_pubkey == self.channel_manager.get_our_node_id() {
+            return Err(Error::SelfPayment);
+        }
+
+        let _in_flight_guard = self.check_in_flight_limit(amt_msat).await?;
+
+        let payment_preimage = keys.get_secure_random_bytes();
+
+        let mut ldk_payment_id = None;
+        let status = match invoice_payer.pay_pubkey(
+            payee_pubkey,
+            PaymentPreimage(payment_preimage),
+            amt_msat,
             40,
         ) {
-            Ok(_payment_id) => {
+            Ok(payment_id) => {
                 println!(
                     "EVENT: initiated sending {} msats to {}",
                     amt_msat, payee_pubkey
                 );
                 print!("> ");
+                ldk_payment_id = Some(hex_utils::hex_str(&payment_id.0));
                 HTLCStatus::Pending
             }
             Err(PaymentError::Invoice(e)) => {
@@ -1131,6 +2298,7 @@ impl LightningNode {
             status: ActiveValue::Set(status.to_string()),
             amt_msat: ActiveValue::Set(Some(amt_msat.try_into().unwrap())),
             origin: ActiveValue::Set(PaymentOrigin::SpontaneousOutgoing.to_string()),
+            ldk_payment_id: ActiveValue::Set(ldk_payment_id),
             ..Default::default()
         };
         payment.insert(self.database.get_connection()).await?;
@@ -1138,16 +2306,74 @@ impl LightningNode {
         Ok(())
     }
 
-    pub async fn send_payment(&self, invoice: &Invoice) -> Result<(), Error> {
-        let status = match self.invoice_payer.pay_invoice(invoice) {
-            Ok(_payment_id) => {
+    // Rejects a payment of `amt_msat` if it would take the outbound payments still pending,
+    // circular ones included, past `max_in_flight_outbound_msat`. The returned guard has to be
+    // held until the payment is recorded as pending, otherwise concurrent payments could all
+    // pass the check together.
+    async fn check_in_flight_limit(
+        &self,
+        amt_msat: u64,
+    ) -> Result<Option<tokio::sync::MutexGuard<'_, ()>>, Error> {
+        let max_in_flight_msat = match self.config.max_in_flight_outbound_msat {
+            Some(max_in_flight_msat) => max_in_flight_msat,
+            None => return Ok(None),
+        };
+
+        let guard = self.in_flight_lock.lock().await;
+        let in_flight_msat = self
+            .database
+            .pending_outgoing_msat(&self.id)
+            .await?
+            .saturating_add(self.circular_payments.in_flight_msat());
+        match in_flight_msat.checked_add(amt_msat) {
+            Some(total_msat) if total_msat <= max_in_flight_msat => Ok(Some(guard)),
+            _ => Err(Error::InFlightLimitExceeded(max_in_flight_msat)),
+        }
+    }
+
+    // Paying one of our own invoices can never find a route since LDK doesn't route
+    // payments back to ourselves, so we refuse these up front with Error::SelfPayment
+    // instead of surfacing a confusing routing failure. Same goes for keysend.
+    pub async fn send_payment(
+        &self,
+        invoice: &Invoice,
+        max_path_length: Option<u8>,
+    ) -> Result<(), Error> {
+        if invoice.recover_payee_pub_key() == self.channel_manager.get_our_node_id() {
+            return Err(Error::SelfPayment);
+        }
+
+        let _in_flight_guard = self
+            .check_in_flight_limit(invoice.amount_milli_satoshis().unwrap_or(0))
+            .await?;
+
+        // The limit counts every channel the payment goes through, a payment to a peer we
+        // have a channel with is 1. It holds for the retries too.
+        let payment_hash = PaymentHash((*invoice.payment_hash()).into_inner());
+        if let Some(max_path_length) = max_path_length {
+            let expires_at = invoice
+                .duration_since_epoch()
+                .saturating_add(invoice.expiry_time())
+                .as_secs();
+            self.path_length_limits
+                .set(payment_hash, max_path_length, expires_at);
+        }
+        let mut routing_error = None;
+        let mut ldk_payment_id = None;
+        let payment = self.invoice_payer.pay_invoice(invoice);
+        if payment.is_err() {
+            self.path_length_limits.remove(&payment_hash);
+        }
+        let (status, failure_reason) = match payment {
+            Ok(payment_id) => {
                 let payee_pubkey = invoice.recover_payee_pub_key();
                 let amt_msat = invoice.amount_milli_satoshis().unwrap();
                 println!(
                     "EVENT: initiated sending {} msats to {}",
                     amt_msat, payee_pubkey
                 );
-                HTLCStatus::Pending
+                ldk_payment_id = Some(hex_utils::hex_str(&payment_id.0));
+                (HTLCStatus::Pending, None)
             }
             Err(PaymentError::Invoice(e)) => {
                 println!("ERROR: invalid invoice: {}", e);
@@ -1155,11 +2381,12 @@ impl LightningNode {
             }
             Err(PaymentError::Routing(e)) => {
                 println!("ERROR: failed to find route: {}", e.err);
-                return Err(e.into());
+                routing_error = Some(e);
+                (HTLCStatus::Failed, Some(PaymentFailureReason::NoRoute))
             }
             Err(PaymentError::Sending(e)) => {
                 println!("ERROR: failed to send payment: {:?}", e);
-                HTLCStatus::Failed
+                (HTLCStatus::Failed, None)
             }
         };
 
@@ -1168,82 +2395,580 @@ impl LightningNode {
         let amt_msat: Option<i64> = invoice
             .amount_milli_satoshis()
             .map(|amt| amt.try_into().unwrap());
+        let failure_reason = failure_reason.map(|reason| reason.to_string());
 
-        let payment = entity::payment::ActiveModel {
-            node_id: ActiveValue::Set(self.id.clone()),
-            payment_hash: ActiveValue::Set(payment_hash),
-            secret: ActiveValue::Set(payment_secret),
-            status: ActiveValue::Set(status.to_string()),
-            amt_msat: ActiveValue::Set(amt_msat),
-            origin: ActiveValue::Set(PaymentOrigin::InvoiceOutgoing.to_string()),
-            invoice: ActiveValue::Set(Some(invoice.to_string())),
-            ..Default::default()
-        };
+        match self
+            .database
+            .find_payment(self.id.clone(), payment_hash.clone())
+            .await?
+        {
+            // paying an invoice again after an earlier attempt failed
+            Some(payment) => {
+                let mut payment: entity::payment::ActiveModel = payment.into();
+                payment.status = ActiveValue::Set(status.to_string());
+                payment.failure_reason = ActiveValue::Set(failure_reason);
+                payment.failure_short_channel_id = ActiveValue::Set(None);
+                payment.ldk_payment_id = ActiveValue::Set(ldk_payment_id);
+                payment.update(self.database.get_connection()).await?;
+            }
+            None => {
+                let payment = entity::payment::ActiveModel {
+                    node_id: ActiveValue::Set(self.id.clone()),
+                    payment_hash: ActiveValue::Set(payment_hash),
+                    secret: ActiveValue::Set(payment_secret),
+                    status: ActiveValue::Set(status.to_string()),
+                    amt_msat: ActiveValue::Set(amt_msat),
+                    origin: ActiveValue::Set(PaymentOrigin::InvoiceOutgoing.to_string()),
+                    invoice: ActiveValue::Set(Some(invoice.to_string())),
+                    failure_reason: ActiveValue::Set(failure_reason),
+                    ldk_payment_id: ActiveValue::Set(ldk_payment_id),
+                    ..Default::default()
+                };
+                payment.insert(self.database.get_connection()).await?;
+            }
+        }
 
-        payment.insert(self.database.get_connection()).await?;
+        match routing_error {
+            Some(e) => Err(e.into()),
+            None => Ok(()),
+        }
+    }
 
-        Ok(())
+    // Prices a route along `hops`, going out through `first_channel` and using the policies in
+    // the network graph for every channel after it. With `last_channel` the final hop comes
+    // back in over that channel of ours, priced with the policy our counterparty sent us.
+    fn build_route(
+        &self,
+        first_channel: &ChannelDetails,
+        last_channel: Option<&ChannelDetails>,
+        hops: &[PublicKey],
+        amount_msat: u64,
+        final_cltv_expiry_delta: u32,
+    ) -> Result<Vec<RouteHop>, Error> {
+        struct Edge {
+            short_channel_id: u64,
+            channel_features: ChannelFeatures,
+            fees: RoutingFees,
+            cltv_expiry_delta: u16,
+            htlc_minimum_msat: u64,
+            htlc_maximum_msat: Option<u64>,
+        }
+
+        let graph = self.network_graph.read_only();
+        let mut edges = vec![Edge {
+            short_channel_id: first_channel.get_outbound_payment_scid().ok_or_else(|| {
+                Error::InvalidRoute(String::from("channel with first hop is not confirmed"))
+            })?,
+            channel_features: ChannelFeatures::known(),
+            fees: RoutingFees {
+                base_msat: 0,
+                proportional_millionths: 0,
+            },
+            cltv_expiry_delta: 0,
+            htlc_minimum_msat: 0,
+            htlc_maximum_msat: Some(first_channel.outbound_capacity_msat),
+        }];
+        for (index, (source, target)) in hops.iter().zip(hops.iter().skip(1)).enumerate() {
+            if let Some(last_channel) = last_channel.filter(|_| index + 2 == hops.len()) {
+                if last_channel.counterparty.node_id != *source {
+                    return Err(Error::InvalidRoute(format!(
+                        "last channel is not with {}",
+                        source
+                    )));
+                }
+                let forwarding_info = last_channel
+                    .counterparty
+                    .forwarding_info
+                    .as_ref()
+                    .ok_or_else(|| {
+                        Error::InvalidRoute(format!("no forwarding policy from {} yet", source))
+                    })?;
+                edges.push(Edge {
+                    short_channel_id: last_channel.get_inbound_payment_scid().ok_or_else(|| {
+                        Error::InvalidRoute(String::from("last channel is not confirmed"))
+                    })?,
+                    channel_features: ChannelFeatures::known(),
+                    fees: RoutingFees {
+                        base_msat: forwarding_info.fee_base_msat,
+                        proportional_millionths: forwarding_info.fee_proportional_millionths,
+                    },
+                    cltv_expiry_delta: forwarding_info.cltv_expiry_delta,
+                    htlc_minimum_msat: 0,
+                    htlc_maximum_msat: Some(last_channel.inbound_capacity_msat),
+                });
+                continue;
+            }
+            let source_id = NodeId::from_pubkey(source);
+            let target_id = NodeId::from_pubkey(target);
+            let edge = graph
+                .nodes()
+                .get(&source_id)
+                .into_iter()
+                .flat_map(|node| node.channels.iter())
+                .filter_map(|short_channel_id| {
+                    let channel = graph.channels().get(short_channel_id)?;
+                    let policy = if channel.node_one == source_id && channel.node_two == target_id {
+                        channel.one_to_two.as_ref()?
+                    } else if channel.node_two == source_id && channel.node_one == target_id {
+                        channel.two_to_one.as_ref()?
+                    } else {
+                        return None;
+                    };
+                    if !policy.enabled {
+                        return None;
+                    }
+                    let htlc_maximum_msat = match (policy.htlc_maximum_msat, channel.capacity_sats)
+                    {
+                        (Some(max), Some(capacity)) => Some(std::cmp::min(max, capacity * 1000)),
+                        (max, capacity) => max.or_else(|| capacity.map(|sats| sats * 1000)),
+                    };
+                    Some(Edge {
+                        short_channel_id: *short_channel_id,
+                        channel_features: channel.features.clone(),
+                        fees: policy.fees,
+                        cltv_expiry_delta: policy.cltv_expiry_delta,
+                        htlc_minimum_msat: policy.htlc_minimum_msat,
+                        htlc_maximum_msat,
+                    })
+                })
+                .next()
+                .ok_or_else(|| {
+                    Error::InvalidRoute(format!("no enabled channel from {} to {}", source, target))
+                })?;
+            edges.push(edge);
+        }
+
+        // Walk back from the destination, each hop's fee_msat is what the next node charges
+        // to forward over its outgoing channel, the last one is the amount itself.
+        let mut route_hops = Vec::with_capacity(hops.len());
+        let mut forwarded_msat = amount_msat;
+        for (index, pubkey) in hops.iter().enumerate().rev() {
+            let overflow = || {
+                Error::InvalidRoute(format!(
+                    "forwarding {} msat to {} overflows",
+                    amount_msat, pubkey
+                ))
+            };
+            let (fee_msat, cltv_expiry_delta) = match edges.get(index + 1) {
+                Some(next_edge) => (
+                    (next_edge.fees.proportional_millionths as u64)
+                        .checked_mul(forwarded_msat)
+                        .map(|proportional_fee| proportional_fee / 1_000_000)
+                        .and_then(|proportional_fee_msat| {
+                            proportional_fee_msat.checked_add(next_edge.fees.base_msat as u64)
+                        })
+                        .ok_or_else(overflow)?,
+                    next_edge.cltv_expiry_delta as u32,
+                ),
+                None => (amount_msat, final_cltv_expiry_delta),
+            };
+            if index + 1 < hops.len() {
+                forwarded_msat = forwarded_msat.checked_add(fee_msat).ok_or_else(overflow)?;
+            }
+
+            let edge = &edges[index];
+            if forwarded_msat < edge.htlc_minimum_msat
+                || edge
+                    .htlc_maximum_msat
+                    .map_or(false, |max| forwarded_msat > max)
+            {
+                return Err(Error::InvalidRoute(format!(
+                    "channel {} to {} can't carry {} msat",
+                    edge.short_channel_id, pubkey, forwarded_msat
+                )));
+            }
+
+            let node_features = graph
+                .nodes()
+                .get(&NodeId::from_pubkey(pubkey))
+                .and_then(|node| node.announcement_info.as_ref())
+                .map(|info| info.features.clone())
+                .unwrap_or_else(NodeFeatures::known);
+            route_hops.push(RouteHop {
+                pubkey: *pubkey,
+                node_features,
+                short_channel_id: edge.short_channel_id,
+                channel_features: edge.channel_features.clone(),
+                fee_msat,
+                cltv_expiry_delta,
+            });
+        }
+        route_hops.reverse();
+        Ok(route_hops)
     }
 
-    pub async fn get_invoice(&self, amt_msat: u64, description: String) -> Result<Invoice, Error> {
-        let currency = match self.config.network {
-            Network::Bitcoin => Currency::Bitcoin,
-            Network::Testnet => Currency::BitcoinTestnet,
-            Network::Regtest => Currency::Regtest,
-            Network::Signet => Currency::Signet,
-        };
+    // Sends along exactly the given hops, skipping pathfinding. The first hop has to be one of
+    // our usable channels and every following hop needs a channel in the graph with a policy
+    // from the previous hop, which is what the fees and cltv deltas are computed from. When
+    // several channels connect the same two nodes the first one that fits is used. Paying an
+    // invoice, `final_cltv_expiry_delta` has to be at least its min_final_cltv_expiry or the
+    // recipient fails the payment, it defaults to what we use for keysends.
+    pub async fn send_along_route(
+        &self,
+        hops: Vec<PublicKey>,
+        amount_msat: u64,
+        payment_hash: PaymentHash,
+        payment_secret: Option<PaymentSecret>,
+        final_cltv_expiry_delta: Option<u32>,
+    ) -> Result<(), Error> {
+        if hops.is_empty() {
+            return Err(Error::InvalidRoute(String::from("no hops given")));
+        }
 
-        let invoice = utils::create_invoice_from_channelmanager(
-            &self.channel_manager,
-            self.keys_manager.clone(),
-            currency,
-            Some(amt_msat),
-            description.clone(),
-            3600, // FIXME invoice_expiry_delta_secs
+        let first_channel = self
+            .channel_manager
+            .list_usable_channels()
+            .into_iter()
+            .find(|channel| channel.counterparty.node_id == hops[0])
+            .ok_or_else(|| {
+                Error::InvalidRoute(format!("no usable channel with first hop {}", hops[0]))
+            })?;
+
+        self.send_through_channel(
+            &first_channel,
+            &hops,
+            amount_msat,
+            final_cltv_expiry_delta.unwrap_or(DEFAULT_FINAL_CLTV_EXPIRY_DELTA),
+            payment_hash,
+            payment_secret,
+        )
+        .await
+    }
+
+    async fn send_through_channel(
+        &self,
+        first_channel: &ChannelDetails,
+        hops: &[PublicKey],
+        amount_msat: u64,
+        final_cltv_expiry_delta: u32,
+        payment_hash: PaymentHash,
+        payment_secret: Option<PaymentSecret>,
+    ) -> Result<(), Error> {
+        let our_node_id = self.channel_manager.get_our_node_id();
+        let route_hops = self.build_route(
+            first_channel,
+            None,
+            hops,
+            amount_msat,
+            final_cltv_expiry_delta,
         )?;
 
-        let payment_hash = hex_utils::hex_str(&(*invoice.payment_hash()).into_inner());
-        let payment_secret = Some(hex_utils::hex_str(&(*invoice.payment_secret()).0));
+        let _in_flight_guard = self.check_in_flight_limit(amount_msat).await?;
+
+        let route = Route {
+            paths: vec![route_hops],
+            payment_params: None,
+        };
+
+        // tracked before sending, LDK can report it done before send_payment returns
+        let circular = hops.last() == Some(&our_node_id);
+        if circular {
+            self.circular_payments.insert(payment_hash, amount_msat);
+        }
+        let payment_id =
+            match self
+                .channel_manager
+                .send_payment(&route, payment_hash, &payment_secret)
+            {
+                Ok(payment_id) => payment_id,
+                Err(e) => {
+                    self.circular_payments.remove(&payment_hash);
+                    println!("ERROR: failed to send payment along route: {:?}", e);
+                    return Err(Error::InvalidRoute(format!("{:?}", e)));
+                }
+            };
+        println!(
+            "EVENT: initiated sending {} msats along {} hops",
+            amount_msat,
+            hops.len()
+        );
+
+        // A circular route pays one of our own invoices which already has a payment row.
+        if circular {
+            return Ok(());
+        }
+        let payment_hash = hex_utils::hex_str(&payment_hash.0);
 
         let payment = entity::payment::ActiveModel {
             node_id: ActiveValue::Set(self.id.clone()),
             payment_hash: ActiveValue::Set(payment_hash),
-            secret: ActiveValue::Set(payment_secret),
+            secret: ActiveValue::Set(payment_secret.map(|secret| hex_utils::hex_str(&secret.0))),
             status: ActiveValue::Set(HTLCStatus::Pending.to_string()),
-            amt_msat: ActiveValue::Set(Some(amt_msat.try_into().unwrap())),
-            origin: ActiveValue::Set(PaymentOrigin::InvoiceIncoming.to_string()),
-            invoice: ActiveValue::Set(Some(invoice.to_string())),
-            label: ActiveValue::Set(Some(description)),
+            amt_msat: ActiveValue::Set(Some(amount_msat.try_into().unwrap())),
+            origin: ActiveValue::Set(PaymentOrigin::InvoiceOutgoing.to_string()),
+            ldk_payment_id: ActiveValue::Set(Some(hex_utils::hex_str(&payment_id.0))),
             ..Default::default()
         };
+        payment.insert(self.database.get_connection()).await?;
 
-        payment
-            .insert(self.database.get_connection())
-            .await
-            .unwrap();
-
-        Ok(invoice)
+        Ok(())
     }
 
-    pub fn list_channels(
+    // Moves enough out of `channel_id` to leave `target_local_msat` behind by paying ourselves
+    // out through it and back in through one of our other channels. Fees are paid from the
+    // drained channel, so they count towards what leaves it. For every return channel the
+    // shortest path through the graph of up to MAX_REBALANCE_HOPS is priced, and the cheapest
+    // one within `max_fee_msat` is used.
+    pub async fn rebalance_to_target(
         &self,
-        pagination: PaginationRequest,
-    ) -> Result<(Vec<Channel>, PaginationResponse), Error> {
-        let query = pagination.query.unwrap_or_else(|| String::from(""));
-        let per_page: usize = pagination.take.try_into().unwrap();
-        let page: usize = pagination.page.try_into().unwrap();
-        let index = page * per_page;
+        channel_id: [u8; 32],
+        target_local_msat: u64,
+        max_fee_msat: u64,
+    ) -> Result<RebalanceResult, Error> {
+        let our_node_id = self.channel_manager.get_our_node_id();
+        let channels = self.channel_manager.list_usable_channels();
+        let channel = channels
+            .iter()
+            .find(|channel| channel.channel_id == channel_id)
+            .ok_or(Error::ChannelNotFound)?;
+
+        if target_local_msat >= channel.balance_msat {
+            return Err(Error::InvalidRoute(format!(
+                "local balance of {} msat is already at or below the target",
+                channel.balance_msat
+            )));
+        }
+        let to_move_msat = channel.balance_msat - target_local_msat;
+        if to_move_msat > channel.outbound_capacity_msat {
+            return Err(Error::InvalidRoute(format!(
+                "can send at most {} msat out of the channel, {} msat needed",
+                channel.outbound_capacity_msat, to_move_msat
+            )));
+        }
 
-        let channels = self
-            .channel_manager
-            .list_channels()
-            .into_iter()
+        let route_fee = |route_hops: &[RouteHop]| {
+            route_hops[..route_hops.len() - 1]
+                .iter()
+                .map(|hop| hop.fee_msat)
+                .sum::<u64>()
+        };
+
+        // the last hop is pinned to the return channel, so a parallel channel with the same
+        // peer works too and the drained channel itself is never picked.
+        let mut best: Option<(Vec<PublicKey>, Vec<RouteHop>, u64)> = None;
+        for return_channel in channels.iter().filter(|return_channel| {
+            return_channel.channel_id != channel.channel_id
+                && return_channel.inbound_capacity_msat >= to_move_msat
+        }) {
+            let mut hops = match self.find_graph_path(
+                channel.counterparty.node_id,
+                return_channel.counterparty.node_id,
+                to_move_msat,
+                MAX_REBALANCE_HOPS - 1,
+            ) {
+                Some(hops) => hops,
+                None => continue,
+            };
+            hops.push(our_node_id);
+
+            // the fee comes out of the amount that leaves the channel, pricing the route
+            // again for what is left over can only make it cheaper.
+            let priced = self
+                .build_route(
+                    channel,
+                    Some(return_channel),
+                    &hops,
+                    to_move_msat,
+                    DEFAULT_FINAL_CLTV_EXPIRY_DELTA,
+                )
+                .and_then(|route_hops| {
+                    let amount_msat = to_move_msat.saturating_sub(route_fee(&route_hops));
+                    self.build_route(
+                        channel,
+                        Some(return_channel),
+                        &hops,
+                        amount_msat,
+                        DEFAULT_FINAL_CLTV_EXPIRY_DELTA,
+                    )
+                });
+            let route_hops = match priced {
+                Ok(route_hops) => route_hops,
+                Err(_) => continue,
+            };
+
+            let fee_msat = route_fee(&route_hops);
+            if fee_msat > max_fee_msat {
+                continue;
+            }
+            if best
+                .as_ref()
+                .map_or(true, |(_, _, best_fee_msat)| fee_msat < *best_fee_msat)
+            {
+                best = Some((hops, route_hops, fee_msat));
+            }
+        }
+
+        let (hops, route_hops, fee_msat) = best.ok_or_else(|| {
+            Error::InvalidRoute(format!(
+                "no route back to us for {} msat within a fee budget of {} msat",
+                to_move_msat, max_fee_msat
+            ))
+        })?;
+        let amount_msat = route_hops.last().unwrap().fee_msat;
+
+        let invoice = self
+            .get_invoice(
+                amount_msat,
+                format!("rebalance of {}", hex_utils::hex_str(&channel_id)),
+            )
+            .await?;
+        let payment_hash = PaymentHash((*invoice.payment_hash()).into_inner());
+        let payment_secret = Some(*invoice.payment_secret());
+
+        let _in_flight_guard = self.check_in_flight_limit(amount_msat).await?;
+        let route = Route {
+            paths: vec![route_hops],
+            payment_params: None,
+        };
+        self.circular_payments.insert(payment_hash, amount_msat);
+        if let Err(e) = self
+            .channel_manager
+            .send_payment(&route, payment_hash, &payment_secret)
+        {
+            self.circular_payments.remove(&payment_hash);
+            println!("ERROR: failed to send rebalance payment: {:?}", e);
+            return Err(Error::InvalidRoute(format!("{:?}", e)));
+        }
+
+        Ok(RebalanceResult {
+            payment_hash: hex_utils::hex_str(&payment_hash.0),
+            amount_msat,
+            fee_msat,
+            hops: hops.iter().map(|hop| hop.to_string()).collect(),
+        })
+    }
+
+    // Breadth first search for the fewest hops from `from` to `to` over enabled channels in the
+    // graph that can carry `amount_msat`, never going through us. Paths are at most
+    // `max_nodes` long, counting both ends.
+    fn find_graph_path(
+        &self,
+        from: PublicKey,
+        to: PublicKey,
+        amount_msat: u64,
+        max_nodes: usize,
+    ) -> Option<Vec<PublicKey>> {
+        if from == to {
+            return Some(vec![from]);
+        }
+        let graph = self.network_graph.read_only();
+        let our_id = NodeId::from_pubkey(&self.channel_manager.get_our_node_id());
+        let from_id = NodeId::from_pubkey(&from);
+        let to_id = NodeId::from_pubkey(&to);
+
+        let mut previous: BTreeMap<NodeId, NodeId> = BTreeMap::new();
+        let mut queue = VecDeque::from([(from_id, 1)]);
+        while let Some((node_id, path_len)) = queue.pop_front() {
+            if path_len >= max_nodes {
+                continue;
+            }
+            let node = match graph.nodes().get(&node_id) {
+                Some(node) => node,
+                None => continue,
+            };
+            for short_channel_id in node.channels.iter() {
+                let channel = match graph.channels().get(short_channel_id) {
+                    Some(channel) => channel,
+                    None => continue,
+                };
+                let (next_id, policy) = if channel.node_one == node_id {
+                    (channel.node_two, channel.one_to_two.as_ref())
+                } else {
+                    (channel.node_one, channel.two_to_one.as_ref())
+                };
+                let usable = policy.map_or(false, |policy| {
+                    policy.enabled
+                        && policy
+                            .htlc_maximum_msat
+                            .map_or(true, |max| max >= amount_msat)
+                });
+                if !usable
+                    || next_id == our_id
+                    || next_id == from_id
+                    || previous.contains_key(&next_id)
+                {
+                    continue;
+                }
+                previous.insert(next_id, node_id);
+
+                if next_id == to_id {
+                    let mut path = vec![to_id];
+                    while let Some(previous_id) = previous.get(path.last().unwrap()) {
+                        path.push(*previous_id);
+                    }
+                    path.reverse();
+                    return path
+                        .iter()
+                        .map(|node_id| PublicKey::from_slice(node_id.as_slice()).ok())
+                        .collect();
+                }
+                queue.push_back((next_id, path_len + 1));
+            }
+        }
+        None
+    }
+
+    pub async fn get_invoice(&self, amt_msat: u64, description: String) -> Result<Invoice, Error> {
+        let currency = match self.config.network {
+            Network::Bitcoin => Currency::Bitcoin,
+            Network::Testnet => Currency::BitcoinTestnet,
+            Network::Regtest => Currency::Regtest,
+            Network::Signet => Currency::Signet,
+        };
+
+        let invoice = utils::create_invoice_from_channelmanager(
+            &self.channel_manager,
+            self.keys_manager.clone(),
+            currency,
+            Some(amt_msat),
+            description.clone(),
+            3600, // FIXME invoice_expiry_delta_secs
+        )?;
+
+        let payment_hash = hex_utils::hex_str(&(*invoice.payment_hash()).into_inner());
+        let payment_secret = Some(hex_utils::hex_str(&(*invoice.payment_secret()).0));
+
+        let payment = entity::payment::ActiveModel {
+            node_id: ActiveValue::Set(self.id.clone()),
+            payment_hash: ActiveValue::Set(payment_hash),
+            secret: ActiveValue::Set(payment_secret),
+            status: ActiveValue::Set(HTLCStatus::Pending.to_string()),
+            amt_msat: ActiveValue::Set(Some(amt_msat.try_into().unwrap())),
+            origin: ActiveValue::Set(PaymentOrigin::InvoiceIncoming.to_string()),
+            invoice: ActiveValue::Set(Some(invoice.to_string())),
+            label: ActiveValue::Set(Some(description)),
+            ..Default::default()
+        };
+
+        payment
+            .insert(self.database.get_connection())
+            .await
+            .unwrap();
+
+        Ok(invoice)
+    }
+
+    pub fn list_channels(
+        &self,
+        pagination: PaginationRequest,
+    ) -> Result<(Vec<Channel>, PaginationResponse), Error> {
+        let query = pagination.query.unwrap_or_else(|| String::from(""));
+        let per_page: usize = pagination.take.try_into().unwrap();
+        let page: usize = pagination.page.try_into().unwrap();
+        let index = page * per_page;
+
+        let mut labels = self.database.list_channel_labels_sync(&self.id)?;
+        let channels = self
+            .channel_manager
+            .list_channels()
+            .into_iter()
             .filter_map(|chan_info| {
                 let mut channel: Channel = chan_info.clone().into();
 
                 channel.alias = self
                     .get_alias_for_channel_counterparty(&chan_info)
                     .map(|alias_bytes| hex_utils::sanitize_string(&alias_bytes));
+                channel.label = labels.remove(&channel.channel_id);
 
                 let match_channel = channel.clone();
                 let matches_channel_id = match_channel.channel_id.contains(&query);
@@ -1256,8 +2981,15 @@ impl LightningNode {
                     .alias
                     .map(|alias| alias.contains(&query))
                     .unwrap_or(false);
-                let matches =
-                    matches_channel_id || matches_funding_txid || matches_pubkey || matches_alias;
+                let matches_label = match_channel
+                    .label
+                    .map(|label| label.contains(&query))
+                    .unwrap_or(false);
+                let matches = matches_channel_id
+                    || matches_funding_txid
+                    || matches_pubkey
+                    || matches_alias
+                    || matches_label;
                 if matches {
                     Some(channel)
                 } else {
@@ -1280,27 +3012,381 @@ impl LightningNode {
         Ok((current_page, pagination_response))
     }
 
-    pub fn list_transactions(
+    // Payments are marked as claiming once the full amount has arrived and we have
+    // called claim_funds. If we restarted before the claim made it into the channel
+    // manager the htlcs are still claimable, so we try to claim them again here.
+    pub async fn claim_pending_payments(&self) -> Result<(), Error> {
+        let payments = self.database.list_claiming_payments(&self.id).await?;
+        for payment in payments {
+            let preimage = payment
+                .preimage
+                .as_ref()
+                .and_then(|preimage| hex_utils::to_vec(preimage))
+                .and_then(|preimage| preimage.try_into().ok());
+
+            match preimage {
+                Some(preimage) => {
+                    // a no-op when there's nothing left to claim, a PaymentClaimed event
+                    // follows otherwise
+                    self.channel_manager.claim_funds(PaymentPreimage(preimage));
+                }
+                None => {
+                    println!(
+                        "missing preimage for claiming payment hash {}",
+                        payment.payment_hash
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // The invoice payer keeps its retry state in memory, after a restart nothing retries our
+    // pending payments and one whose paths all failed would stay pending, counting against
+    // the in-flight limit. Abandoning them has LDK send PaymentFailed once no htlc of theirs
+    // is left, right away if there is none, while a path that still succeeds sends
+    // PaymentSent as usual. Payments are only stored once LDK took them, but LDK can lose
+    // track of one when its channel manager wasn't persisted after that. Nothing resolves
+    // those, so they are failed once they are older than any htlc could stay in flight.
+    pub async fn reconcile_pending_payments(&self) -> Result<(), Error> {
+        let now = entity::seconds_since_epoch();
+        for payment in self
+            .database
+            .list_pending_outgoing_payments(&self.id)
+            .await?
+        {
+            // LDK picks a random id for each payment, rows from before we stored it can't be
+            // abandoned and are only failed once they're stale
+            let payment_id = payment
+                .ldk_payment_id
+                .as_deref()
+                .and_then(hex_utils::to_vec)
+                .and_then(|payment_id| payment_id.try_into().ok());
+            if let Some(payment_id) = payment_id {
+                self.channel_manager.abandon_payment(PaymentId(payment_id));
+            }
+
+            if now - payment.created_at > MAX_PENDING_PAYMENT_SECS {
+                println!(
+                    "failing payment {} that has been pending since {}",
+                    payment.payment_hash, payment.created_at
+                );
+                let mut payment: entity::payment::ActiveModel = payment.into();
+                payment.status = ActiveValue::Set(HTLCStatus::Failed.to_string());
+                payment.failure_reason =
+                    ActiveValue::Set(Some(PaymentFailureReason::RetriesExhausted.to_string()));
+                payment.update(self.database.get_connection()).await?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn sync_wallet(&self) {
+        self.chain_manager.request_sync();
+    }
+
+    // The wallet only recognizes deposits to scripts it has already derived and cached, so
+    // funds sent to addresses past the cached ones are missed until more are cached.
+    pub fn scanned_addresses(&self) -> Result<usize, Error> {
+        let wallet = self.wallet.lock().unwrap();
+        let script_pubkeys = wallet.database().iter_script_pubkeys(None)?;
+        Ok(script_pubkeys.len())
+    }
+
+    pub async fn rescan_wallet(&self, from_height: u32) -> Result<u32, Error> {
+        let wallet_database = self.wallet.lock().unwrap().database().for_rescan();
+        self.chain_manager
+            .rescan(&wallet_database, from_height)
+            .await
+    }
+
+    // Caches the first `addresses` scripts on both keychains and, if that derived anything
+    // new, rescans from `rescan_from_height` so deposits to the new scripts are picked up.
+    // This is a fixed count, not a gap limit counted from the last used address.
+    pub async fn cache_wallet_addresses(
+        &self,
+        addresses: u32,
+        rescan_from_height: u32,
+    ) -> Result<(usize, Option<u32>), Error> {
+        let extended = {
+            let wallet = self.wallet.lock().unwrap();
+            wallet.ensure_addresses_cached(addresses)?
+        };
+
+        let blocks_scanned = match extended {
+            true => Some(self.rescan_wallet(rescan_from_height).await?),
+            false => None,
+        };
+
+        Ok((self.scanned_addresses()?, blocks_scanned))
+    }
+
+    pub async fn new_deposit_address(&self, label: String) -> Result<String, Error> {
+        let address = {
+            let wallet = self.wallet.lock().unwrap();
+            wallet.get_address(AddressIndex::New)?.address.to_string()
+        };
+        self.database
+            .create_deposit_address(&self.id, &address, label)
+            .await?;
+        Ok(address)
+    }
+
+    // Settled payments, forwards and confirmed on-chain transactions between `from` and `to`
+    // (seconds since epoch, inclusive), oldest first.
+    pub fn ledger(&self, from: i64, to: i64) -> Result<LedgerCursor, Error> {
+        let transactions = {
+            let wallet = self.wallet.lock().unwrap();
+            wallet.list_transactions(false)?
+        };
+        let mut onchain = vec![];
+        for details in transactions {
+            let timestamp = match &details.confirmation_time {
+                Some(block_time) => block_time.timestamp as i64,
+                None => continue,
+            };
+            if timestamp < from || timestamp > to {
+                continue;
+            }
+            let fee_sats = if details.sent > 0 {
+                details.fee.unwrap_or(0)
+            } else {
+                0
+            };
+            onchain.push(LedgerEntry {
+                timestamp,
+                kind: String::from("onchain"),
+                reference: details.txid.to_string(),
+                amount_msat: (details.received as i64 - details.sent as i64) * 1000,
+                fee_msat: fee_sats as i64 * 1000,
+                label: None,
+            });
+        }
+        onchain.sort_by_key(|entry| entry.timestamp);
+
+        Ok(LedgerCursor {
+            database: self.database.clone(),
+            node_id: self.id.clone(),
+            from,
+            to,
+            payments: VecDeque::new(),
+            next_payments_page: Some(0),
+            forwards: VecDeque::new(),
+            next_forwards_page: Some(0),
+            onchain: onchain.into(),
+        })
+    }
+
+    // For spends built elsewhere, e.g. multi-party or air-gapped ones. Every input has to be
+    // either ours, which we sign, or come with signatures already. We don't judge whether
+    // foreign signatures are enough, the psbt just has to finalize.
+    pub fn sign_and_broadcast_psbt(
+        &self,
+        mut psbt: PartiallySignedTransaction,
+    ) -> Result<Txid, Error> {
+        let tx = {
+            let wallet = self.wallet.lock().unwrap();
+            for (txin, input) in psbt.unsigned_tx.input.iter().zip(psbt.inputs.iter()) {
+                let signed = input.final_script_sig.is_some()
+                    || input.final_script_witness.is_some()
+                    || !input.partial_sigs.is_empty();
+                if !signed && wallet.get_utxo(txin.previous_output)?.is_none() {
+                    return Err(Error::InvalidPsbt(format!(
+                        "input {} is not ours and isn't signed",
+                        txin.previous_output
+                    )));
+                }
+            }
+
+            let finalized = wallet.sign(&mut psbt, SignOptions::default())?;
+            if !finalized {
+                return Err(Error::InvalidPsbt(String::from(
+                    "not every input could be finalized",
+                )));
+            }
+            psbt.extract_tx()
+        };
+
+        let txid = tx.txid();
+        self.broadcaster.set_context(
+            txid,
+            BroadcastContext {
+                purpose: BroadcastPurpose::Psbt,
+                context: Some(format!("{} inputs", tx.input.len())),
+                feerate_sat_per_vb: None,
+            },
+        );
+        self.broadcaster.broadcast_transaction(&tx);
+        Ok(txid)
+    }
+
+    // Bumps a stuck funding tx by spending its change back to ourselves (CPFP), so that the
+    // two together pay `sat_per_vb`. Replacing it with build_fee_bump is not an option, the
+    // peers only signed commitments spending the original txid.
+    pub fn bump_funding_fee(&self, txid: Txid, sat_per_vb: u64) -> Result<Txid, Error> {
+        const CHILD_TX_VBYTES: u64 = 11 + 68 + 31;
+        const MAX_BUMP_SAT_PER_VB: u64 = 10_000;
+
+        if sat_per_vb == 0 || sat_per_vb > MAX_BUMP_SAT_PER_VB {
+            return Err(Error::InvalidFeeRate(sat_per_vb, MAX_BUMP_SAT_PER_VB));
+        }
+
+        let funds_channel = self
+            .channel_manager
+            .list_channels()
+            .iter()
+            .any(|channel| channel.funding_txo.map(|txo| txo.txid) == Some(txid));
+        if !funds_channel {
+            return Err(Error::BatchFundingNotFound(txid.to_string()));
+        }
+
+        let child_tx = {
+            let wallet = self.wallet.lock().unwrap();
+            let details = wallet
+                .get_tx(&txid, true)?
+                .ok_or_else(|| Error::BatchFundingNotFound(txid.to_string()))?;
+            if details.confirmation_time.is_some() {
+                return Err(Error::FundingTxConfirmed(txid.to_string()));
+            }
+            let parent_vbytes = details
+                .transaction
+                .as_ref()
+                .map(|tx| (tx.weight() as u64 + 3) / 4)
+                .unwrap_or_default();
+            let parent_fee = details.fee.unwrap_or_default();
+
+            let change = wallet
+                .list_unspent()?
+                .into_iter()
+                .filter(|utxo| utxo.outpoint.txid == txid)
+                .map(|utxo| utxo.outpoint)
+                .collect::<Vec<OutPoint>>();
+            if change.is_empty() {
+                return Err(Error::NoChangeToBump(txid.to_string()));
+            }
+
+            let package_fee = (parent_vbytes + CHILD_TX_VBYTES)
+                .checked_mul(sat_per_vb)
+                .ok_or(Error::InvalidFeeRate(sat_per_vb, MAX_BUMP_SAT_PER_VB))?;
+            if package_fee <= parent_fee {
+                return Err(Error::Generic(format!(
+                    "funding tx already pays at least {} sat/vB",
+                    sat_per_vb
+                )));
+            }
+
+            let drain_script = match &self.change_script {
+                Some(change_script) => change_script.clone(),
+                None => wallet.get_address(AddressIndex::New)?.script_pubkey(),
+            };
+            let mut tx_builder = wallet.build_tx();
+            tx_builder
+                .add_utxos(&change)?
+                .manually_selected_only()
+                .drain_to(drain_script)
+                .fee_absolute(package_fee - parent_fee);
+            let (mut psbt, _tx_details) = tx_builder.finish()?;
+            let finalized = wallet.sign(&mut psbt, SignOptions::default())?;
+            if !finalized {
+                return Err(Error::SigningFailed(String::from(
+                    "not every fee bump input is signed",
+                )));
+            }
+            psbt.extract_tx()
+        };
+
+        let child_txid = child_tx.txid();
+        self.broadcaster.set_context(
+            child_txid,
+            BroadcastContext {
+                purpose: BroadcastPurpose::FeeBump,
+                context: Some(format!("funding tx {}", txid)),
+                feerate_sat_per_vb: Some(sat_per_vb as f64),
+            },
+        );
+        self.broadcaster.broadcast_transaction(&child_tx);
+        Ok(child_txid)
+    }
+
+    // Hands every wallet transaction that hasn't confirmed yet back to the backend, for when
+    // it lost its mempool. Commitment and claim transactions aren't wallet transactions, LDK
+    // rebroadcasts those itself on every block. Transactions the broadcaster wouldn't send,
+    // e.g. spends of a stale channel, are reported as failed without being submitted.
+    pub async fn rebroadcast_unconfirmed(&self) -> Result<Vec<RebroadcastResult>, Error> {
+        let unconfirmed = {
+            let wallet = self.wallet.lock().unwrap();
+            wallet
+                .list_transactions(true)?
+                .into_iter()
+                .filter(|details| details.confirmation_time.is_none())
+                .filter_map(|details| details.transaction)
+                .collect::<Vec<_>>()
+        };
+
+        let mut results = vec![];
+        for tx in unconfirmed {
+            let error = match self.broadcaster.rebroadcast_refusal(&tx) {
+                Some(refusal) => Some(format!("not rebroadcast, {}", refusal)),
+                None => self
+                    .chain_manager
+                    .submitter
+                    .submit_transaction(&tx)
+                    .await
+                    .err(),
+            };
+            results.push(RebroadcastResult {
+                txid: tx.txid().to_string(),
+                succeeded: error.is_none(),
+                error,
+            });
+        }
+        Ok(results)
+    }
+
+    pub async fn list_transactions(
         &self,
         pagination: PaginationRequest,
-    ) -> Result<(Vec<TransactionDetails>, PaginationResponse), Error> {
+    ) -> Result<(Vec<LabeledTransaction>, PaginationResponse), Error> {
         let query = pagination.query.unwrap_or_else(|| String::from(""));
         let per_page: usize = pagination.take.try_into().unwrap();
         let page: usize = pagination.page.try_into().unwrap();
         let index = page * per_page;
 
-        let bdk_wallet = self.wallet.lock().unwrap();
-
-        let transaction_details = bdk_wallet
-            .list_transactions(false)?
+        let deposit_labels = self
+            .database
+            .list_deposit_addresses(&self.id)
+            .await?
             .into_iter()
-            .filter(|tx_details| {
-                let match_transaction_details = tx_details.clone();
-                match_transaction_details.txid.to_string().contains(&query)
-            })
-            .collect::<Vec<TransactionDetails>>();
+            .map(|deposit_address| (deposit_address.address, deposit_address.label))
+            .collect::<HashMap<String, String>>();
+
+        let transactions = {
+            let bdk_wallet = self.wallet.lock().unwrap();
+            bdk_wallet
+                .list_transactions(true)?
+                .into_iter()
+                .filter(|tx_details| tx_details.txid.to_string().contains(&query))
+                .map(|mut details| {
+                    let labels = details
+                        .transaction
+                        .take()
+                        .map(|tx| {
+                            tx.output
+                                .iter()
+                                .filter_map(|txout| {
+                                    Address::from_script(&txout.script_pubkey, self.config.network)
+                                })
+                                .filter_map(|address| deposit_labels.get(&address.to_string()))
+                                .cloned()
+                                .collect::<Vec<String>>()
+                        })
+                        .unwrap_or_default();
+                    LabeledTransaction { details, labels }
+                })
+                .collect::<Vec<LabeledTransaction>>()
+        };
 
-        let paginated_transactions = PagedVec::new(&transaction_details, per_page);
+        let paginated_transactions = PagedVec::new(&transactions, per_page);
         let current_page = paginated_transactions
             .page(index)
             .map(|transactions_page| transactions_page.1.to_vec())
@@ -1309,7 +3395,7 @@ impl LightningNode {
 
         let pagination_response = PaginationResponse {
             has_more: next_page.is_some(),
-            total: transaction_details.len() as u64,
+            total: transactions.len() as u64,
         };
         Ok((current_page, pagination_response))
     }
@@ -1356,26 +3442,608 @@ impl LightningNode {
         }
     }
 
+    // Channels we don't know about fail right away, the rest are closed together and each
+    // gets a close_requested entry in its timeline.
+    pub async fn close_channels(
+        &self,
+        requests: Vec<BatchCloseRequest>,
+        timeout_ms: u64,
+    ) -> Vec<CloseChannelResult> {
+        let channels = self.channel_manager.list_channels();
+        let close_requests = requests
+            .iter()
+            .map(|request| {
+                let channel_id = hex_utils::to_vec(&request.channel_id)?;
+                channels
+                    .iter()
+                    .find(|channel| channel.channel_id[..] == channel_id[..])
+                    .map(|channel| CloseChannelRequest {
+                        channel_id: channel.channel_id,
+                        force: request.force,
+                        counterparty_node_id: channel.counterparty.node_id,
+                    })
+            })
+            .collect::<Vec<_>>();
+
+        let mut opener = self.channel_opener();
+        let closed = opener
+            .close_batch(
+                close_requests.iter().flatten().cloned().collect(),
+                timeout_ms,
+                BatchOpenOptions::default().poll_interval_ms,
+            )
+            .await;
+
+        // ldk refusing to start a close leaves nothing to record
+        let started = closed
+            .iter()
+            .filter(|(_request, result)| !matches!(result, Err(Error::LdkApi(_))));
+        for (request, _result) in started {
+            let _res = self
+                .database
+                .record_channel_event(
+                    &self.id,
+                    &hex_utils::hex_str(&request.channel_id),
+                    "close_requested",
+                    "operator",
+                    Some(String::from(if request.force {
+                        "force"
+                    } else {
+                        "cooperative"
+                    })),
+                )
+                .await;
+        }
+
+        let mut closed = closed.into_iter();
+        requests
+            .into_iter()
+            .zip(close_requests)
+            .map(|(request, close_request)| {
+                let result = match close_request {
+                    Some(_) => closed.next().unwrap().1,
+                    None => Err(Error::ChannelNotFound),
+                };
+                CloseChannelResult {
+                    channel_id: request.channel_id,
+                    error: result.is_err(),
+                    error_message: result.err().map(|e| e.to_string()),
+                }
+            })
+            .collect()
+    }
+
+    // The fee we'd earn forwarding `amount_msat` from one of our channels to another. It's
+    // charged by the outgoing channel's policy, the same way LDK checks it when forwarding.
+    // Capacity is only what the channels could carry right now, htlc limits aren't checked.
+    pub fn estimate_forward_fee(
+        &self,
+        in_channel_id: &str,
+        out_channel_id: &str,
+        amount_msat: u64,
+    ) -> Result<ForwardFeeEstimate, Error> {
+        let channels = self.channel_manager.list_channels();
+        let find_channel = |channel_id: &str| {
+            hex_utils::to_vec(channel_id)
+                .and_then(|channel_id| {
+                    channels
+                        .iter()
+                        .find(|channel| channel.channel_id[..] == channel_id[..])
+                })
+                .ok_or(Error::ChannelNotFound)
+        };
+        let in_channel = find_channel(in_channel_id)?;
+        let out_channel = find_channel(out_channel_id)?;
+        if in_channel.channel_id == out_channel.channel_id {
+            return Err(Error::InvalidRoute(String::from(
+                "cannot forward back out the channel it came in on",
+            )));
+        }
+
+        // ChannelDetails doesn't carry our policy, it's in the channel_update we announced.
+        // Channels without one forward with the default config.
+        let our_node_id = NodeId::from_pubkey(&self.channel_manager.get_our_node_id());
+        let announced_fees = out_channel.short_channel_id.and_then(|short_channel_id| {
+            let graph = self.network_graph.read_only();
+            graph.channels().get(&short_channel_id).and_then(|info| {
+                let direction = if info.node_one == our_node_id {
+                    info.one_to_two.as_ref()
+                } else {
+                    info.two_to_one.as_ref()
+                };
+                direction.map(|direction| direction.fees)
+            })
+        });
+        let (forwarding_fee_base_msat, forwarding_fee_proportional_millionths) =
+            match announced_fees {
+                Some(fees) => (fees.base_msat, fees.proportional_millionths),
+                None => {
+                    let config = &self
+                        .channel_manager
+                        .get_current_default_configuration()
+                        .channel_options;
+                    (
+                        config.forwarding_fee_base_msat,
+                        config.forwarding_fee_proportional_millionths,
+                    )
+                }
+            };
+        let fee_msat = (forwarding_fee_base_msat as u64).saturating_add(
+            amount_msat.saturating_mul(forwarding_fee_proportional_millionths as u64) / 1_000_000,
+        );
+
+        Ok(ForwardFeeEstimate {
+            in_channel_id: hex_utils::hex_str(&in_channel.channel_id),
+            out_channel_id: hex_utils::hex_str(&out_channel.channel_id),
+            amount_msat,
+            forwarding_fee_base_msat,
+            forwarding_fee_proportional_millionths,
+            fee_msat,
+            within_capacity: in_channel.inbound_capacity_msat
+                >= amount_msat.saturating_add(fee_msat)
+                && out_channel.outbound_capacity_msat >= amount_msat,
+        })
+    }
+
+    // Estimates what a force close would return to our wallet right now without touching the
+    // channel. Inbound htlcs we don't have the preimage for are left out since they go back
+    // to the peer, and if we are the funder the commitment fee is already taken out of
+    // to_local. Sweep sizes are rough estimates for p2wpkh destinations.
+    pub fn simulate_force_close(
+        &self,
+        channel_id: [u8; 32],
+        sat_per_vb: u64,
+    ) -> Result<ForceCloseSimulation, Error> {
+        const TO_LOCAL_SWEEP_VBYTES: u64 = 122;
+        const HTLC_TIMEOUT_VBYTES: u64 = 166;
+        const HTLC_SUCCESS_VBYTES: u64 = 176;
+
+        let channel = self
+            .channel_manager
+            .list_channels()
+            .into_iter()
+            .find(|channel| channel.channel_id == channel_id)
+            .ok_or(Error::ChannelNotFound)?;
+
+        let balances = match channel.funding_txo {
+            Some(funding_txo) => self
+                .chain_monitor
+                .get_monitor(funding_txo)
+                .map(|monitor| monitor.get_claimable_balances())
+                .unwrap_or_default(),
+            None => vec![],
+        };
+
+        let mut to_local_sats = 0;
+        let mut pending_htlcs = vec![];
+        let mut awaiting_confirmations_sats = 0;
+        let mut contentious_sats = 0;
+        let mut contentious_htlcs = 0;
+        for balance in balances {
+            match balance {
+                Balance::ClaimableOnChannelClose {
+                    claimable_amount_satoshis,
+                } => to_local_sats += claimable_amount_satoshis,
+                Balance::MaybeClaimableHTLCAwaitingTimeout {
+                    claimable_amount_satoshis,
+                    claimable_height,
+                } => pending_htlcs.push(SimulatedHtlc {
+                    amount_sats: claimable_amount_satoshis,
+                    claimable_height,
+                }),
+                // the channel is already closing, these are claimed whether we close or not
+                Balance::ClaimableAwaitingConfirmations {
+                    claimable_amount_satoshis,
+                    ..
+                } => awaiting_confirmations_sats += claimable_amount_satoshis,
+                // we have the preimage, but only get the htlc if we claim it before it times out
+                Balance::ContentiousClaimable {
+                    claimable_amount_satoshis,
+                    ..
+                } => {
+                    contentious_sats += claimable_amount_satoshis;
+                    contentious_htlcs += 1;
+                }
+            }
+        }
+
+        let htlc_sats = pending_htlcs
+            .iter()
+            .map(|htlc| htlc.amount_sats)
+            .sum::<u64>();
+
+        // LDK only reports inbound htlcs it has the preimage for, the rest go back to the
+        // counterparty once they time out. What's left of the channel after our balance,
+        // outbound htlcs, the reserve and the counterparty's capacity is in them. The balances
+        // LDK hands out are floored at zero and dust htlcs aren't in `pending_htlcs`, so this
+        // is an estimate.
+        let value_to_self_msat = channel
+            .outbound_capacity_msat
+            .saturating_add(channel.unspendable_punishment_reserve.unwrap_or(0) * 1000)
+            .saturating_add(htlc_sats * 1000);
+        let pending_inbound_htlcs_sats = (channel.channel_value_satoshis * 1000)
+            .saturating_sub(value_to_self_msat)
+            .saturating_sub(channel.counterparty.unspendable_punishment_reserve * 1000)
+            .saturating_sub(channel.inbound_capacity_msat)
+            / 1000;
+
+        let sweep_vbytes = if to_local_sats > 0 {
+            TO_LOCAL_SWEEP_VBYTES
+        } else {
+            0
+        };
+        let htlc_vbytes =
+            pending_htlcs.len() as u64 * (HTLC_TIMEOUT_VBYTES + TO_LOCAL_SWEEP_VBYTES);
+        let contentious_vbytes = contentious_htlcs * (HTLC_SUCCESS_VBYTES + TO_LOCAL_SWEEP_VBYTES);
+        let estimated_fees_sats = (sweep_vbytes + htlc_vbytes + contentious_vbytes) * sat_per_vb;
+
+        Ok(ForceCloseSimulation {
+            channel_id: hex_utils::hex_str(&channel_id),
+            to_local_sats,
+            csv_delay: channel.force_close_spend_delay,
+            pending_htlcs,
+            pending_inbound_htlcs_sats,
+            awaiting_confirmations_sats,
+            contentious_sats,
+            estimated_fees_sats,
+            recoverable_sats: (to_local_sats
+                + htlc_sats
+                + awaiting_confirmations_sats
+                + contentious_sats)
+                .saturating_sub(estimated_fees_sats),
+        })
+    }
+
     fn get_channel_counterparty(&self, channel_id: &[u8; 32]) -> PublicKey {
         let chans = self.channel_manager.list_channels();
         let chan = chans.iter().find(|c| *channel_id == c.channel_id).unwrap();
         chan.counterparty.node_id
     }
 
-    pub fn node_info(&self) -> Result<NodeInfo, Error> {
+    pub async fn node_info(&self) -> Result<NodeInfo, Error> {
         let chans = self.channel_manager.list_channels();
         let local_balance_msat = chans.iter().map(|c| c.balance_msat).sum::<u64>();
+        let in_flight_outbound_msat = self
+            .database
+            .pending_outgoing_msat(&self.id)
+            .await?
+            .saturating_add(self.circular_payments.in_flight_msat());
 
         Ok(NodeInfo {
             version: version::get_version(),
             node_pubkey: self.get_pubkey(),
             num_channels: chans.len() as u32,
             num_usable_channels: chans.iter().filter(|c| c.is_usable).count() as u32,
+            num_pending_channels: num_pending_channels(&self.channel_manager) as u32,
             num_peers: self.peer_manager.get_peer_node_ids().len() as u32,
             local_balance_msat,
+            last_sync_timestamp: self.chain_manager.last_sync(),
+            in_flight_outbound_msat,
+        })
+    }
+
+    // The block the node has synced to. The timestamp comes from a separate header fetch and
+    // is left out if the backend can't serve it.
+    pub async fn get_chain_tip(&self) -> Result<ChainTip, Error> {
+        if self.chain_manager.last_sync() == 0 {
+            return Err(Error::ChainTipUnavailable);
+        }
+
+        let best_block = self.channel_manager.current_best_block();
+        let block_hash = best_block.block_hash();
+        let timestamp = self
+            .chain_manager
+            .block_source
+            .get_header(&block_hash, Some(best_block.height()))
+            .await
+            .ok()
+            .map(|header| header.header.time);
+
+        Ok(ChainTip {
+            height: best_block.height(),
+            block_hash: block_hash.to_string(),
+            timestamp,
+        })
+    }
+
+    // Returns the serialized gossip our node is broadcasting, as it ended up in the network
+    // graph. Timestamps are the ones inside the announcement or update, or when we received
+    // the channel announcement since those don't carry one.
+    pub fn gossip_messages(&self) -> (Option<GossipMessage>, Vec<ChannelGossip>) {
+        let our_node_id = NodeId::from_pubkey(&self.channel_manager.get_our_node_id());
+        let graph = self.network_graph.read_only();
+
+        let node_announcement = graph
+            .nodes()
+            .get(&our_node_id)
+            .and_then(|node| node.announcement_info.as_ref())
+            .and_then(|info| {
+                info.announcement_message
+                    .as_ref()
+                    .map(|message| GossipMessage {
+                        message: hex_utils::hex_str(&message.encode()),
+                        timestamp: info.last_update as u64,
+                    })
+            });
+
+        let channels = self
+            .channel_manager
+            .list_channels()
+            .iter()
+            .filter_map(|channel| channel.short_channel_id)
+            .map(|short_channel_id| {
+                let channel_info = graph.channels().get(&short_channel_id);
+                // announcements carry no timestamp, like gossip_timestamp_filter we use the
+                // newest update of the channel
+                let announcement = channel_info.and_then(|info| {
+                    info.announcement_message
+                        .as_ref()
+                        .map(|message| GossipMessage {
+                            message: hex_utils::hex_str(&message.encode()),
+                            timestamp: [&info.one_to_two, &info.two_to_one]
+                                .iter()
+                                .filter_map(|direction| direction.as_ref())
+                                .map(|direction| direction.last_update as u64)
+                                .max()
+                                .unwrap_or(0),
+                        })
+                });
+                let update = channel_info
+                    .and_then(|info| {
+                        if info.node_one == our_node_id {
+                            info.one_to_two.as_ref()
+                        } else {
+                            info.two_to_one.as_ref()
+                        }
+                    })
+                    .and_then(|direction| {
+                        direction
+                            .last_update_message
+                            .as_ref()
+                            .map(|message| GossipMessage {
+                                message: hex_utils::hex_str(&message.encode()),
+                                timestamp: direction.last_update as u64,
+                            })
+                    });
+                ChannelGossip {
+                    short_channel_id,
+                    announcement,
+                    update,
+                }
+            })
+            .collect();
+
+        (node_announcement, channels)
+    }
+
+    // The scorer has no way to set a channel's liquidity directly, so we feed it the failures
+    // that would have taught it the same bounds: failing at the channel lowers the upper
+    // bound and failing further down the path raises the lower one. Raising the lower bound
+    // to the capacity first clears whatever it had learned before. The upper bound can't be
+    // set to the full capacity this way and ends up one msat below it.
+    pub fn set_scorer_liquidity(
+        &self,
+        short_channel_id: u64,
+        target_pubkey: PublicKey,
+        min_liquidity_msat: u64,
+        max_liquidity_msat: u64,
+    ) -> Result<(), Error> {
+        let target = NodeId::from_pubkey(&target_pubkey);
+        let capacity_msat = {
+            let graph = self.network_graph.read_only();
+            let channel = graph
+                .channels()
+                .get(&short_channel_id)
+                .ok_or(Error::ChannelNotFound)?;
+            if channel.node_one != target && channel.node_two != target {
+                return Err(Error::Generic(String::from(
+                    "target is not one of the channel's nodes",
+                )));
+            }
+            channel
+                .capacity_sats
+                .ok_or_else(|| Error::Generic(String::from("channel capacity is unknown")))?
+                * 1000
+        };
+
+        if min_liquidity_msat > max_liquidity_msat || max_liquidity_msat > capacity_msat {
+            return Err(Error::Generic(String::from(
+                "liquidity must satisfy min <= max <= capacity",
+            )));
+        }
+        let max_liquidity_msat = std::cmp::min(max_liquidity_msat, capacity_msat - 1);
+        let min_liquidity_msat = std::cmp::min(min_liquidity_msat, max_liquidity_msat);
+
+        let hop = |short_channel_id: u64, fee_msat: u64| RouteHop {
+            pubkey: target_pubkey,
+            node_features: NodeFeatures::empty(),
+            short_channel_id,
+            channel_features: ChannelFeatures::empty(),
+            fee_msat,
+            cltv_expiry_delta: 0,
+        };
+        let unknown_channel_id = u64::MAX;
+        let mut scorer = self.scorer.lock().unwrap();
+        let mut fail = |amount_msat: u64, failed_channel_id: u64| {
+            let path = [
+                hop(short_channel_id, amount_msat),
+                hop(unknown_channel_id, amount_msat),
+            ];
+            scorer.payment_path_failed(&path.iter().collect::<Vec<_>>(), failed_channel_id);
+        };
+
+        fail(capacity_msat, unknown_channel_id);
+        fail(max_liquidity_msat, short_channel_id);
+        if min_liquidity_msat > 0 {
+            fail(min_liquidity_msat, unknown_channel_id);
+        }
+
+        Ok(())
+    }
+
+    // The feerates the node would use right now. Funding and on-chain sends are built by the
+    // wallet from the Normal estimate, raised to `min_confirmation_target`, like open_batch
+    // does. Cooperative closes start negotiating at the channel manager's Background, which
+    // has the tolerance applied, and force close claims use the plain HighPriority estimate.
+    pub fn current_feerates(&self) -> CurrentFeerates {
+        let ldk_fee_estimator = SenseiFeeEstimator {
+            fee_estimator: self.chain_manager.fee_estimator.clone(),
+            feerate_tolerance_pct: self.config.feerate_update_tolerance_pct,
+            max_commitment_feerate: self.config.max_commitment_feerate_sats_per_kw,
+        };
+        let feerate = |sat_per_kw: u32| Feerate {
+            sat_per_kw,
+            sat_per_vb: fee_estimator::sat_per_vb(sat_per_kw),
+            at_min_feerate: sat_per_kw <= MIN_FEERATE,
+        };
+
+        let wallet_feerate = feerate(
+            self.chain_manager
+                .wallet_feerate(ConfirmationTarget::Normal),
+        );
+
+        CurrentFeerates {
+            channel_funding: wallet_feerate.clone(),
+            cooperative_close: feerate(
+                ldk_fee_estimator.get_est_sat_per_1000_weight(ConfirmationTarget::Background),
+            ),
+            force_close: feerate(
+                self.chain_manager
+                    .fee_estimator
+                    .get_est_sat_per_1000_weight(ConfirmationTarget::HighPriority),
+            ),
+            onchain_send: wallet_feerate,
+        }
+    }
+
+    pub fn liquidity_summary(&self) -> LiquiditySummary {
+        let mut summary = LiquiditySummary::default();
+        let mut peers: BTreeMap<String, Liquidity> = BTreeMap::new();
+
+        for chan in self.channel_manager.list_channels() {
+            // capacity for channels that aren't ready yet is based on the balance
+            // since the htlc limits are only meaningful once the channel is open
+            if !chan.is_channel_ready {
+                let inbound_msat = (chan.channel_value_satoshis * 1000) - chan.balance_msat;
+                summary.pending.add(inbound_msat, chan.balance_msat);
+                continue;
+            }
+
+            let (inbound_msat, outbound_msat) =
+                (chan.inbound_capacity_msat, chan.outbound_capacity_msat);
+            summary.total.add(inbound_msat, outbound_msat);
+            if chan.is_public {
+                summary.public.add(inbound_msat, outbound_msat);
+            } else {
+                summary.private.add(inbound_msat, outbound_msat);
+            }
+            peers
+                .entry(chan.counterparty.node_id.to_string())
+                .or_default()
+                .add(inbound_msat, outbound_msat);
+        }
+
+        summary.peers = peers
+            .into_iter()
+            .map(|(counterparty_pubkey, liquidity)| PeerLiquidity {
+                counterparty_pubkey,
+                liquidity,
+            })
+            .collect();
+        summary
+    }
+
+    pub async fn channel_htlc_stats(&self, channel_id: &str) -> Result<ChannelHtlcStats, Error> {
+        let stats = self
+            .database
+            .find_channel_htlc_stats(&self.id, channel_id)
+            .await?;
+        Ok(match stats {
+            Some(stats) => ChannelHtlcStats {
+                channel_id: stats.channel_id,
+                inbound_succeeded: stats.inbound_succeeded.try_into().unwrap(),
+                inbound_failed: stats.inbound_failed.try_into().unwrap(),
+                outbound_succeeded: stats.outbound_succeeded.try_into().unwrap(),
+                outbound_failed: stats.outbound_failed.try_into().unwrap(),
+            },
+            None => ChannelHtlcStats {
+                channel_id: channel_id.to_string(),
+                ..Default::default()
+            },
         })
     }
 
+    pub async fn channels_by_peer(&self) -> Result<Vec<PeerChannels>, Error> {
+        let mut peers: BTreeMap<String, PeerChannels> = BTreeMap::new();
+        let mut channel_peers: HashMap<String, String> = HashMap::new();
+
+        for chan_info in self.channel_manager.list_channels() {
+            let counterparty_pubkey = chan_info.counterparty.node_id.to_string();
+            let mut channel: Channel = chan_info.clone().into();
+            channel.alias = self
+                .get_alias_for_channel_counterparty(&chan_info)
+                .map(|alias_bytes| hex_utils::sanitize_string(&alias_bytes));
+
+            let peer = peers
+                .entry(counterparty_pubkey.clone())
+                .or_insert_with(|| PeerChannels {
+                    counterparty_pubkey: counterparty_pubkey.clone(),
+                    label: None,
+                    alias: channel.alias.clone(),
+                    channel_count: 0,
+                    capacity_sats: 0,
+                    local_balance_msat: 0,
+                    remote_balance_msat: 0,
+                    forwards_in: 0,
+                    forwards_out: 0,
+                    fees_earned_msat: 0,
+                    channels: vec![],
+                });
+            peer.channel_count += 1;
+            peer.capacity_sats += chan_info.channel_value_satoshis;
+            peer.local_balance_msat += chan_info.balance_msat;
+            peer.remote_balance_msat +=
+                (chan_info.channel_value_satoshis * 1000).saturating_sub(chan_info.balance_msat);
+            peer.channels.push(channel);
+
+            channel_peers.insert(
+                hex_utils::hex_str(&chan_info.channel_id),
+                counterparty_pubkey,
+            );
+        }
+
+        for forward in self
+            .database
+            .list_forwards_in_range(&self.id, 0, i64::MAX)
+            .await?
+        {
+            let prev_peer = forward
+                .prev_channel_id
+                .and_then(|channel_id| channel_peers.get(&channel_id));
+            if let Some(peer) = prev_peer.and_then(|pubkey| peers.get_mut(pubkey)) {
+                peer.forwards_in += 1;
+            }
+
+            let next_peer = forward
+                .next_channel_id
+                .and_then(|channel_id| channel_peers.get(&channel_id));
+            if let Some(peer) = next_peer.and_then(|pubkey| peers.get_mut(pubkey)) {
+                peer.forwards_out += 1;
+                peer.fees_earned_msat += forward.fee_earned_msat.unwrap_or(0) as u64;
+            }
+        }
+
+        for peer in peers.values_mut() {
+            peer.label = self
+                .database
+                .find_peer(&self.id, &peer.counterparty_pubkey)
+                .await?
+                .and_then(|known_peer| known_peer.label);
+        }
+
+        Ok(peers.into_values().collect())
+    }
+
     pub fn get_pubkey(&self) -> String {
         self.channel_manager.get_our_node_id().to_string()
     }
@@ -1403,41 +4071,665 @@ impl LightningNode {
         )?)
     }
 
-    pub fn verify_message(
+    pub fn verify_message(
+        &self,
+        message: String,
+        signature: String,
+    ) -> Result<(bool, String), Error> {
+        let pubkey = self.channel_manager.get_our_node_id();
+
+        let valid =
+            lightning::util::message_signing::verify(message.as_bytes(), &signature, &pubkey);
+
+        Ok((valid, pubkey.to_string()))
+    }
+
+    // Channels that aren't ready yet. Before the funding tx is generated the channel id is
+    // the temporary one. We only know about the funding tx of channels we opened, it's in
+    // our wallet once broadcast and its confirmations follow the wallet sync, for inbound
+    // channels all we have is the funding outpoint the peer gave us.
+    pub fn list_pending_channels(&self) -> Result<Vec<PendingChannel>, Error> {
+        let tip_height = self.channel_manager.current_best_block().height();
+        let wallet = self.wallet.lock().unwrap();
+
+        self.channel_manager
+            .list_channels()
+            .into_iter()
+            .filter(|channel| !channel.is_channel_ready)
+            .map(|channel| {
+                let funding_tx = match channel.funding_txo {
+                    Some(funding_txo) if channel.is_outbound => {
+                        wallet.get_tx(&funding_txo.txid, false)?
+                    }
+                    _ => None,
+                };
+                let confirmations = funding_tx.as_ref().map(|tx| match &tx.confirmation_time {
+                    Some(block_time) => tip_height.saturating_sub(block_time.height) + 1,
+                    None => 0,
+                });
+
+                Ok(PendingChannel {
+                    channel_id: hex_utils::hex_str(&channel.channel_id),
+                    counterparty_pubkey: channel.counterparty.node_id.to_string(),
+                    channel_value_satoshis: channel.channel_value_satoshis,
+                    is_outbound: channel.is_outbound,
+                    funding_txid: channel.funding_txo.map(|txo| txo.txid.to_string()),
+                    funding_broadcast: funding_tx.is_some(),
+                    confirmations,
+                    confirmations_required: channel.confirmations_required,
+                })
+            })
+            .collect()
+    }
+
+    // Funding txs of our outbound channels that have at least the confirmations the channel
+    // requires, with their confirmations and block height.
+    pub fn confirmed_funding_txs(&self) -> Result<Vec<(Txid, u32, u32)>, Error> {
+        let tip_height = self.channel_manager.current_best_block().height();
+        let wallet = self.wallet.lock().unwrap();
+
+        let mut confirmed = vec![];
+        for channel in self.channel_manager.list_channels() {
+            let funding_txid = match channel.funding_txo {
+                Some(funding_txo) if channel.is_outbound => funding_txo.txid,
+                _ => continue,
+            };
+            let block_height = match wallet.get_tx(&funding_txid, false)? {
+                Some(details) => match details.confirmation_time {
+                    Some(block_time) => block_time.height,
+                    None => continue,
+                },
+                None => continue,
+            };
+            let confirmations = tip_height.saturating_sub(block_height) + 1;
+            if confirmations >= channel.confirmations_required.unwrap_or(1)
+                && !confirmed.iter().any(|(txid, _, _)| *txid == funding_txid)
+            {
+                confirmed.push((funding_txid, confirmations, block_height));
+            }
+        }
+        Ok(confirmed)
+    }
+
+    // Records the changes in our channels that LDK has no events for. `recorded` has the
+    // detail last recorded per channel and kind, what isn't in it yet is looked up in the
+    // database so a restart doesn't record it again.
+    async fn record_channel_changes(
+        &self,
+        recorded: &mut HashMap<([u8; 32], &'static str), Option<String>>,
+    ) {
+        for channel in self.channel_manager.list_channels() {
+            let channel_id = hex_utils::hex_str(&channel.channel_id);
+            let mut changes = vec![];
+            if let (false, Some(funding_txo)) = (channel.is_outbound, channel.funding_txo) {
+                changes.push((
+                    "opened",
+                    "peer",
+                    Some(format!(
+                        "funding tx {} from {}",
+                        funding_txo.txid, channel.counterparty.node_id
+                    )),
+                ));
+            }
+            if channel.is_channel_ready {
+                changes.push(("channel_ready", "ldk", None));
+            }
+            if let Some(info) = &channel.counterparty.forwarding_info {
+                changes.push((
+                    "policy_updated",
+                    "peer",
+                    Some(format!(
+                        "base fee {} msat, {} ppm, cltv delta {}",
+                        info.fee_base_msat,
+                        info.fee_proportional_millionths,
+                        info.cltv_expiry_delta
+                    )),
+                ));
+            }
+
+            for (kind, source, detail) in changes {
+                let key = (channel.channel_id, kind);
+                if !recorded.contains_key(&key) {
+                    match self
+                        .database
+                        .latest_channel_event(&self.id, &channel_id, kind)
+                        .await
+                    {
+                        Ok(Some(event)) => {
+                            recorded.insert(key, event.detail);
+                        }
+                        Ok(None) => {}
+                        Err(e) => {
+                            println!("failed to look up channel events: {}", e);
+                            continue;
+                        }
+                    }
+                }
+                if recorded.get(&key) == Some(&detail) {
+                    continue;
+                }
+                match self
+                    .database
+                    .record_channel_event(&self.id, &channel_id, kind, source, detail.clone())
+                    .await
+                {
+                    Ok(_event) => {
+                        recorded.insert(key, detail);
+                    }
+                    Err(e) => println!("failed to record channel event: {}", e),
+                }
+            }
+        }
+    }
+
+    // Sends FundingConfirmed for every confirmed funding tx it wasn't sent for yet, ones that
+    // got there while the node was down included. What was sent is kept in the database.
+    pub async fn announce_funding_confirmations(&self) -> Result<(), Error> {
+        let announced = self
+            .database
+            .list_funding_confirmed_announced(&self.id)
+            .await?
+            .into_iter()
+            .collect::<HashSet<_>>();
+        for (txid, confirmations, block_height) in self.confirmed_funding_txs()? {
+            let txid_str = txid.to_string();
+            if announced.contains(&txid_str) {
+                continue;
+            }
+            self.event_sender
+                .send(SenseiEvent::FundingConfirmed {
+                    node_id: self.id.clone(),
+                    txid,
+                    confirmations,
+                    block_height,
+                })
+                .unwrap_or_default();
+            let funded_channels =
+                self.channel_manager
+                    .list_channels()
+                    .into_iter()
+                    .filter(|channel| {
+                        channel
+                            .funding_txo
+                            .map_or(false, |funding_txo| funding_txo.txid == txid)
+                    });
+            for channel in funded_channels {
+                let _res = self
+                    .database
+                    .record_channel_event(
+                        &self.id,
+                        &hex_utils::hex_str(&channel.channel_id),
+                        "funding_confirmed",
+                        "sensei",
+                        Some(format!(
+                            "{} confirmations at height {}",
+                            confirmations, block_height
+                        )),
+                    )
+                    .await;
+            }
+            self.database
+                .set_funding_confirmed_announced(&self.id, &txid_str)
+                .await?;
+        }
+        Ok(())
+    }
+
+    // Compares the funding tx's feerate to the current estimates to guess how long it has
+    // left, so an operator can decide whether to fee-bump it. Only funding txs we built
+    // are in our wallet with a fee, inbound channels always come back unknown.
+    pub fn funding_confirmation_eta(
+        &self,
+        channel_id: [u8; 32],
+    ) -> Result<FundingConfirmationEta, Error> {
+        let channel = self
+            .channel_manager
+            .list_channels()
+            .into_iter()
+            .find(|channel| channel.channel_id == channel_id)
+            .ok_or(Error::ChannelNotFound)?;
+
+        let funding_tx = match channel.funding_txo {
+            Some(funding_txo) if channel.is_outbound => {
+                let wallet = self.wallet.lock().unwrap();
+                wallet.get_tx(&funding_txo.txid, true)?
+            }
+            _ => None,
+        };
+
+        let feerate_sat_per_kw = funding_tx.as_ref().and_then(|details| {
+            let weight = details.transaction.as_ref()?.weight() as u64;
+            let fee = details.fee?;
+            Some((fee * 1000 / weight) as u32)
+        });
+
+        // comparing to the default estimates would make up an answer
+        let estimates_known = self
+            .chain_manager
+            .fee_estimates_known
+            .load(Ordering::Acquire);
+        let eta_blocks = match &funding_tx {
+            Some(details) if details.confirmation_time.is_some() => Some(0),
+            _ if !estimates_known => None,
+            _ => feerate_sat_per_kw.and_then(|sat_per_kw| {
+                fee_estimator::confirmation_eta_blocks(
+                    sat_per_kw,
+                    self.chain_manager.fee_estimator.as_ref(),
+                )
+            }),
+        };
+
+        Ok(FundingConfirmationEta {
+            channel_id: hex_utils::hex_str(&channel.channel_id),
+            funding_txid: channel.funding_txo.map(|txo| txo.txid.to_string()),
+            feerate_sat_per_kw,
+            eta_blocks,
+        })
+    }
+
+    // How much of the last `window_secs` the channel's peer was connected to us, from the
+    // peer event log. A channel younger than the window is only measured since it opened.
+    pub async fn channel_uptime(
+        &self,
+        channel_id: [u8; 32],
+        window_secs: u64,
+    ) -> Result<ChannelUptime, Error> {
+        let channel = self
+            .channel_manager
+            .list_channels()
+            .into_iter()
+            .find(|channel| channel.channel_id == channel_id)
+            .ok_or(Error::ChannelNotFound)?;
+        let channel_id = hex_utils::hex_str(&channel.channel_id);
+        let pubkey = channel.counterparty.node_id.to_string();
+
+        let now = entity::seconds_since_epoch();
+        let window_start = now.saturating_sub(window_secs.try_into().unwrap_or(i64::MAX));
+        let opened_at = match self
+            .database
+            .find_channel_by_channel_id(&self.id, &channel_id)
+            .await?
+        {
+            Some(channel) => Some(channel.created_at),
+            None => self
+                .database
+                .list_channel_events(&self.id, &channel_id)
+                .await?
+                .first()
+                .map(|event| event.created_at),
+        };
+        let start = opened_at.map_or(window_start, |opened_at| opened_at.max(window_start));
+
+        let events = self
+            .database
+            .list_peer_events(&self.id, &pubkey, start)
+            .await?;
+        let connected_at_start = match self
+            .database
+            .last_peer_event_before(&self.id, &pubkey, start)
+            .await?
+        {
+            Some(event) => Some(event.connected),
+            None if events.is_empty() => Some(connected_to_peer(
+                &channel.counterparty.node_id,
+                self.peer_manager.clone(),
+            )),
+            None => None,
+        };
+
+        let uptime = uptime::peer_uptime(&events, connected_at_start, start, now);
+        let observed_secs = (now - start) as u64;
+        Ok(ChannelUptime {
+            channel_id,
+            counterparty_pubkey: pubkey,
+            window_secs,
+            observed_secs,
+            connected_secs: uptime.connected_secs,
+            uptime_pct: match observed_secs {
+                0 => None,
+                _ => Some(uptime.connected_secs as f64 * 100.0 / observed_secs as f64),
+            },
+            disconnections: uptime.disconnections,
+        })
+    }
+
+    // Serializes the live monitor for one channel and seals it with the passphrase, so it can
+    // be kept as an extra backup or moved to a node with the same seed.
+    pub fn export_channel_monitor(
+        &self,
+        channel_id: [u8; 32],
+        passphrase: &[u8],
+    ) -> Result<Vec<u8>, Error> {
+        let funding_txo = self
+            .channel_manager
+            .list_channels()
+            .into_iter()
+            .find(|channel| channel.channel_id == channel_id)
+            .and_then(|channel| channel.funding_txo)
+            .ok_or(Error::ChannelNotFound)?;
+
+        let monitor = self
+            .chain_monitor
+            .get_monitor(funding_txo)
+            .map_err(|_e| Error::ChannelNotFound)?
+            .encode();
+
+        let mut export = self.monitor_mac(&monitor).into_inner().to_vec();
+        export.extend_from_slice(&monitor);
+
+        let cryptor = RingCryptor::new();
+        Ok(cryptor.seal_with_passphrase(passphrase, &export)?)
+    }
+
+    // The monitor carries its channel's keys itself, so anyone's monitor would deserialize
+    // fine. Exports are tagged with a key only our node secret derives to tell ours apart.
+    fn monitor_mac(&self, monitor: &[u8]) -> Hmac<Sha256> {
+        let node_secret = self.keys_manager.get_node_secret(Recipient::Node).unwrap();
+        let mut engine = HmacEngine::<Sha256>::new(&node_secret[..]);
+        engine.input(b"sensei channel monitor export");
+        engine.input(monitor);
+        Hmac::from_engine(engine)
+    }
+
+    // Only monitors exported by this node, for channels the channel manager no longer knows,
+    // are imported. We never replace a monitor we already have. A monitor the channel manager
+    // doesn't know about gets its latest commitment broadcast on the next restart, and if the
+    // export is older than the channel's last state that is a revoked commitment the peer
+    // can take the whole channel with. The caller has to accept that explicitly. The monitor
+    // only sees blocks from here on so it should be imported before anything it cares about
+    // happens on chain.
+    pub fn import_channel_monitor(
+        &self,
+        encrypted_monitor: &[u8],
+        passphrase: &[u8],
+        accept_force_close: bool,
+    ) -> Result<OutPoint, Error> {
+        if !accept_force_close {
+            return Err(Error::InvalidChannelMonitor(String::from(
+                "importing a monitor broadcasts its commitment, set accept_force_close",
+            )));
+        }
+
+        let cryptor = RingCryptor::new();
+        let export = cryptor.open(passphrase, encrypted_monitor)?;
+        if export.len() < 32 {
+            return Err(Error::InvalidChannelMonitor(String::from(
+                "export is too short",
+            )));
+        }
+        let (mac, monitor_bytes) = export.split_at(32);
+        if mac != &self.monitor_mac(monitor_bytes)[..] {
+            return Err(Error::InvalidChannelMonitor(String::from(
+                "monitor was not exported by this node",
+            )));
+        }
+
+        let (_best_block_hash, monitor) = <(BlockHash, ChannelMonitor<InMemorySigner>)>::read(
+            &mut Cursor::new(&monitor_bytes),
+            &*self.keys_manager,
+        )
+        .map_err(|e| Error::InvalidChannelMonitor(format!("{:?}", e)))?;
+
+        let funding_txo = monitor.get_funding_txo().0;
+        if self.chain_monitor.list_monitors().contains(&funding_txo) {
+            return Err(Error::ChannelMonitorExists(
+                funding_txo.into_bitcoin_outpoint().to_string(),
+            ));
+        }
+        let known_channel = self
+            .channel_manager
+            .list_channels()
+            .iter()
+            .any(|channel| channel.funding_txo == Some(funding_txo));
+        if known_channel {
+            return Err(Error::ChannelMonitorExists(
+                funding_txo.into_bitcoin_outpoint().to_string(),
+            ));
+        }
+
+        self.chain_monitor
+            .watch_channel(funding_txo, monitor)
+            .map_err(|e| Error::InvalidChannelMonitor(format!("{:?}", e)))?;
+
+        Ok(funding_txo.into_bitcoin_outpoint())
+    }
+
+    // Forwarding fees earned over the last `window_secs`, per day and compared to the window
+    // before it. The database adds the fees up per day, only the days are loaded.
+    pub async fn fee_revenue_rate(&self, window_secs: u64) -> Result<FeeRevenue, Error> {
+        const MAX_WINDOW_SECS: u64 = 366 * SECS_PER_DAY as u64;
+        if window_secs == 0 || window_secs > MAX_WINDOW_SECS {
+            return Err(Error::Generic(format!(
+                "window must be between 1 and {} seconds",
+                MAX_WINDOW_SECS
+            )));
+        }
+
+        let end = entity::seconds_since_epoch();
+        let start = end - window_secs as i64;
+        let daily_fees = self
+            .database
+            .daily_forward_fees(&self.id, start, end)
+            .await?;
+        let previous_fee_earned_msat = self
+            .database
+            .daily_forward_fees(&self.id, start - window_secs as i64, start)
+            .await?
+            .iter()
+            .map(|day| day.fee_earned_msat.max(0) as u64)
+            .sum();
+        Ok(revenue::fee_revenue(
+            &daily_fees,
+            previous_fee_earned_msat,
+            start,
+            end,
+        ))
+    }
+
+    pub fn list_unspent(&self) -> Result<Vec<Utxo>, Error> {
+        let wallet = self.wallet.lock().unwrap();
+        let local_utxos = wallet.list_unspent()?;
+        let utxos = local_utxos
+            .into_iter()
+            .map(|u| Utxo {
+                amount_sat: u.txout.value,
+                spk: u.txout.script_pubkey.to_hex(),
+                txid: u.outpoint.txid.to_hex(),
+                output_index: u.outpoint.vout,
+            })
+            .collect();
+
+        Ok(utxos)
+    }
+
+    pub async fn delete_payment(&self, payment_hash: String) -> Result<(), Error> {
+        self.database
+            .delete_payment(self.id.clone(), payment_hash)
+            .await
+    }
+
+    // Claims or fails back a payment held by the `hold` amount mismatch policy. LDK fails
+    // the htlcs back by itself once they get close to expiring, so this can come too late.
+    // An accepted payment only succeeds once its PaymentClaimed event arrives.
+    pub async fn resolve_held_payment(
+        &self,
+        payment_hash: [u8; 32],
+        accept: bool,
+    ) -> Result<(), Error> {
+        let payment = self
+            .database
+            .find_payment(self.id.clone(), hex_utils::hex_str(&payment_hash))
+            .await?
+            .filter(|payment| payment.status == HTLCStatus::Held.to_string())
+            .ok_or(Error::PaymentNotFound)?;
+
+        let preimage = payment
+            .preimage
+            .as_ref()
+            .and_then(|preimage| hex_utils::to_vec(preimage))
+            .and_then(|preimage| preimage.try_into().ok());
+
+        let mut payment: entity::payment::ActiveModel = payment.into();
+        match preimage {
+            Some(preimage) if accept => {
+                payment.status = ActiveValue::Set(HTLCStatus::Claiming.to_string());
+                payment.update(self.database.get_connection()).await?;
+                self.channel_manager.claim_funds(PaymentPreimage(preimage));
+            }
+            _ => {
+                self.channel_manager
+                    .fail_htlc_backwards(&PaymentHash(payment_hash));
+                payment.status = ActiveValue::Set(HTLCStatus::Failed.to_string());
+                payment.update(self.database.get_connection()).await?;
+            }
+        }
+        Ok(())
+    }
+
+    // Replaces whatever metadata the payment had, an empty map clears it.
+    pub async fn set_payment_metadata(
+        &self,
+        payment_hash: String,
+        metadata: BTreeMap<String, String>,
+    ) -> Result<(), Error> {
+        let metadata = encode_payment_metadata(&metadata)?;
+        self.database
+            .set_payment_metadata(self.id.clone(), payment_hash, metadata)
+            .await?
+            .ok_or(Error::PaymentNotFound)?;
+        Ok(())
+    }
+
+    // Subscriptions get a new invoice every `interval_secs`, starting at `first_invoice_at`
+    // or right away. The invoice is tagged with the subscription's id in its metadata.
+    pub async fn create_subscription(
+        &self,
+        amount_msat: u64,
+        description: String,
+        interval_secs: u64,
+        first_invoice_at: Option<i64>,
+    ) -> Result<entity::subscription::Model, Error> {
+        if interval_secs < MIN_SUBSCRIPTION_INTERVAL_SECS {
+            return Err(Error::InvalidSubscription(format!(
+                "interval must be at least {} seconds",
+                MIN_SUBSCRIPTION_INTERVAL_SECS
+            )));
+        }
+        let next_invoice_at = first_invoice_at.unwrap_or_else(entity::seconds_since_epoch);
+        self.database
+            .create_subscription(
+                &self.id,
+                amount_msat,
+                description,
+                interval_secs,
+                next_invoice_at,
+            )
+            .await
+    }
+
+    // Creates an invoice for every subscription that's due. A node that was down for a few
+    // intervals gets a single invoice per subscription, not one for every interval it missed.
+    // One subscription failing doesn't hold up the rest, it's tried again on the next run.
+    pub async fn invoice_due_subscriptions(&self) -> Result<Vec<Invoice>, Error> {
+        let now = entity::seconds_since_epoch();
+        let mut invoices = vec![];
+        for subscription in self.database.list_due_subscriptions(&self.id, now).await? {
+            let subscription_id = subscription.id.clone();
+            match self.invoice_subscription(subscription, now).await {
+                Ok(invoice) => invoices.push(invoice),
+                Err(e) => println!(
+                    "failed to create invoice for subscription {}: {}",
+                    subscription_id, e
+                ),
+            }
+        }
+        Ok(invoices)
+    }
+
+    // The next invoice time moves forward before the invoice is created, so a crash in
+    // between skips an invoice instead of sending it twice.
+    async fn invoice_subscription(
         &self,
-        message: String,
-        signature: String,
-    ) -> Result<(bool, String), Error> {
-        let pubkey = self.channel_manager.get_our_node_id();
-
-        let valid =
-            lightning::util::message_signing::verify(message.as_bytes(), &signature, &pubkey);
-
-        Ok((valid, pubkey.to_string()))
-    }
+        subscription: entity::subscription::Model,
+        now: i64,
+    ) -> Result<Invoice, Error> {
+        let missed_intervals = (now - subscription.next_invoice_at) / subscription.interval_secs;
+        let next_invoice_at =
+            subscription.next_invoice_at + (missed_intervals + 1) * subscription.interval_secs;
+        self.database
+            .set_subscription_next_invoice_at(subscription.clone(), next_invoice_at)
+            .await?;
 
-    pub fn list_unspent(&self) -> Result<Vec<Utxo>, Error> {
-        let wallet = self.wallet.lock().unwrap();
-        let local_utxos = wallet.list_unspent()?;
-        let utxos = local_utxos
-            .into_iter()
-            .map(|u| Utxo {
-                amount_sat: u.txout.value,
-                spk: u.txout.script_pubkey.to_hex(),
-                txid: u.outpoint.txid.to_hex(),
-                output_index: u.outpoint.vout,
+        let invoice = match self
+            .get_invoice(
+                subscription.amount_msat as u64,
+                subscription.description.clone(),
+            )
+            .await
+        {
+            Ok(invoice) => invoice,
+            Err(e) => {
+                // nothing went out, so it's still due
+                let due_at = subscription.next_invoice_at;
+                self.database
+                    .set_subscription_next_invoice_at(subscription, due_at)
+                    .await?;
+                return Err(e);
+            }
+        };
+        let mut metadata = BTreeMap::new();
+        metadata.insert(String::from("subscription_id"), subscription.id.clone());
+        self.set_invoice_metadata(&invoice, metadata).await?;
+
+        self.event_sender
+            .send(SenseiEvent::SubscriptionInvoiceCreated {
+                node_id: self.id.clone(),
+                subscription_id: subscription.id.clone(),
+                invoice: invoice.to_string(),
             })
-            .collect();
-
-        Ok(utxos)
+            .unwrap_or_default();
+        Ok(invoice)
     }
 
-    pub async fn delete_payment(&self, payment_hash: String) -> Result<(), Error> {
+    // Channels we didn't open ourselves get their row when they're first labeled.
+    pub async fn label_channel(&self, channel_id: [u8; 32], label: String) -> Result<(), Error> {
+        let channel = self
+            .channel_manager
+            .list_channels()
+            .into_iter()
+            .find(|channel| channel.channel_id == channel_id)
+            .ok_or(Error::ChannelNotFound)?;
+        let channel_id = hex_utils::hex_str(&channel_id);
+        if self
+            .database
+            .find_channel_by_channel_id(&self.id, &channel_id)
+            .await?
+            .is_none()
+        {
+            self.database
+                .create_channel(
+                    &self.id,
+                    channel.user_channel_id,
+                    &channel.counterparty.node_id.to_string(),
+                    &channel_id,
+                    channel.channel_value_satoshis,
+                )
+                .await?;
+        }
         self.database
-            .delete_payment(self.id.clone(), payment_hash)
+            .label_channel(&self.id, &channel_id, label)
             .await
     }
 
+    pub async fn set_invoice_metadata(
+        &self,
+        invoice: &Invoice,
+        metadata: BTreeMap<String, String>,
+    ) -> Result<(), Error> {
+        let payment_hash = hex_utils::hex_str(&(*invoice.payment_hash()).into_inner());
+        self.set_payment_metadata(payment_hash, metadata).await
+    }
+
     pub async fn label_payment(&self, label: String, payment_hash: String) -> Result<(), Error> {
         self.database
             .label_payment(self.id.clone(), payment_hash, label)
@@ -1445,6 +4737,11 @@ impl LightningNode {
     }
 
     pub async fn call(&self, request: NodeRequest) -> Result<NodeResponse, NodeRequestError> {
+        let stale_channels = self.stale_channels.lock().unwrap().len();
+        if stale_channels > 0 && !allowed_while_halted(&request) {
+            return Err(Error::StaleStateHalt(stale_channels).into());
+        }
+
         match request {
             NodeRequest::StartNode { passphrase: _ } => Ok(NodeResponse::StartNode {}),
             NodeRequest::StopNode {} => Ok(NodeResponse::StopNode {}),
@@ -1455,6 +4752,66 @@ impl LightningNode {
                     address: address_info.address.to_string(),
                 })
             }
+            NodeRequest::NewDepositAddress { label } => {
+                let address = self.new_deposit_address(label.clone()).await?;
+                Ok(NodeResponse::NewDepositAddress { address, label })
+            }
+            NodeRequest::LiquiditySummary {} => Ok(NodeResponse::LiquiditySummary {
+                summary: self.liquidity_summary(),
+            }),
+            NodeRequest::ChannelsByPeer {} => {
+                let peers = self.channels_by_peer().await?;
+                Ok(NodeResponse::ChannelsByPeer { peers })
+            }
+            NodeRequest::GetFeerates {} => Ok(NodeResponse::GetFeerates {
+                feerates: self.current_feerates(),
+            }),
+            NodeRequest::GetChainTip {} => {
+                let chain_tip = self.get_chain_tip().await?;
+                Ok(NodeResponse::GetChainTip { chain_tip })
+            }
+            NodeRequest::SyncWallet {} => {
+                self.sync_wallet();
+                Ok(NodeResponse::SyncWallet {})
+            }
+            NodeRequest::GetScannedAddresses {} => {
+                let scanned_addresses = self.scanned_addresses()?;
+                Ok(NodeResponse::GetScannedAddresses { scanned_addresses })
+            }
+            NodeRequest::CacheWalletAddresses {
+                addresses,
+                rescan_from_height,
+            } => {
+                let (scanned_addresses, blocks_scanned) = self
+                    .cache_wallet_addresses(addresses, rescan_from_height)
+                    .await?;
+                Ok(NodeResponse::CacheWalletAddresses {
+                    scanned_addresses,
+                    blocks_scanned,
+                })
+            }
+            NodeRequest::RebroadcastUnconfirmed {} => {
+                let results = self.rebroadcast_unconfirmed().await?;
+                Ok(NodeResponse::RebroadcastUnconfirmed { results })
+            }
+            NodeRequest::SignAndBroadcastPsbt { psbt } => {
+                let psbt = parse_psbt(&psbt)?;
+                let txid = self.sign_and_broadcast_psbt(psbt)?;
+                Ok(NodeResponse::SignAndBroadcastPsbt {
+                    txid: txid.to_string(),
+                })
+            }
+            NodeRequest::BumpFundingFee { txid, sat_per_vb } => {
+                let txid = Txid::from_str(&txid).map_err(|e| Error::Generic(e.to_string()))?;
+                let child_txid = self.bump_funding_fee(txid, sat_per_vb)?;
+                Ok(NodeResponse::BumpFundingFee {
+                    txid: child_txid.to_string(),
+                })
+            }
+            NodeRequest::RescanWallet { from_height } => {
+                let blocks_scanned = self.rescan_wallet(from_height).await?;
+                Ok(NodeResponse::RescanWallet { blocks_scanned })
+            }
             NodeRequest::GetBalance {} => {
                 // TODO: split confirmed vs uncofirmed chain balance
                 //       we currently only have 'unconfirmed' utxos from transactions we broadcast
@@ -1492,7 +4849,19 @@ impl LightningNode {
                     usable_channel_inbound_capacity_msats,
                 })
             }
-            NodeRequest::OpenChannels { requests } => {
+            NodeRequest::ValidateChannelBatch { requests } => {
+                let validation = self.validate_batch(&requests)?;
+                Ok(NodeResponse::ValidateChannelBatch { validation })
+            }
+            NodeRequest::OpenChannels {
+                requests,
+                funding_utxos,
+                unspendable_utxos,
+                max_fee_sats,
+                funding_timeout_ms,
+            } => {
+                let funding_utxos = parse_funding_utxos(funding_utxos)?;
+
                 // for channel in &channels {
 
                 //     // pub counterparty_pubkey: String,
@@ -1517,30 +4886,106 @@ impl LightningNode {
                 //     });
                 // }
 
-                let responses = self.open_channels(requests.clone()).await;
+                let mut options = BatchOpenOptions {
+                    funding_utxos,
+                    unspendable_utxos: parse_funding_utxos(unspendable_utxos)?.unwrap_or_default(),
+                    max_fee_sats,
+                    ..self.batch_open_options()
+                };
+                if let Some(funding_timeout_ms) = funding_timeout_ms {
+                    options.funding_timeout_ms = funding_timeout_ms;
+                }
+
+                let responses = self.open_channels(requests.clone(), options).await;
 
                 Ok(NodeResponse::OpenChannels {
                     requests,
-                    results: responses
-                        .into_iter()
-                        .map(|(_request, result)| match result {
-                            Ok(temp_channel_id) => OpenChannelResult {
-                                error: false,
-                                error_message: None,
-                                temp_channel_id: Some(hex_utils::hex_str(&temp_channel_id)),
-                            },
-                            Err(e) => OpenChannelResult {
-                                error: true,
-                                error_message: Some(e.to_string()),
-                                temp_channel_id: None,
-                            },
-                        })
-                        .collect::<Vec<_>>(),
+                    results: responses.into_iter().map(Into::into).collect::<Vec<_>>(),
+                })
+            }
+            NodeRequest::OpenChannel {
+                request,
+                funding_utxos,
+                unspendable_utxos,
+                max_fee_sats,
+                funding_timeout_ms,
+            } => {
+                let mut options = BatchOpenOptions {
+                    funding_utxos: parse_funding_utxos(funding_utxos)?,
+                    unspendable_utxos: parse_funding_utxos(unspendable_utxos)?.unwrap_or_default(),
+                    max_fee_sats,
+                    ..self.batch_open_options()
+                };
+                if let Some(funding_timeout_ms) = funding_timeout_ms {
+                    options.funding_timeout_ms = funding_timeout_ms;
+                }
+
+                let result = self.open_channel(request.clone(), options).await;
+
+                Ok(NodeResponse::OpenChannel {
+                    request,
+                    result: result.into(),
+                })
+            }
+            NodeRequest::BuildBatchFunding {
+                requests,
+                funding_utxos,
+                unspendable_utxos,
+                max_fee_sats,
+                funding_timeout_ms,
+            } => {
+                let mut options = BatchOpenOptions {
+                    funding_utxos: parse_funding_utxos(funding_utxos)?,
+                    unspendable_utxos: parse_funding_utxos(unspendable_utxos)?.unwrap_or_default(),
+                    max_fee_sats,
+                    ..self.batch_open_options()
+                };
+                if let Some(funding_timeout_ms) = funding_timeout_ms {
+                    options.funding_timeout_ms = funding_timeout_ms;
+                }
+
+                let (psbt, results) = self.build_batch_funding(requests, options).await?;
+
+                Ok(NodeResponse::BuildBatchFunding {
+                    psbt: base64::encode(bitcoin::consensus::encode::serialize(&psbt)),
+                    results: results.into_iter().map(Into::into).collect::<Vec<_>>(),
+                })
+            }
+            NodeRequest::FinalizeBatchFunding { psbt } => {
+                let psbt = parse_psbt(&psbt)?;
+                let results = self.finalize_batch_funding(psbt).await?;
+
+                Ok(NodeResponse::FinalizeBatchFunding {
+                    results: results.into_iter().map(Into::into).collect::<Vec<_>>(),
+                })
+            }
+            NodeRequest::OpenChannelAndPay {
+                request,
+                invoice,
+                ready_timeout_ms,
+            } => {
+                let invoice = self.get_invoice_from_str(&invoice)?;
+                let (result, payment) = self
+                    .open_channel_and_pay(
+                        request,
+                        &invoice,
+                        self.batch_open_options(),
+                        ready_timeout_ms,
+                    )
+                    .await?;
+
+                Ok(NodeResponse::OpenChannelAndPay {
+                    result: result.into(),
+                    paid: payment.is_ok(),
+                    payment_error: payment.err().map(|e| e.to_string()),
                 })
             }
-            NodeRequest::SendPayment { invoice } => {
+            NodeRequest::SendPayment {
+                invoice,
+                max_path_length,
+            } => {
                 let invoice = self.get_invoice_from_str(&invoice)?;
-                self.send_payment(&invoice).await?;
+                self.send_payment(&invoice, max_path_length).await?;
                 Ok(NodeResponse::SendPayment {})
             }
             NodeRequest::DecodeInvoice { invoice } => {
@@ -1560,11 +5005,54 @@ impl LightningNode {
                 }
                 None => Err(NodeRequestError::Sensei("invalid dest_pubkey".into())),
             },
+            NodeRequest::SendAlongRoute {
+                hops,
+                amount_msat,
+                payment_hash,
+                payment_secret,
+                final_cltv_expiry_delta,
+            } => {
+                let hops = hops
+                    .iter()
+                    .map(|hop| parse_pubkey(hop))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let payment_hash = parse_hash_32(&payment_hash).map(PaymentHash)?;
+                let payment_secret = payment_secret
+                    .map(|secret| parse_hash_32(&secret).map(PaymentSecret))
+                    .transpose()?;
+                self.send_along_route(
+                    hops,
+                    amount_msat,
+                    payment_hash,
+                    payment_secret,
+                    final_cltv_expiry_delta,
+                )
+                .await?;
+                Ok(NodeResponse::SendAlongRoute {})
+            }
+            NodeRequest::RebalanceToTarget {
+                channel_id,
+                target_local_msat,
+                max_fee_msat,
+            } => {
+                let channel_id = parse_hash_32(&channel_id)?;
+                let rebalance = self
+                    .rebalance_to_target(channel_id, target_local_msat, max_fee_msat)
+                    .await?;
+                Ok(NodeResponse::RebalanceToTarget { rebalance })
+            }
             NodeRequest::GetInvoice {
                 amt_msat,
                 description,
+                metadata,
             } => {
+                if let Some(metadata) = &metadata {
+                    encode_payment_metadata(metadata)?;
+                }
                 let invoice = self.get_invoice(amt_msat, description).await?;
+                if let Some(metadata) = metadata {
+                    self.set_invoice_metadata(&invoice, metadata).await?;
+                }
                 let invoice_str = format!("{}", invoice);
                 Ok(NodeResponse::GetInvoice {
                     invoice: invoice_str,
@@ -1577,6 +5065,26 @@ impl LightningNode {
                 self.label_payment(label, payment_hash).await?;
                 Ok(NodeResponse::LabelPayment {})
             }
+            NodeRequest::SetPaymentMetadata {
+                payment_hash,
+                metadata,
+            } => {
+                self.set_payment_metadata(payment_hash, metadata).await?;
+                Ok(NodeResponse::SetPaymentMetadata {})
+            }
+            NodeRequest::SetInvoiceMetadata { invoice, metadata } => {
+                let invoice = self.get_invoice_from_str(&invoice)?;
+                self.set_invoice_metadata(&invoice, metadata).await?;
+                Ok(NodeResponse::SetInvoiceMetadata {})
+            }
+            NodeRequest::ResolveHeldPayment {
+                payment_hash,
+                accept,
+            } => {
+                let payment_hash = parse_hash_32(&payment_hash)?;
+                self.resolve_held_payment(payment_hash, accept).await?;
+                Ok(NodeResponse::ResolveHeldPayment {})
+            }
             NodeRequest::DeletePayment { payment_hash } => {
                 self.delete_payment(payment_hash).await?;
                 Ok(NodeResponse::DeletePayment {})
@@ -1594,6 +5102,9 @@ impl LightningNode {
 
                 if found_peer.is_none() {
                     self.connect_to_peer(pubkey, addr).await?;
+                    self.database
+                        .mark_peer_connected(&self.id, &pubkey.to_string())
+                        .await?;
                 }
 
                 Ok(NodeResponse::ConnectPeer {})
@@ -1606,7 +5117,7 @@ impl LightningNode {
                 })
             }
             NodeRequest::ListTransactions { pagination } => {
-                let (transactions, pagination) = self.list_transactions(pagination)?;
+                let (transactions, pagination) = self.list_transactions(pagination).await?;
                 Ok(NodeResponse::ListTransactions {
                     transactions,
                     pagination,
@@ -1620,16 +5131,115 @@ impl LightningNode {
                 })
             }
             NodeRequest::CloseChannel { channel_id, force } => {
-                let mut channel_id_bytes = [0u8; 32];
-                let bytes = hex_utils::to_vec(&channel_id);
-                if let Some(bytes) = bytes {
-                    channel_id_bytes.copy_from_slice(&bytes)
-                }
+                let channel_id_bytes = parse_channel_id(&channel_id)?;
                 self.close_channel(channel_id_bytes, force)?;
+                let _res = self
+                    .database
+                    .record_channel_event(
+                        &self.id,
+                        &channel_id,
+                        "close_requested",
+                        "operator",
+                        Some(String::from(if force { "force" } else { "cooperative" })),
+                    )
+                    .await;
                 Ok(NodeResponse::CloseChannel {})
             }
+            NodeRequest::CloseChannels {
+                requests,
+                timeout_ms,
+            } => {
+                let timeout_ms =
+                    timeout_ms.unwrap_or_else(|| BatchOpenOptions::default().funding_timeout_ms);
+                let results = self.close_channels(requests, timeout_ms).await;
+                Ok(NodeResponse::CloseChannels { results })
+            }
+            NodeRequest::LabelChannel { channel_id, label } => {
+                self.label_channel(parse_channel_id(&channel_id)?, label)
+                    .await?;
+                Ok(NodeResponse::LabelChannel {})
+            }
+            NodeRequest::SimulateForceClose {
+                channel_id,
+                sat_per_vb,
+            } => {
+                let channel_id_bytes = parse_channel_id(&channel_id)?;
+                let simulation = self.simulate_force_close(channel_id_bytes, sat_per_vb)?;
+                Ok(NodeResponse::SimulateForceClose { simulation })
+            }
+            NodeRequest::ChannelUptime {
+                channel_id,
+                window_secs,
+            } => {
+                let channel_id = parse_hash_32(&channel_id)?;
+                let uptime = self.channel_uptime(channel_id, window_secs).await?;
+                Ok(NodeResponse::ChannelUptime { uptime })
+            }
+            NodeRequest::ExportChannelMonitor {
+                channel_id,
+                passphrase,
+            } => {
+                let channel_id = parse_hash_32(&channel_id)?;
+                let monitor = self.export_channel_monitor(channel_id, passphrase.as_bytes())?;
+                Ok(NodeResponse::ExportChannelMonitor {
+                    monitor: hex_utils::hex_str(&monitor),
+                })
+            }
+            NodeRequest::ImportChannelMonitor {
+                monitor,
+                passphrase,
+                accept_force_close,
+            } => {
+                let monitor = hex_utils::to_vec(&monitor).ok_or_else(|| {
+                    Error::InvalidChannelMonitor(String::from("monitor is not valid hex"))
+                })?;
+                let funding_txo = self.import_channel_monitor(
+                    &monitor,
+                    passphrase.as_bytes(),
+                    accept_force_close,
+                )?;
+                Ok(NodeResponse::ImportChannelMonitor {
+                    funding_txo: funding_txo.to_string(),
+                })
+            }
+            NodeRequest::FeeRevenueRate { window_secs } => {
+                let revenue = self.fee_revenue_rate(window_secs).await?;
+                Ok(NodeResponse::FeeRevenueRate { revenue })
+            }
+            NodeRequest::FundingConfirmationEta { channel_id } => {
+                let channel_id = parse_hash_32(&channel_id)?;
+                let eta = self.funding_confirmation_eta(channel_id)?;
+                Ok(NodeResponse::FundingConfirmationEta { eta })
+            }
+            NodeRequest::EstimateForwardFee {
+                in_channel_id,
+                out_channel_id,
+                amount_msat,
+            } => {
+                let estimate =
+                    self.estimate_forward_fee(&in_channel_id, &out_channel_id, amount_msat)?;
+                Ok(NodeResponse::EstimateForwardFee { estimate })
+            }
+            NodeRequest::ChannelTimeline { channel_id } => {
+                let events = self
+                    .database
+                    .list_channel_events(&self.id, &channel_id)
+                    .await?;
+                Ok(NodeResponse::ChannelTimeline { events })
+            }
+            NodeRequest::GetChannelFundingInputs { channel_id } => {
+                let funding_inputs = self
+                    .database
+                    .list_channel_funding_inputs(&self.id, &channel_id)
+                    .await?;
+                Ok(NodeResponse::GetChannelFundingInputs { funding_inputs })
+            }
+            NodeRequest::GetChannelHtlcStats { channel_id } => {
+                let stats = self.channel_htlc_stats(&channel_id).await?;
+                Ok(NodeResponse::GetChannelHtlcStats { stats })
+            }
             NodeRequest::NodeInfo {} => {
-                let node_info = self.node_info()?;
+                let node_info = self.node_info().await?;
                 Ok(NodeResponse::NodeInfo { node_info })
             }
             NodeRequest::ListPeers {} => {
@@ -1644,6 +5254,26 @@ impl LightningNode {
                 let (valid, pubkey) = self.verify_message(message, signature)?;
                 Ok(NodeResponse::VerifyMessage { valid, pubkey })
             }
+            NodeRequest::ListPendingChannels {} => {
+                let channels = self.list_pending_channels()?;
+                Ok(NodeResponse::ListPendingChannels { channels })
+            }
+            NodeRequest::GetStaleState {} => {
+                let channels = self.stale_channels.lock().unwrap().clone();
+                Ok(NodeResponse::GetStaleState { channels })
+            }
+            NodeRequest::AcknowledgeStaleState {} => {
+                let channels = self.acknowledge_stale_state()?;
+                Ok(NodeResponse::AcknowledgeStaleState { channels })
+            }
+            NodeRequest::ListInflightOpens {} => {
+                let opens = self.list_inflight_opens();
+                Ok(NodeResponse::ListInflightOpens { opens })
+            }
+            NodeRequest::CancelInflightOpen { custom_id } => {
+                self.cancel_inflight_open(custom_id)?;
+                Ok(NodeResponse::CancelInflightOpen {})
+            }
             NodeRequest::ListUnspent {} => {
                 let utxos = self.list_unspent()?;
                 Ok(NodeResponse::ListUnspent { utxos })
@@ -1668,6 +5298,48 @@ impl LightningNode {
                     num_known_edge_policies,
                 })
             }
+            NodeRequest::GetGraphNode { pubkey } => {
+                if self.config.network_graph_snapshot_interval_secs.is_none() {
+                    return Err(Error::NetworkGraphNotPersisted.into());
+                }
+                let node = self.database.find_graph_node(&pubkey).await?;
+                let channels = self.database.list_graph_channels(&pubkey).await?;
+                Ok(NodeResponse::GetGraphNode { node, channels })
+            }
+            NodeRequest::FindGraphPath {
+                from_pubkey,
+                to_pubkey,
+            } => {
+                if self.config.network_graph_snapshot_interval_secs.is_none() {
+                    return Err(Error::NetworkGraphNotPersisted.into());
+                }
+                let from_pubkey = from_pubkey.unwrap_or_else(|| self.get_pubkey());
+                let channels = self.database.list_all_graph_channels().await?;
+                let path = shortest_path(&channels, &from_pubkey, &to_pubkey);
+                Ok(NodeResponse::FindGraphPath { path })
+            }
+            NodeRequest::SetScorerLiquidity {
+                short_channel_id,
+                target_pubkey,
+                min_liquidity_msat,
+                max_liquidity_msat,
+            } => {
+                let target_pubkey = parse_pubkey(&target_pubkey)?;
+                self.set_scorer_liquidity(
+                    short_channel_id,
+                    target_pubkey,
+                    min_liquidity_msat,
+                    max_liquidity_msat,
+                )?;
+                Ok(NodeResponse::SetScorerLiquidity {})
+            }
+            NodeRequest::GetGossip {} => {
+                let (node_announcement, channels) = self.gossip_messages();
+                Ok(NodeResponse::GetGossip {
+                    node_announcement,
+                    channels,
+                })
+            }
             NodeRequest::ListKnownPeers { pagination } => {
                 let (peers, pagination) = self.database.list_peers(&self.id, pagination).await?;
                 Ok(NodeResponse::ListKnownPeers { peers, pagination })
@@ -1677,26 +5349,10 @@ impl LightningNode {
                 label,
                 zero_conf,
             } => {
-                let peer = match self.database.find_peer(&self.id, &pubkey).await? {
-                    Some(peer) => {
-                        let mut peer: entity::peer::ActiveModel = peer.into();
-                        peer.label = ActiveValue::Set(Some(label));
-                        peer.zero_conf = ActiveValue::Set(zero_conf);
-                        peer.update(self.database.get_connection())
-                    }
-                    None => {
-                        let peer = entity::peer::ActiveModel {
-                            node_id: ActiveValue::Set(self.id.clone()),
-                            pubkey: ActiveValue::Set(pubkey),
-                            label: ActiveValue::Set(Some(label)),
-                            zero_conf: ActiveValue::Set(zero_conf),
-                            ..Default::default()
-                        };
-                        peer.insert(self.database.get_connection())
-                    }
-                };
-
-                let _res = peer.await.map_err(Error::Db)?;
+                let _peer = self
+                    .database
+                    .upsert_peer(&self.id, &pubkey, Some(label), zero_conf)
+                    .await?;
 
                 Ok(NodeResponse::AddKnownPeer {})
             }
@@ -1704,10 +5360,90 @@ impl LightningNode {
                 let _res = self.database.delete_peer(&self.id, &pubkey).await?;
                 Ok(NodeResponse::RemoveKnownPeer {})
             }
+            NodeRequest::ListSubscriptions {} => {
+                let subscriptions = self.database.list_subscriptions(&self.id).await?;
+                Ok(NodeResponse::ListSubscriptions { subscriptions })
+            }
+            NodeRequest::CreateSubscription {
+                amt_msat,
+                description,
+                interval_secs,
+                first_invoice_at,
+            } => {
+                let subscription = self
+                    .create_subscription(amt_msat, description, interval_secs, first_invoice_at)
+                    .await?;
+                Ok(NodeResponse::CreateSubscription { subscription })
+            }
+            NodeRequest::DeleteSubscription { id } => {
+                self.database.delete_subscription(&self.id, &id).await?;
+                Ok(NodeResponse::DeleteSubscription {})
+            }
         }
     }
 }
 
+// Requests that only read state, so the operator can look around before acknowledging.
+fn allowed_while_halted(request: &NodeRequest) -> bool {
+    matches!(
+        request,
+        NodeRequest::StopNode {}
+            | NodeRequest::NodeInfo {}
+            | NodeRequest::GetBalance {}
+            | NodeRequest::ListUnspent {}
+            | NodeRequest::ListChannels { .. }
+            | NodeRequest::ListPendingChannels {}
+            | NodeRequest::ListPayments { .. }
+            | NodeRequest::GetStaleState {}
+            | NodeRequest::AcknowledgeStaleState {}
+    )
+}
+
+fn parse_channel_id(channel_id: &str) -> Result<[u8; 32], Error> {
+    hex_utils::to_vec(channel_id)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| Error::InvalidChannelId(channel_id.to_string()))
+}
+
+fn parse_hash_32(hex: &str) -> Result<[u8; 32], NodeRequestError> {
+    hex_utils::to_vec(hex)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| NodeRequestError::Sensei(String::from("expected 32 bytes of hex")))
+}
+
+// Only checks the shape of a host:port, resolving it is left to connecting.
+fn is_host_port(host_port: &str) -> bool {
+    match host_port.rsplit_once(':') {
+        Some((host, port)) => !host.is_empty() && port.parse::<u16>().is_ok(),
+        None => false,
+    }
+}
+
+// psbts are passed around as base64 of their consensus serialization
+fn parse_psbt(psbt: &str) -> Result<PartiallySignedTransaction, Error> {
+    let psbt_bytes = base64::decode(psbt).map_err(|e| Error::InvalidPsbt(e.to_string()))?;
+    bitcoin::consensus::encode::deserialize(&psbt_bytes)
+        .map_err(|e| Error::InvalidPsbt(e.to_string()))
+}
+
+fn parse_funding_utxos(funding_utxos: Option<Vec<String>>) -> Result<Option<Vec<OutPoint>>, Error> {
+    funding_utxos
+        .map(|funding_utxos| {
+            funding_utxos
+                .iter()
+                .map(|outpoint| {
+                    OutPoint::from_str(outpoint).map_err(|_e| {
+                        Error::InvalidFundingUtxos(format!(
+                            "{} is not a txid:vout outpoint",
+                            outpoint
+                        ))
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .transpose()
+}
+
 pub fn parse_pubkey(pubkey: &str) -> Result<PublicKey, std::io::Error> {
     let pubkey = hex_utils::to_compressed_pubkey(pubkey);
     if pubkey.is_none() {
@@ -1719,33 +5455,114 @@ pub fn parse_pubkey(pubkey: &str) -> Result<PublicKey, std::io::Error> {
     Ok(pubkey.unwrap())
 }
 
-pub async fn parse_peer_addr(peer_addr_str: &str) -> Result<SocketAddr, std::io::Error> {
-    let peer_addr = peer_addr_str.to_socket_addrs().map(|mut r| r.next());
+// Where to dial a peer. A clearnet host can resolve to several addresses, they're tried in
+// order. Onion hosts can't be resolved locally, they go to the Tor proxy as they are.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PeerAddress {
+    Clearnet(Vec<SocketAddr>),
+    Onion { host: String, port: u16 },
+}
+
+impl PeerAddress {
+    // The host of an announced v3 onion address is the base32 of its key, checksum and
+    // version.
+    pub fn from_onion_v3(ed25519_pubkey: [u8; 32], checksum: u16, version: u8, port: u16) -> Self {
+        const ALPHABET: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
+
+        let mut bytes = ed25519_pubkey.to_vec();
+        bytes.extend_from_slice(&checksum.to_be_bytes());
+        bytes.push(version);
+
+        // 35 bytes are exactly 56 base32 characters, there's no padding
+        let mut host = String::with_capacity(62);
+        let mut buffer = 0u16;
+        let mut bits = 0;
+        for byte in bytes {
+            buffer = (buffer << 8) | byte as u16;
+            bits += 8;
+            while bits >= 5 {
+                bits -= 5;
+                host.push(ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+            }
+        }
+        host.push_str(".onion");
+
+        PeerAddress::Onion { host, port }
+    }
+
+    // Moves the addresses of the preferred family to the front, keeping the resolver's order
+    // otherwise.
+    pub fn prefer(&mut self, family: Option<AddressFamily>) {
+        if let (PeerAddress::Clearnet(addrs), Some(family)) = (self, family) {
+            addrs.sort_by_key(|addr| match family {
+                AddressFamily::Ipv4 => !addr.is_ipv4(),
+                AddressFamily::Ipv6 => !addr.is_ipv6(),
+            });
+        }
+    }
+}
+
+impl Display for PeerAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PeerAddress::Clearnet(addrs) => {
+                let addrs = addrs
+                    .iter()
+                    .map(|addr| addr.to_string())
+                    .collect::<Vec<String>>();
+                write!(f, "{}", addrs.join(", "))
+            }
+            PeerAddress::Onion { host, port } => write!(f, "{}:{}", host, port),
+        }
+    }
+}
+
+pub async fn parse_peer_addr(peer_addr_str: &str) -> Result<PeerAddress, std::io::Error> {
+    if let Some((host, port)) = peer_addr_str.rsplit_once(':') {
+        if host.to_ascii_lowercase().ends_with(".onion") {
+            let port = port.parse::<u16>().map_err(|_| {
+                std::io::Error::new(
+                    std::io::ErrorKind::Other,
+                    "ERROR: couldn't parse the port of the onion address",
+                )
+            })?;
+            return Ok(PeerAddress::Onion {
+                host: host.to_ascii_lowercase(),
+                port,
+            });
+        }
+    }
+
+    let addrs = peer_addr_str
+        .to_socket_addrs()
+        .map(|addrs| addrs.collect::<Vec<SocketAddr>>())
+        .unwrap_or_default();
 
-    if peer_addr.is_err() || peer_addr.as_ref().unwrap().is_none() {
+    if addrs.is_empty() {
         return Err(std::io::Error::new(
             std::io::ErrorKind::Other,
             "ERROR: couldn't parse host:port into a socket address",
         ));
     }
 
-    let addr = peer_addr.unwrap().unwrap();
-
     let listen_addr = public_ip::addr()
         .await
         .unwrap_or_else(|| [127, 0, 0, 1].into());
 
-    let connect_address = match listen_addr == addr.ip() {
-        true => format!("127.0.0.1:{}", addr.port()).parse().unwrap(),
-        false => addr,
-    };
+    let connect_addresses = addrs
+        .into_iter()
+        .map(|addr| match listen_addr == addr.ip() {
+            true => format!("127.0.0.1:{}", addr.port()).parse().unwrap(),
+            false => addr,
+        })
+        .collect();
 
-    Ok(connect_address)
+    Ok(PeerAddress::Clearnet(connect_addresses))
 }
 
 pub async fn parse_peer_info(
     peer_pubkey_and_ip_addr: String,
-) -> Result<(PublicKey, SocketAddr), std::io::Error> {
+) -> Result<(PublicKey, PeerAddress), std::io::Error> {
     let mut pubkey_and_addr = peer_pubkey_and_ip_addr.split('@');
     let pubkey = pubkey_and_addr.next();
     let peer_addr_str = pubkey_and_addr.next();
@@ -1766,42 +5583,139 @@ pub fn connected_to_peer(pubkey: &PublicKey, peer_manager: Arc<PeerManager>) ->
     peer_manager.get_peer_node_ids().contains(pubkey)
 }
 
+// Peers behind Tor or on flaky connections often need a few tries. The backoff doubles
+// after every failed attempt, set `max_attempts` to 1 to fail fast.
+#[derive(Clone, Copy, Debug)]
+pub struct ConnectRetry {
+    pub max_attempts: u32,
+    pub initial_backoff_ms: u64,
+}
+
+impl Default for ConnectRetry {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff_ms: 1000,
+        }
+    }
+}
+
+impl From<&SenseiConfig> for ConnectRetry {
+    fn from(config: &SenseiConfig) -> Self {
+        Self {
+            max_attempts: std::cmp::max(config.peer_connect_max_attempts, 1),
+            initial_backoff_ms: config.peer_connect_initial_backoff_ms,
+        }
+    }
+}
+
 pub(crate) async fn connect_peer_if_necessary(
     pubkey: PublicKey,
-    peer_addr: SocketAddr,
+    mut peer_addr: PeerAddress,
+    peer_manager: Arc<PeerManager>,
+    config: &SenseiConfig,
+    retry: ConnectRetry,
+) -> Result<(), Error> {
+    if !config.peer_allowed(&pubkey) {
+        return Err(Error::PeerNotAllowed(pubkey.to_string()));
+    }
+    let tor_proxy = config.tor_proxy_addr()?;
+    if matches!(peer_addr, PeerAddress::Onion { .. }) && tor_proxy.is_none() {
+        return Err(Error::InvalidPeerAddress(format!(
+            "{}, onion addresses need tor_proxy to be set",
+            peer_addr
+        )));
+    }
+
+    peer_addr.prefer(config.preferred_address_family);
+
+    let mut backoff_ms = retry.initial_backoff_ms;
+    for attempt in 1..=std::cmp::max(retry.max_attempts, 1) {
+        if connect_peer_once(pubkey, &peer_addr, peer_manager.clone(), tor_proxy)
+            .await
+            .is_ok()
+        {
+            return Ok(());
+        }
+        if attempt < retry.max_attempts {
+            tokio::time::sleep(Duration::from_millis(backoff_ms)).await;
+            backoff_ms = backoff_ms.saturating_mul(2);
+        }
+    }
+    Err(Error::PeerConnectFailed(format!(
+        "{}@{}",
+        pubkey, peer_addr
+    )))
+}
+
+async fn connect_peer_once(
+    pubkey: PublicKey,
+    peer_addr: &PeerAddress,
     peer_manager: Arc<PeerManager>,
+    tor_proxy: Option<SocketAddr>,
 ) -> Result<(), ()> {
     if connected_to_peer(&pubkey, peer_manager.clone()) {
         return Ok(());
     }
 
-    match lightning_net_tokio::connect_outbound(Arc::clone(&peer_manager), pubkey, peer_addr).await
-    {
-        Some(connection_closed_future) => {
-            let mut connection_closed_future = Box::pin(connection_closed_future);
-            loop {
-                match futures::poll!(&mut connection_closed_future) {
-                    std::task::Poll::Ready(_) => {
-                        println!("ERROR: Peer disconnected before we finished the handshake");
-                        return Err(());
-                    }
-                    std::task::Poll::Pending => {}
-                }
-                // Avoid blocking the tokio context by sleeping a bit
-                match peer_manager
-                    .get_peer_node_ids()
-                    .iter()
-                    .find(|id| **id == pubkey)
+    match (peer_addr, tor_proxy) {
+        (PeerAddress::Clearnet(addrs), _) => {
+            // round robin dns or a dual stack host can hand out addresses that don't work
+            for addr in addrs {
+                if let Some(connection_closed_future) =
+                    lightning_net_tokio::connect_outbound(Arc::clone(&peer_manager), pubkey, *addr)
+                        .await
                 {
-                    Some(_) => break,
-                    None => tokio::time::sleep(Duration::from_millis(10)).await,
+                    if wait_for_handshake(pubkey, connection_closed_future, &peer_manager)
+                        .await
+                        .is_ok()
+                    {
+                        return Ok(());
+                    }
                 }
             }
+            Err(())
+        }
+        (PeerAddress::Onion { host, port }, Some(proxy)) => {
+            // tor circuits take a lot longer to build than a tcp connection
+            let stream =
+                tokio::time::timeout(Duration::from_secs(30), socks::connect(proxy, host, *port))
+                    .await
+                    .map_err(|_| ())?
+                    .map_err(|_| ())?;
+            let connection_closed_future = lightning_net_tokio::setup_outbound(
+                Arc::clone(&peer_manager),
+                pubkey,
+                stream.into_std().map_err(|_| ())?,
+            );
+            wait_for_handshake(pubkey, connection_closed_future, &peer_manager).await
+        }
+        (PeerAddress::Onion { .. }, None) => Err(()),
+    }
+}
+
+async fn wait_for_handshake(
+    pubkey: PublicKey,
+    connection_closed_future: impl Future<Output = ()>,
+    peer_manager: &PeerManager,
+) -> Result<(), ()> {
+    let mut connection_closed_future = Box::pin(connection_closed_future);
+    loop {
+        match futures::poll!(&mut connection_closed_future) {
+            std::task::Poll::Ready(_) => {
+                println!("ERROR: Peer disconnected before we finished the handshake");
+                return Err(());
+            }
+            std::task::Poll::Pending => {}
         }
-        None => {
-            //println!("ERROR: failed to connect to peer");
-            return Err(());
+        // Avoid blocking the tokio context by sleeping a bit
+        match peer_manager
+            .get_peer_node_ids()
+            .iter()
+            .find(|id| **id == pubkey)
+        {
+            Some(_) => return Ok(()),
+            None => tokio::time::sleep(Duration::from_millis(10)).await,
         }
     }
-    Ok(())
 }
@@ -2,13 +2,15 @@ use std::{
     collections::HashMap,
     fs::{self},
     io::Cursor,
-    net::SocketAddr,
     ops::Deref,
     path::{Path, PathBuf},
     sync::Arc,
 };
 
-use crate::{disk::FilesystemLogger, node::NetworkGraph};
+use crate::{
+    disk::FilesystemLogger,
+    node::{NetworkGraph, PeerAddress},
+};
 use bitcoin::secp256k1::PublicKey;
 use bitcoin::{
     blockdata::constants::genesis_block, hashes::hex::FromHex, BlockHash, Network, Txid,
@@ -188,8 +190,8 @@ impl SenseiPersister {
     pub fn read_scorer(
         &self,
         network_graph: Arc<NetworkGraph>,
+        params: ProbabilisticScoringParameters,
     ) -> ProbabilisticScorer<Arc<NetworkGraph>, Arc<FilesystemLogger>> {
-        let params = ProbabilisticScoringParameters::default();
         if let Ok(Some(contents)) = self.store.read("scorer") {
             let mut cursor = Cursor::new(contents);
             if let Ok(scorer) = ProbabilisticScorer::read(
@@ -237,13 +239,13 @@ impl SenseiPersister {
 
     pub async fn read_channel_peer_data(
         &self,
-    ) -> Result<HashMap<PublicKey, SocketAddr>, std::io::Error> {
+    ) -> Result<HashMap<PublicKey, PeerAddress>, std::io::Error> {
         let mut peer_data = HashMap::new();
         let raw_peer_data = self.get_raw_channel_peer_data();
         for line in raw_peer_data.lines() {
             match node::parse_peer_info(line.to_string()).await {
-                Ok((pubkey, socket_addr)) => {
-                    peer_data.insert(pubkey, socket_addr);
+                Ok((pubkey, peer_addr)) => {
+                    peer_data.insert(pubkey, peer_addr);
                 }
                 Err(_e) => {
                     // ignore these errors for now
@@ -0,0 +1,84 @@
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+const MAX_BACKOFF: Duration = Duration::from_secs(3600);
+
+struct Attempts {
+    window_start: Instant,
+    count: u32,
+    blocked_until: Option<Instant>,
+    strikes: u32,
+}
+
+// Limits inbound connection attempts per remote ip. The peer's pubkey is only known once
+// LDK has finished the noise handshake, which is already too late to turn a flood away,
+// so limits are keyed by ip and exemptions are ip addresses as well.
+//
+// Going over `max_attempts` within `window` blocks the ip, starting at one window and
+// doubling every time it happens again until the ip stays quiet for a full window.
+pub struct ConnectionRateLimiter {
+    max_attempts: u32,
+    window: Duration,
+    exempt_ips: HashSet<IpAddr>,
+    attempts: HashMap<IpAddr, Attempts>,
+}
+
+impl ConnectionRateLimiter {
+    pub fn new(max_attempts: u32, window: Duration, exempt_ips: HashSet<IpAddr>) -> Self {
+        Self {
+            max_attempts,
+            window,
+            exempt_ips,
+            attempts: HashMap::new(),
+        }
+    }
+
+    // Records an attempt from `ip`, returning how long it is blocked for when rejected.
+    pub fn check(&mut self, ip: IpAddr, now: Instant) -> Result<(), Duration> {
+        if self.exempt_ips.contains(&ip) {
+            return Ok(());
+        }
+
+        let window = self.window;
+        self.attempts.retain(|_, attempts| {
+            attempts.blocked_until.map_or(false, |until| until > now)
+                || now.duration_since(attempts.window_start) < window * 2
+        });
+
+        let attempts = self.attempts.entry(ip).or_insert(Attempts {
+            window_start: now,
+            count: 0,
+            blocked_until: None,
+            strikes: 0,
+        });
+
+        if let Some(until) = attempts.blocked_until {
+            if until > now {
+                return Err(until - now);
+            }
+            attempts.blocked_until = None;
+        }
+
+        if now.duration_since(attempts.window_start) >= window {
+            if attempts.count <= self.max_attempts {
+                attempts.strikes = 0;
+            }
+            attempts.window_start = now;
+            attempts.count = 0;
+        }
+
+        attempts.count += 1;
+        if attempts.count <= self.max_attempts {
+            return Ok(());
+        }
+
+        let backoff = std::cmp::min(
+            window.saturating_mul(2u32.saturating_pow(attempts.strikes)),
+            MAX_BACKOFF,
+        );
+        attempts.strikes += 1;
+        attempts.blocked_until = Some(now + backoff);
+        Err(backoff)
+    }
+}
@@ -0,0 +1,71 @@
+use crate::database::DailyForwardFees;
+use serde::Serialize;
+
+pub const SECS_PER_DAY: i64 = 86400;
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct DailyFeeRevenue {
+    // start of the utc day
+    pub day: i64,
+    pub fee_earned_msat: u64,
+    pub forwards: u64,
+}
+
+// `trend_pct` compares the window to the one right before it, None when that one earned
+// nothing.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct FeeRevenue {
+    pub window_secs: u64,
+    pub fee_earned_msat: u64,
+    pub forwards: u64,
+    pub fee_earned_msat_per_day: f64,
+    pub previous_fee_earned_msat: u64,
+    pub trend_pct: Option<f64>,
+    pub days: Vec<DailyFeeRevenue>,
+}
+
+// Lays out the daily fees of the window after `start` up to `end`, days without forwards
+// included, next to what the window before it earned.
+pub fn fee_revenue(
+    daily_fees: &[DailyForwardFees],
+    previous_fee_earned_msat: u64,
+    start: i64,
+    end: i64,
+) -> FeeRevenue {
+    let window_secs = end - start;
+
+    let first_day = start - start.rem_euclid(SECS_PER_DAY);
+    let mut days = (first_day..=end)
+        .step_by(SECS_PER_DAY as usize)
+        .map(|day| DailyFeeRevenue {
+            day,
+            fee_earned_msat: 0,
+            forwards: 0,
+        })
+        .collect::<Vec<_>>();
+
+    for daily in daily_fees {
+        let index = ((daily.day - first_day) / SECS_PER_DAY) as usize;
+        if let Some(day) = days.get_mut(index) {
+            day.fee_earned_msat += daily.fee_earned_msat.max(0) as u64;
+            day.forwards += daily.forwards.max(0) as u64;
+        }
+    }
+
+    let fee_earned_msat = days.iter().map(|day| day.fee_earned_msat).sum::<u64>();
+    FeeRevenue {
+        window_secs: window_secs.max(0) as u64,
+        fee_earned_msat,
+        forwards: days.iter().map(|day| day.forwards).sum(),
+        fee_earned_msat_per_day: match window_secs {
+            secs if secs > 0 => fee_earned_msat as f64 * SECS_PER_DAY as f64 / secs as f64,
+            _ => 0.0,
+        },
+        previous_fee_earned_msat,
+        trend_pct: match previous_fee_earned_msat {
+            0 => None,
+            previous => Some((fee_earned_msat as f64 - previous as f64) * 100.0 / previous as f64),
+        },
+        days,
+    }
+}
@@ -0,0 +1,170 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+use bitcoin::secp256k1::PublicKey;
+use lightning::ln::channelmanager::ChannelDetails;
+use lightning::ln::msgs::{ErrorAction, LightningError};
+use lightning::ln::PaymentHash;
+use lightning::routing::gossip::NodeId;
+use lightning::routing::router::{Route, RouteHop, RouteParameters};
+use lightning::routing::scoring::{ChannelUsage, Score};
+use lightning_invoice::payment::Router;
+use lightning_invoice::utils::DefaultRouter;
+
+use crate::disk::FilesystemLogger;
+use crate::node::NetworkGraph;
+
+// How many times a payment with a path length limit is routed again before giving up.
+const MAX_LIMITED_ROUTE_ATTEMPTS: usize = 10;
+
+// Path length limits by payment hash, with the time (unix seconds) they can be dropped at.
+// Retries go through the router too, so a limit stays until the invoice expires.
+#[derive(Clone, Default)]
+pub struct PathLengthLimits {
+    limits: Arc<Mutex<HashMap<PaymentHash, (usize, u64)>>>,
+}
+
+impl PathLengthLimits {
+    pub fn set(&self, payment_hash: PaymentHash, max_path_length: u8, expires_at: u64) {
+        let now = entity::seconds_since_epoch() as u64;
+        let mut limits = self.limits.lock().unwrap();
+        limits.retain(|_payment_hash, (_max_path_length, expires_at)| *expires_at > now);
+        limits.insert(payment_hash, (max_path_length as usize, expires_at));
+    }
+
+    pub fn remove(&self, payment_hash: &PaymentHash) {
+        self.limits.lock().unwrap().remove(payment_hash);
+    }
+
+    fn get(&self, payment_hash: &PaymentHash) -> Option<usize> {
+        self.limits
+            .lock()
+            .unwrap()
+            .get(payment_hash)
+            .map(|(max_path_length, _expires_at)| *max_path_length)
+    }
+}
+
+// Routes like DefaultRouter. When a payment has a path length limit and a path comes back
+// longer than that, one of its channels is avoided and the payment is routed again. The
+// last hop is never avoided, it may be the only way to reach the payee.
+pub struct SenseiRouter {
+    inner: DefaultRouter<Arc<NetworkGraph>, Arc<FilesystemLogger>>,
+    path_length_limits: PathLengthLimits,
+}
+
+impl SenseiRouter {
+    pub fn new(
+        inner: DefaultRouter<Arc<NetworkGraph>, Arc<FilesystemLogger>>,
+        path_length_limits: PathLengthLimits,
+    ) -> Self {
+        Self {
+            inner,
+            path_length_limits,
+        }
+    }
+}
+
+impl<S: Score> Router<S> for SenseiRouter {
+    fn find_route(
+        &self,
+        payer: &PublicKey,
+        route_params: &RouteParameters,
+        payment_hash: &PaymentHash,
+        first_hops: Option<&[&ChannelDetails]>,
+        scorer: &S,
+    ) -> Result<Route, LightningError> {
+        let max_path_length = match self.path_length_limits.get(payment_hash) {
+            Some(max_path_length) => max_path_length,
+            None => {
+                return self
+                    .inner
+                    .find_route(payer, route_params, payment_hash, first_hops, scorer)
+            }
+        };
+
+        let mut avoided = HashSet::new();
+        for _attempt in 0..MAX_LIMITED_ROUTE_ATTEMPTS {
+            let avoiding_scorer = AvoidingScorer {
+                inner: scorer,
+                avoided: &avoided,
+            };
+            let route = self.inner.find_route(
+                payer,
+                route_params,
+                payment_hash,
+                first_hops,
+                &avoiding_scorer,
+            )?;
+
+            let too_long = route
+                .paths
+                .iter()
+                .filter(|path| path.len() > max_path_length)
+                .collect::<Vec<_>>();
+            if too_long.is_empty() {
+                return Ok(route);
+            }
+
+            let mut avoided_more = false;
+            for path in too_long {
+                if let Some(short_channel_id) = channel_to_avoid(path, &avoided) {
+                    avoided.insert(short_channel_id);
+                    avoided_more = true;
+                }
+            }
+            if !avoided_more {
+                break;
+            }
+        }
+
+        Err(LightningError {
+            err: format!(
+                "no route to {} within {} hops",
+                route_params.payment_params.payee_pubkey, max_path_length
+            ),
+            action: ErrorAction::IgnoreError,
+        })
+    }
+}
+
+// The channel closest to the middle of the path that isn't avoided yet, leaving out the last
+// hop.
+fn channel_to_avoid(path: &[RouteHop], avoided: &HashSet<u64>) -> Option<u64> {
+    let middle = path.len() / 2;
+    let mut hops = path[..path.len() - 1]
+        .iter()
+        .enumerate()
+        .collect::<Vec<_>>();
+    hops.sort_by_key(|(index, _hop)| (*index as isize - middle as isize).abs());
+    hops.into_iter()
+        .map(|(_index, hop)| hop.short_channel_id)
+        .find(|short_channel_id| !avoided.contains(short_channel_id))
+}
+
+// Scores like `inner`, except that avoided channels get the highest penalty there is.
+struct AvoidingScorer<'a, S: Score> {
+    inner: &'a S,
+    avoided: &'a HashSet<u64>,
+}
+
+impl<'a, S: Score> Score for AvoidingScorer<'a, S> {
+    fn channel_penalty_msat(
+        &self,
+        short_channel_id: u64,
+        source: &NodeId,
+        target: &NodeId,
+        usage: ChannelUsage,
+    ) -> u64 {
+        if self.avoided.contains(&short_channel_id) {
+            return u64::max_value();
+        }
+        self.inner
+            .channel_penalty_msat(short_channel_id, source, target, usage)
+    }
+
+    // only used to find routes, what happens to payments is scored by `inner`
+    fn payment_path_failed(&mut self, _path: &[&RouteHop], _short_channel_id: u64) {}
+
+    fn payment_path_successful(&mut self, _path: &[&RouteHop]) {}
+}
@@ -70,6 +70,9 @@ pub enum AdminRequest {
         alias: String,
         passphrase: String,
         start: bool,
+        mnemonic: Option<String>,
+        mnemonic_passphrase: Option<String>,
+        rescan_from_height: Option<u32>,
     },
     BatchCreateNode {
         nodes: Vec<NodeCreateInfo>,
@@ -190,7 +193,7 @@ impl AdminService {
             });
 
         for port in config.port_range_min..config.port_range_max {
-            if !used_ports.contains(&port) {
+            if !used_ports.contains(&port) && port != config.root_node_listen_port {
                 available_ports.push_back(port);
             }
         }
@@ -295,7 +298,13 @@ impl AdminService {
                 start,
             } => {
                 let (node, macaroon) = self
-                    .create_node(username, alias, passphrase.clone(), node::NodeRole::Root)
+                    .create_node(
+                        username,
+                        alias,
+                        passphrase.clone(),
+                        node::NodeRole::Root,
+                        None,
+                    )
                     .await?;
 
                 let root_token = self.database.create_root_access_token().await.unwrap();
@@ -381,13 +390,48 @@ impl AdminService {
                 alias,
                 passphrase,
                 start,
+                mnemonic,
+                mnemonic_passphrase,
+                rescan_from_height,
             } => {
+                let seed = match mnemonic {
+                    Some(mnemonic) => {
+                        let seed = LightningNode::seed_from_mnemonic(
+                            &mnemonic,
+                            mnemonic_passphrase.as_deref(),
+                        )?;
+                        let pubkey = LightningNode::get_node_pubkey_from_seed(&seed)?;
+                        if self.database.get_node_by_pubkey(&pubkey).await?.is_some() {
+                            return Err(Error::Generic(String::from(
+                                "a node was already created from this mnemonic",
+                            )));
+                        }
+                        Some(seed.to_vec())
+                    }
+                    None => None,
+                };
+                let imported = seed.is_some();
+
                 let (node, macaroon) = self
-                    .create_node(username, alias, passphrase.clone(), node::NodeRole::Default)
+                    .create_node(
+                        username,
+                        alias,
+                        passphrase.clone(),
+                        node::NodeRole::Default,
+                        seed,
+                    )
                     .await?;
 
                 let macaroon = macaroon.serialize(macaroon::Format::V2)?;
 
+                // an imported wallet can already have history, it's looked for once the node
+                // runs, starting at its birthday
+                if imported {
+                    self.database
+                        .set_pending_wallet_rescan(&node.id, rescan_from_height.unwrap_or(0))
+                        .await?;
+                }
+
                 if start {
                     self.start_node(node.clone(), passphrase).await?;
                 }
@@ -493,6 +537,7 @@ impl AdminService {
                     info.alias,
                     info.passphrase,
                     NodeRole::Default,
+                    None,
                 )
             })
             .collect::<Vec<_>>();
@@ -537,6 +582,7 @@ impl AdminService {
         alias: String,
         passphrase: String,
         role: node::NodeRole,
+        seed: Option<Vec<u8>>,
     ) -> Result<
         (
             entity::node::Model,
@@ -551,7 +597,7 @@ impl AdminService {
         let listen_addr = self.config.api_host.clone();
 
         let listen_port: i32 = match role {
-            node::NodeRole::Root => 9735,
+            node::NodeRole::Root => self.config.root_node_listen_port.into(),
             node::NodeRole::Default => {
                 let mut available_ports = self.available_ports.lock().await;
                 available_ports.pop_front().unwrap().into()
@@ -566,7 +612,7 @@ impl AdminService {
         fs::create_dir_all(node_directory)?;
 
         // NODE SEED
-        let seed = LightningNode::generate_seed();
+        let seed = seed.unwrap_or_else(|| LightningNode::generate_seed().to_vec());
         let encrypted_seed = LightningNode::encrypt_seed(&seed, passphrase.as_bytes())?;
 
         let seed_active_model = self
@@ -574,7 +620,7 @@ impl AdminService {
             .get_seed_active_model(node_id.clone(), encrypted_seed);
 
         // NODE PUBKEY
-        let node_pubkey = LightningNode::get_node_pubkey_from_seed(&seed);
+        let node_pubkey = LightningNode::get_node_pubkey_from_seed(&seed)?;
 
         // NODE MACAROON
         let (macaroon, macaroon_id) = LightningNode::generate_macaroon(&seed, node_pubkey.clone())?;
@@ -629,9 +675,11 @@ impl AdminService {
         alias: String,
         passphrase: String,
         role: node::NodeRole,
+        seed: Option<Vec<u8>>,
     ) -> Result<(node::Model, Macaroon), crate::error::Error> {
-        let (node, macaroon, db_node, db_seed, db_macaroon) =
-            self.build_node(username, alias, passphrase, role).await?;
+        let (node, macaroon, db_node, db_seed, db_macaroon) = self
+            .build_node(username, alias, passphrase, role, seed)
+            .await?;
 
         db_seed.insert(self.database.get_connection()).await?;
         db_macaroon.insert(self.database.get_connection()).await?;
@@ -678,7 +726,7 @@ impl AdminService {
 
         match status {
             None => {
-                let (lightning_node, handles, background_processor) = LightningNode::new(
+                let new_node = LightningNode::new(
                     self.config.clone(),
                     node.id.clone(),
                     vec![node.listen_addr.clone()],
@@ -698,7 +746,20 @@ impl AdminService {
                     self.database.clone(),
                     self.event_sender.clone(),
                 )
-                .await?;
+                .await;
+
+                // let the node be started again if it failed, e.g. its port was taken
+                let (lightning_node, handles, background_processor) = match new_node {
+                    Ok(new_node) => new_node,
+                    Err(e) => {
+                        let mut node_directory = self.node_directory.lock().await;
+                        node_directory.remove(&node.pubkey);
+                        return Err(e);
+                    }
+                };
+
+                lightning_node.claim_pending_payments().await?;
+                lightning_node.reconcile_pending_payments().await?;
 
                 println!(
                     "starting {}@{}:{}",
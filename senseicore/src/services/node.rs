@@ -8,9 +8,12 @@
 // licenses.
 
 use crate::node::{LightningNode, LocalInvoice};
+use crate::revenue::FeeRevenue;
+use crate::stale_state::StaleChannel;
 use bdk::TransactionDetails;
 use futures::Future;
 use lightning::util::config::{ChannelConfig, ChannelHandshakeLimits, UserConfig};
+use std::collections::BTreeMap;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 use tower::Service;
@@ -27,14 +30,24 @@ pub struct Peer {
     pub node_pubkey: String,
 }
 
+#[derive(Serialize, Clone, Debug)]
+pub struct LabeledTransaction {
+    #[serde(flatten)]
+    pub details: TransactionDetails,
+    pub labels: Vec<String>,
+}
+
 #[derive(Serialize)]
 pub struct NodeInfo {
     pub version: String,
     pub node_pubkey: String,
     pub num_channels: u32,
     pub num_usable_channels: u32,
+    pub num_pending_channels: u32,
     pub num_peers: u32,
     pub local_balance_msat: u64,
+    pub last_sync_timestamp: i64,
+    pub in_flight_outbound_msat: u64,
 }
 
 // #[derive(Serialize)]
@@ -87,6 +100,7 @@ pub struct Channel {
     pub is_public: bool,
     pub counterparty_pubkey: String,
     pub alias: Option<String>,
+    pub label: Option<String>,
 }
 
 impl From<ChannelDetails> for Channel {
@@ -112,10 +126,231 @@ impl From<ChannelDetails> for Channel {
             is_public: channel_detail.is_public,
             counterparty_pubkey: channel_detail.counterparty.node_id.to_string(),
             alias: None,
+            label: None,
         }
     }
 }
 
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct Liquidity {
+    pub inbound_msat: u64,
+    pub outbound_msat: u64,
+}
+
+impl Liquidity {
+    pub fn add(&mut self, inbound_msat: u64, outbound_msat: u64) {
+        self.inbound_msat += inbound_msat;
+        self.outbound_msat += outbound_msat;
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct PeerLiquidity {
+    pub counterparty_pubkey: String,
+    #[serde(flatten)]
+    pub liquidity: Liquidity,
+}
+
+// Forwards are attributed to the peer of the channel they came in or went out on, the fee
+// earned goes to the outgoing side.
+#[derive(Serialize, Clone, Debug)]
+pub struct RebroadcastResult {
+    pub txid: String,
+    pub succeeded: bool,
+    pub error: Option<String>,
+}
+
+// HTLCs settled or failed over a channel since it was opened. Counters are kept per channel
+// id, which comes from the funding outpoint, so a channel reopened with the same peer starts
+// again from zero while the closed one keeps its own. Failed inbound HTLCs and failed
+// forwards are not reported by LDK yet, only our own payments count as outbound failures,
+// and only when the channel itself is the one that failed the path.
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct ChannelHtlcStats {
+    pub channel_id: String,
+    pub inbound_succeeded: u64,
+    pub inbound_failed: u64,
+    pub outbound_succeeded: u64,
+    pub outbound_failed: u64,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct PeerChannels {
+    pub counterparty_pubkey: String,
+    pub label: Option<String>,
+    pub alias: Option<String>,
+    pub channel_count: usize,
+    pub capacity_sats: u64,
+    pub local_balance_msat: u64,
+    pub remote_balance_msat: u64,
+    pub forwards_in: u64,
+    pub forwards_out: u64,
+    pub fees_earned_msat: u64,
+    pub channels: Vec<Channel>,
+}
+
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct LiquiditySummary {
+    pub total: Liquidity,
+    pub public: Liquidity,
+    pub private: Liquidity,
+    pub pending: Liquidity,
+    pub peers: Vec<PeerLiquidity>,
+}
+
+// One line of the accounting ledger. `amount_msat` is the signed change to our balance,
+// fees included, and `fee_msat` is the part of it that went to fees, or what we earned for
+// forwards. On-chain amounts are converted to msat so every entry uses the same unit.
+#[derive(Serialize, Clone, Debug)]
+pub struct LedgerEntry {
+    pub timestamp: i64,
+    pub kind: String,
+    pub reference: String,
+    pub amount_msat: i64,
+    pub fee_msat: i64,
+    pub label: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct Feerate {
+    pub sat_per_kw: u32,
+    pub sat_per_vb: f32,
+    // the estimate is at LDK's 253 sat/kw floor, usually because the backend has none
+    pub at_min_feerate: bool,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ChainTip {
+    pub height: u32,
+    pub block_hash: String,
+    pub timestamp: Option<u32>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct CurrentFeerates {
+    pub channel_funding: Feerate,
+    pub cooperative_close: Feerate,
+    pub force_close: Feerate,
+    pub onchain_send: Feerate,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct PendingChannel {
+    pub channel_id: String,
+    pub counterparty_pubkey: String,
+    pub channel_value_satoshis: u64,
+    pub is_outbound: bool,
+    pub funding_txid: Option<String>,
+    pub funding_broadcast: bool,
+    pub confirmations: Option<u32>,
+    pub confirmations_required: Option<u32>,
+}
+
+// A channel from build_batch_funding that is waiting for its psbt to be finalized.
+#[derive(Serialize, Clone, Debug)]
+pub struct InflightOpen {
+    pub custom_id: u64,
+    pub temp_channel_id: String,
+    pub counterparty_pubkey: String,
+    pub amount_sats: u64,
+    pub funding_txid: String,
+}
+
+// Only covers the part of the window the channel has existed for, `observed_secs`. Channels
+// we didn't open ourselves are taken to be at least as old as their first recorded event.
+#[derive(Serialize, Clone, Debug)]
+pub struct ChannelUptime {
+    pub channel_id: String,
+    pub counterparty_pubkey: String,
+    pub window_secs: u64,
+    pub observed_secs: u64,
+    pub connected_secs: u64,
+    pub uptime_pct: Option<f64>,
+    pub disconnections: u32,
+}
+
+// `eta_blocks` is 0 once confirmed and None when it can't be estimated: the funding tx isn't
+// ours, its fee is unknown, it pays less than the Background estimate or bitcoind has no
+// estimates yet.
+#[derive(Serialize, Clone, Debug)]
+pub struct FundingConfirmationEta {
+    pub channel_id: String,
+    pub funding_txid: Option<String>,
+    pub feerate_sat_per_kw: Option<u32>,
+    pub eta_blocks: Option<u32>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct GossipMessage {
+    pub message: String,
+    pub timestamp: u64,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ChannelGossip {
+    pub short_channel_id: u64,
+    pub announcement: Option<GossipMessage>,
+    pub update: Option<GossipMessage>,
+}
+
+// The payment settles asynchronously, follow it through its payment hash.
+#[derive(Serialize, Clone, Debug)]
+pub struct RebalanceResult {
+    pub payment_hash: String,
+    pub amount_msat: u64,
+    pub fee_msat: u64,
+    pub hops: Vec<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct SimulatedHtlc {
+    pub amount_sats: u64,
+    pub claimable_height: u32,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ForceCloseSimulation {
+    pub channel_id: String,
+    pub to_local_sats: u64,
+    pub csv_delay: Option<u16>,
+    pub pending_htlcs: Vec<SimulatedHtlc>,
+    pub pending_inbound_htlcs_sats: u64,
+    pub awaiting_confirmations_sats: u64,
+    pub contentious_sats: u64,
+    pub estimated_fees_sats: u64,
+    pub recoverable_sats: u64,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ForwardFeeEstimate {
+    pub in_channel_id: String,
+    pub out_channel_id: String,
+    pub amount_msat: u64,
+    pub forwarding_fee_base_msat: u32,
+    pub forwarding_fee_proportional_millionths: u32,
+    pub fee_msat: u64,
+    pub within_capacity: bool,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct OpenChannelValidation {
+    pub counterparty_pubkey: String,
+    pub custom_id: Option<u64>,
+    pub errors: Vec<String>,
+}
+
+// `estimated_fee_sats` assumes the largest utxos are spent first, coin selection may end up
+// using more inputs.
+#[derive(Serialize, Clone, Debug)]
+pub struct BatchValidation {
+    pub requests: Vec<OpenChannelValidation>,
+    pub errors: Vec<String>,
+    pub total_sats: u64,
+    pub estimated_fee_sats: u64,
+    pub available_sats: u64,
+    pub sufficient_funds: bool,
+}
+
 #[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct OpenChannelRequest {
     pub counterparty_pubkey: String,
@@ -165,11 +400,28 @@ impl From<&OpenChannelRequest> for UserConfig {
     }
 }
 
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct BatchCloseRequest {
+    pub channel_id: String,
+    pub force: bool,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct CloseChannelResult {
+    pub channel_id: String,
+    pub error: bool,
+    pub error_message: Option<String>,
+}
+
 #[derive(Serialize, Clone, Debug)]
 pub struct OpenChannelResult {
     pub error: bool,
     pub error_message: Option<String>,
     pub temp_channel_id: Option<String>,
+    pub funding_txid: Option<String>,
+    pub funding_tx_fee_sats: Option<u64>,
+    pub funding_tx_vsize: Option<u64>,
+    pub funding_tx_sat_per_vb: Option<f32>,
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
@@ -186,37 +438,127 @@ pub enum NodeRequest {
     },
     StopNode {},
     GetUnusedAddress {},
+    NewDepositAddress {
+        label: String,
+    },
+    SyncWallet {},
+    RebroadcastUnconfirmed {},
+    SignAndBroadcastPsbt {
+        psbt: String,
+    },
+    BumpFundingFee {
+        txid: String,
+        sat_per_vb: u64,
+    },
+    GetScannedAddresses {},
+    CacheWalletAddresses {
+        addresses: u32,
+        rescan_from_height: u32,
+    },
+    RescanWallet {
+        from_height: u32,
+    },
     GetBalance {},
+    LiquiditySummary {},
+    ChannelsByPeer {},
+    GetFeerates {},
+    GetChainTip {},
     OpenChannels {
         requests: Vec<OpenChannelRequest>,
+        funding_utxos: Option<Vec<String>>,
+        unspendable_utxos: Option<Vec<String>>,
+        max_fee_sats: Option<u64>,
+        funding_timeout_ms: Option<u64>,
+    },
+    OpenChannel {
+        request: OpenChannelRequest,
+        funding_utxos: Option<Vec<String>>,
+        unspendable_utxos: Option<Vec<String>>,
+        max_fee_sats: Option<u64>,
+        funding_timeout_ms: Option<u64>,
+    },
+    BuildBatchFunding {
+        requests: Vec<OpenChannelRequest>,
+        funding_utxos: Option<Vec<String>>,
+        unspendable_utxos: Option<Vec<String>>,
+        max_fee_sats: Option<u64>,
+        funding_timeout_ms: Option<u64>,
+    },
+    FinalizeBatchFunding {
+        psbt: String,
+    },
+    ValidateChannelBatch {
+        requests: Vec<OpenChannelRequest>,
+    },
+    OpenChannelAndPay {
+        request: OpenChannelRequest,
+        invoice: String,
+        ready_timeout_ms: Option<u64>,
     },
     SendPayment {
         invoice: String,
+        max_path_length: Option<u8>,
     },
     Keysend {
         dest_pubkey: String,
         amt_msat: u64,
     },
+    SendAlongRoute {
+        hops: Vec<String>,
+        amount_msat: u64,
+        payment_hash: String,
+        payment_secret: Option<String>,
+        final_cltv_expiry_delta: Option<u32>,
+    },
+    RebalanceToTarget {
+        channel_id: String,
+        target_local_msat: u64,
+        max_fee_msat: u64,
+    },
     DecodeInvoice {
         invoice: String,
     },
     GetInvoice {
         amt_msat: u64,
         description: String,
+        metadata: Option<BTreeMap<String, String>>,
     },
     LabelPayment {
         label: String,
         payment_hash: String,
     },
+    LabelChannel {
+        channel_id: String,
+        label: String,
+    },
+    SetPaymentMetadata {
+        payment_hash: String,
+        metadata: BTreeMap<String, String>,
+    },
+    SetInvoiceMetadata {
+        invoice: String,
+        metadata: BTreeMap<String, String>,
+    },
     DeletePayment {
         payment_hash: String,
     },
+    ResolveHeldPayment {
+        payment_hash: String,
+        accept: bool,
+    },
     ConnectPeer {
         node_connection_string: String,
     },
     ListChannels {
         pagination: PaginationRequest,
     },
+    ListPendingChannels {},
+    ListInflightOpens {},
+    GetStaleState {},
+    AcknowledgeStaleState {},
+    CancelInflightOpen {
+        custom_id: u64,
+    },
     ListPayments {
         pagination: PaginationRequest,
         filter: PaymentsFilter,
@@ -228,6 +570,47 @@ pub enum NodeRequest {
         channel_id: String,
         force: bool,
     },
+    CloseChannels {
+        requests: Vec<BatchCloseRequest>,
+        timeout_ms: Option<u64>,
+    },
+    ChannelTimeline {
+        channel_id: String,
+    },
+    GetChannelFundingInputs {
+        channel_id: String,
+    },
+    GetChannelHtlcStats {
+        channel_id: String,
+    },
+    SimulateForceClose {
+        channel_id: String,
+        sat_per_vb: u64,
+    },
+    FundingConfirmationEta {
+        channel_id: String,
+    },
+    ChannelUptime {
+        channel_id: String,
+        window_secs: u64,
+    },
+    FeeRevenueRate {
+        window_secs: u64,
+    },
+    ExportChannelMonitor {
+        channel_id: String,
+        passphrase: String,
+    },
+    ImportChannelMonitor {
+        monitor: String,
+        passphrase: String,
+        accept_force_close: bool,
+    },
+    EstimateForwardFee {
+        in_channel_id: String,
+        out_channel_id: String,
+        amount_msat: u64,
+    },
     NodeInfo {},
     ListPeers {},
     SignMessage {
@@ -239,6 +622,20 @@ pub enum NodeRequest {
     },
     ListUnspent {},
     NetworkGraphInfo {},
+    GetGraphNode {
+        pubkey: String,
+    },
+    FindGraphPath {
+        from_pubkey: Option<String>,
+        to_pubkey: String,
+    },
+    GetGossip {},
+    SetScorerLiquidity {
+        short_channel_id: u64,
+        target_pubkey: String,
+        min_liquidity_msat: u64,
+        max_liquidity_msat: u64,
+    },
     ListKnownPeers {
         pagination: PaginationRequest,
     },
@@ -250,6 +647,16 @@ pub enum NodeRequest {
     RemoveKnownPeer {
         pubkey: String,
     },
+    ListSubscriptions {},
+    CreateSubscription {
+        amt_msat: u64,
+        description: String,
+        interval_secs: u64,
+        first_invoice_at: Option<i64>,
+    },
+    DeleteSubscription {
+        id: String,
+    },
 }
 
 #[derive(Serialize)]
@@ -260,6 +667,30 @@ pub enum NodeResponse {
     GetUnusedAddress {
         address: String,
     },
+    NewDepositAddress {
+        address: String,
+        label: String,
+    },
+    SyncWallet {},
+    RebroadcastUnconfirmed {
+        results: Vec<RebroadcastResult>,
+    },
+    SignAndBroadcastPsbt {
+        txid: String,
+    },
+    BumpFundingFee {
+        txid: String,
+    },
+    GetScannedAddresses {
+        scanned_addresses: usize,
+    },
+    CacheWalletAddresses {
+        scanned_addresses: usize,
+        blocks_scanned: Option<u32>,
+    },
+    RescanWallet {
+        blocks_scanned: u32,
+    },
     GetBalance {
         onchain_balance_sats: u64,
         channel_balance_msats: u64,
@@ -268,34 +699,119 @@ pub enum NodeResponse {
         usable_channel_outbound_capacity_msats: u64,
         usable_channel_inbound_capacity_msats: u64,
     },
+    LiquiditySummary {
+        summary: LiquiditySummary,
+    },
+    ChannelsByPeer {
+        peers: Vec<PeerChannels>,
+    },
+    GetFeerates {
+        feerates: CurrentFeerates,
+    },
+    GetChainTip {
+        chain_tip: ChainTip,
+    },
     OpenChannels {
         requests: Vec<OpenChannelRequest>,
         results: Vec<OpenChannelResult>,
     },
+    OpenChannel {
+        request: OpenChannelRequest,
+        result: OpenChannelResult,
+    },
+    BuildBatchFunding {
+        psbt: String,
+        results: Vec<OpenChannelResult>,
+    },
+    FinalizeBatchFunding {
+        results: Vec<OpenChannelResult>,
+    },
+    ValidateChannelBatch {
+        validation: BatchValidation,
+    },
+    OpenChannelAndPay {
+        result: OpenChannelResult,
+        paid: bool,
+        payment_error: Option<String>,
+    },
     SendPayment {},
     DecodeInvoice {
         invoice: LocalInvoice,
     },
     Keysend {},
+    SendAlongRoute {},
+    RebalanceToTarget {
+        rebalance: RebalanceResult,
+    },
     GetInvoice {
         invoice: String,
     },
     LabelPayment {},
+    LabelChannel {},
+    SetPaymentMetadata {},
+    SetInvoiceMetadata {},
     DeletePayment {},
+    ResolveHeldPayment {},
     ConnectPeer {},
     ListChannels {
         channels: Vec<Channel>,
         pagination: PaginationResponse,
     },
+    ListPendingChannels {
+        channels: Vec<PendingChannel>,
+    },
+    ListInflightOpens {
+        opens: Vec<InflightOpen>,
+    },
+    CancelInflightOpen {},
+    GetStaleState {
+        channels: Vec<StaleChannel>,
+    },
+    AcknowledgeStaleState {
+        channels: Vec<StaleChannel>,
+    },
     ListPayments {
         payments: Vec<entity::payment::Model>,
         pagination: PaginationResponse,
     },
     ListTransactions {
-        transactions: Vec<TransactionDetails>,
+        transactions: Vec<LabeledTransaction>,
         pagination: PaginationResponse,
     },
     CloseChannel {},
+    CloseChannels {
+        results: Vec<CloseChannelResult>,
+    },
+    ChannelTimeline {
+        events: Vec<entity::channel_event::Model>,
+    },
+    GetChannelFundingInputs {
+        funding_inputs: Vec<entity::channel_funding_input::Model>,
+    },
+    GetChannelHtlcStats {
+        stats: ChannelHtlcStats,
+    },
+    SimulateForceClose {
+        simulation: ForceCloseSimulation,
+    },
+    FundingConfirmationEta {
+        eta: FundingConfirmationEta,
+    },
+    ChannelUptime {
+        uptime: ChannelUptime,
+    },
+    FeeRevenueRate {
+        revenue: FeeRevenue,
+    },
+    ExportChannelMonitor {
+        monitor: String,
+    },
+    ImportChannelMonitor {
+        funding_txo: String,
+    },
+    EstimateForwardFee {
+        estimate: ForwardFeeEstimate,
+    },
     NodeInfo {
         node_info: NodeInfo,
     },
@@ -317,12 +833,31 @@ pub enum NodeResponse {
         num_nodes: u64,
         num_known_edge_policies: u64,
     },
+    GetGraphNode {
+        node: Option<entity::graph_node::Model>,
+        channels: Vec<entity::graph_channel::Model>,
+    },
+    FindGraphPath {
+        path: Option<Vec<String>>,
+    },
+    GetGossip {
+        node_announcement: Option<GossipMessage>,
+        channels: Vec<ChannelGossip>,
+    },
+    SetScorerLiquidity {},
     ListKnownPeers {
         peers: Vec<entity::peer::Model>,
         pagination: PaginationResponse,
     },
     AddKnownPeer {},
     RemoveKnownPeer {},
+    ListSubscriptions {
+        subscriptions: Vec<entity::subscription::Model>,
+    },
+    CreateSubscription {
+        subscription: entity::subscription::Model,
+    },
+    DeleteSubscription {},
     Error(NodeRequestError),
 }
 
@@ -0,0 +1,66 @@
+use std::io;
+use std::net::SocketAddr;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+const SOCKS_VERSION: u8 = 5;
+const NO_AUTH: u8 = 0;
+const CMD_CONNECT: u8 = 1;
+const ATYP_IPV4: u8 = 1;
+const ATYP_DOMAIN: u8 = 3;
+const ATYP_IPV6: u8 = 4;
+
+fn proxy_error(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, message)
+}
+
+// Just enough SOCKS5 to reach onion services through a Tor proxy. The host is sent as a
+// domain name so the proxy resolves it, onion addresses never go through local DNS.
+pub async fn connect(proxy: SocketAddr, host: &str, port: u16) -> io::Result<TcpStream> {
+    if host.len() > u8::MAX as usize {
+        return Err(proxy_error(format!("host {} is too long for socks5", host)));
+    }
+
+    let mut stream = TcpStream::connect(proxy).await?;
+
+    stream.write_all(&[SOCKS_VERSION, 1, NO_AUTH]).await?;
+    let mut method = [0u8; 2];
+    stream.read_exact(&mut method).await?;
+    if method != [SOCKS_VERSION, NO_AUTH] {
+        return Err(proxy_error(String::from(
+            "proxy wants authentication, only unauthenticated socks5 is supported",
+        )));
+    }
+
+    let mut request = vec![SOCKS_VERSION, CMD_CONNECT, 0, ATYP_DOMAIN, host.len() as u8];
+    request.extend_from_slice(host.as_bytes());
+    request.extend_from_slice(&port.to_be_bytes());
+    stream.write_all(&request).await?;
+
+    let mut reply = [0u8; 4];
+    stream.read_exact(&mut reply).await?;
+    if reply[1] != 0 {
+        return Err(proxy_error(format!(
+            "proxy failed to connect to {}:{} with code {}",
+            host, port, reply[1]
+        )));
+    }
+
+    // the reply ends with the address the proxy bound to, which we have no use for
+    let addr_len = match reply[3] {
+        ATYP_IPV4 => 4,
+        ATYP_IPV6 => 16,
+        ATYP_DOMAIN => stream.read_u8().await? as usize,
+        atyp => {
+            return Err(proxy_error(format!(
+                "unknown address type {} from proxy",
+                atyp
+            )))
+        }
+    };
+    let mut bound_addr = vec![0u8; addr_len + 2];
+    stream.read_exact(&mut bound_addr).await?;
+
+    Ok(stream)
+}
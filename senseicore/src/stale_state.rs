@@ -0,0 +1,61 @@
+use std::{collections::HashMap, fs, io};
+
+use serde::{Deserialize, Serialize};
+
+// The latest monitor update id seen for every channel, keyed by funding outpoint. It lives
+// in the node's data dir instead of the database so that restoring an old database backup
+// doesn't roll it back along with the monitors it is checked against.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct MonitorCheckpoints {
+    pub update_ids: HashMap<String, u64>,
+}
+
+impl MonitorCheckpoints {
+    pub fn read(path: &str) -> io::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str(&contents)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn write(&self, path: &str) -> io::Result<()> {
+        let tmp_path = format!("{}.tmp", path);
+        fs::write(&tmp_path, serde_json::to_string(self)?)?;
+        fs::rename(tmp_path, path)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StaleChannel {
+    pub funding_txo: String,
+    pub checkpoint_update_id: u64,
+    // no monitor at all was loaded for the channel
+    pub monitor_update_id: Option<u64>,
+}
+
+// Channels whose loaded monitor is behind the checkpoint, or missing. Broadcasting from
+// one of those could put an old commitment on chain and hand the peer our whole balance.
+pub fn stale_channels(
+    checkpoints: &MonitorCheckpoints,
+    monitor_update_ids: &HashMap<String, u64>,
+) -> Vec<StaleChannel> {
+    let mut stale = checkpoints
+        .update_ids
+        .iter()
+        .filter_map(|(funding_txo, checkpoint_update_id)| {
+            let monitor_update_id = monitor_update_ids.get(funding_txo).copied();
+            match monitor_update_id {
+                Some(update_id) if update_id >= *checkpoint_update_id => None,
+                _ => Some(StaleChannel {
+                    funding_txo: funding_txo.clone(),
+                    checkpoint_update_id: *checkpoint_update_id,
+                    monitor_update_id,
+                }),
+            }
+        })
+        .collect::<Vec<_>>();
+    stale.sort_by(|a, b| a.funding_txo.cmp(&b.funding_txo));
+    stale
+}
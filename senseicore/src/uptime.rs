@@ -0,0 +1,44 @@
+use entity::peer_event;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Uptime {
+    pub connected_secs: u64,
+    pub disconnections: u32,
+}
+
+// Adds up how long a peer was connected between `start` and `end` from its connectivity
+// changes in that span, sorted oldest first. `connected_at_start` comes from the last
+// change before `start`. Without one the first change tells us, a peer that connects
+// wasn't connected before. With no changes at all the caller has to know.
+pub fn peer_uptime(
+    events: &[peer_event::Model],
+    connected_at_start: Option<bool>,
+    start: i64,
+    end: i64,
+) -> Uptime {
+    let mut connected = connected_at_start
+        .or_else(|| events.first().map(|event| !event.connected))
+        .unwrap_or(false);
+    let mut since = start;
+    let mut uptime = Uptime {
+        connected_secs: 0,
+        disconnections: 0,
+    };
+
+    for event in events {
+        let at = event.created_at.clamp(since, end);
+        if connected {
+            uptime.connected_secs += (at - since) as u64;
+            if !event.connected {
+                uptime.disconnections += 1;
+            }
+        }
+        connected = event.connected;
+        since = at;
+    }
+    if connected {
+        uptime.connected_secs += (end - since) as u64;
+    }
+
+    uptime
+}
@@ -0,0 +1,191 @@
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::setup_database;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    use bitcoin::{OutPoint, Script, Transaction, TxIn, Txid, Witness};
+    use lightning::chain::chaininterface::BroadcasterInterface;
+    use senseicore::chain::broadcaster::{
+        BroadcastContext, BroadcastPurpose, RelayRejections, SenseiBroadcaster, RELAY_REJECTION_TTL,
+    };
+    use senseicore::chain::database::WalletDatabase;
+    use senseicore::channels::reserved_funding_inputs;
+    use senseicore::events::SenseiEvent;
+    use tokio::runtime::Handle;
+    use tokio::sync::broadcast;
+
+    #[derive(Default)]
+    struct CountingBroadcaster {
+        broadcasts: AtomicUsize,
+    }
+
+    impl BroadcasterInterface for CountingBroadcaster {
+        fn broadcast_transaction(&self, _tx: &Transaction) {
+            self.broadcasts.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    async fn setup_broadcaster(
+        max_relay_rejections: u32,
+    ) -> (
+        SenseiBroadcaster,
+        Arc<CountingBroadcaster>,
+        broadcast::Receiver<SenseiEvent>,
+    ) {
+        let database = Arc::new(setup_database().await);
+        let wallet_database =
+            WalletDatabase::new(String::from("node"), database, Handle::current(), None);
+        let (event_sender, event_receiver) = broadcast::channel(16);
+        let backend = Arc::new(CountingBroadcaster::default());
+        let broadcaster = SenseiBroadcaster::new(
+            String::from("node"),
+            backend.clone(),
+            Arc::new(Mutex::new(wallet_database)),
+            event_sender,
+            None,
+            Arc::new(RelayRejections::default()),
+            max_relay_rejections,
+            Duration::from_secs(600),
+        );
+        (broadcaster, backend, event_receiver)
+    }
+
+    fn tx(lock_time: u32) -> Transaction {
+        Transaction {
+            version: 2,
+            lock_time,
+            input: vec![],
+            output: vec![],
+        }
+    }
+
+    fn funding_tx(lock_time: u32, inputs: &[OutPoint]) -> Transaction {
+        Transaction {
+            input: inputs
+                .iter()
+                .map(|outpoint| TxIn {
+                    previous_output: *outpoint,
+                    script_sig: Script::new(),
+                    sequence: 0xFFFFFFFF,
+                    witness: Witness::new(),
+                })
+                .collect(),
+            ..tx(lock_time)
+        }
+    }
+
+    fn outpoint(vout: u32) -> OutPoint {
+        OutPoint {
+            txid: Txid::default(),
+            vout,
+        }
+    }
+
+    #[tokio::test]
+    async fn abandons_funding_tx_missing_a_signature() {
+        let (broadcaster, backend, mut event_receiver) = setup_broadcaster(0).await;
+
+        let funding_tx = tx(0);
+        let txid = funding_tx.txid();
+
+        // two channels in the batch, only one of the peers signs
+        broadcaster.set_debounce(txid, 2, vec![]);
+        broadcaster.broadcast_transaction(&funding_tx);
+        assert!(broadcaster.expire_debounces(Instant::now()).is_empty());
+
+        let expired = broadcaster.expire_debounces(Instant::now() + Duration::from_secs(601));
+        assert_eq!(expired, vec![txid]);
+        match event_receiver.try_recv().unwrap() {
+            SenseiEvent::FundingBroadcastTimedOut {
+                txid: timed_out_txid,
+                unsigned_channels,
+                ..
+            } => {
+                assert_eq!(timed_out_txid, txid);
+                assert_eq!(unsigned_channels, 1);
+            }
+            _ => panic!("expected a FundingBroadcastTimedOut event"),
+        }
+
+        // the missing peer signing late can't get it broadcast either
+        broadcaster.broadcast_transaction(&funding_tx);
+        assert_eq!(backend.broadcasts.load(Ordering::SeqCst), 0);
+    }
+
+    // broadcasting goes through the wallet database, which blocks in place
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn only_gives_up_on_tagged_txs() {
+        let (broadcaster, backend, _event_receiver) = setup_broadcaster(2).await;
+
+        // untagged txs are LDK's claims, they're rebroadcast no matter what
+        let claim_tx = tx(1);
+        broadcaster.relay_rejections.record(claim_tx.txid());
+        broadcaster.relay_rejections.record(claim_tx.txid());
+        broadcaster.broadcast_transaction(&claim_tx);
+        assert_eq!(backend.broadcasts.load(Ordering::SeqCst), 1);
+
+        let sweep_tx = tx(2);
+        broadcaster.set_context(
+            sweep_tx.txid(),
+            BroadcastContext {
+                purpose: BroadcastPurpose::Sweep,
+                context: None,
+                feerate_sat_per_vb: None,
+            },
+        );
+        broadcaster.relay_rejections.record(sweep_tx.txid());
+        broadcaster.relay_rejections.record(sweep_tx.txid());
+        broadcaster.broadcast_transaction(&sweep_tx);
+        assert_eq!(backend.broadcasts.load(Ordering::SeqCst), 1);
+
+        // once it's accepted or its rejections expire it is broadcast again
+        broadcaster.relay_rejections.clear(&sweep_tx.txid());
+        broadcaster.broadcast_transaction(&sweep_tx);
+        assert_eq!(backend.broadcasts.load(Ordering::SeqCst), 2);
+
+        broadcaster.relay_rejections.record(sweep_tx.txid());
+        broadcaster.relay_rejections.record(sweep_tx.txid());
+        broadcaster.prune(Instant::now() + RELAY_REJECTION_TTL);
+        broadcaster.broadcast_transaction(&sweep_tx);
+        assert_eq!(backend.broadcasts.load(Ordering::SeqCst), 3);
+    }
+
+    // broadcasting goes through the wallet database, which blocks in place
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn reserves_funding_inputs_until_broadcast() {
+        let (broadcaster, backend, _event_receiver) = setup_broadcaster(0).await;
+        let pending_batch_fundings = Mutex::new(HashMap::new());
+
+        // the first open waits on its peer's signature while a second one is built
+        let first_tx = funding_tx(0, &[outpoint(0)]);
+        broadcaster.set_debounce(first_tx.txid(), 1, vec![outpoint(0)]);
+        assert_eq!(
+            reserved_funding_inputs(&broadcaster, &pending_batch_fundings),
+            vec![outpoint(0)]
+        );
+
+        let second_tx = funding_tx(1, &[outpoint(1)]);
+        broadcaster.set_debounce(second_tx.txid(), 1, vec![outpoint(1)]);
+        let mut reserved = reserved_funding_inputs(&broadcaster, &pending_batch_fundings);
+        reserved.sort();
+        assert_eq!(reserved, vec![outpoint(0), outpoint(1)]);
+
+        // once broadcast bdk knows its inputs are spent
+        broadcaster.broadcast_transaction(&first_tx);
+        assert_eq!(backend.broadcasts.load(Ordering::SeqCst), 1);
+        assert_eq!(
+            reserved_funding_inputs(&broadcaster, &pending_batch_fundings),
+            vec![outpoint(1)]
+        );
+
+        // an abandoned one never spends them
+        let expired = broadcaster.expire_debounces(Instant::now() + Duration::from_secs(601));
+        assert_eq!(expired, vec![second_tx.txid()]);
+        assert!(reserved_funding_inputs(&broadcaster, &pending_batch_fundings).is_empty());
+    }
+}
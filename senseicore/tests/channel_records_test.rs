@@ -0,0 +1,100 @@
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::setup_database;
+    use senseicore::database::SenseiDatabase;
+    use senseicore::error::Error;
+
+    async fn funded_channel_database() -> SenseiDatabase {
+        let database = setup_database().await;
+        database
+            .create_channel("node", 7, "02aaaa", "temp", 100_000)
+            .await
+            .unwrap();
+        database
+            .update_channel_funding("node", 7, "chan", "txid")
+            .await
+            .unwrap();
+        database
+    }
+
+    #[tokio::test]
+    async fn short_channel_id_is_only_set_once() {
+        let database = funded_channel_database().await;
+        database
+            .set_channel_short_channel_id("node", "chan", 42)
+            .await
+            .unwrap();
+        database
+            .set_channel_short_channel_id("node", "chan", 43)
+            .await
+            .unwrap();
+
+        let channel = database.find_channel("node", 7).await.unwrap().unwrap();
+        assert_eq!(channel.channel_id, "chan");
+        assert_eq!(channel.funding_txid, Some(String::from("txid")));
+        assert_eq!(channel.short_channel_id, Some(42));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn labels_are_listed_by_channel_id() {
+        let database = funded_channel_database().await;
+        assert!(matches!(
+            database
+                .label_channel("node", "unknown", String::from("hub"))
+                .await,
+            Err(Error::ChannelNotFound)
+        ));
+        database
+            .label_channel("node", "chan", String::from("hub"))
+            .await
+            .unwrap();
+
+        let labels = database.list_channel_labels_sync("node").unwrap();
+        assert_eq!(labels.get("chan"), Some(&String::from("hub")));
+        assert!(database
+            .list_channel_labels_sync("other-node")
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn latest_channel_event_is_per_kind() {
+        let database = setup_database().await;
+        database
+            .record_channel_event(
+                "node",
+                "chan",
+                "policy_updated",
+                "peer",
+                Some(String::from("base fee 1000 msat, 1 ppm, cltv delta 40")),
+            )
+            .await
+            .unwrap();
+        database
+            .record_channel_event("node", "chan", "channel_ready", "ldk", None)
+            .await
+            .unwrap();
+
+        let policy = database
+            .latest_channel_event("node", "chan", "policy_updated")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            policy.detail,
+            Some(String::from("base fee 1000 msat, 1 ppm, cltv delta 40"))
+        );
+        assert!(database
+            .latest_channel_event("node", "chan", "opened")
+            .await
+            .unwrap()
+            .is_none());
+        assert!(database
+            .latest_channel_event("other-node", "chan", "channel_ready")
+            .await
+            .unwrap()
+            .is_none());
+    }
+}
@@ -0,0 +1,409 @@
+#[cfg(test)]
+mod test {
+    use bdk::database::{BatchOperations, MemoryDatabase};
+    use bdk::template::Bip84;
+    use bdk::{KeychainKind, LocalUtxo};
+    use bitcoin::secp256k1::{PublicKey, Secp256k1, SecretKey};
+    use bitcoin::util::bip32::ExtendedPrivKey;
+    use bitcoin::{Network, OutPoint, Script, TxOut, Txid};
+    use senseicore::channels::{
+        check_wallet_funds, estimate_wallet_funds, funding_filter, initiate_concurrently,
+        wait_for_events, wait_for_events_with, EventFilter, PendingOpens,
+    };
+    use senseicore::error::Error;
+    use senseicore::events::SenseiEvent;
+    use senseicore::services::node::OpenChannelRequest;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::sync::broadcast;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn sees_every_event_from_concurrent_opens() {
+        let (event_sender, mut event_receiver): (
+            broadcast::Sender<SenseiEvent>,
+            broadcast::Receiver<SenseiEvent>,
+        ) = broadcast::channel(256);
+        let counterparty_node_id = PublicKey::from_secret_key(
+            &Secp256k1::new(),
+            &SecretKey::from_slice(&[1; 32]).unwrap(),
+        );
+        let requests = (1..=32u64)
+            .map(|user_channel_id| OpenChannelRequest {
+                custom_id: Some(user_channel_id),
+                ..open_request(100_000)
+            })
+            .collect::<Vec<_>>();
+
+        // every open reports back whenever it finishes, before its filter is registered
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let max_in_flight = Arc::new(AtomicUsize::new(0));
+        let initiated = initiate_concurrently(requests, 4, |request| {
+            let event_sender = event_sender.clone();
+            let in_flight = in_flight.clone();
+            let max_in_flight = max_in_flight.clone();
+            async move {
+                let user_channel_id = request.custom_id.unwrap();
+                let running = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                max_in_flight.fetch_max(running, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis((user_channel_id * 7) % 50)).await;
+                event_sender
+                    .send(SenseiEvent::FundingGenerationReady {
+                        node_id: String::from("node"),
+                        temporary_channel_id: [user_channel_id as u8; 32],
+                        channel_value_satoshis: 100_000,
+                        output_script: Script::new(),
+                        user_channel_id,
+                        counterparty_node_id,
+                    })
+                    .unwrap();
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                (request, Ok([user_channel_id as u8; 32]))
+            }
+        })
+        .await;
+
+        assert_eq!(max_in_flight.load(Ordering::SeqCst), 4);
+        let initiated_ids = initiated
+            .iter()
+            .map(|(request, result)| {
+                assert!(result.is_ok());
+                request.custom_id.unwrap()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(initiated_ids, (1..=32u64).collect::<Vec<_>>());
+
+        let filters = initiated_ids
+            .into_iter()
+            .map(|user_channel_id| funding_filter(String::from("node"), user_channel_id))
+            .collect::<Vec<_>>();
+        let (events, skipped) = wait_for_events(&mut event_receiver, filters, 1000, 10).await;
+        assert_eq!(skipped, 0);
+
+        let mut user_channel_ids = events
+            .iter()
+            .filter_map(|event| match event {
+                SenseiEvent::FundingGenerationReady {
+                    user_channel_id, ..
+                } => Some(*user_channel_id),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        user_channel_ids.sort_unstable();
+        assert_eq!(user_channel_ids, (1..=32u64).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn initiates_one_at_a_time_with_zero_concurrency() {
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let max_in_flight = Arc::new(AtomicUsize::new(0));
+        let requests = (1..=4u64)
+            .map(|user_channel_id| OpenChannelRequest {
+                custom_id: Some(user_channel_id),
+                ..open_request(100_000)
+            })
+            .collect::<Vec<_>>();
+
+        let initiated = initiate_concurrently(requests, 0, |request| {
+            let in_flight = in_flight.clone();
+            let max_in_flight = max_in_flight.clone();
+            async move {
+                let running = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                max_in_flight.fetch_max(running, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(5)).await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                let result = match request.custom_id {
+                    Some(2) => Err(Error::Generic(String::from("peer rejected"))),
+                    _ => Ok([0; 32]),
+                };
+                (request, result)
+            }
+        })
+        .await;
+
+        assert_eq!(max_in_flight.load(Ordering::SeqCst), 1);
+        assert_eq!(initiated.len(), 4);
+        assert!(initiated[1].1.is_err());
+        assert!(initiated
+            .iter()
+            .enumerate()
+            .all(|(index, (request, _))| request.custom_id == Some(index as u64 + 1)));
+    }
+
+    #[tokio::test]
+    async fn waits_on_different_kinds_of_events() {
+        let (event_sender, mut event_receiver): (
+            broadcast::Sender<SenseiEvent>,
+            broadcast::Receiver<SenseiEvent>,
+        ) = broadcast::channel(16);
+        let counterparty_node_id = PublicKey::from_secret_key(
+            &Secp256k1::new(),
+            &SecretKey::from_slice(&[1; 32]).unwrap(),
+        );
+
+        event_sender
+            .send(SenseiEvent::EventLoopRestarted {
+                node_id: String::from("node"),
+                reason: String::from("test"),
+            })
+            .unwrap();
+        event_sender
+            .send(SenseiEvent::FundingGenerationReady {
+                node_id: String::from("node"),
+                temporary_channel_id: [1; 32],
+                channel_value_satoshis: 100_000,
+                output_script: Script::new(),
+                user_channel_id: 1,
+                counterparty_node_id,
+            })
+            .unwrap();
+
+        let filters = vec![
+            EventFilter {
+                f: Box::new(|event| matches!(event, SenseiEvent::FundingGenerationReady { .. })),
+            },
+            EventFilter {
+                f: Box::new(|event| matches!(event, SenseiEvent::EventLoopRestarted { .. })),
+            },
+        ];
+
+        let (events, _skipped) = wait_for_events(&mut event_receiver, filters, 100, 10).await;
+
+        assert_eq!(events.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn times_out_with_a_zero_poll_interval() {
+        let (_event_sender, mut event_receiver): (
+            broadcast::Sender<SenseiEvent>,
+            broadcast::Receiver<SenseiEvent>,
+        ) = broadcast::channel(16);
+
+        let filters = vec![EventFilter {
+            f: Box::new(|event| matches!(event, SenseiEvent::EventLoopRestarted { .. })),
+        }];
+
+        let (events, _skipped) = tokio::time::timeout(
+            std::time::Duration::from_secs(5),
+            wait_for_events(&mut event_receiver, filters, 50, 0),
+        )
+        .await
+        .expect("a zero poll interval should still time out");
+        assert!(events.is_empty());
+    }
+
+    #[tokio::test]
+    async fn reports_events_skipped_by_a_lagging_receiver() {
+        let (event_sender, mut event_receiver): (
+            broadcast::Sender<SenseiEvent>,
+            broadcast::Receiver<SenseiEvent>,
+        ) = broadcast::channel(2);
+
+        // the event we wait for is pushed out of the channel by the ones after it
+        for restart in 0..4 {
+            event_sender
+                .send(SenseiEvent::EventLoopRestarted {
+                    node_id: String::from("node"),
+                    reason: restart.to_string(),
+                })
+                .unwrap();
+        }
+
+        let filters = vec![EventFilter {
+            f: Box::new(
+                |event| matches!(event, SenseiEvent::EventLoopRestarted { reason, .. } if reason == "0"),
+            ),
+        }];
+
+        let (events, skipped) = wait_for_events(&mut event_receiver, filters, 50, 10).await;
+
+        assert!(events.is_empty());
+        assert_eq!(skipped, 2);
+    }
+
+    #[test]
+    fn empty_wallet_has_no_spendable_funds() {
+        let xprv = ExtendedPrivKey::new_master(Network::Regtest, &[2; 32]).unwrap();
+        let wallet = bdk::Wallet::new(
+            Bip84(xprv, KeychainKind::External),
+            Some(Bip84(xprv, KeychainKind::Internal)),
+            Network::Regtest,
+            MemoryDatabase::new(),
+        )
+        .unwrap();
+        let request = OpenChannelRequest {
+            counterparty_pubkey: String::from(
+                "02eec7245d6b7d2ccb30380bfbe2a3648cd7a942653f5aa340edcea1f283686619",
+            ),
+            amount_sats: 100_000,
+            public: true,
+            custom_id: None,
+            push_amount_msats: None,
+            counterparty_host_port: None,
+            forwarding_fee_proportional_millionths: None,
+            forwarding_fee_base_msat: None,
+            cltv_expiry_delta: None,
+            max_dust_htlc_exposure_msat: None,
+            force_close_avoidance_max_fee_satoshis: None,
+        };
+
+        let result = check_wallet_funds(&wallet, &[request], 1.0, &[]);
+
+        assert!(matches!(result, Err(Error::NoSpendableFunds(0))));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn reports_matches_as_they_arrive() {
+        let (event_sender, mut event_receiver) = broadcast::channel(16);
+        let funding_ready = |user_channel_id: u64| SenseiEvent::FundingGenerationReady {
+            node_id: String::from("node"),
+            temporary_channel_id: [user_channel_id as u8; 32],
+            channel_value_satoshis: 100_000,
+            output_script: Script::new(),
+            user_channel_id,
+            counterparty_node_id: PublicKey::from_secret_key(
+                &Secp256k1::new(),
+                &SecretKey::from_slice(&[1; 32]).unwrap(),
+            ),
+        };
+        let filter = |request_user_channel_id: u64| EventFilter {
+            f: Box::new(move |event| {
+                matches!(
+                    event,
+                    SenseiEvent::FundingGenerationReady { user_channel_id, .. }
+                        if *user_channel_id == request_user_channel_id
+                )
+            }),
+        };
+
+        // the second event is only sent once the first one was reported, waiting for the
+        // end to report anything would time out on it
+        let (matched_sender, mut matched_receiver) = tokio::sync::mpsc::unbounded_channel();
+        event_sender.send(funding_ready(1)).unwrap();
+        let second = tokio::spawn(async move {
+            matched_receiver.recv().await.unwrap();
+            event_sender.send(funding_ready(2)).unwrap();
+        });
+
+        let (events, skipped) = wait_for_events_with(
+            &mut event_receiver,
+            vec![filter(1), filter(2)],
+            2000,
+            10,
+            |_event| matched_sender.send(()).unwrap_or_default(),
+        )
+        .await;
+        second.await.unwrap();
+
+        assert_eq!(skipped, 0);
+        assert_eq!(events.len(), 2);
+    }
+
+    #[test]
+    fn concurrent_opens_count_each_other() {
+        let pending_opens = PendingOpens::default();
+        let first = pending_opens.reserve(Some(3), &[1, 2], Vec::new).unwrap();
+
+        // the first batch isn't in the ChannelManager yet, its slots are still taken
+        let res = pending_opens.reserve(Some(3), &[3, 4], Vec::new);
+        assert!(matches!(res, Err(Error::TooManyPendingChannels(3))));
+        assert!(matches!(
+            pending_opens.check(Some(3), 2, Vec::new),
+            Err(Error::TooManyPendingChannels(3))
+        ));
+
+        // a channel that is reserved and in the ChannelManager is counted once
+        let second = pending_opens.reserve(Some(3), &[3], || vec![1, 2]).unwrap();
+        drop(first);
+        drop(second);
+        assert!(pending_opens.check(Some(3), 3, Vec::new).is_ok());
+        assert!(matches!(
+            pending_opens.check(Some(3), 2, || vec![1, 2]),
+            Err(Error::TooManyPendingChannels(3))
+        ));
+    }
+
+    #[test]
+    fn estimates_largest_utxos_first_without_excluded_ones() {
+        let xprv = ExtendedPrivKey::new_master(Network::Regtest, &[2; 32]).unwrap();
+        let mut database = MemoryDatabase::new();
+        let outpoints = (0..3)
+            .map(|vout| OutPoint::new(Txid::default(), vout))
+            .collect::<Vec<_>>();
+        for (outpoint, value) in outpoints.iter().zip([50_000, 30_000, 20_000]) {
+            database
+                .set_utxo(&LocalUtxo {
+                    outpoint: *outpoint,
+                    txout: TxOut {
+                        value,
+                        script_pubkey: Script::new(),
+                    },
+                    keychain: KeychainKind::External,
+                    is_spent: false,
+                })
+                .unwrap();
+        }
+        let wallet = bdk::Wallet::new(
+            Bip84(xprv, KeychainKind::External),
+            Some(Bip84(xprv, KeychainKind::Internal)),
+            Network::Regtest,
+            database,
+        )
+        .unwrap();
+        let request = OpenChannelRequest {
+            counterparty_pubkey: String::from(
+                "02eec7245d6b7d2ccb30380bfbe2a3648cd7a942653f5aa340edcea1f283686619",
+            ),
+            amount_sats: 40_000,
+            public: true,
+            custom_id: None,
+            push_amount_msats: None,
+            counterparty_host_port: None,
+            forwarding_fee_proportional_millionths: None,
+            forwarding_fee_base_msat: None,
+            cltv_expiry_delta: None,
+            max_dust_htlc_exposure_msat: None,
+            force_close_avoidance_max_fee_satoshis: None,
+        };
+
+        // the 50k utxo covers it alone: overhead, one input, one channel and change
+        let estimate =
+            estimate_wallet_funds(&wallet, std::slice::from_ref(&request), 1.0, &[]).unwrap();
+        assert_eq!(estimate.balance_sats, 100_000);
+        assert_eq!(estimate.fee_sats, 11 + 68 + 43 + 31);
+
+        // without it, the 30k and 20k utxos are both needed
+        let estimate = estimate_wallet_funds(
+            &wallet,
+            std::slice::from_ref(&request),
+            1.0,
+            &outpoints[..1],
+        )
+        .unwrap();
+        assert_eq!(estimate.batch_sats, 40_000);
+        assert_eq!(estimate.balance_sats, 50_000);
+        assert_eq!(estimate.fee_sats, 11 + 2 * 68 + 43 + 31);
+        assert!(estimate.check().is_ok());
+
+        let result = check_wallet_funds(&wallet, &[request], 1.0, &outpoints[..2]);
+        assert!(matches!(result, Err(Error::InsufficientFunds(_, 20_000))));
+    }
+
+    fn open_request(amount_sats: u64) -> OpenChannelRequest {
+        OpenChannelRequest {
+            counterparty_pubkey: String::from(
+                "02eec7245d6b7d2ccb30380bfbe2a3648cd7a942653f5aa340edcea1f283686619",
+            ),
+            amount_sats,
+            public: true,
+            custom_id: None,
+            push_amount_msats: None,
+            counterparty_host_port: None,
+            forwarding_fee_proportional_millionths: None,
+            forwarding_fee_base_msat: None,
+            cltv_expiry_delta: None,
+            max_dust_htlc_exposure_msat: None,
+            force_close_avoidance_max_fee_satoshis: None,
+        }
+    }
+}
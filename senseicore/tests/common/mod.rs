@@ -0,0 +1,16 @@
+use entity::sea_orm::{ConnectOptions, Database};
+use migration::{Migrator, MigratorTrait};
+use senseicore::database::SenseiDatabase;
+use tokio::runtime::Handle;
+
+// A migrated in-memory database. Every connection to sqlite::memory: gets its own database,
+// so only one is kept around.
+pub async fn setup_database() -> SenseiDatabase {
+    let mut db_connection_options = ConnectOptions::new(String::from("sqlite::memory:"));
+    db_connection_options.max_connections(1).min_connections(1);
+    let db_connection = Database::connect(db_connection_options).await.unwrap();
+    Migrator::up(&db_connection, None)
+        .await
+        .expect("failed to run migrations");
+    SenseiDatabase::new(db_connection, Handle::current())
+}
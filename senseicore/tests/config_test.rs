@@ -0,0 +1,80 @@
+#[cfg(test)]
+mod test {
+    use bitcoin::secp256k1::{PublicKey, Secp256k1, SecretKey};
+    use senseicore::config::SenseiConfig;
+
+    fn pubkey(byte: u8) -> PublicKey {
+        let secp = Secp256k1::new();
+        PublicKey::from_secret_key(&secp, &SecretKey::from_slice(&[byte; 32]).unwrap())
+    }
+
+    #[test]
+    fn allows_every_peer_when_allowlist_disabled() {
+        let config = SenseiConfig::default();
+        assert!(config.peer_allowed(&pubkey(1)));
+    }
+
+    #[test]
+    fn allows_only_listed_peers_when_enabled() {
+        let config = SenseiConfig {
+            peer_allowlist_enabled: true,
+            peer_allowlist: vec![pubkey(1).to_string()],
+            ..Default::default()
+        };
+        assert!(config.peer_allowed(&pubkey(1)));
+        assert!(!config.peer_allowed(&pubkey(2)));
+    }
+
+    #[test]
+    fn empty_allowlist_rejects_everyone() {
+        let config = SenseiConfig {
+            peer_allowlist_enabled: true,
+            ..Default::default()
+        };
+        assert!(!config.peer_allowed(&pubkey(1)));
+    }
+
+    #[test]
+    fn rejects_a_tor_proxy_that_isnt_a_socket_address() {
+        let config = SenseiConfig {
+            tor_proxy: Some(String::from("localhost:9050")),
+            ..Default::default()
+        };
+        assert!(config.tor_proxy_addr().is_err());
+
+        let config = SenseiConfig {
+            tor_proxy: Some(String::from("127.0.0.1:9050")),
+            ..Default::default()
+        };
+        assert_eq!(
+            config.tor_proxy_addr().unwrap(),
+            Some("127.0.0.1:9050".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn formats_ipv6_bind_addresses_with_brackets() {
+        for p2p_bind_address in ["::1", "[::1]"] {
+            let config = SenseiConfig {
+                p2p_bind_address: String::from(p2p_bind_address),
+                ..Default::default()
+            };
+            assert_eq!(
+                config.p2p_bind_addr(9735).unwrap().to_string(),
+                "[::1]:9735"
+            );
+        }
+
+        let config = SenseiConfig::default();
+        assert_eq!(
+            config.p2p_bind_addr(9735).unwrap().to_string(),
+            "0.0.0.0:9735"
+        );
+
+        let config = SenseiConfig {
+            p2p_bind_address: String::from("localhost"),
+            ..Default::default()
+        };
+        assert!(config.p2p_bind_addr(9735).is_err());
+    }
+}
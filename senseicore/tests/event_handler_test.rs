@@ -0,0 +1,154 @@
+#[cfg(test)]
+mod test {
+    use lightning::ln::channelmanager::PaymentId;
+    use lightning::ln::PaymentHash;
+    use lightning::util::events::{Event, EventHandler};
+    use senseicore::event_handler::{inbound_zero_conf, SupervisedEventHandler};
+    use senseicore::events::SenseiEvent;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::sync::broadcast;
+
+    struct PanicOnceHandler {
+        calls: Arc<AtomicUsize>,
+    }
+
+    impl EventHandler for PanicOnceHandler {
+        fn handle_event(&self, _event: &Event) {
+            if self.calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                panic!("injected handler panic");
+            }
+        }
+    }
+
+    #[test]
+    fn recovers_from_handler_panic() {
+        let (event_sender, mut event_receiver): (
+            broadcast::Sender<SenseiEvent>,
+            broadcast::Receiver<SenseiEvent>,
+        ) = broadcast::channel(16);
+        let calls = Arc::new(AtomicUsize::new(0));
+        let handler = SupervisedEventHandler::new(
+            String::from("node"),
+            PanicOnceHandler {
+                calls: calls.clone(),
+            },
+            event_sender,
+        );
+
+        let event = Event::PendingHTLCsForwardable {
+            time_forwardable: Duration::from_millis(100),
+        };
+        handler.handle_event(&event);
+
+        // the event that panicked is retried
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        match event_receiver.try_recv().unwrap() {
+            SenseiEvent::EventLoopRestarted { node_id, reason } => {
+                assert_eq!(node_id, "node");
+                assert_eq!(reason, "injected handler panic");
+            }
+            _ => panic!("expected an EventLoopRestarted event"),
+        }
+        assert!(event_receiver.try_recv().is_err());
+    }
+
+    struct AlwaysPanicHandler {
+        calls: Arc<AtomicUsize>,
+    }
+
+    impl EventHandler for AlwaysPanicHandler {
+        fn handle_event(&self, _event: &Event) {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            panic!("injected handler panic");
+        }
+    }
+
+    #[test]
+    fn halts_when_the_event_keeps_panicking() {
+        let (event_sender, _event_receiver) = broadcast::channel(16);
+        let calls = Arc::new(AtomicUsize::new(0));
+        let handler = SupervisedEventHandler::new(
+            String::from("node"),
+            AlwaysPanicHandler {
+                calls: calls.clone(),
+            },
+            event_sender,
+        );
+
+        let event = Event::PendingHTLCsForwardable {
+            time_forwardable: Duration::from_millis(100),
+        };
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            handler.handle_event(&event)
+        }));
+
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn halts_without_retrying_events_with_side_effects() {
+        let (event_sender, mut event_receiver) = broadcast::channel(16);
+        let calls = Arc::new(AtomicUsize::new(0));
+        let handler = SupervisedEventHandler::new(
+            String::from("node"),
+            PanicOnceHandler {
+                calls: calls.clone(),
+            },
+            event_sender,
+        );
+
+        let event = Event::PaymentFailed {
+            payment_id: PaymentId([1; 32]),
+            payment_hash: PaymentHash([2; 32]),
+        };
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            handler.handle_event(&event)
+        }));
+
+        // a retry could record the failure twice
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert!(matches!(
+            event_receiver.try_recv(),
+            Ok(SenseiEvent::EventLoopRestarted { .. })
+        ));
+    }
+
+    #[test]
+    fn leaves_wallet_events_uncaught() {
+        let (event_sender, mut event_receiver) = broadcast::channel(16);
+        let calls = Arc::new(AtomicUsize::new(0));
+        let handler = SupervisedEventHandler::new(
+            String::from("node"),
+            PanicOnceHandler {
+                calls: calls.clone(),
+            },
+            event_sender,
+        );
+
+        let event = Event::SpendableOutputs { outputs: vec![] };
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            handler.handle_event(&event)
+        }));
+
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert!(event_receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn rejects_zero_conf_request_from_untrusted_peer() {
+        assert!(inbound_zero_conf(true, false).is_err());
+        assert_eq!(inbound_zero_conf(false, false), Ok(false));
+    }
+
+    #[test]
+    fn accepts_zero_conf_from_trusted_peer() {
+        assert_eq!(inbound_zero_conf(true, true), Ok(true));
+        assert_eq!(inbound_zero_conf(false, true), Ok(true));
+    }
+}
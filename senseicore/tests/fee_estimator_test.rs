@@ -0,0 +1,71 @@
+#[cfg(test)]
+mod test {
+    use lightning::chain::chaininterface::{ConfirmationTarget, FeeEstimator};
+    use senseicore::chain::fee_estimator::{
+        confirmation_eta_blocks, is_peer_feerate_rejection, sat_per_vb, MIN_FEERATE,
+    };
+
+    struct FixedFeeEstimator;
+
+    impl FeeEstimator for FixedFeeEstimator {
+        fn get_est_sat_per_1000_weight(&self, confirmation_target: ConfirmationTarget) -> u32 {
+            match confirmation_target {
+                ConfirmationTarget::Background => 500,
+                ConfirmationTarget::Normal => 2_000,
+                ConfirmationTarget::HighPriority => 5_000,
+            }
+        }
+    }
+
+    #[test]
+    fn ldk_floor_is_one_sat_per_vb() {
+        assert_eq!(sat_per_vb(MIN_FEERATE), 1.0);
+        assert_eq!(sat_per_vb(250), 1.0);
+        assert_eq!(sat_per_vb(0), 1.0);
+    }
+
+    #[test]
+    fn converts_typical_feerates() {
+        assert_eq!(sat_per_vb(254), 1.016);
+        assert_eq!(sat_per_vb(500), 2.0);
+        assert_eq!(sat_per_vb(2_500), 10.0);
+        assert_eq!(sat_per_vb(6_250), 25.0);
+    }
+
+    #[test]
+    fn converts_high_feerates() {
+        assert_eq!(sat_per_vb(125_000), 500.0);
+        assert_eq!(sat_per_vb(250_000), 1000.0);
+    }
+
+    #[test]
+    fn eta_follows_the_highest_target_met() {
+        assert_eq!(confirmation_eta_blocks(5_000, &FixedFeeEstimator), Some(6));
+        assert_eq!(confirmation_eta_blocks(10_000, &FixedFeeEstimator), Some(6));
+        assert_eq!(confirmation_eta_blocks(4_999, &FixedFeeEstimator), Some(18));
+        assert_eq!(confirmation_eta_blocks(2_000, &FixedFeeEstimator), Some(18));
+        assert_eq!(confirmation_eta_blocks(500, &FixedFeeEstimator), Some(144));
+    }
+
+    #[test]
+    fn eta_below_background_is_unknown() {
+        assert_eq!(confirmation_eta_blocks(499, &FixedFeeEstimator), None);
+        assert_eq!(
+            confirmation_eta_blocks(MIN_FEERATE, &FixedFeeEstimator),
+            None
+        );
+    }
+
+    #[test]
+    fn recognizes_peer_feerate_rejections() {
+        assert!(is_peer_feerate_rejection(
+            "Peer's feerate much too low. Actual: 253. Our expected lower limit: 1000 (- 250)"
+        ));
+        assert!(is_peer_feerate_rejection(
+            "Peer's feerate much too high. Actual: 90000. Our expected upper limit: 20000"
+        ));
+        assert!(!is_peer_feerate_rejection(
+            "Funding tx feerate below the minimum"
+        ));
+    }
+}
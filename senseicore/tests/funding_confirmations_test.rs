@@ -0,0 +1,38 @@
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::setup_database;
+
+    #[tokio::test]
+    async fn announced_funding_txs_are_kept_per_node() {
+        let database = setup_database().await;
+        assert!(database
+            .list_funding_confirmed_announced("node")
+            .await
+            .unwrap()
+            .is_empty());
+
+        database
+            .set_funding_confirmed_announced("node", "txid1")
+            .await
+            .unwrap();
+        // announcing twice doesn't add another row
+        database
+            .set_funding_confirmed_announced("node", "txid1")
+            .await
+            .unwrap();
+        database
+            .set_funding_confirmed_announced("other", "txid2")
+            .await
+            .unwrap();
+
+        assert_eq!(
+            database
+                .list_funding_confirmed_announced("node")
+                .await
+                .unwrap(),
+            vec![String::from("txid1")]
+        );
+    }
+}
@@ -0,0 +1,49 @@
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::setup_database;
+    use entity::forward;
+    use entity::sea_orm::{ActiveModelTrait, ActiveValue};
+
+    #[tokio::test]
+    async fn pages_forwards_oldest_first() {
+        let database = setup_database().await;
+
+        for (node_id, created_at) in [
+            ("node", 30),
+            ("node", 10),
+            ("other", 15),
+            ("node", 20),
+            ("node", 500),
+        ] {
+            forward::ActiveModel {
+                created_at: ActiveValue::Set(created_at),
+                node_id: ActiveValue::Set(node_id.to_string()),
+                prev_channel_id: ActiveValue::Set(None),
+                next_channel_id: ActiveValue::Set(None),
+                fee_earned_msat: ActiveValue::Set(Some(1000)),
+                claim_from_onchain_tx: ActiveValue::Set(false),
+                ..Default::default()
+            }
+            .insert(database.get_connection())
+            .await
+            .unwrap();
+        }
+
+        let mut pages = vec![];
+        for page in 0..3 {
+            let forwards = database
+                .list_forwards_page_in_range("node", 0, 100, page, 2)
+                .await
+                .unwrap();
+            pages.push(
+                forwards
+                    .into_iter()
+                    .map(|forward| forward.created_at)
+                    .collect::<Vec<_>>(),
+            );
+        }
+        assert_eq!(pages, vec![vec![10, 20], vec![30], vec![]]);
+    }
+}
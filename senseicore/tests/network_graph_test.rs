@@ -0,0 +1,108 @@
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::setup_database;
+    use entity::{graph_channel, graph_node};
+    use senseicore::network_graph::shortest_path;
+
+    fn channel(short_channel_id: i64, node_one: &str, node_two: &str) -> graph_channel::Model {
+        graph_channel::Model {
+            id: short_channel_id.to_string(),
+            created_at: 0,
+            updated_at: 0,
+            short_channel_id,
+            node_one: node_one.to_string(),
+            node_two: node_two.to_string(),
+            capacity_sats: None,
+            one_to_two_fee_base_msat: None,
+            one_to_two_fee_proportional_millionths: None,
+            two_to_one_fee_base_msat: None,
+            two_to_one_fee_proportional_millionths: None,
+        }
+    }
+
+    #[test]
+    fn finds_fewest_hops() {
+        let channels = vec![
+            channel(1, "a", "b"),
+            channel(2, "b", "c"),
+            channel(3, "c", "d"),
+            channel(4, "b", "d"),
+        ];
+        assert_eq!(
+            shortest_path(&channels, "a", "d"),
+            Some(vec!["a".to_string(), "b".to_string(), "d".to_string()])
+        );
+    }
+
+    #[test]
+    fn channels_go_both_ways() {
+        let channels = vec![channel(1, "b", "a"), channel(2, "c", "b")];
+        assert_eq!(
+            shortest_path(&channels, "a", "c"),
+            Some(vec!["a".to_string(), "b".to_string(), "c".to_string()])
+        );
+    }
+
+    #[test]
+    fn no_path_between_disconnected_nodes() {
+        let channels = vec![channel(1, "a", "b"), channel(2, "c", "d")];
+        assert_eq!(shortest_path(&channels, "a", "d"), None);
+        assert_eq!(shortest_path(&channels, "a", "e"), None);
+    }
+
+    #[test]
+    fn path_to_self_is_just_the_node() {
+        assert_eq!(shortest_path(&[], "a", "a"), Some(vec!["a".to_string()]));
+    }
+
+    fn node(pubkey: &str, alias: &str) -> graph_node::Model {
+        graph_node::Model {
+            id: String::new(),
+            created_at: 0,
+            updated_at: 0,
+            pubkey: pubkey.to_string(),
+            alias: Some(alias.to_string()),
+            last_update: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn snapshots_only_write_what_changed() {
+        let database = setup_database().await;
+
+        database
+            .replace_network_graph(
+                vec![node("a", "alice"), node("b", "bob"), node("c", "carol")],
+                vec![channel(1, "a", "b"), channel(2, "b", "c")],
+            )
+            .await
+            .unwrap();
+        let alice = database.find_graph_node("a").await.unwrap().unwrap();
+        let bob = database.find_graph_node("b").await.unwrap().unwrap();
+        let first_channel = database.list_graph_channels("a").await.unwrap()[0].clone();
+
+        let mut updated_channel = channel(1, "a", "b");
+        updated_channel.capacity_sats = Some(100_000);
+        database
+            .replace_network_graph(
+                vec![node("a", "alice"), node("b", "bobby")],
+                vec![updated_channel],
+            )
+            .await
+            .unwrap();
+
+        // unchanged rows are left as they were, changed ones keep their id
+        assert_eq!(database.find_graph_node("a").await.unwrap(), Some(alice));
+        let renamed = database.find_graph_node("b").await.unwrap().unwrap();
+        assert_eq!(renamed.id, bob.id);
+        assert_eq!(renamed.alias, Some(String::from("bobby")));
+        assert_eq!(database.find_graph_node("c").await.unwrap(), None);
+
+        let channels = database.list_all_graph_channels().await.unwrap();
+        assert_eq!(channels.len(), 1);
+        assert_eq!(channels[0].id, first_channel.id);
+        assert_eq!(channels[0].capacity_sats, Some(100_000));
+    }
+}
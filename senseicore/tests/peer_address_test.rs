@@ -0,0 +1,67 @@
+#[cfg(test)]
+mod test {
+    use senseicore::config::AddressFamily;
+    use senseicore::node::PeerAddress;
+    use std::net::SocketAddr;
+
+    fn addrs(addrs: &[&str]) -> Vec<SocketAddr> {
+        addrs.iter().map(|addr| addr.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn keeps_resolver_order_without_a_preference() {
+        let resolved = addrs(&["[2001:db8::1]:9735", "10.0.0.1:9735", "[2001:db8::2]:9735"]);
+        let mut peer_addr = PeerAddress::Clearnet(resolved.clone());
+        peer_addr.prefer(None);
+        assert_eq!(peer_addr, PeerAddress::Clearnet(resolved));
+    }
+
+    #[test]
+    fn moves_preferred_family_first_keeping_order() {
+        let mut peer_addr = PeerAddress::Clearnet(addrs(&[
+            "[2001:db8::1]:9735",
+            "10.0.0.1:9735",
+            "[2001:db8::2]:9735",
+            "10.0.0.2:9735",
+        ]));
+
+        peer_addr.prefer(Some(AddressFamily::Ipv4));
+        assert_eq!(
+            peer_addr,
+            PeerAddress::Clearnet(addrs(&[
+                "10.0.0.1:9735",
+                "10.0.0.2:9735",
+                "[2001:db8::1]:9735",
+                "[2001:db8::2]:9735",
+            ]))
+        );
+
+        peer_addr.prefer(Some(AddressFamily::Ipv6));
+        assert_eq!(
+            peer_addr,
+            PeerAddress::Clearnet(addrs(&[
+                "[2001:db8::1]:9735",
+                "[2001:db8::2]:9735",
+                "10.0.0.1:9735",
+                "10.0.0.2:9735",
+            ]))
+        );
+    }
+
+    #[test]
+    fn builds_onion_host_from_announced_v3_address() {
+        let ed25519_pubkey = [
+            209, 179, 139, 131, 168, 59, 62, 217, 24, 197, 187, 105, 221, 68, 74, 213, 107, 200,
+            213, 131, 90, 145, 77, 231, 52, 71, 71, 78, 95, 2, 89, 27,
+        ];
+        assert_eq!(
+            PeerAddress::from_onion_v3(ed25519_pubkey, 56793, 3, 9735),
+            PeerAddress::Onion {
+                host: String::from(
+                    "2gzyxa5ihm7nsggfxnu52rck2vv4rvmdlkiu3zzui5du4xyclen53wid.onion"
+                ),
+                port: 9735,
+            }
+        );
+    }
+}
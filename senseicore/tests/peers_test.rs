@@ -0,0 +1,106 @@
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::setup_database;
+    use senseicore::database::SenseiDatabase;
+
+    async fn seeded_database() -> SenseiDatabase {
+        let database = setup_database().await;
+        database
+            .upsert_peer("node", "02aaaa", Some(String::from("acme hub")), false)
+            .await
+            .unwrap();
+        database
+            .upsert_peer("node", "02aabb", Some(String::from("acme backup")), true)
+            .await
+            .unwrap();
+        database
+            .upsert_peer("node", "03cccc", Some(String::from("friend")), false)
+            .await
+            .unwrap();
+        database
+            .upsert_peer(
+                "other-node",
+                "02aaaa",
+                Some(String::from("acme hub")),
+                false,
+            )
+            .await
+            .unwrap();
+        database
+    }
+
+    fn pubkeys(peers: Vec<entity::peer::Model>) -> Vec<String> {
+        let mut pubkeys: Vec<String> = peers.into_iter().map(|peer| peer.pubkey).collect();
+        pubkeys.sort();
+        pubkeys
+    }
+
+    #[tokio::test]
+    async fn empty_filters_return_all_of_the_nodes_peers() {
+        let database = seeded_database().await;
+        let peers = database.find_peers("node", None, None).await.unwrap();
+        assert_eq!(pubkeys(peers), vec!["02aaaa", "02aabb", "03cccc"]);
+    }
+
+    #[tokio::test]
+    async fn filters_by_label_substring() {
+        let database = seeded_database().await;
+        let peers = database
+            .find_peers("node", Some(String::from("acme")), None)
+            .await
+            .unwrap();
+        assert_eq!(pubkeys(peers), vec!["02aaaa", "02aabb"]);
+
+        let peers = database
+            .find_peers("node", Some(String::from("backup")), None)
+            .await
+            .unwrap();
+        assert_eq!(pubkeys(peers), vec!["02aabb"]);
+    }
+
+    #[tokio::test]
+    async fn filters_by_pubkey_prefix() {
+        let database = seeded_database().await;
+        let peers = database
+            .find_peers("node", None, Some(String::from("02aa")))
+            .await
+            .unwrap();
+        assert_eq!(pubkeys(peers), vec!["02aaaa", "02aabb"]);
+
+        // a prefix, not a substring
+        let peers = database
+            .find_peers("node", None, Some(String::from("cccc")))
+            .await
+            .unwrap();
+        assert!(peers.is_empty());
+    }
+
+    #[tokio::test]
+    async fn combines_filters() {
+        let database = seeded_database().await;
+        let peers = database
+            .find_peers("node", Some(String::from("hub")), Some(String::from("02")))
+            .await
+            .unwrap();
+        assert_eq!(pubkeys(peers), vec!["02aaaa"]);
+    }
+
+    #[tokio::test]
+    async fn upsert_keeps_one_row_per_pubkey() {
+        let database = seeded_database().await;
+        let peer = database
+            .upsert_peer("node", "02aaaa", Some(String::from("renamed")), true)
+            .await
+            .unwrap();
+        assert!(peer.zero_conf);
+
+        let peers = database
+            .find_peers("node", None, Some(String::from("02aaaa")))
+            .await
+            .unwrap();
+        assert_eq!(peers.len(), 1);
+        assert_eq!(peers[0].label, Some(String::from("renamed")));
+    }
+}
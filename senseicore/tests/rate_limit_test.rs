@@ -0,0 +1,40 @@
+#[cfg(test)]
+mod test {
+    use senseicore::rate_limit::ConnectionRateLimiter;
+    use std::collections::HashSet;
+    use std::net::IpAddr;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn blocks_with_backoff_after_limit() {
+        let ip: IpAddr = "10.0.0.1".parse().unwrap();
+        let window = Duration::from_secs(60);
+        let mut limiter = ConnectionRateLimiter::new(2, window, HashSet::new());
+        let now = Instant::now();
+
+        assert!(limiter.check(ip, now).is_ok());
+        assert!(limiter.check(ip, now).is_ok());
+        assert_eq!(limiter.check(ip, now), Err(window));
+        assert!(limiter.check(ip, now + window / 2).is_err());
+
+        let now = now + window;
+        assert!(limiter.check(ip, now).is_ok());
+        assert!(limiter.check(ip, now).is_ok());
+        assert_eq!(limiter.check(ip, now), Err(window * 2));
+
+        let other: IpAddr = "10.0.0.2".parse().unwrap();
+        assert!(limiter.check(other, now).is_ok());
+    }
+
+    #[test]
+    fn never_blocks_exempt_ips() {
+        let ip: IpAddr = "10.0.0.1".parse().unwrap();
+        let mut limiter =
+            ConnectionRateLimiter::new(1, Duration::from_secs(60), HashSet::from([ip]));
+        let now = Instant::now();
+
+        for _ in 0..10 {
+            assert!(limiter.check(ip, now).is_ok());
+        }
+    }
+}
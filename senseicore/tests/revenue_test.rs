@@ -0,0 +1,97 @@
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::setup_database;
+    use entity::forward;
+    use entity::sea_orm::{ActiveModelTrait, ActiveValue};
+    use senseicore::database::DailyForwardFees;
+    use senseicore::revenue::{fee_revenue, SECS_PER_DAY};
+
+    fn daily(day: i64, fee_earned_msat: i64, forwards: i64) -> DailyForwardFees {
+        DailyForwardFees {
+            day,
+            fee_earned_msat,
+            forwards,
+        }
+    }
+
+    #[test]
+    fn lays_out_fees_per_day() {
+        let start = 10 * SECS_PER_DAY;
+        let end = start + 3 * SECS_PER_DAY;
+        let daily_fees = vec![
+            daily(start, 1500, 2),
+            daily(start + 2 * SECS_PER_DAY, 3000, 1),
+        ];
+
+        let revenue = fee_revenue(&daily_fees, 0, start, end);
+        assert_eq!(revenue.fee_earned_msat, 4500);
+        assert_eq!(revenue.forwards, 3);
+        assert_eq!(revenue.fee_earned_msat_per_day, 1500.0);
+        assert_eq!(
+            revenue
+                .days
+                .iter()
+                .map(|day| day.fee_earned_msat)
+                .collect::<Vec<_>>(),
+            vec![1500, 0, 3000, 0]
+        );
+        assert_eq!(revenue.trend_pct, None);
+    }
+
+    #[test]
+    fn compares_to_the_previous_window() {
+        let start = 10 * SECS_PER_DAY;
+        let end = start + SECS_PER_DAY;
+        let daily_fees = vec![daily(start, 3000, 1)];
+
+        let revenue = fee_revenue(&daily_fees, 2000, start, end);
+        assert_eq!(revenue.fee_earned_msat, 3000);
+        assert_eq!(revenue.previous_fee_earned_msat, 2000);
+        assert_eq!(revenue.trend_pct, Some(50.0));
+    }
+
+    #[tokio::test]
+    async fn adds_up_fees_per_day_in_the_database() {
+        let database = setup_database().await;
+
+        let start = 10 * SECS_PER_DAY;
+        for (node_id, created_at, fee_earned_msat) in [
+            ("node", start, Some(9999)),
+            ("node", start + 10, Some(1000)),
+            ("node", start + 20, None),
+            ("node", start + SECS_PER_DAY + 5, Some(3000)),
+            ("other", start + 10, Some(7777)),
+        ] {
+            forward::ActiveModel {
+                created_at: ActiveValue::Set(created_at),
+                node_id: ActiveValue::Set(node_id.to_string()),
+                prev_channel_id: ActiveValue::Set(None),
+                next_channel_id: ActiveValue::Set(None),
+                fee_earned_msat: ActiveValue::Set(fee_earned_msat),
+                claim_from_onchain_tx: ActiveValue::Set(false),
+                ..Default::default()
+            }
+            .insert(database.get_connection())
+            .await
+            .unwrap();
+        }
+
+        // the forward right at `start` belongs to the window before it
+        assert_eq!(
+            database
+                .daily_forward_fees("node", start, start + 2 * SECS_PER_DAY)
+                .await
+                .unwrap(),
+            vec![daily(start, 1000, 2), daily(start + SECS_PER_DAY, 3000, 1)]
+        );
+        assert_eq!(
+            database
+                .daily_forward_fees("node", start - SECS_PER_DAY, start)
+                .await
+                .unwrap(),
+            vec![daily(start, 9999, 1)]
+        );
+    }
+}
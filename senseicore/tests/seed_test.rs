@@ -0,0 +1,56 @@
+#[cfg(test)]
+mod test {
+    use senseicore::error::Error;
+    use senseicore::hex_utils;
+    use senseicore::node::LightningNode;
+
+    const MNEMONIC: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    #[test]
+    fn derives_bip39_seed_from_mnemonic() {
+        let seed = LightningNode::seed_from_mnemonic(MNEMONIC, Some("TREZOR")).unwrap();
+
+        assert_eq!(
+            hex_utils::hex_str(&seed),
+            "c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04"
+        );
+    }
+
+    #[test]
+    fn rejects_bad_checksum_and_unknown_words() {
+        let bad_checksum = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon";
+        let unknown_word = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon sensei";
+
+        assert!(matches!(
+            LightningNode::seed_from_mnemonic(bad_checksum, None),
+            Err(Error::InvalidMnemonic(_))
+        ));
+        assert!(matches!(
+            LightningNode::seed_from_mnemonic(unknown_word, None),
+            Err(Error::InvalidMnemonic(_))
+        ));
+    }
+
+    #[test]
+    fn imported_seeds_get_their_own_ldk_seed() {
+        let generated = [7; 32];
+        assert_eq!(LightningNode::ldk_seed(&generated).unwrap(), generated);
+
+        let imported = LightningNode::seed_from_mnemonic(MNEMONIC, None).unwrap();
+        let ldk_seed = LightningNode::ldk_seed(&imported).unwrap();
+        assert_ne!(ldk_seed[..], imported[..32]);
+        assert_eq!(ldk_seed, LightningNode::ldk_seed(&imported).unwrap());
+    }
+
+    #[test]
+    fn rejects_seeds_of_other_lengths() {
+        assert!(matches!(
+            LightningNode::ldk_seed(&[7; 16]),
+            Err(Error::InvalidSeedLength)
+        ));
+        assert!(matches!(
+            LightningNode::get_node_pubkey_from_seed(&[7; 33]),
+            Err(Error::InvalidSeedLength)
+        ));
+    }
+}
@@ -1,17 +1,18 @@
 #[cfg(test)]
 mod test {
-    use bitcoin::{Address, Amount, Network};
+    use bitcoin::{Address, Amount, Network, Txid};
     use bitcoincore_rpc::RpcApi;
     use bitcoind::BitcoinD;
     use entity::sea_orm::{ConnectOptions, Database};
     use futures::{future, Future};
+    use lightning::chain::chaininterface::{ConfirmationTarget, FeeEstimator};
     use migration::{Migrator, MigratorTrait};
     use senseicore::events::SenseiEvent;
     use senseicore::node::{HTLCStatus, LightningNode};
     use senseicore::services::node::{Channel, OpenChannelRequest};
     use senseicore::services::{PaginationRequest, PaymentsFilter};
     use serial_test::serial;
-    use std::{str::FromStr, sync::Arc, time::Duration};
+    use std::{collections::HashSet, str::FromStr, sync::Arc, time::Duration};
     use tokio::runtime::{Builder, Handle};
     use tokio::sync::broadcast;
 
@@ -24,6 +25,17 @@ mod test {
 
     use senseicore::services::admin::{AdminRequest, AdminResponse, AdminService};
 
+    // Estimates the same feerate for every target, in place of bitcoind's estimates.
+    struct FixedFeeEstimator {
+        sat_per_kw: u32,
+    }
+
+    impl FeeEstimator for FixedFeeEstimator {
+        fn get_est_sat_per_1000_weight(&self, _confirmation_target: ConfirmationTarget) -> u32 {
+            self.sat_per_kw
+        }
+    }
+
     async fn fund_node(bitcoind: &BitcoinD, node: Arc<LightningNode>) {
         let miner_address = bitcoind.client.get_new_address(None, None).unwrap();
         let fund_address = match node.call(NodeRequest::GetUnusedAddress {}).await.unwrap() {
@@ -74,6 +86,9 @@ mod test {
                 passphrase: String::from(passphrase),
                 alias: String::from(username),
                 start,
+                mnemonic: None,
+                mnemonic_passphrase: None,
+                rescan_from_height: None,
             })
             .await
             .unwrap()
@@ -259,6 +274,31 @@ mod test {
             .unwrap();
     }
 
+    async fn restart_node(
+        admin_service: &AdminService,
+        node: Arc<LightningNode>,
+        passphrase: &str,
+    ) -> Arc<LightningNode> {
+        let pubkey = node.get_pubkey();
+        admin_service
+            .call(AdminRequest::StopNode {
+                pubkey: pubkey.clone(),
+            })
+            .await
+            .unwrap();
+        admin_service
+            .call(AdminRequest::StartNode {
+                pubkey: pubkey.clone(),
+                passphrase: String::from(passphrase),
+            })
+            .await
+            .unwrap();
+
+        let directory = admin_service.node_directory.lock().await;
+        let handle = directory.get(&pubkey).unwrap();
+        handle.as_ref().unwrap().node.clone()
+    }
+
     async fn get_onchain_balance_sats(node: Arc<LightningNode>) -> u64 {
         match node.call(NodeRequest::GetBalance {}).await.unwrap() {
             NodeResponse::GetBalance {
@@ -303,6 +343,10 @@ mod test {
 
         from.call(NodeRequest::OpenChannels {
             requests: channel_requests,
+            funding_utxos: None,
+            unspendable_utxos: None,
+            max_fee_sats: None,
+            funding_timeout_ms: None,
         })
         .await
         .unwrap();
@@ -383,7 +427,7 @@ mod test {
         .map(|channel| {
             let counterparty = to
                 .iter()
-                .find(|to| to.node_info().unwrap().node_pubkey == channel.counterparty_pubkey)
+                .find(|to| to.get_pubkey() == channel.counterparty_pubkey)
                 .unwrap();
             (channel, counterparty.clone())
         })
@@ -417,6 +461,10 @@ mod test {
                 max_dust_htlc_exposure_msat: None,
                 force_close_avoidance_max_fee_satoshis: None,
             }],
+            funding_utxos: None,
+            unspendable_utxos: None,
+            max_fee_sats: None,
+            funding_timeout_ms: None,
         })
         .await
         .unwrap();
@@ -474,6 +522,7 @@ mod test {
             .call(NodeRequest::GetInvoice {
                 amt_msat: amt_sat * 1000,
                 description: String::from("test"),
+                metadata: None,
             })
             .await
             .unwrap()
@@ -500,9 +549,12 @@ mod test {
     }
 
     async fn pay_invoice(node: Arc<LightningNode>, invoice: String) {
-        node.call(NodeRequest::SendPayment { invoice })
-            .await
-            .unwrap();
+        node.call(NodeRequest::SendPayment {
+            invoice,
+            max_path_length: None,
+        })
+        .await
+        .unwrap();
     }
 
     fn within_range(actual: f64, expected: f64, pct_err: f64) -> bool {
@@ -545,6 +597,7 @@ mod test {
         sensei_dir: &str,
         bitcoind: &BitcoinD,
         persistence_handle: Handle,
+        fee_estimator: Option<Arc<dyn FeeEstimator + Send + Sync>>,
     ) -> AdminService {
         let (event_sender, _event_receiver): (
             broadcast::Sender<SenseiEvent>,
@@ -581,8 +634,11 @@ mod test {
             SenseiChainManager::new(
                 config.clone(),
                 bitcoind_client.clone(),
+                fee_estimator.unwrap_or_else(|| bitcoind_client.clone()),
                 bitcoind_client.clone(),
-                bitcoind_client,
+                bitcoind_client.clone(),
+                bitcoind_client.relay_rejections.clone(),
+                bitcoind_client.fee_estimates_known.clone(),
             )
             .await
             .unwrap(),
@@ -599,6 +655,17 @@ mod test {
     }
 
     fn run_test<F>(name: &str, test: fn(BitcoinD, AdminService) -> F) -> F::Output
+    where
+        F: Future,
+    {
+        run_test_with_fee_estimator(name, None, test)
+    }
+
+    fn run_test_with_fee_estimator<F>(
+        name: &str,
+        fee_estimator: Option<Arc<dyn FeeEstimator + Send + Sync>>,
+        test: fn(BitcoinD, AdminService) -> F,
+    ) -> F::Output
     where
         F: Future,
     {
@@ -620,8 +687,13 @@ mod test {
             .block_on(async move {
                 let sensei_dir = format!("./.sensei-tests/{}", name);
                 let bitcoind = setup_bitcoind();
-                let admin_service =
-                    setup_sensei(&sensei_dir, &bitcoind, persistence_runtime_handle).await;
+                let admin_service = setup_sensei(
+                    &sensei_dir,
+                    &bitcoind,
+                    persistence_runtime_handle,
+                    fee_estimator,
+                )
+                .await;
                 let output = test(bitcoind, admin_service.clone()).await;
                 admin_service.stop().await.unwrap();
                 output
@@ -795,15 +867,203 @@ mod test {
         }
     }
 
+    async fn batch_open_fee_ceiling_test(bitcoind: BitcoinD, admin_service: AdminService) {
+        let alice = create_root_node(&admin_service, "alice", "alice", true).await;
+        let bob = create_node(&admin_service, "bob", "bob", true).await;
+        fund_node(&bitcoind, alice.clone()).await;
+
+        let response = alice
+            .call(NodeRequest::OpenChannels {
+                requests: vec![OpenChannelRequest {
+                    counterparty_pubkey: bob.get_pubkey(),
+                    counterparty_host_port: Some(format!(
+                        "{}:{}",
+                        bob.listen_addresses.first().unwrap(),
+                        bob.listen_port
+                    )),
+                    amount_sats: 1_000_000,
+                    public: true,
+                    custom_id: None,
+                    push_amount_msats: None,
+                    forwarding_fee_proportional_millionths: None,
+                    forwarding_fee_base_msat: None,
+                    cltv_expiry_delta: None,
+                    max_dust_htlc_exposure_msat: None,
+                    force_close_avoidance_max_fee_satoshis: None,
+                }],
+                funding_utxos: None,
+                unspendable_utxos: None,
+                // plenty at regtest feerates, a fraction of what the spike costs
+                max_fee_sats: Some(5_000),
+                funding_timeout_ms: None,
+            })
+            .await
+            .unwrap();
+
+        let results = match response {
+            NodeResponse::OpenChannels { results, .. } => results,
+            _ => panic!("unexpected response"),
+        };
+        assert!(results[0].error);
+        assert!(results[0]
+            .error_message
+            .as_ref()
+            .unwrap()
+            .contains("above the maximum of 5000 sats"));
+
+        // the channel that was waiting on funding is gone, bob doesn't keep it around either
+        let alice_test = alice.clone();
+        let bob_test = bob.clone();
+        let no_channels = move || {
+            alice_test.channel_manager.list_channels().is_empty()
+                && bob_test.channel_manager.list_channels().is_empty()
+        };
+        assert!(wait_until(no_channels, 15000, 250).await);
+    }
+
+    async fn back_to_back_opens_test(bitcoind: BitcoinD, admin_service: AdminService) {
+        let alice = create_root_node(&admin_service, "alice", "alice", true).await;
+        let bob = create_node(&admin_service, "bob", "bob", true).await;
+        let charlie = create_node(&admin_service, "charlie", "charlie", true).await;
+        // a single utxo, the first funding tx holds it until its peer signs
+        fund_node(&bitcoind, alice.clone()).await;
+
+        let open = |to: Arc<LightningNode>| {
+            alice.call(NodeRequest::OpenChannels {
+                requests: vec![OpenChannelRequest {
+                    counterparty_pubkey: to.get_pubkey(),
+                    counterparty_host_port: Some(format!(
+                        "{}:{}",
+                        to.listen_addresses.first().unwrap(),
+                        to.listen_port
+                    )),
+                    amount_sats: 1_000_000,
+                    public: true,
+                    custom_id: None,
+                    push_amount_msats: None,
+                    forwarding_fee_proportional_millionths: None,
+                    forwarding_fee_base_msat: None,
+                    cltv_expiry_delta: None,
+                    max_dust_htlc_exposure_msat: None,
+                    force_close_avoidance_max_fee_satoshis: None,
+                }],
+                funding_utxos: None,
+                unspendable_utxos: None,
+                max_fee_sats: None,
+                funding_timeout_ms: None,
+            })
+        };
+        let (bob_response, charlie_response) = future::join(open(bob), open(charlie)).await;
+
+        // the second open either waited for the first tx's change or found nothing to spend,
+        // it never spent the utxo the first one is holding
+        let funding_txids = vec![bob_response.unwrap(), charlie_response.unwrap()]
+            .into_iter()
+            .flat_map(|response| match response {
+                NodeResponse::OpenChannels { results, .. } => results,
+                _ => panic!("unexpected response"),
+            })
+            .filter_map(|result| result.funding_txid)
+            .map(|txid| Txid::from_str(&txid).unwrap())
+            .collect::<Vec<Txid>>();
+        assert!(!funding_txids.is_empty());
+
+        let mut spent = HashSet::new();
+        for txid in funding_txids {
+            let in_mempool = || bitcoind.client.get_raw_transaction(&txid, None).is_ok();
+            assert!(wait_until(in_mempool, 15000, 250).await);
+            let funding_tx = bitcoind.client.get_raw_transaction(&txid, None).unwrap();
+            for input in funding_tx.input {
+                assert!(spent.insert(input.previous_output));
+            }
+        }
+    }
+
+    async fn mpp_receive_restart_test(bitcoind: BitcoinD, admin_service: AdminService) {
+        let alice = create_root_node(&admin_service, "alice", "alice", true).await;
+        let bob = create_node(&admin_service, "bob", "bob", true).await;
+        let charlie = create_node(&admin_service, "charlie", "charlie", true).await;
+        fund_node(&bitcoind, alice.clone()).await;
+        fund_node(&bitcoind, bob.clone()).await;
+        let _alice_bob_channel =
+            open_channel(&bitcoind, alice.clone(), bob.clone(), 1_000_000).await;
+        let _bob_charlie_channel =
+            open_channel(&bitcoind, bob.clone(), charlie.clone(), 1_000_000).await;
+        let _alice_charlie_channel =
+            open_channel(&bitcoind, alice.clone(), charlie.clone(), 1_000_000).await;
+
+        // larger than any single channel so the payment has to be split
+        let invoice_amt = 1_500_000;
+        let invoice = create_invoice(charlie.clone(), invoice_amt).await;
+        let mut event_receiver = charlie.event_sender.subscribe();
+        pay_invoice(alice.clone(), invoice).await;
+
+        let charlie_id = charlie.id.clone();
+        let filter = move |event| match event {
+            SenseiEvent::PaymentClaimed {
+                node_id,
+                amount_msat,
+                ..
+            } => node_id == charlie_id && amount_msat == invoice_amt * 1000,
+            _ => false,
+        };
+        let event = wait_for_event(&mut event_receiver, filter, 60000, 250).await;
+        assert!(event.is_some());
+
+        // the claim was persisted before the event went out, so it has to
+        // survive the restart without waiting on anything else
+        let charlie = restart_node(&admin_service, charlie, "charlie").await;
+
+        let pagination = PaginationRequest {
+            page: 0,
+            take: 1,
+            query: None,
+        };
+        let filter = PaymentsFilter {
+            status: Some(HTLCStatus::Succeeded.to_string()),
+            origin: None,
+        };
+        let (payments, pagination) = charlie
+            .database
+            .list_payments_sync(charlie.id.clone(), pagination, filter)
+            .unwrap();
+        assert_eq!(pagination.total, 1);
+        assert_eq!(payments[0].amt_msat, Some((invoice_amt * 1000) as i64));
+    }
+
     #[test]
     #[serial]
     fn run_batch_open_channel_test() {
         run_test("batch_open_channels", batch_open_channels_test)
     }
 
+    #[test]
+    #[serial]
+    fn run_batch_open_fee_ceiling_test() {
+        // 100 sat/vB, the funding tx alone comes to well over 10k sats
+        let spike = Arc::new(FixedFeeEstimator { sat_per_kw: 25_000 });
+        run_test_with_fee_estimator(
+            "batch_open_fee_ceiling",
+            Some(spike),
+            batch_open_fee_ceiling_test,
+        )
+    }
+
+    #[test]
+    #[serial]
+    fn run_back_to_back_opens_test() {
+        run_test("back_to_back_opens", back_to_back_opens_test)
+    }
+
     #[test]
     #[serial]
     fn run_smoke_test() {
         run_test("smoke_test", smoke_test)
     }
+
+    #[test]
+    #[serial]
+    fn run_mpp_receive_restart_test() {
+        run_test("mpp_receive_restart", mpp_receive_restart_test)
+    }
 }
@@ -0,0 +1,85 @@
+#[cfg(test)]
+mod test {
+    use senseicore::node::{parse_peer_addr, PeerAddress};
+    use senseicore::socks;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    const ONION_HOST: &str = "vww6ybal4bd7szmgncyruucpgfkqahzddi37ktceo3ah7ngmcopnpyyd.onion";
+
+    #[tokio::test]
+    async fn parses_onion_addresses_without_resolving_them() {
+        let addr = parse_peer_addr(&format!("{}:9735", ONION_HOST.to_uppercase()))
+            .await
+            .unwrap();
+        assert_eq!(
+            addr,
+            PeerAddress::Onion {
+                host: String::from(ONION_HOST),
+                port: 9735
+            }
+        );
+        assert!(parse_peer_addr(&format!("{}:notaport", ONION_HOST))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn connects_through_the_proxy_by_hostname() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy = listener.local_addr().unwrap();
+
+        let fake_proxy = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut greeting = [0u8; 3];
+            stream.read_exact(&mut greeting).await.unwrap();
+            assert_eq!(greeting, [5, 1, 0]);
+            stream.write_all(&[5, 0]).await.unwrap();
+
+            let mut header = [0u8; 5];
+            stream.read_exact(&mut header).await.unwrap();
+            assert_eq!(header[..4], [5, 1, 0, 3]);
+            let mut host = vec![0u8; header[4] as usize];
+            stream.read_exact(&mut host).await.unwrap();
+            let mut port = [0u8; 2];
+            stream.read_exact(&mut port).await.unwrap();
+            stream
+                .write_all(&[5, 0, 0, 1, 127, 0, 0, 1, 0, 0])
+                .await
+                .unwrap();
+            stream.write_all(b"hello").await.unwrap();
+            (String::from_utf8(host).unwrap(), u16::from_be_bytes(port))
+        });
+
+        let mut stream = socks::connect(proxy, ONION_HOST, 9735).await.unwrap();
+        let mut hello = [0u8; 5];
+        stream.read_exact(&mut hello).await.unwrap();
+        assert_eq!(&hello, b"hello");
+
+        let (host, port) = fake_proxy.await.unwrap();
+        assert_eq!(host, ONION_HOST);
+        assert_eq!(port, 9735);
+    }
+
+    #[tokio::test]
+    async fn fails_when_the_proxy_refuses() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut greeting = [0u8; 3];
+            stream.read_exact(&mut greeting).await.unwrap();
+            stream.write_all(&[5, 0]).await.unwrap();
+            let mut request = vec![0u8; 5 + ONION_HOST.len() + 2];
+            stream.read_exact(&mut request).await.unwrap();
+            // host unreachable
+            stream
+                .write_all(&[5, 4, 0, 1, 0, 0, 0, 0, 0, 0])
+                .await
+                .unwrap();
+        });
+
+        assert!(socks::connect(proxy, ONION_HOST, 9735).await.is_err());
+    }
+}
@@ -0,0 +1,60 @@
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use senseicore::stale_state::{stale_channels, MonitorCheckpoints, StaleChannel};
+
+    fn checkpoints(update_ids: &[(&str, u64)]) -> MonitorCheckpoints {
+        MonitorCheckpoints {
+            update_ids: update_ids
+                .iter()
+                .map(|(funding_txo, update_id)| (funding_txo.to_string(), *update_id))
+                .collect(),
+        }
+    }
+
+    fn monitors(update_ids: &[(&str, u64)]) -> HashMap<String, u64> {
+        checkpoints(update_ids).update_ids
+    }
+
+    #[test]
+    fn monitors_at_or_past_the_checkpoint_are_fine() {
+        let checkpoints = checkpoints(&[("a:0", 10), ("b:1", 4)]);
+        let monitors = monitors(&[("a:0", 10), ("b:1", 7), ("c:0", 1)]);
+        assert!(stale_channels(&checkpoints, &monitors).is_empty());
+    }
+
+    #[test]
+    fn finds_monitors_behind_the_checkpoint() {
+        let checkpoints = checkpoints(&[("b:1", 4), ("a:0", 10)]);
+        let monitors = monitors(&[("a:0", 9), ("b:1", 3)]);
+        assert_eq!(
+            stale_channels(&checkpoints, &monitors),
+            vec![
+                StaleChannel {
+                    funding_txo: String::from("a:0"),
+                    checkpoint_update_id: 10,
+                    monitor_update_id: Some(9),
+                },
+                StaleChannel {
+                    funding_txo: String::from("b:1"),
+                    checkpoint_update_id: 4,
+                    monitor_update_id: Some(3),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn finds_missing_monitors() {
+        let checkpoints = checkpoints(&[("a:0", 2)]);
+        assert_eq!(
+            stale_channels(&checkpoints, &HashMap::new()),
+            vec![StaleChannel {
+                funding_txo: String::from("a:0"),
+                checkpoint_update_id: 2,
+                monitor_update_id: None,
+            }]
+        );
+    }
+}
@@ -0,0 +1,63 @@
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::setup_database;
+    use senseicore::error::Error;
+
+    #[tokio::test]
+    async fn rejects_values_the_database_cant_hold() {
+        let database = setup_database().await;
+        let res = database
+            .create_subscription("node", u64::MAX, String::from("sub"), 3600, 0)
+            .await;
+        assert!(matches!(res, Err(Error::InvalidSubscription(_))));
+        let res = database
+            .create_subscription("node", 1000, String::from("sub"), u64::MAX, 0)
+            .await;
+        assert!(matches!(res, Err(Error::InvalidSubscription(_))));
+        assert!(database
+            .list_subscriptions("node")
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn lists_due_subscriptions() {
+        let database = setup_database().await;
+        let subscription = database
+            .create_subscription("node", 1000, String::from("sub"), 3600, 100)
+            .await
+            .unwrap();
+        assert_eq!(
+            database
+                .list_due_subscriptions("node", 99)
+                .await
+                .unwrap()
+                .len(),
+            0
+        );
+        assert_eq!(
+            database
+                .list_due_subscriptions("node", 100)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+
+        database
+            .set_subscription_next_invoice_at(subscription, 3700)
+            .await
+            .unwrap();
+        assert_eq!(
+            database
+                .list_due_subscriptions("node", 100)
+                .await
+                .unwrap()
+                .len(),
+            0
+        );
+    }
+}
@@ -0,0 +1,92 @@
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::setup_database;
+    use entity::peer_event;
+    use senseicore::uptime::{peer_uptime, Uptime};
+
+    fn event(created_at: i64, connected: bool) -> peer_event::Model {
+        peer_event::Model {
+            id: created_at.to_string(),
+            created_at,
+            updated_at: created_at,
+            node_id: String::from("node"),
+            pubkey: String::from("peer"),
+            connected,
+        }
+    }
+
+    #[test]
+    fn counts_time_between_connects_and_disconnects() {
+        let events = vec![event(100, false), event(160, true), event(400, false)];
+        assert_eq!(
+            peer_uptime(&events, Some(true), 0, 1000),
+            Uptime {
+                connected_secs: 100 + 240,
+                disconnections: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn infers_the_starting_state_from_the_first_change() {
+        let events = vec![event(300, true)];
+        assert_eq!(
+            peer_uptime(&events, None, 0, 1000),
+            Uptime {
+                connected_secs: 700,
+                disconnections: 0,
+            }
+        );
+
+        let events = vec![event(300, false)];
+        assert_eq!(
+            peer_uptime(&events, None, 0, 1000),
+            Uptime {
+                connected_secs: 300,
+                disconnections: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn no_changes_keeps_the_given_state() {
+        assert_eq!(peer_uptime(&[], Some(true), 500, 1000).connected_secs, 500);
+        assert_eq!(peer_uptime(&[], Some(false), 500, 1000).connected_secs, 0);
+        assert_eq!(peer_uptime(&[], None, 500, 1000).connected_secs, 0);
+    }
+
+    #[test]
+    fn repeated_changes_are_not_double_counted() {
+        let events = vec![event(100, false), event(200, false), event(300, true)];
+        assert_eq!(
+            peer_uptime(&events, Some(true), 0, 400),
+            Uptime {
+                connected_secs: 200,
+                disconnections: 1,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn backdated_disconnects_keep_their_time() {
+        let database = setup_database().await;
+
+        assert_eq!(database.peer_events_heartbeat("node").await.unwrap(), None);
+        database
+            .set_peer_events_heartbeat("node", 1234)
+            .await
+            .unwrap();
+        assert_eq!(
+            database.peer_events_heartbeat("node").await.unwrap(),
+            Some(1234)
+        );
+
+        let event = database
+            .record_peer_event_at("node", "peer", false, 1234)
+            .await
+            .unwrap();
+        assert_eq!(event.created_at, 1234);
+    }
+}
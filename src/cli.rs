@@ -278,6 +278,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     alias: alias.to_string(),
                     passphrase,
                     start: false,
+                    mnemonic: None,
+                    mnemonic_passphrase: None,
+                    rescan_from_height: None,
                 });
                 let response = admin_client.create_node(request).await?;
                 println!("{:?}", response.into_inner());
@@ -358,6 +361,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                         max_dust_htlc_exposure_msat: None,
                         force_close_avoidance_max_fee_satoshis: None,
                     }],
+                    funding_utxos: vec![],
+                    unspendable_utxos: vec![],
+                    max_fee_sats: None,
+                    funding_timeout_ms: None,
                 });
 
                 let response = client.open_channels(request).await?;
@@ -389,6 +396,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
                 let request = tonic::Request::new(PayInvoiceRequest {
                     invoice: invoice.to_string(),
+                    max_path_length: None,
                 });
 
                 let response = client.pay_invoice(request).await?;
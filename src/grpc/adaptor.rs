@@ -243,6 +243,10 @@ impl From<OpenChannelsRequest> for NodeRequest {
                         .force_close_avoidance_max_fee_satoshis,
                 })
                 .collect::<Vec<_>>(),
+            funding_utxos: (!req.funding_utxos.is_empty()).then(|| req.funding_utxos),
+            unspendable_utxos: (!req.unspendable_utxos.is_empty()).then(|| req.unspendable_utxos),
+            max_fee_sats: req.max_fee_sats,
+            funding_timeout_ms: req.funding_timeout_ms,
         }
     }
 }
@@ -291,6 +295,9 @@ impl From<PayInvoiceRequest> for NodeRequest {
     fn from(req: PayInvoiceRequest) -> Self {
         NodeRequest::SendPayment {
             invoice: req.invoice,
+            max_path_length: req
+                .max_path_length
+                .map(|max_path_length| u8::try_from(max_path_length).unwrap_or(u8::MAX)),
         }
     }
 }
@@ -352,6 +359,7 @@ impl From<CreateInvoiceRequest> for NodeRequest {
         NodeRequest::GetInvoice {
             amt_msat: req.amt_msat,
             description: req.description,
+            metadata: None,
         }
     }
 }
@@ -641,6 +649,7 @@ impl From<entity::peer::Model> for KnownPeer {
             pubkey: peer.pubkey,
             label: peer.label,
             zero_conf: peer.zero_conf,
+            last_connected_at: peer.last_connected_at,
         }
     }
 }
@@ -111,6 +111,9 @@ impl From<CreateNodeRequest> for AdminRequest {
             alias: req.alias,
             passphrase: req.passphrase,
             start: req.start,
+            mnemonic: req.mnemonic,
+            mnemonic_passphrase: req.mnemonic_passphrase,
+            rescan_from_height: req.rescan_from_height,
         }
     }
 }
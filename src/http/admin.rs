@@ -105,6 +105,9 @@ pub struct CreateNodeParams {
     pub passphrase: String,
     pub alias: String,
     pub start: bool,
+    pub mnemonic: Option<String>,
+    pub mnemonic_passphrase: Option<String>,
+    pub rescan_from_height: Option<u32>,
 }
 
 impl From<CreateNodeParams> for AdminRequest {
@@ -114,6 +117,9 @@ impl From<CreateNodeParams> for AdminRequest {
             passphrase: params.passphrase,
             alias: params.alias,
             start: params.start,
+            mnemonic: params.mnemonic,
+            mnemonic_passphrase: params.mnemonic_passphrase,
+            rescan_from_height: params.rescan_from_height,
         }
     }
 }
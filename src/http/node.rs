@@ -7,16 +7,23 @@
 // You may not use this file except in accordance with one or both of these
 // licenses.
 
+use std::collections::BTreeMap;
 use std::sync::Arc;
 
 use crate::http::auth_header::AuthHeader;
 use crate::AdminService;
+use axum::body::{boxed, StreamBody};
 use axum::extract::{Extension, Json, Query};
+use axum::response::Response;
 use axum::routing::{delete, get, post};
 use axum::Router;
-use http::{HeaderValue, StatusCode};
+use futures::StreamExt;
+use http::{header, HeaderValue, StatusCode};
+use senseicore::node::LightningNode;
 use senseicore::services::admin::AdminRequest;
-use senseicore::services::node::{NodeRequest, NodeRequestError, NodeResponse, OpenChannelRequest};
+use senseicore::services::node::{
+    BatchCloseRequest, NodeRequest, NodeRequestError, NodeResponse, OpenChannelRequest,
+};
 use senseicore::services::{
     ListChannelsParams, ListKnownPeersParams, ListPaymentsParams, ListTransactionsParams,
 };
@@ -31,6 +38,7 @@ use super::utils::get_macaroon_hex_str_from_cookies_or_header;
 pub struct GetInvoiceParams {
     pub amt_msat: u64,
     pub description: String,
+    pub metadata: Option<BTreeMap<String, String>>,
 }
 
 impl From<GetInvoiceParams> for NodeRequest {
@@ -38,6 +46,7 @@ impl From<GetInvoiceParams> for NodeRequest {
         Self::GetInvoice {
             amt_msat: params.amt_msat,
             description: params.description,
+            metadata: params.metadata,
         }
     }
 }
@@ -57,6 +66,92 @@ impl From<LabelPaymentParams> for NodeRequest {
     }
 }
 
+#[derive(Deserialize)]
+pub struct LabelChannelParams {
+    pub channel_id: String,
+    pub label: String,
+}
+
+impl From<LabelChannelParams> for NodeRequest {
+    fn from(params: LabelChannelParams) -> Self {
+        Self::LabelChannel {
+            channel_id: params.channel_id,
+            label: params.label,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct PaymentMetadataParams {
+    pub payment_hash: String,
+    pub metadata: BTreeMap<String, String>,
+}
+
+impl From<PaymentMetadataParams> for NodeRequest {
+    fn from(params: PaymentMetadataParams) -> Self {
+        Self::SetPaymentMetadata {
+            payment_hash: params.payment_hash,
+            metadata: params.metadata,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct InvoiceMetadataParams {
+    pub invoice: String,
+    pub metadata: BTreeMap<String, String>,
+}
+
+impl From<InvoiceMetadataParams> for NodeRequest {
+    fn from(params: InvoiceMetadataParams) -> Self {
+        Self::SetInvoiceMetadata {
+            invoice: params.invoice,
+            metadata: params.metadata,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct NewDepositAddressParams {
+    pub label: String,
+}
+
+impl From<NewDepositAddressParams> for NodeRequest {
+    fn from(params: NewDepositAddressParams) -> Self {
+        Self::NewDepositAddress {
+            label: params.label,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct CacheWalletAddressesParams {
+    pub addresses: u32,
+    pub rescan_from_height: u32,
+}
+
+impl From<CacheWalletAddressesParams> for NodeRequest {
+    fn from(params: CacheWalletAddressesParams) -> Self {
+        Self::CacheWalletAddresses {
+            addresses: params.addresses,
+            rescan_from_height: params.rescan_from_height,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct RescanWalletParams {
+    pub from_height: u32,
+}
+
+impl From<RescanWalletParams> for NodeRequest {
+    fn from(params: RescanWalletParams) -> Self {
+        Self::RescanWallet {
+            from_height: params.from_height,
+        }
+    }
+}
+
 #[derive(Deserialize)]
 pub struct DeletePaymentParams {
     pub payment_hash: String,
@@ -70,15 +165,147 @@ impl From<DeletePaymentParams> for NodeRequest {
     }
 }
 
+#[derive(Deserialize)]
+pub struct ResolveHeldPaymentParams {
+    pub payment_hash: String,
+    pub accept: bool,
+}
+
+impl From<ResolveHeldPaymentParams> for NodeRequest {
+    fn from(params: ResolveHeldPaymentParams) -> Self {
+        Self::ResolveHeldPayment {
+            payment_hash: params.payment_hash,
+            accept: params.accept,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct CancelInflightOpenParams {
+    pub custom_id: u64,
+}
+
+impl From<CancelInflightOpenParams> for NodeRequest {
+    fn from(params: CancelInflightOpenParams) -> Self {
+        Self::CancelInflightOpen {
+            custom_id: params.custom_id,
+        }
+    }
+}
+
 #[derive(Deserialize)]
 pub struct BatchOpenChannelParams {
     requests: Vec<OpenChannelRequest>,
+    funding_utxos: Option<Vec<String>>,
+    unspendable_utxos: Option<Vec<String>>,
+    max_fee_sats: Option<u64>,
+    funding_timeout_ms: Option<u64>,
 }
 
 impl From<BatchOpenChannelParams> for NodeRequest {
     fn from(params: BatchOpenChannelParams) -> Self {
         Self::OpenChannels {
             requests: params.requests,
+            funding_utxos: params.funding_utxos,
+            unspendable_utxos: params.unspendable_utxos,
+            max_fee_sats: params.max_fee_sats,
+            funding_timeout_ms: params.funding_timeout_ms,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct OpenChannelParams {
+    request: OpenChannelRequest,
+    funding_utxos: Option<Vec<String>>,
+    unspendable_utxos: Option<Vec<String>>,
+    max_fee_sats: Option<u64>,
+    funding_timeout_ms: Option<u64>,
+}
+
+impl From<OpenChannelParams> for NodeRequest {
+    fn from(params: OpenChannelParams) -> Self {
+        Self::OpenChannel {
+            request: params.request,
+            funding_utxos: params.funding_utxos,
+            unspendable_utxos: params.unspendable_utxos,
+            max_fee_sats: params.max_fee_sats,
+            funding_timeout_ms: params.funding_timeout_ms,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct BuildBatchFundingParams {
+    requests: Vec<OpenChannelRequest>,
+    funding_utxos: Option<Vec<String>>,
+    unspendable_utxos: Option<Vec<String>>,
+    max_fee_sats: Option<u64>,
+    funding_timeout_ms: Option<u64>,
+}
+
+impl From<BuildBatchFundingParams> for NodeRequest {
+    fn from(params: BuildBatchFundingParams) -> Self {
+        Self::BuildBatchFunding {
+            requests: params.requests,
+            funding_utxos: params.funding_utxos,
+            unspendable_utxos: params.unspendable_utxos,
+            max_fee_sats: params.max_fee_sats,
+            funding_timeout_ms: params.funding_timeout_ms,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct FinalizeBatchFundingParams {
+    psbt: String,
+}
+
+impl From<FinalizeBatchFundingParams> for NodeRequest {
+    fn from(params: FinalizeBatchFundingParams) -> Self {
+        Self::FinalizeBatchFunding { psbt: params.psbt }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SignAndBroadcastPsbtParams {
+    pub psbt: String,
+}
+
+impl From<SignAndBroadcastPsbtParams> for NodeRequest {
+    fn from(params: SignAndBroadcastPsbtParams) -> Self {
+        Self::SignAndBroadcastPsbt { psbt: params.psbt }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct BumpFundingFeeParams {
+    pub txid: String,
+    pub sat_per_vb: u64,
+}
+
+impl From<BumpFundingFeeParams> for NodeRequest {
+    fn from(params: BumpFundingFeeParams) -> Self {
+        Self::BumpFundingFee {
+            txid: params.txid,
+            sat_per_vb: params.sat_per_vb,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct OpenChannelAndPayParams {
+    request: OpenChannelRequest,
+    invoice: String,
+    ready_timeout_ms: Option<u64>,
+}
+
+impl From<OpenChannelAndPayParams> for NodeRequest {
+    fn from(params: OpenChannelAndPayParams) -> Self {
+        Self::OpenChannelAndPay {
+            request: params.request,
+            invoice: params.invoice,
+            ready_timeout_ms: params.ready_timeout_ms,
         }
     }
 }
@@ -86,12 +313,14 @@ impl From<BatchOpenChannelParams> for NodeRequest {
 #[derive(Deserialize)]
 pub struct SendPaymentParams {
     pub invoice: String,
+    pub max_path_length: Option<u8>,
 }
 
 impl From<SendPaymentParams> for NodeRequest {
     fn from(params: SendPaymentParams) -> Self {
         Self::SendPayment {
             invoice: params.invoice,
+            max_path_length: params.max_path_length,
         }
     }
 }
@@ -143,6 +372,21 @@ pub struct CloseChannelParams {
     pub force: bool,
 }
 
+#[derive(Deserialize)]
+pub struct CloseChannelsParams {
+    pub requests: Vec<BatchCloseRequest>,
+    pub timeout_ms: Option<u64>,
+}
+
+impl From<CloseChannelsParams> for NodeRequest {
+    fn from(params: CloseChannelsParams) -> Self {
+        Self::CloseChannels {
+            requests: params.requests,
+            timeout_ms: params.timeout_ms,
+        }
+    }
+}
+
 impl From<CloseChannelParams> for NodeRequest {
     fn from(params: CloseChannelParams) -> Self {
         Self::CloseChannel {
@@ -152,6 +396,241 @@ impl From<CloseChannelParams> for NodeRequest {
     }
 }
 
+#[derive(Deserialize)]
+pub struct ChannelTimelineParams {
+    pub channel_id: String,
+}
+
+impl From<ChannelTimelineParams> for NodeRequest {
+    fn from(params: ChannelTimelineParams) -> Self {
+        Self::ChannelTimeline {
+            channel_id: params.channel_id,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ChannelFundingInputsParams {
+    pub channel_id: String,
+}
+
+impl From<ChannelFundingInputsParams> for NodeRequest {
+    fn from(params: ChannelFundingInputsParams) -> Self {
+        Self::GetChannelFundingInputs {
+            channel_id: params.channel_id,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ChannelHtlcStatsParams {
+    pub channel_id: String,
+}
+
+impl From<ChannelHtlcStatsParams> for NodeRequest {
+    fn from(params: ChannelHtlcStatsParams) -> Self {
+        Self::GetChannelHtlcStats {
+            channel_id: params.channel_id,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct EstimateForwardFeeParams {
+    pub in_channel_id: String,
+    pub out_channel_id: String,
+    pub amount_msat: u64,
+}
+
+impl From<EstimateForwardFeeParams> for NodeRequest {
+    fn from(params: EstimateForwardFeeParams) -> Self {
+        Self::EstimateForwardFee {
+            in_channel_id: params.in_channel_id,
+            out_channel_id: params.out_channel_id,
+            amount_msat: params.amount_msat,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct GetGraphNodeParams {
+    pub pubkey: String,
+}
+
+impl From<GetGraphNodeParams> for NodeRequest {
+    fn from(params: GetGraphNodeParams) -> Self {
+        Self::GetGraphNode {
+            pubkey: params.pubkey,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct FindGraphPathParams {
+    pub from_pubkey: Option<String>,
+    pub to_pubkey: String,
+}
+
+impl From<FindGraphPathParams> for NodeRequest {
+    fn from(params: FindGraphPathParams) -> Self {
+        Self::FindGraphPath {
+            from_pubkey: params.from_pubkey,
+            to_pubkey: params.to_pubkey,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SimulateForceCloseParams {
+    pub channel_id: String,
+    pub sat_per_vb: u64,
+}
+
+impl From<SimulateForceCloseParams> for NodeRequest {
+    fn from(params: SimulateForceCloseParams) -> Self {
+        Self::SimulateForceClose {
+            channel_id: params.channel_id,
+            sat_per_vb: params.sat_per_vb,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ChannelUptimeParams {
+    pub channel_id: String,
+    pub window_secs: u64,
+}
+
+impl From<ChannelUptimeParams> for NodeRequest {
+    fn from(params: ChannelUptimeParams) -> Self {
+        Self::ChannelUptime {
+            channel_id: params.channel_id,
+            window_secs: params.window_secs,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct FeeRevenueRateParams {
+    pub window_secs: u64,
+}
+
+impl From<FeeRevenueRateParams> for NodeRequest {
+    fn from(params: FeeRevenueRateParams) -> Self {
+        Self::FeeRevenueRate {
+            window_secs: params.window_secs,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ExportChannelMonitorParams {
+    pub channel_id: String,
+    pub passphrase: String,
+}
+
+impl From<ExportChannelMonitorParams> for NodeRequest {
+    fn from(params: ExportChannelMonitorParams) -> Self {
+        Self::ExportChannelMonitor {
+            channel_id: params.channel_id,
+            passphrase: params.passphrase,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ImportChannelMonitorParams {
+    pub monitor: String,
+    pub passphrase: String,
+    pub accept_force_close: bool,
+}
+
+impl From<ImportChannelMonitorParams> for NodeRequest {
+    fn from(params: ImportChannelMonitorParams) -> Self {
+        Self::ImportChannelMonitor {
+            monitor: params.monitor,
+            passphrase: params.passphrase,
+            accept_force_close: params.accept_force_close,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct FundingConfirmationEtaParams {
+    pub channel_id: String,
+}
+
+impl From<FundingConfirmationEtaParams> for NodeRequest {
+    fn from(params: FundingConfirmationEtaParams) -> Self {
+        Self::FundingConfirmationEta {
+            channel_id: params.channel_id,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SetScorerLiquidityParams {
+    pub short_channel_id: u64,
+    pub target_pubkey: String,
+    pub min_liquidity_msat: u64,
+    pub max_liquidity_msat: u64,
+}
+
+impl From<SetScorerLiquidityParams> for NodeRequest {
+    fn from(params: SetScorerLiquidityParams) -> Self {
+        Self::SetScorerLiquidity {
+            short_channel_id: params.short_channel_id,
+            target_pubkey: params.target_pubkey,
+            min_liquidity_msat: params.min_liquidity_msat,
+            max_liquidity_msat: params.max_liquidity_msat,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SendAlongRouteParams {
+    pub hops: Vec<String>,
+    pub amount_msat: u64,
+    pub payment_hash: String,
+    pub payment_secret: Option<String>,
+    pub final_cltv_expiry_delta: Option<u32>,
+}
+
+impl From<SendAlongRouteParams> for NodeRequest {
+    fn from(params: SendAlongRouteParams) -> Self {
+        Self::SendAlongRoute {
+            hops: params.hops,
+            amount_msat: params.amount_msat,
+            payment_hash: params.payment_hash,
+            payment_secret: params.payment_secret,
+            final_cltv_expiry_delta: params.final_cltv_expiry_delta,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct RebalanceToTargetParams {
+    pub channel_id: String,
+    pub target_local_msat: u64,
+    pub max_fee_msat: u64,
+}
+
+impl From<RebalanceToTargetParams> for NodeRequest {
+    fn from(params: RebalanceToTargetParams) -> Self {
+        Self::RebalanceToTarget {
+            channel_id: params.channel_id,
+            target_local_msat: params.target_local_msat,
+            max_fee_msat: params.max_fee_msat,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ExportLedgerParams {
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+}
+
 #[derive(Deserialize)]
 pub struct StartNodeParams {
     pub passphrase: String,
@@ -223,14 +702,63 @@ impl From<RemoveKnownPeerParams> for NodeRequest {
     }
 }
 
-pub fn add_routes(router: Router) -> Router {
-    router
-        .route("/v1/node/payments", get(handle_get_payments))
-        .route("/v1/node/wallet/address", get(get_unused_address))
+#[derive(Deserialize)]
+pub struct CreateSubscriptionParams {
+    pub amt_msat: u64,
+    pub description: String,
+    pub interval_secs: u64,
+    pub first_invoice_at: Option<i64>,
+}
+
+impl From<CreateSubscriptionParams> for NodeRequest {
+    fn from(params: CreateSubscriptionParams) -> Self {
+        Self::CreateSubscription {
+            amt_msat: params.amt_msat,
+            description: params.description,
+            interval_secs: params.interval_secs,
+            first_invoice_at: params.first_invoice_at,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct DeleteSubscriptionParams {
+    pub id: String,
+}
+
+impl From<DeleteSubscriptionParams> for NodeRequest {
+    fn from(params: DeleteSubscriptionParams) -> Self {
+        Self::DeleteSubscription { id: params.id }
+    }
+}
+
+pub fn add_routes(router: Router) -> Router {
+    router
+        .route("/v1/node/payments", get(handle_get_payments))
+        .route("/v1/node/wallet/address", get(get_unused_address))
+        .route("/v1/node/wallet/deposit-address", post(new_deposit_address))
         .route("/v1/node/wallet/balance", get(get_wallet_balance))
+        .route("/v1/node/wallet/sync", post(sync_wallet))
+        .route("/v1/node/wallet/rescan", post(rescan_wallet))
+        .route("/v1/node/wallet/rebroadcast", post(rebroadcast_unconfirmed))
+        .route(
+            "/v1/node/wallet/psbt/broadcast",
+            post(sign_and_broadcast_psbt),
+        )
+        .route("/v1/node/wallet/scanned", get(get_scanned_addresses))
+        .route(
+            "/v1/node/wallet/cached-addresses",
+            post(cache_wallet_addresses),
+        )
         .route("/v1/node/wallet/utxos", get(list_unspent))
         .route("/v1/node/channels", get(get_channels))
+        .route("/v1/node/liquidity", get(get_liquidity_summary))
+        .route("/v1/node/channels/by-peer", get(get_channels_by_peer))
+        .route("/v1/node/feerates", get(get_feerates))
+        .route("/v1/node/chain/tip", get(get_chain_tip))
         .route("/v1/node/transactions", get(get_transactions))
+        .route("/v1/node/ledger", get(export_ledger))
+        .route("/v1/node/forwards/revenue", get(get_fee_revenue_rate))
         .route("/v1/node/info", get(get_info))
         .route("/v1/node/peers", get(get_peers))
         .route("/v1/node/stop", get(stop_node))
@@ -239,17 +767,75 @@ pub fn add_routes(router: Router) -> Router {
         .route("/v1/node/invoices/pay", post(pay_invoice))
         .route("/v1/node/invoices/decode", post(decode_invoice))
         .route("/v1/node/payments/label", post(label_payment))
+        .route("/v1/node/payments/metadata", post(set_payment_metadata))
+        .route("/v1/node/invoices/metadata", post(set_invoice_metadata))
         .route("/v1/node/payments/delete", post(delete_payment))
+        .route("/v1/node/payments/held/resolve", post(resolve_held_payment))
         .route("/v1/node/channels/open", post(open_channels))
+        .route("/v1/node/channels/open-single", post(open_channel))
+        .route("/v1/node/channels/open-and-pay", post(open_channel_and_pay))
+        .route("/v1/node/channels/open/build", post(build_batch_funding))
+        .route(
+            "/v1/node/channels/open/finalize",
+            post(finalize_batch_funding),
+        )
+        .route("/v1/node/channels/validate", post(validate_channel_batch))
         .route("/v1/node/channels/close", post(close_channel))
+        .route("/v1/node/channels/close-batch", post(close_channels))
+        .route("/v1/node/channels/pending", get(list_pending_channels))
+        .route("/v1/node/stale-state", get(get_stale_state))
+        .route(
+            "/v1/node/stale-state/acknowledge",
+            post(acknowledge_stale_state),
+        )
+        .route("/v1/node/channels/open/inflight", get(list_inflight_opens))
+        .route("/v1/node/channels/open/cancel", post(cancel_inflight_open))
+        .route("/v1/node/channels/uptime", get(get_channel_uptime))
+        .route(
+            "/v1/node/channels/monitor/export",
+            post(export_channel_monitor),
+        )
+        .route(
+            "/v1/node/channels/monitor/import",
+            post(import_channel_monitor),
+        )
+        .route("/v1/node/channels/funding/bump", post(bump_funding_fee))
+        .route(
+            "/v1/node/channels/funding-eta",
+            get(get_funding_confirmation_eta),
+        )
+        .route("/v1/node/channels/timeline", get(get_channel_timeline))
+        .route(
+            "/v1/node/channels/funding-inputs",
+            get(get_channel_funding_inputs),
+        )
+        .route("/v1/node/channels/htlc-stats", get(get_channel_htlc_stats))
+        .route("/v1/node/channels/label", post(label_channel))
+        .route(
+            "/v1/node/channels/simulate-force-close",
+            get(simulate_force_close),
+        )
+        .route(
+            "/v1/node/channels/estimate-forward-fee",
+            get(estimate_forward_fee),
+        )
         .route("/v1/node/keysend", post(keysend))
+        .route("/v1/node/payments/route", post(send_along_route))
+        .route("/v1/node/channels/rebalance", post(rebalance_to_target))
         .route("/v1/node/peers/connect", post(connect_peer))
         .route("/v1/node/sign/message", post(sign_message))
         .route("/v1/node/verify/message", post(verify_message))
         .route("/v1/node/network-graph/info", get(network_graph_info))
+        .route("/v1/node/network-graph/node", get(get_graph_node))
+        .route("/v1/node/network-graph/path", get(find_graph_path))
+        .route("/v1/node/gossip", get(get_gossip))
+        .route("/v1/node/scorer/liquidity", post(set_scorer_liquidity))
         .route("/v1/node/known-peers", get(list_known_peers))
         .route("/v1/node/known-peers", post(add_known_peer))
         .route("/v1/node/known-peers", delete(remove_known_peer))
+        .route("/v1/node/subscriptions", get(list_subscriptions))
+        .route("/v1/node/subscriptions", post(create_subscription))
+        .route("/v1/node/subscriptions", delete(delete_subscription))
 }
 
 pub async fn get_unused_address(
@@ -274,6 +860,116 @@ pub async fn get_wallet_balance(
     handle_authenticated_request(admin_service, NodeRequest::GetBalance {}, macaroon, cookies).await
 }
 
+pub async fn get_liquidity_summary(
+    Extension(admin_service): Extension<Arc<AdminService>>,
+    AuthHeader { macaroon, token: _ }: AuthHeader,
+    cookies: Cookies,
+) -> Result<Json<NodeResponse>, StatusCode> {
+    handle_authenticated_request(
+        admin_service,
+        NodeRequest::LiquiditySummary {},
+        macaroon,
+        cookies,
+    )
+    .await
+}
+
+pub async fn get_channels_by_peer(
+    Extension(admin_service): Extension<Arc<AdminService>>,
+    AuthHeader { macaroon, token: _ }: AuthHeader,
+    cookies: Cookies,
+) -> Result<Json<NodeResponse>, StatusCode> {
+    handle_authenticated_request(
+        admin_service,
+        NodeRequest::ChannelsByPeer {},
+        macaroon,
+        cookies,
+    )
+    .await
+}
+
+pub async fn get_chain_tip(
+    Extension(admin_service): Extension<Arc<AdminService>>,
+    AuthHeader { macaroon, token: _ }: AuthHeader,
+    cookies: Cookies,
+) -> Result<Json<NodeResponse>, StatusCode> {
+    handle_authenticated_request(
+        admin_service,
+        NodeRequest::GetChainTip {},
+        macaroon,
+        cookies,
+    )
+    .await
+}
+
+pub async fn get_feerates(
+    Extension(admin_service): Extension<Arc<AdminService>>,
+    AuthHeader { macaroon, token: _ }: AuthHeader,
+    cookies: Cookies,
+) -> Result<Json<NodeResponse>, StatusCode> {
+    handle_authenticated_request(
+        admin_service,
+        NodeRequest::GetFeerates {},
+        macaroon,
+        cookies,
+    )
+    .await
+}
+
+pub async fn sync_wallet(
+    Extension(admin_service): Extension<Arc<AdminService>>,
+    AuthHeader { macaroon, token: _ }: AuthHeader,
+    cookies: Cookies,
+) -> Result<Json<NodeResponse>, StatusCode> {
+    handle_authenticated_request(admin_service, NodeRequest::SyncWallet {}, macaroon, cookies).await
+}
+
+pub async fn sign_and_broadcast_psbt(
+    Extension(admin_service): Extension<Arc<AdminService>>,
+    Json(payload): Json<Value>,
+    AuthHeader { macaroon, token: _ }: AuthHeader,
+    cookies: Cookies,
+) -> Result<Json<NodeResponse>, StatusCode> {
+    let request = {
+        let params: Result<SignAndBroadcastPsbtParams, _> = serde_json::from_value(payload);
+        match params {
+            Ok(params) => Ok(params.into()),
+            Err(_) => Err(StatusCode::UNPROCESSABLE_ENTITY),
+        }
+    }?;
+    handle_authenticated_request(admin_service, request, macaroon, cookies).await
+}
+
+pub async fn bump_funding_fee(
+    Extension(admin_service): Extension<Arc<AdminService>>,
+    Json(payload): Json<Value>,
+    AuthHeader { macaroon, token: _ }: AuthHeader,
+    cookies: Cookies,
+) -> Result<Json<NodeResponse>, StatusCode> {
+    let request = {
+        let params: Result<BumpFundingFeeParams, _> = serde_json::from_value(payload);
+        match params {
+            Ok(params) => Ok(params.into()),
+            Err(_) => Err(StatusCode::UNPROCESSABLE_ENTITY),
+        }
+    }?;
+    handle_authenticated_request(admin_service, request, macaroon, cookies).await
+}
+
+pub async fn rebroadcast_unconfirmed(
+    Extension(admin_service): Extension<Arc<AdminService>>,
+    AuthHeader { macaroon, token: _ }: AuthHeader,
+    cookies: Cookies,
+) -> Result<Json<NodeResponse>, StatusCode> {
+    handle_authenticated_request(
+        admin_service,
+        NodeRequest::RebroadcastUnconfirmed {},
+        macaroon,
+        cookies,
+    )
+    .await
+}
+
 pub async fn handle_get_payments(
     Extension(admin_service): Extension<Arc<AdminService>>,
     Query(params): Query<ListPaymentsParams>,
@@ -301,117 +997,557 @@ pub async fn get_channels(
     handle_authenticated_request(admin_service, request, macaroon, cookies).await
 }
 
+pub async fn get_channel_timeline(
+    Extension(admin_service): Extension<Arc<AdminService>>,
+    Query(params): Query<ChannelTimelineParams>,
+    AuthHeader { macaroon, token: _ }: AuthHeader,
+    cookies: Cookies,
+) -> Result<Json<NodeResponse>, StatusCode> {
+    handle_authenticated_request(admin_service, params.into(), macaroon, cookies).await
+}
+
+pub async fn get_channel_funding_inputs(
+    Extension(admin_service): Extension<Arc<AdminService>>,
+    Query(params): Query<ChannelFundingInputsParams>,
+    AuthHeader { macaroon, token: _ }: AuthHeader,
+    cookies: Cookies,
+) -> Result<Json<NodeResponse>, StatusCode> {
+    handle_authenticated_request(admin_service, params.into(), macaroon, cookies).await
+}
+
+pub async fn get_channel_htlc_stats(
+    Extension(admin_service): Extension<Arc<AdminService>>,
+    Query(params): Query<ChannelHtlcStatsParams>,
+    AuthHeader { macaroon, token: _ }: AuthHeader,
+    cookies: Cookies,
+) -> Result<Json<NodeResponse>, StatusCode> {
+    handle_authenticated_request(admin_service, params.into(), macaroon, cookies).await
+}
+
+pub async fn simulate_force_close(
+    Extension(admin_service): Extension<Arc<AdminService>>,
+    Query(params): Query<SimulateForceCloseParams>,
+    AuthHeader { macaroon, token: _ }: AuthHeader,
+    cookies: Cookies,
+) -> Result<Json<NodeResponse>, StatusCode> {
+    handle_authenticated_request(admin_service, params.into(), macaroon, cookies).await
+}
+
+pub async fn get_channel_uptime(
+    Extension(admin_service): Extension<Arc<AdminService>>,
+    Query(params): Query<ChannelUptimeParams>,
+    AuthHeader { macaroon, token: _ }: AuthHeader,
+    cookies: Cookies,
+) -> Result<Json<NodeResponse>, StatusCode> {
+    handle_authenticated_request(admin_service, params.into(), macaroon, cookies).await
+}
+
+pub async fn get_fee_revenue_rate(
+    Extension(admin_service): Extension<Arc<AdminService>>,
+    Query(params): Query<FeeRevenueRateParams>,
+    AuthHeader { macaroon, token: _ }: AuthHeader,
+    cookies: Cookies,
+) -> Result<Json<NodeResponse>, StatusCode> {
+    handle_authenticated_request(admin_service, params.into(), macaroon, cookies).await
+}
+
+pub async fn export_channel_monitor(
+    Extension(admin_service): Extension<Arc<AdminService>>,
+    Json(payload): Json<Value>,
+    AuthHeader { macaroon, token: _ }: AuthHeader,
+    cookies: Cookies,
+) -> Result<Json<NodeResponse>, StatusCode> {
+    let request = {
+        let params: Result<ExportChannelMonitorParams, _> = serde_json::from_value(payload);
+        match params {
+            Ok(params) => Ok(params.into()),
+            Err(_) => Err(StatusCode::UNPROCESSABLE_ENTITY),
+        }
+    }?;
+    handle_authenticated_request(admin_service, request, macaroon, cookies).await
+}
+
+pub async fn import_channel_monitor(
+    Extension(admin_service): Extension<Arc<AdminService>>,
+    Json(payload): Json<Value>,
+    AuthHeader { macaroon, token: _ }: AuthHeader,
+    cookies: Cookies,
+) -> Result<Json<NodeResponse>, StatusCode> {
+    let request = {
+        let params: Result<ImportChannelMonitorParams, _> = serde_json::from_value(payload);
+        match params {
+            Ok(params) => Ok(params.into()),
+            Err(_) => Err(StatusCode::UNPROCESSABLE_ENTITY),
+        }
+    }?;
+    handle_authenticated_request(admin_service, request, macaroon, cookies).await
+}
+
+pub async fn get_funding_confirmation_eta(
+    Extension(admin_service): Extension<Arc<AdminService>>,
+    Query(params): Query<FundingConfirmationEtaParams>,
+    AuthHeader { macaroon, token: _ }: AuthHeader,
+    cookies: Cookies,
+) -> Result<Json<NodeResponse>, StatusCode> {
+    handle_authenticated_request(admin_service, params.into(), macaroon, cookies).await
+}
+
+pub async fn estimate_forward_fee(
+    Extension(admin_service): Extension<Arc<AdminService>>,
+    Query(params): Query<EstimateForwardFeeParams>,
+    AuthHeader { macaroon, token: _ }: AuthHeader,
+    cookies: Cookies,
+) -> Result<Json<NodeResponse>, StatusCode> {
+    handle_authenticated_request(admin_service, params.into(), macaroon, cookies).await
+}
+
 pub async fn get_transactions(
     Extension(admin_service): Extension<Arc<AdminService>>,
     Query(params): Query<ListTransactionsParams>,
     AuthHeader { macaroon, token: _ }: AuthHeader,
     cookies: Cookies,
 ) -> Result<Json<NodeResponse>, StatusCode> {
-    let request = NodeRequest::ListTransactions {
-        pagination: params.clone().into(),
-    };
-
+    let request = NodeRequest::ListTransactions {
+        pagination: params.clone().into(),
+    };
+
+    handle_authenticated_request(admin_service, request, macaroon, cookies).await
+}
+
+pub async fn get_info(
+    Extension(admin_service): Extension<Arc<AdminService>>,
+    AuthHeader { macaroon, token: _ }: AuthHeader,
+    cookies: Cookies,
+) -> Result<Json<NodeResponse>, StatusCode> {
+    handle_authenticated_request(admin_service, NodeRequest::NodeInfo {}, macaroon, cookies).await
+}
+
+pub async fn get_peers(
+    Extension(admin_service): Extension<Arc<AdminService>>,
+    AuthHeader { macaroon, token: _ }: AuthHeader,
+    cookies: Cookies,
+) -> Result<Json<NodeResponse>, StatusCode> {
+    handle_authenticated_request(admin_service, NodeRequest::ListPeers {}, macaroon, cookies).await
+}
+
+pub async fn stop_node(
+    Extension(admin_service): Extension<Arc<AdminService>>,
+    AuthHeader { macaroon, token: _ }: AuthHeader,
+    cookies: Cookies,
+) -> Result<Json<NodeResponse>, StatusCode> {
+    handle_authenticated_request(admin_service, NodeRequest::StopNode {}, macaroon, cookies).await
+}
+
+// The ledger can get large so it bypasses NodeRequest and its single json response. Entries
+// are read and serialized one by one as the body is streamed out.
+pub async fn export_ledger(
+    Extension(admin_service): Extension<Arc<AdminService>>,
+    Query(params): Query<ExportLedgerParams>,
+    AuthHeader { macaroon, token: _ }: AuthHeader,
+    cookies: Cookies,
+) -> Result<Response, StatusCode> {
+    let node = get_authenticated_node(admin_service, macaroon, cookies).await?;
+    let cursor = node
+        .ledger(params.from.unwrap_or(0), params.to.unwrap_or(i64::MAX))
+        .map_err(|_e| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let entries = futures::stream::unfold(Some(cursor), |cursor| async move {
+        let mut cursor = cursor?;
+        match cursor.next_entry().await {
+            Ok(Some(entry)) => Some((Ok(entry), Some(cursor))),
+            Ok(None) => None,
+            // ends the stream after the error, which aborts the response
+            Err(e) => Some((Err(e), None)),
+        }
+    });
+    let chunks = entries.enumerate().map(|(index, entry)| {
+        let separator = if index == 0 { "" } else { "," };
+        let json = match entry {
+            Ok(entry) => serde_json::to_string(&entry).map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+        json.map(|json| format!("{}{}", separator, json))
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))
+    });
+    let body = futures::stream::once(async { Ok(String::from("[")) })
+        .chain(chunks)
+        .chain(futures::stream::once(async { Ok(String::from("]")) }));
+
+    Ok(Response::builder()
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(boxed(StreamBody::new(body)))
+        .unwrap())
+}
+
+async fn get_authenticated_node(
+    admin_service: Arc<AdminService>,
+    macaroon: Option<HeaderValue>,
+    cookies: Cookies,
+) -> Result<Arc<LightningNode>, StatusCode> {
+    let macaroon_hex_string = get_macaroon_hex_str_from_cookies_or_header(&cookies, macaroon)?;
+
+    let (macaroon, session) = utils::macaroon_with_session_from_hex_str(&macaroon_hex_string)
+        .map_err(|_e| StatusCode::UNAUTHORIZED)?;
+
+    let node = {
+        let node_directory = admin_service.node_directory.lock().await;
+        match node_directory.get(&session.pubkey) {
+            Some(Some(handle)) => handle.node.clone(),
+            _ => return Err(StatusCode::UNAUTHORIZED),
+        }
+    };
+
+    node.verify_macaroon(macaroon, session)
+        .await
+        .map_err(|_e| StatusCode::UNAUTHORIZED)?;
+
+    Ok(node)
+}
+
+pub async fn handle_authenticated_request(
+    admin_service: Arc<AdminService>,
+    request: NodeRequest,
+    macaroon: Option<HeaderValue>,
+    cookies: Cookies,
+) -> Result<Json<NodeResponse>, StatusCode> {
+    let macaroon_hex_string = get_macaroon_hex_str_from_cookies_or_header(&cookies, macaroon)?;
+
+    let (macaroon, session) = utils::macaroon_with_session_from_hex_str(&macaroon_hex_string)
+        .map_err(|_e| StatusCode::UNAUTHORIZED)?;
+
+    let pubkey = session.pubkey.clone();
+    let node_directory = admin_service.node_directory.lock().await;
+    let node = node_directory.get(&session.pubkey);
+
+    match node {
+        Some(Some(handle)) => {
+            handle
+                .node
+                .verify_macaroon(macaroon, session)
+                .await
+                .map_err(|_e| StatusCode::UNAUTHORIZED)?;
+
+            match request {
+                NodeRequest::StopNode {} => {
+                    let admin_request = AdminRequest::StopNode { pubkey };
+                    let _ = admin_service
+                        .call(admin_request)
+                        .await
+                        .map_err(|_e| StatusCode::UNPROCESSABLE_ENTITY)?;
+                    Ok(Json(NodeResponse::StopNode {}))
+                }
+                _ => match handle.node.call(request).await {
+                    Ok(response) => Ok(Json(response)),
+                    Err(err) => Ok(Json(NodeResponse::Error(err))),
+                },
+            }
+        }
+        Some(None) => {
+            // TODO: rethink this Some(None) business
+            let err = senseicore::error::Error::Unauthenticated;
+            let node_request_error: NodeRequestError = err.into();
+            Ok(Json(NodeResponse::Error(node_request_error)))
+        }
+        None => match request {
+            NodeRequest::StartNode { passphrase } => {
+                drop(node_directory);
+                let req = AdminRequest::StartNode {
+                    passphrase,
+                    pubkey: session.pubkey,
+                };
+                let _ = admin_service
+                    .call(req)
+                    .await
+                    .map_err(|_e| StatusCode::UNAUTHORIZED)?;
+                Ok(Json(NodeResponse::StartNode {}))
+            }
+            _ => {
+                let err = senseicore::error::Error::Unauthenticated;
+                let node_request_error: NodeRequestError = err.into();
+                Ok(Json(NodeResponse::Error(node_request_error)))
+            }
+        },
+    }
+}
+
+pub async fn start_node(
+    Extension(admin_service): Extension<Arc<AdminService>>,
+    Json(payload): Json<Value>,
+    AuthHeader { macaroon, token: _ }: AuthHeader,
+    cookies: Cookies,
+) -> Result<Json<NodeResponse>, StatusCode> {
+    let request = {
+        let params: Result<StartNodeParams, _> = serde_json::from_value(payload);
+        match params {
+            Ok(params) => Ok(params.into()),
+            Err(_) => Err(StatusCode::UNPROCESSABLE_ENTITY),
+        }
+    }?;
+    handle_authenticated_request(admin_service, request, macaroon, cookies).await
+}
+
+pub async fn create_invoice(
+    Extension(admin_service): Extension<Arc<AdminService>>,
+    Json(payload): Json<Value>,
+    AuthHeader { macaroon, token: _ }: AuthHeader,
+    cookies: Cookies,
+) -> Result<Json<NodeResponse>, StatusCode> {
+    let request = {
+        let params: Result<GetInvoiceParams, _> = serde_json::from_value(payload);
+        match params {
+            Ok(params) => Ok(params.into()),
+            Err(_) => Err(StatusCode::UNPROCESSABLE_ENTITY),
+        }
+    }?;
+    handle_authenticated_request(admin_service, request, macaroon, cookies).await
+}
+
+pub async fn label_payment(
+    Extension(admin_service): Extension<Arc<AdminService>>,
+    Json(payload): Json<Value>,
+    AuthHeader { macaroon, token: _ }: AuthHeader,
+    cookies: Cookies,
+) -> Result<Json<NodeResponse>, StatusCode> {
+    let request = {
+        let params: Result<LabelPaymentParams, _> = serde_json::from_value(payload);
+        match params {
+            Ok(params) => Ok(params.into()),
+            Err(_) => Err(StatusCode::UNPROCESSABLE_ENTITY),
+        }
+    }?;
+    handle_authenticated_request(admin_service, request, macaroon, cookies).await
+}
+
+pub async fn label_channel(
+    Extension(admin_service): Extension<Arc<AdminService>>,
+    Json(payload): Json<Value>,
+    AuthHeader { macaroon, token: _ }: AuthHeader,
+    cookies: Cookies,
+) -> Result<Json<NodeResponse>, StatusCode> {
+    let request = {
+        let params: Result<LabelChannelParams, _> = serde_json::from_value(payload);
+        match params {
+            Ok(params) => Ok(params.into()),
+            Err(_) => Err(StatusCode::UNPROCESSABLE_ENTITY),
+        }
+    }?;
+    handle_authenticated_request(admin_service, request, macaroon, cookies).await
+}
+
+pub async fn set_payment_metadata(
+    Extension(admin_service): Extension<Arc<AdminService>>,
+    Json(payload): Json<Value>,
+    AuthHeader { macaroon, token: _ }: AuthHeader,
+    cookies: Cookies,
+) -> Result<Json<NodeResponse>, StatusCode> {
+    let request = {
+        let params: Result<PaymentMetadataParams, _> = serde_json::from_value(payload);
+        match params {
+            Ok(params) => Ok(params.into()),
+            Err(_) => Err(StatusCode::UNPROCESSABLE_ENTITY),
+        }
+    }?;
+    handle_authenticated_request(admin_service, request, macaroon, cookies).await
+}
+
+pub async fn set_invoice_metadata(
+    Extension(admin_service): Extension<Arc<AdminService>>,
+    Json(payload): Json<Value>,
+    AuthHeader { macaroon, token: _ }: AuthHeader,
+    cookies: Cookies,
+) -> Result<Json<NodeResponse>, StatusCode> {
+    let request = {
+        let params: Result<InvoiceMetadataParams, _> = serde_json::from_value(payload);
+        match params {
+            Ok(params) => Ok(params.into()),
+            Err(_) => Err(StatusCode::UNPROCESSABLE_ENTITY),
+        }
+    }?;
+    handle_authenticated_request(admin_service, request, macaroon, cookies).await
+}
+
+pub async fn new_deposit_address(
+    Extension(admin_service): Extension<Arc<AdminService>>,
+    Json(payload): Json<Value>,
+    AuthHeader { macaroon, token: _ }: AuthHeader,
+    cookies: Cookies,
+) -> Result<Json<NodeResponse>, StatusCode> {
+    let request = {
+        let params: Result<NewDepositAddressParams, _> = serde_json::from_value(payload);
+        match params {
+            Ok(params) => Ok(params.into()),
+            Err(_) => Err(StatusCode::UNPROCESSABLE_ENTITY),
+        }
+    }?;
+    handle_authenticated_request(admin_service, request, macaroon, cookies).await
+}
+
+pub async fn get_scanned_addresses(
+    Extension(admin_service): Extension<Arc<AdminService>>,
+    AuthHeader { macaroon, token: _ }: AuthHeader,
+    cookies: Cookies,
+) -> Result<Json<NodeResponse>, StatusCode> {
+    handle_authenticated_request(
+        admin_service,
+        NodeRequest::GetScannedAddresses {},
+        macaroon,
+        cookies,
+    )
+    .await
+}
+
+pub async fn cache_wallet_addresses(
+    Extension(admin_service): Extension<Arc<AdminService>>,
+    Json(payload): Json<Value>,
+    AuthHeader { macaroon, token: _ }: AuthHeader,
+    cookies: Cookies,
+) -> Result<Json<NodeResponse>, StatusCode> {
+    let request = {
+        let params: Result<CacheWalletAddressesParams, _> = serde_json::from_value(payload);
+        match params {
+            Ok(params) => Ok(params.into()),
+            Err(_) => Err(StatusCode::UNPROCESSABLE_ENTITY),
+        }
+    }?;
+    handle_authenticated_request(admin_service, request, macaroon, cookies).await
+}
+
+pub async fn rescan_wallet(
+    Extension(admin_service): Extension<Arc<AdminService>>,
+    Json(payload): Json<Value>,
+    AuthHeader { macaroon, token: _ }: AuthHeader,
+    cookies: Cookies,
+) -> Result<Json<NodeResponse>, StatusCode> {
+    let request = {
+        let params: Result<RescanWalletParams, _> = serde_json::from_value(payload);
+        match params {
+            Ok(params) => Ok(params.into()),
+            Err(_) => Err(StatusCode::UNPROCESSABLE_ENTITY),
+        }
+    }?;
+    handle_authenticated_request(admin_service, request, macaroon, cookies).await
+}
+
+pub async fn resolve_held_payment(
+    Extension(admin_service): Extension<Arc<AdminService>>,
+    Json(payload): Json<Value>,
+    AuthHeader { macaroon, token: _ }: AuthHeader,
+    cookies: Cookies,
+) -> Result<Json<NodeResponse>, StatusCode> {
+    let request = {
+        let params: Result<ResolveHeldPaymentParams, _> = serde_json::from_value(payload);
+        match params {
+            Ok(params) => Ok(params.into()),
+            Err(_) => Err(StatusCode::UNPROCESSABLE_ENTITY),
+        }
+    }?;
+    handle_authenticated_request(admin_service, request, macaroon, cookies).await
+}
+
+pub async fn delete_payment(
+    Extension(admin_service): Extension<Arc<AdminService>>,
+    Json(payload): Json<Value>,
+    AuthHeader { macaroon, token: _ }: AuthHeader,
+    cookies: Cookies,
+) -> Result<Json<NodeResponse>, StatusCode> {
+    let request = {
+        let params: Result<DeletePaymentParams, _> = serde_json::from_value(payload);
+        match params {
+            Ok(params) => Ok(params.into()),
+            Err(_) => Err(StatusCode::UNPROCESSABLE_ENTITY),
+        }
+    }?;
+    handle_authenticated_request(admin_service, request, macaroon, cookies).await
+}
+
+pub async fn pay_invoice(
+    Extension(admin_service): Extension<Arc<AdminService>>,
+    Json(payload): Json<Value>,
+    AuthHeader { macaroon, token: _ }: AuthHeader,
+    cookies: Cookies,
+) -> Result<Json<NodeResponse>, StatusCode> {
+    let request = {
+        let params: Result<SendPaymentParams, _> = serde_json::from_value(payload);
+        match params {
+            Ok(params) => Ok(params.into()),
+            Err(_) => Err(StatusCode::UNPROCESSABLE_ENTITY),
+        }
+    }?;
     handle_authenticated_request(admin_service, request, macaroon, cookies).await
 }
 
-pub async fn get_info(
+pub async fn decode_invoice(
     Extension(admin_service): Extension<Arc<AdminService>>,
+    Json(payload): Json<Value>,
     AuthHeader { macaroon, token: _ }: AuthHeader,
     cookies: Cookies,
 ) -> Result<Json<NodeResponse>, StatusCode> {
-    handle_authenticated_request(admin_service, NodeRequest::NodeInfo {}, macaroon, cookies).await
+    let request = {
+        let params: Result<DecodeInvoiceParams, _> = serde_json::from_value(payload);
+        match params {
+            Ok(params) => Ok(params.into()),
+            Err(_) => Err(StatusCode::UNPROCESSABLE_ENTITY),
+        }
+    }?;
+    handle_authenticated_request(admin_service, request, macaroon, cookies).await
 }
 
-pub async fn get_peers(
+pub async fn open_channel(
     Extension(admin_service): Extension<Arc<AdminService>>,
+    Json(payload): Json<Value>,
     AuthHeader { macaroon, token: _ }: AuthHeader,
     cookies: Cookies,
 ) -> Result<Json<NodeResponse>, StatusCode> {
-    handle_authenticated_request(admin_service, NodeRequest::ListPeers {}, macaroon, cookies).await
+    let request = {
+        let params: Result<OpenChannelParams, _> = serde_json::from_value(payload);
+        match params {
+            Ok(params) => Ok(params.into()),
+            Err(_) => Err(StatusCode::UNPROCESSABLE_ENTITY),
+        }
+    }?;
+    handle_authenticated_request(admin_service, request, macaroon, cookies).await
 }
 
-pub async fn stop_node(
+pub async fn open_channel_and_pay(
     Extension(admin_service): Extension<Arc<AdminService>>,
+    Json(payload): Json<Value>,
     AuthHeader { macaroon, token: _ }: AuthHeader,
     cookies: Cookies,
 ) -> Result<Json<NodeResponse>, StatusCode> {
-    handle_authenticated_request(admin_service, NodeRequest::StopNode {}, macaroon, cookies).await
+    let request = {
+        let params: Result<OpenChannelAndPayParams, _> = serde_json::from_value(payload);
+        match params {
+            Ok(params) => Ok(params.into()),
+            Err(_) => Err(StatusCode::UNPROCESSABLE_ENTITY),
+        }
+    }?;
+    handle_authenticated_request(admin_service, request, macaroon, cookies).await
 }
 
-pub async fn handle_authenticated_request(
-    admin_service: Arc<AdminService>,
-    request: NodeRequest,
-    macaroon: Option<HeaderValue>,
+pub async fn open_channels(
+    Extension(admin_service): Extension<Arc<AdminService>>,
+    Json(payload): Json<Value>,
+    AuthHeader { macaroon, token: _ }: AuthHeader,
     cookies: Cookies,
 ) -> Result<Json<NodeResponse>, StatusCode> {
-    let macaroon_hex_string = get_macaroon_hex_str_from_cookies_or_header(&cookies, macaroon)?;
-
-    let (macaroon, session) = utils::macaroon_with_session_from_hex_str(&macaroon_hex_string)
-        .map_err(|_e| StatusCode::UNAUTHORIZED)?;
-
-    let pubkey = session.pubkey.clone();
-    let node_directory = admin_service.node_directory.lock().await;
-    let node = node_directory.get(&session.pubkey);
-
-    match node {
-        Some(Some(handle)) => {
-            handle
-                .node
-                .verify_macaroon(macaroon, session)
-                .await
-                .map_err(|_e| StatusCode::UNAUTHORIZED)?;
-
-            match request {
-                NodeRequest::StopNode {} => {
-                    let admin_request = AdminRequest::StopNode { pubkey };
-                    let _ = admin_service
-                        .call(admin_request)
-                        .await
-                        .map_err(|_e| StatusCode::UNPROCESSABLE_ENTITY)?;
-                    Ok(Json(NodeResponse::StopNode {}))
-                }
-                _ => match handle.node.call(request).await {
-                    Ok(response) => Ok(Json(response)),
-                    Err(err) => Ok(Json(NodeResponse::Error(err))),
-                },
-            }
-        }
-        Some(None) => {
-            // TODO: rethink this Some(None) business
-            let err = senseicore::error::Error::Unauthenticated;
-            let node_request_error: NodeRequestError = err.into();
-            Ok(Json(NodeResponse::Error(node_request_error)))
+    let request = {
+        let params: Result<BatchOpenChannelParams, _> = serde_json::from_value(payload);
+        match params {
+            Ok(params) => Ok(params.into()),
+            Err(_) => Err(StatusCode::UNPROCESSABLE_ENTITY),
         }
-        None => match request {
-            NodeRequest::StartNode { passphrase } => {
-                drop(node_directory);
-                let req = AdminRequest::StartNode {
-                    passphrase,
-                    pubkey: session.pubkey,
-                };
-                let _ = admin_service
-                    .call(req)
-                    .await
-                    .map_err(|_e| StatusCode::UNAUTHORIZED)?;
-                Ok(Json(NodeResponse::StartNode {}))
-            }
-            _ => {
-                let err = senseicore::error::Error::Unauthenticated;
-                let node_request_error: NodeRequestError = err.into();
-                Ok(Json(NodeResponse::Error(node_request_error)))
-            }
-        },
-    }
+    }?;
+    handle_authenticated_request(admin_service, request, macaroon, cookies).await
 }
 
-pub async fn start_node(
+pub async fn build_batch_funding(
     Extension(admin_service): Extension<Arc<AdminService>>,
     Json(payload): Json<Value>,
     AuthHeader { macaroon, token: _ }: AuthHeader,
     cookies: Cookies,
 ) -> Result<Json<NodeResponse>, StatusCode> {
     let request = {
-        let params: Result<StartNodeParams, _> = serde_json::from_value(payload);
+        let params: Result<BuildBatchFundingParams, _> = serde_json::from_value(payload);
         match params {
             Ok(params) => Ok(params.into()),
             Err(_) => Err(StatusCode::UNPROCESSABLE_ENTITY),
@@ -420,14 +1556,14 @@ pub async fn start_node(
     handle_authenticated_request(admin_service, request, macaroon, cookies).await
 }
 
-pub async fn create_invoice(
+pub async fn finalize_batch_funding(
     Extension(admin_service): Extension<Arc<AdminService>>,
     Json(payload): Json<Value>,
     AuthHeader { macaroon, token: _ }: AuthHeader,
     cookies: Cookies,
 ) -> Result<Json<NodeResponse>, StatusCode> {
     let request = {
-        let params: Result<GetInvoiceParams, _> = serde_json::from_value(payload);
+        let params: Result<FinalizeBatchFundingParams, _> = serde_json::from_value(payload);
         match params {
             Ok(params) => Ok(params.into()),
             Err(_) => Err(StatusCode::UNPROCESSABLE_ENTITY),
@@ -436,30 +1572,32 @@ pub async fn create_invoice(
     handle_authenticated_request(admin_service, request, macaroon, cookies).await
 }
 
-pub async fn label_payment(
+pub async fn validate_channel_batch(
     Extension(admin_service): Extension<Arc<AdminService>>,
     Json(payload): Json<Value>,
     AuthHeader { macaroon, token: _ }: AuthHeader,
     cookies: Cookies,
 ) -> Result<Json<NodeResponse>, StatusCode> {
     let request = {
-        let params: Result<LabelPaymentParams, _> = serde_json::from_value(payload);
+        let params: Result<BatchOpenChannelParams, _> = serde_json::from_value(payload);
         match params {
-            Ok(params) => Ok(params.into()),
+            Ok(params) => Ok(NodeRequest::ValidateChannelBatch {
+                requests: params.requests,
+            }),
             Err(_) => Err(StatusCode::UNPROCESSABLE_ENTITY),
         }
     }?;
     handle_authenticated_request(admin_service, request, macaroon, cookies).await
 }
 
-pub async fn delete_payment(
+pub async fn close_channel(
     Extension(admin_service): Extension<Arc<AdminService>>,
     Json(payload): Json<Value>,
     AuthHeader { macaroon, token: _ }: AuthHeader,
     cookies: Cookies,
 ) -> Result<Json<NodeResponse>, StatusCode> {
     let request = {
-        let params: Result<DeletePaymentParams, _> = serde_json::from_value(payload);
+        let params: Result<CloseChannelParams, _> = serde_json::from_value(payload);
         match params {
             Ok(params) => Ok(params.into()),
             Err(_) => Err(StatusCode::UNPROCESSABLE_ENTITY),
@@ -468,14 +1606,14 @@ pub async fn delete_payment(
     handle_authenticated_request(admin_service, request, macaroon, cookies).await
 }
 
-pub async fn pay_invoice(
+pub async fn close_channels(
     Extension(admin_service): Extension<Arc<AdminService>>,
     Json(payload): Json<Value>,
     AuthHeader { macaroon, token: _ }: AuthHeader,
     cookies: Cookies,
 ) -> Result<Json<NodeResponse>, StatusCode> {
     let request = {
-        let params: Result<SendPaymentParams, _> = serde_json::from_value(payload);
+        let params: Result<CloseChannelsParams, _> = serde_json::from_value(payload);
         match params {
             Ok(params) => Ok(params.into()),
             Err(_) => Err(StatusCode::UNPROCESSABLE_ENTITY),
@@ -484,14 +1622,14 @@ pub async fn pay_invoice(
     handle_authenticated_request(admin_service, request, macaroon, cookies).await
 }
 
-pub async fn decode_invoice(
+pub async fn set_scorer_liquidity(
     Extension(admin_service): Extension<Arc<AdminService>>,
     Json(payload): Json<Value>,
     AuthHeader { macaroon, token: _ }: AuthHeader,
     cookies: Cookies,
 ) -> Result<Json<NodeResponse>, StatusCode> {
     let request = {
-        let params: Result<DecodeInvoiceParams, _> = serde_json::from_value(payload);
+        let params: Result<SetScorerLiquidityParams, _> = serde_json::from_value(payload);
         match params {
             Ok(params) => Ok(params.into()),
             Err(_) => Err(StatusCode::UNPROCESSABLE_ENTITY),
@@ -500,14 +1638,14 @@ pub async fn decode_invoice(
     handle_authenticated_request(admin_service, request, macaroon, cookies).await
 }
 
-pub async fn open_channels(
+pub async fn send_along_route(
     Extension(admin_service): Extension<Arc<AdminService>>,
     Json(payload): Json<Value>,
     AuthHeader { macaroon, token: _ }: AuthHeader,
     cookies: Cookies,
 ) -> Result<Json<NodeResponse>, StatusCode> {
     let request = {
-        let params: Result<BatchOpenChannelParams, _> = serde_json::from_value(payload);
+        let params: Result<SendAlongRouteParams, _> = serde_json::from_value(payload);
         match params {
             Ok(params) => Ok(params.into()),
             Err(_) => Err(StatusCode::UNPROCESSABLE_ENTITY),
@@ -516,14 +1654,14 @@ pub async fn open_channels(
     handle_authenticated_request(admin_service, request, macaroon, cookies).await
 }
 
-pub async fn close_channel(
+pub async fn rebalance_to_target(
     Extension(admin_service): Extension<Arc<AdminService>>,
     Json(payload): Json<Value>,
     AuthHeader { macaroon, token: _ }: AuthHeader,
     cookies: Cookies,
 ) -> Result<Json<NodeResponse>, StatusCode> {
     let request = {
-        let params: Result<CloseChannelParams, _> = serde_json::from_value(payload);
+        let params: Result<RebalanceToTargetParams, _> = serde_json::from_value(payload);
         match params {
             Ok(params) => Ok(params.into()),
             Err(_) => Err(StatusCode::UNPROCESSABLE_ENTITY),
@@ -624,6 +1762,104 @@ pub async fn network_graph_info(
     .await
 }
 
+pub async fn get_graph_node(
+    Extension(admin_service): Extension<Arc<AdminService>>,
+    Query(params): Query<GetGraphNodeParams>,
+    AuthHeader { macaroon, token: _ }: AuthHeader,
+    cookies: Cookies,
+) -> Result<Json<NodeResponse>, StatusCode> {
+    handle_authenticated_request(admin_service, params.into(), macaroon, cookies).await
+}
+
+pub async fn find_graph_path(
+    Extension(admin_service): Extension<Arc<AdminService>>,
+    Query(params): Query<FindGraphPathParams>,
+    AuthHeader { macaroon, token: _ }: AuthHeader,
+    cookies: Cookies,
+) -> Result<Json<NodeResponse>, StatusCode> {
+    handle_authenticated_request(admin_service, params.into(), macaroon, cookies).await
+}
+
+pub async fn list_inflight_opens(
+    Extension(admin_service): Extension<Arc<AdminService>>,
+    AuthHeader { macaroon, token: _ }: AuthHeader,
+    cookies: Cookies,
+) -> Result<Json<NodeResponse>, StatusCode> {
+    handle_authenticated_request(
+        admin_service,
+        NodeRequest::ListInflightOpens {},
+        macaroon,
+        cookies,
+    )
+    .await
+}
+
+pub async fn cancel_inflight_open(
+    Extension(admin_service): Extension<Arc<AdminService>>,
+    Json(payload): Json<Value>,
+    AuthHeader { macaroon, token: _ }: AuthHeader,
+    cookies: Cookies,
+) -> Result<Json<NodeResponse>, StatusCode> {
+    let request = {
+        let params: Result<CancelInflightOpenParams, _> = serde_json::from_value(payload);
+        match params {
+            Ok(params) => Ok(params.into()),
+            Err(_) => Err(StatusCode::UNPROCESSABLE_ENTITY),
+        }
+    }?;
+    handle_authenticated_request(admin_service, request, macaroon, cookies).await
+}
+
+pub async fn get_stale_state(
+    Extension(admin_service): Extension<Arc<AdminService>>,
+    AuthHeader { macaroon, token: _ }: AuthHeader,
+    cookies: Cookies,
+) -> Result<Json<NodeResponse>, StatusCode> {
+    handle_authenticated_request(
+        admin_service,
+        NodeRequest::GetStaleState {},
+        macaroon,
+        cookies,
+    )
+    .await
+}
+
+pub async fn acknowledge_stale_state(
+    Extension(admin_service): Extension<Arc<AdminService>>,
+    AuthHeader { macaroon, token: _ }: AuthHeader,
+    cookies: Cookies,
+) -> Result<Json<NodeResponse>, StatusCode> {
+    handle_authenticated_request(
+        admin_service,
+        NodeRequest::AcknowledgeStaleState {},
+        macaroon,
+        cookies,
+    )
+    .await
+}
+
+pub async fn list_pending_channels(
+    Extension(admin_service): Extension<Arc<AdminService>>,
+    AuthHeader { macaroon, token: _ }: AuthHeader,
+    cookies: Cookies,
+) -> Result<Json<NodeResponse>, StatusCode> {
+    handle_authenticated_request(
+        admin_service,
+        NodeRequest::ListPendingChannels {},
+        macaroon,
+        cookies,
+    )
+    .await
+}
+
+pub async fn get_gossip(
+    Extension(admin_service): Extension<Arc<AdminService>>,
+    AuthHeader { macaroon, token: _ }: AuthHeader,
+    cookies: Cookies,
+) -> Result<Json<NodeResponse>, StatusCode> {
+    handle_authenticated_request(admin_service, NodeRequest::GetGossip {}, macaroon, cookies).await
+}
+
 pub async fn list_known_peers(
     Extension(admin_service): Extension<Arc<AdminService>>,
     Query(params): Query<ListKnownPeersParams>,
@@ -668,3 +1904,49 @@ pub async fn remove_known_peer(
     }?;
     handle_authenticated_request(admin_service, request, macaroon, cookies).await
 }
+
+pub async fn list_subscriptions(
+    Extension(admin_service): Extension<Arc<AdminService>>,
+    AuthHeader { macaroon, token: _ }: AuthHeader,
+    cookies: Cookies,
+) -> Result<Json<NodeResponse>, StatusCode> {
+    handle_authenticated_request(
+        admin_service,
+        NodeRequest::ListSubscriptions {},
+        macaroon,
+        cookies,
+    )
+    .await
+}
+
+pub async fn create_subscription(
+    Extension(admin_service): Extension<Arc<AdminService>>,
+    Json(payload): Json<Value>,
+    AuthHeader { macaroon, token: _ }: AuthHeader,
+    cookies: Cookies,
+) -> Result<Json<NodeResponse>, StatusCode> {
+    let request = {
+        let params: Result<CreateSubscriptionParams, _> = serde_json::from_value(payload);
+        match params {
+            Ok(params) => Ok(params.into()),
+            Err(_) => Err(StatusCode::UNPROCESSABLE_ENTITY),
+        }
+    }?;
+    handle_authenticated_request(admin_service, request, macaroon, cookies).await
+}
+
+pub async fn delete_subscription(
+    Extension(admin_service): Extension<Arc<AdminService>>,
+    Json(payload): Json<Value>,
+    AuthHeader { macaroon, token: _ }: AuthHeader,
+    cookies: Cookies,
+) -> Result<Json<NodeResponse>, StatusCode> {
+    let request = {
+        let params: Result<DeleteSubscriptionParams, _> = serde_json::from_value(payload);
+        match params {
+            Ok(params) => Ok(params.into()),
+            Err(_) => Err(StatusCode::UNPROCESSABLE_ENTITY),
+        }
+    }?;
+    handle_authenticated_request(admin_service, request, macaroon, cookies).await
+}
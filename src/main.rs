@@ -87,6 +87,10 @@ struct SenseiArgs {
     api_port: Option<u16>,
     #[clap(long, env = "DATABASE_URL")]
     database_url: Option<String>,
+    #[clap(long, env = "P2P_BIND_ADDRESS")]
+    p2p_bind_address: Option<String>,
+    #[clap(long, env = "ROOT_NODE_LISTEN_PORT")]
+    root_node_listen_port: Option<u16>,
 }
 
 pub type AdminRequestResponse = (AdminRequest, Sender<AdminResponse>);
@@ -146,6 +150,12 @@ fn main() {
     if let Some(database_url) = args.database_url {
         config.database_url = database_url;
     }
+    if let Some(p2p_bind_address) = args.p2p_bind_address {
+        config.p2p_bind_address = p2p_bind_address;
+    }
+    if let Some(root_node_listen_port) = args.root_node_listen_port {
+        config.root_node_listen_port = root_node_listen_port;
+    }
 
     if !config.database_url.starts_with("postgres:") && !config.database_url.starts_with("mysql:") {
         let sqlite_path = format!("{}/{}/{}", sensei_dir, config.network, config.database_url);
@@ -205,7 +215,10 @@ fn main() {
                 config.clone(),
                 bitcoind_client.clone(),
                 bitcoind_client.clone(),
-                bitcoind_client,
+                bitcoind_client.clone(),
+                bitcoind_client.clone(),
+                bitcoind_client.relay_rejections.clone(),
+                bitcoind_client.fee_estimates_known.clone(),
             )
             .await
             .unwrap(),